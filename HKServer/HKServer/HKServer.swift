@@ -43,7 +43,7 @@ class HKServer : HomeControllerDelegate {
     // ============= HomeControllerDelegate ===============
 
     func isReady() {
-        service = HomeKitServiceProvider(homeManager: homeController.homeManager)
+        service = HomeKitServiceProvider(homeController: homeController)
         let serverConfiguration = Server.Configuration(
             target: self.target,
             eventLoopGroup: self.eventLoopGroup,
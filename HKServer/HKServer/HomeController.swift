@@ -12,9 +12,19 @@ protocol HomeControllerDelegate : class {
     func isReady() -> Void
 }
 
-class HomeController : NSObject, HMHomeManagerDelegate {
+typealias CharacteristicObserver = (HMAccessory, HMService, HMCharacteristic) -> Void
+typealias HomeObserver = (HMHome, Org_Hkserver_HomeEvent.OneOf_Event) -> Void
+
+class HomeController : NSObject, HMHomeManagerDelegate, HMHomeDelegate, HMAccessoryDelegate {
     private(set) public var homeManager: HMHomeManager
     public weak var delegate: HomeControllerDelegate?
+
+    private var observersLock = NSLock()
+    private var characteristicObservers: [UUID: CharacteristicObserver] = [:]
+    private var homeObservers: [UUID: HomeObserver] = [:]
+    // HMHomeDelegate reports every trigger change the same way, so remember
+    // which triggers were enabled to tell enabling apart from other updates
+    private var enabledTriggers: Set<UUID> = []
     
     override init() {
         self.homeManager = HMHomeManager()
@@ -23,9 +33,212 @@ class HomeController : NSObject, HMHomeManagerDelegate {
     }
     
     func homeManagerDidUpdateHomes(_ manager: HMHomeManager) {
+        manager.homes.forEach { $0.delegate = self }
+        manager.homes
+            .flatMap { $0.accessories }
+            .forEach { $0.delegate = self }
+        observersLock.lock()
+        enabledTriggers = Set(manager.homes.flatMap { $0.triggers }.filter { $0.isEnabled }.map { $0.uniqueIdentifier })
+        observersLock.unlock()
         self.delegate?.isReady()
     }
 
+    // Registers a block that is called whenever a characteristic with
+    // notifications enabled reports a new value.
+    public func addCharacteristicObserver(_ observer: @escaping CharacteristicObserver) -> UUID {
+        let token = UUID()
+        observersLock.lock()
+        characteristicObservers[token] = observer
+        observersLock.unlock()
+        return token
+    }
+
+    public func removeCharacteristicObserver(_ token: UUID) {
+        observersLock.lock()
+        characteristicObservers.removeValue(forKey: token)
+        observersLock.unlock()
+    }
+
+    // Registers a block that is called whenever the configuration of a home
+    // changes.
+    public func addHomeObserver(_ observer: @escaping HomeObserver) -> UUID {
+        let token = UUID()
+        observersLock.lock()
+        homeObservers[token] = observer
+        observersLock.unlock()
+        return token
+    }
+
+    public func removeHomeObserver(_ token: UUID) {
+        observersLock.lock()
+        homeObservers.removeValue(forKey: token)
+        observersLock.unlock()
+    }
+
+    private func notify(home: HMHome, event: Org_Hkserver_HomeEvent.OneOf_Event) {
+        observersLock.lock()
+        let observers = Array(homeObservers.values)
+        observersLock.unlock()
+        observers.forEach { $0(home, event) }
+    }
+
+    private func pair(_ obj: NameOrUuidFilterable) -> Org_Hkserver_NameUuidPair {
+        return HomeKitServiceProvider.nameUuidPair(obj: obj)
+    }
+
+    private func membership(container: NameOrUuidFilterable, member: NameOrUuidFilterable) -> Org_Hkserver_HomeEvent.MembershipChanged {
+        var change = Org_Hkserver_HomeEvent.MembershipChanged()
+        change.container = pair(container)
+        change.member = pair(member)
+        return change
+    }
+
+    // ============= HMHomeDelegate ===============
+
+    func homeDidUpdateName(_ home: HMHome) {
+        notify(home: home, event: .homeRenamed(pair(home)))
+    }
+
+    func home(_ home: HMHome, didAdd room: HMRoom) {
+        notify(home: home, event: .roomAdded(pair(room)))
+    }
+
+    func home(_ home: HMHome, didRemove room: HMRoom) {
+        notify(home: home, event: .roomRemoved(pair(room)))
+    }
+
+    func home(_ home: HMHome, didUpdateNameFor room: HMRoom) {
+        notify(home: home, event: .roomRenamed(pair(room)))
+    }
+
+    func home(_ home: HMHome, didAdd zone: HMZone) {
+        notify(home: home, event: .zoneAdded(pair(zone)))
+    }
+
+    func home(_ home: HMHome, didRemove zone: HMZone) {
+        notify(home: home, event: .zoneRemoved(pair(zone)))
+    }
+
+    func home(_ home: HMHome, didUpdateNameFor zone: HMZone) {
+        notify(home: home, event: .zoneRenamed(pair(zone)))
+    }
+
+    func home(_ home: HMHome, didAdd room: HMRoom, to zone: HMZone) {
+        notify(home: home, event: .roomAddedToZone(membership(container: zone, member: room)))
+    }
+
+    func home(_ home: HMHome, didRemove room: HMRoom, from zone: HMZone) {
+        notify(home: home, event: .roomRemovedFromZone(membership(container: zone, member: room)))
+    }
+
+    func home(_ home: HMHome, didAdd accessory: HMAccessory) {
+        accessory.delegate = self
+        notify(home: home, event: .accessoryAdded(pair(accessory)))
+    }
+
+    func home(_ home: HMHome, didRemove accessory: HMAccessory) {
+        notify(home: home, event: .accessoryRemoved(pair(accessory)))
+    }
+
+    func home(_ home: HMHome, didUpdate room: HMRoom, for accessory: HMAccessory) {
+        var moved = Org_Hkserver_HomeEvent.AccessoryMoved()
+        moved.accessory = pair(accessory)
+        moved.room = pair(room)
+        notify(home: home, event: .accessoryMoved(moved))
+    }
+
+    func home(_ home: HMHome, didAdd group: HMServiceGroup) {
+        notify(home: home, event: .serviceGroupAdded(pair(group)))
+    }
+
+    func home(_ home: HMHome, didRemove group: HMServiceGroup) {
+        notify(home: home, event: .serviceGroupRemoved(pair(group)))
+    }
+
+    func home(_ home: HMHome, didUpdateNameFor group: HMServiceGroup) {
+        notify(home: home, event: .serviceGroupRenamed(pair(group)))
+    }
+
+    func home(_ home: HMHome, didAdd service: HMService, to group: HMServiceGroup) {
+        notify(home: home, event: .serviceAddedToGroup(membership(container: group, member: service)))
+    }
+
+    func home(_ home: HMHome, didRemove service: HMService, from group: HMServiceGroup) {
+        notify(home: home, event: .serviceRemovedFromGroup(membership(container: group, member: service)))
+    }
+
+    func home(_ home: HMHome, didAdd actionSet: HMActionSet) {
+        notify(home: home, event: .actionSetAdded(pair(actionSet)))
+    }
+
+    func home(_ home: HMHome, didRemove actionSet: HMActionSet) {
+        notify(home: home, event: .actionSetRemoved(pair(actionSet)))
+    }
+
+    func home(_ home: HMHome, didUpdateNameFor actionSet: HMActionSet) {
+        notify(home: home, event: .actionSetRenamed(pair(actionSet)))
+    }
+
+    func home(_ home: HMHome, didUpdateActionsFor actionSet: HMActionSet) {
+        notify(home: home, event: .actionSetUpdated(pair(actionSet)))
+    }
+
+    func home(_ home: HMHome, didAdd trigger: HMTrigger) {
+        observersLock.lock()
+        if trigger.isEnabled {
+            enabledTriggers.insert(trigger.uniqueIdentifier)
+        }
+        observersLock.unlock()
+        notify(home: home, event: .triggerAdded(pair(trigger)))
+    }
+
+    func home(_ home: HMHome, didRemove trigger: HMTrigger) {
+        observersLock.lock()
+        enabledTriggers.remove(trigger.uniqueIdentifier)
+        observersLock.unlock()
+        notify(home: home, event: .triggerRemoved(pair(trigger)))
+    }
+
+    func home(_ home: HMHome, didUpdateNameFor trigger: HMTrigger) {
+        notify(home: home, event: .triggerRenamed(pair(trigger)))
+    }
+
+    func home(_ home: HMHome, didUpdate trigger: HMTrigger) {
+        observersLock.lock()
+        let wasEnabled = enabledTriggers.contains(trigger.uniqueIdentifier)
+        if trigger.isEnabled {
+            enabledTriggers.insert(trigger.uniqueIdentifier)
+        } else {
+            enabledTriggers.remove(trigger.uniqueIdentifier)
+        }
+        observersLock.unlock()
+
+        if wasEnabled != trigger.isEnabled {
+            var changed = Org_Hkserver_HomeEvent.TriggerEnabledChanged()
+            changed.trigger = pair(trigger)
+            changed.isEnabled = trigger.isEnabled
+            notify(home: home, event: .triggerEnabledChanged(changed))
+        } else {
+            notify(home: home, event: .triggerUpdated(pair(trigger)))
+        }
+    }
+
+    // ============= HMAccessoryDelegate ===============
+
+    func accessoryDidUpdateName(_ accessory: HMAccessory) {
+        guard let home = homeManager.homes.first(where: { $0.accessories.contains(accessory) }) else {
+            return
+        }
+        notify(home: home, event: .accessoryRenamed(pair(accessory)))
+    }
+
+    func accessory(_ accessory: HMAccessory, service: HMService, didUpdateValueFor characteristic: HMCharacteristic) {
+        observersLock.lock()
+        let observers = Array(characteristicObservers.values)
+        observersLock.unlock()
+        observers.forEach { $0(accessory, service, characteristic) }
+    }
+
     private func isAuthorized(status: HMHomeManagerAuthorizationStatus) -> Bool {
         return status.contains(.determined) && status.contains(.authorized)
     }
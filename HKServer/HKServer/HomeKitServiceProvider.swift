@@ -147,10 +147,13 @@ extension HomeKitServiceError : GRPCStatusTransformable {
 }
 
 class HomeKitServiceProvider : Org_Hkserver_HomeKitServiceProvider {
-    public var homeManager: HMHomeManager
+    public var homeController: HomeController
+    public var homeManager: HMHomeManager {
+        return homeController.homeManager
+    }
 
-    init(homeManager: HMHomeManager) {
-        self.homeManager = homeManager
+    init(homeController: HomeController) {
+        self.homeController = homeController
     }
 
     // ========== Org_Hkserver_HomeKitServiceProvider ============
@@ -627,7 +630,8 @@ class HomeKitServiceProvider : Org_Hkserver_HomeKitServiceProvider {
             return context.eventLoop.makeFailedFuture(HomeKitServiceError.notFound(objectType: "accessory", pattern: request.name))
         }
 
-        guard let room = home.rooms.first(where: { $0.matchesExactly(nameOrUuid: request.room) }) else {
+        // The room for the entire home is not part of home.rooms, but accessories can be moved back to it
+        guard let room = (home.rooms + [home.roomForEntireHome()]).first(where: { $0.matchesExactly(nameOrUuid: request.room) }) else {
             return context.eventLoop.makeFailedFuture(HomeKitServiceError.notFound(objectType: "room", pattern: request.room))
         }
 
@@ -707,35 +711,668 @@ class HomeKitServiceProvider : Org_Hkserver_HomeKitServiceProvider {
     }
     
     func addRemoveActions(request: Org_Hkserver_AddRemoveActionSetRequest, context: StatusOnlyCallContext) -> EventLoopFuture<Org_Hkserver_AddRemoveActionSetResponse> {
-        return context.eventLoop.makeFailedFuture(HomeKitServiceError.nyi)
+        guard let home = self.findHome(pattern: request.home) else {
+            return context.eventLoop.makeFailedFuture(HomeKitServiceError.homeNotFound(pattern: request.home))
+        }
+
+        let promise = context.eventLoop.makePromise(of: Org_Hkserver_AddRemoveActionSetResponse.self)
+        switch request.operation {
+        case .add:
+            // Check every action before creating anything
+            var actions: [HMCharacteristicWriteAction<NSCopying>] = []
+            for definition in request.actionDefinition {
+                switch HomeKitServiceProvider.characteristicWriteAction(home: home, definition: definition) {
+                case .success(let action):
+                    actions.append(action)
+                case .failure(let error):
+                    return context.eventLoop.makeFailedFuture(error)
+                }
+            }
+
+            home.addActionSet(withName: request.name, completionHandler: { actionSet, error in
+                if let error = error {
+                    promise.fail(HomeKitServiceError(other: error))
+                    return
+                }
+
+                guard let actionSet = actionSet else {
+                    promise.fail(HomeKitServiceError.unexpected)
+                    return
+                }
+
+                // Add the actions
+                let futures = actions.map({ action -> EventLoopFuture<Void> in
+                    let addPromise: EventLoopPromise<Void> = context.eventLoop.makePromise(of: Void.self)
+                    actionSet.addAction(action, completionHandler: { error in
+                        if let error = error {
+                            addPromise.fail(HomeKitServiceError(other: error))
+                            return
+                        }
+                        addPromise.succeed(())
+                    })
+                    return addPromise.futureResult
+                })
+                EventLoopFuture.andAllSucceed(futures, on: context.eventLoop)
+                    .map({ () -> Org_Hkserver_AddRemoveActionSetResponse in
+                        var response = Org_Hkserver_AddRemoveActionSetResponse()
+                        response.home = HomeKitServiceProvider.nameUuidPair(obj: home)
+                        response.actionSet = HomeKitServiceProvider.nameUuidPair(obj: actionSet)
+                        return response
+                    })
+                    .cascade(to: promise)
+            })
+        case .remove:
+            if let actionSet = home.actionSets.first(where: { actionSet in actionSet.matches(pattern: request.name) }) {
+                // Capture name/uuid pair before removing it
+                let actionSetNameUuid = HomeKitServiceProvider.nameUuidPair(obj: actionSet)
+                home.removeActionSet(actionSet, completionHandler: { error in
+                    if let error = error {
+                        promise.fail(HomeKitServiceError(other: error))
+                        return
+                    }
+
+                    var response = Org_Hkserver_AddRemoveActionSetResponse()
+                    response.home = HomeKitServiceProvider.nameUuidPair(obj: home)
+                    response.actionSet = actionSetNameUuid
+                    promise.succeed(response)
+                })
+            } else {
+                promise.fail(HomeKitServiceError.notFound(objectType: "action set", pattern: request.name))
+            }
+        case .UNRECOGNIZED(_):
+            promise.fail(HomeKitServiceError(code: .invalidArgument, message: "Invalid value for operation"))
+        }
+        return promise.futureResult
     }
     
     func addRemoveTriggers(request: Org_Hkserver_AddRemoveTriggersRequest, context: StatusOnlyCallContext) -> EventLoopFuture<Org_Hkserver_AddRemoveTriggersResponse> {
-        return context.eventLoop.makeFailedFuture(HomeKitServiceError.nyi)
+        guard let home = self.findHome(pattern: request.home) else {
+            return context.eventLoop.makeFailedFuture(HomeKitServiceError.homeNotFound(pattern: request.home))
+        }
+
+        let promise = context.eventLoop.makePromise(of: Org_Hkserver_AddRemoveTriggersResponse.self)
+        switch request.operation {
+        case .add:
+            // Check the definition and action sets before creating anything
+            let trigger: HMTrigger
+            switch request.definition.triggerDefinition {
+            case .timer(let definition)?:
+                switch HomeKitServiceProvider.timerTrigger(name: request.name, definition: definition) {
+                case .success(let timer):
+                    trigger = timer
+                case .failure(let error):
+                    return context.eventLoop.makeFailedFuture(error)
+                }
+            case .event(let definition)?:
+                let predicate: NSPredicate?
+                switch HomeKitServiceProvider.eventTriggerPredicate(home: home, definition: definition) {
+                case .success(let p):
+                    predicate = p
+                case .failure(let error):
+                    return context.eventLoop.makeFailedFuture(error)
+                }
+                switch HomeKitServiceProvider.eventTriggerEvents(home: home, definition: definition) {
+                case .success(let (events, endEvents, recurrences)):
+                    trigger = HMEventTrigger(name: request.name, events: events, end: endEvents, recurrences: recurrences, predicate: predicate)
+                case .failure(let error):
+                    return context.eventLoop.makeFailedFuture(error)
+                }
+            case nil:
+                return context.eventLoop.makeFailedFuture(HomeKitServiceError(code: .invalidArgument, message: "Adding a trigger requires a trigger definition"))
+            }
+            var actionSets: [HMActionSet] = []
+            for nameOrUuid in request.actionSets {
+                guard let actionSet = home.actionSets.first(where: { $0.matchesExactly(nameOrUuid: nameOrUuid) }) else {
+                    return context.eventLoop.makeFailedFuture(HomeKitServiceError.notFound(objectType: "action set", pattern: nameOrUuid))
+                }
+                actionSets.append(actionSet)
+            }
+
+            home.addTrigger(trigger, completionHandler: { error in
+                if let error = error {
+                    promise.fail(HomeKitServiceError(other: error))
+                    return
+                }
+
+                // Add the action sets
+                var futures = actionSets.map({ actionSet -> EventLoopFuture<Void> in
+                    let addPromise: EventLoopPromise<Void> = context.eventLoop.makePromise(of: Void.self)
+                    trigger.addActionSet(actionSet, completionHandler: { error in
+                        if let error = error {
+                            addPromise.fail(HomeKitServiceError(other: error))
+                            return
+                        }
+                        addPromise.succeed(())
+                    })
+                    return addPromise.futureResult
+                })
+                // Event triggers can only be told to execute once after they are added
+                if let eventTrigger = trigger as? HMEventTrigger, case .event(let definition)? = request.definition.triggerDefinition, definition.executesOnce {
+                    let oncePromise: EventLoopPromise<Void> = context.eventLoop.makePromise(of: Void.self)
+                    eventTrigger.updateExecuteOnce(true, completionHandler: { error in
+                        if let error = error {
+                            oncePromise.fail(HomeKitServiceError(other: error))
+                            return
+                        }
+                        oncePromise.succeed(())
+                    })
+                    futures.append(oncePromise.futureResult)
+                }
+                EventLoopFuture.andAllSucceed(futures, on: context.eventLoop)
+                    .map({ () -> Org_Hkserver_AddRemoveTriggersResponse in
+                        var response = Org_Hkserver_AddRemoveTriggersResponse()
+                        response.home = HomeKitServiceProvider.nameUuidPair(obj: home)
+                        response.trigger = HomeKitServiceProvider.nameUuidPair(obj: trigger)
+                        return response
+                    })
+                    .cascade(to: promise)
+            })
+        case .remove:
+            if let trigger = home.triggers.first(where: { trigger in trigger.matches(pattern: request.name) }) {
+                // Capture name/uuid pair before removing it
+                let triggerNameUuid = HomeKitServiceProvider.nameUuidPair(obj: trigger)
+                home.removeTrigger(trigger, completionHandler: { error in
+                    if let error = error {
+                        promise.fail(HomeKitServiceError(other: error))
+                        return
+                    }
+
+                    var response = Org_Hkserver_AddRemoveTriggersResponse()
+                    response.home = HomeKitServiceProvider.nameUuidPair(obj: home)
+                    response.trigger = triggerNameUuid
+                    promise.succeed(response)
+                })
+            } else {
+                promise.fail(HomeKitServiceError.notFound(objectType: "trigger", pattern: request.name))
+            }
+        case .UNRECOGNIZED(_):
+            promise.fail(HomeKitServiceError(code: .invalidArgument, message: "Invalid value for operation"))
+        }
+        return promise.futureResult
     }
     
     func enableDisableTrigger(request: Org_Hkserver_EnableDisableTriggerRequest, context: StatusOnlyCallContext) -> EventLoopFuture<Org_Hkserver_EnableDisableTriggerResponse> {
-        return context.eventLoop.makeFailedFuture(HomeKitServiceError.nyi)
+        guard let home = self.findHome(pattern: request.home) else {
+            return context.eventLoop.makeFailedFuture(HomeKitServiceError.homeNotFound(pattern: request.home))
+        }
+        guard let trigger = home.triggers.first(where: { $0.matchesExactly(nameOrUuid: request.name) }) else {
+            return context.eventLoop.makeFailedFuture(HomeKitServiceError.notFound(objectType: "trigger", pattern: request.name))
+        }
+
+        let promise = context.eventLoop.makePromise(of: Org_Hkserver_EnableDisableTriggerResponse.self)
+        trigger.enable(request.enable, completionHandler: { error in
+            if let error = error {
+                promise.fail(HomeKitServiceError(other: error))
+                return
+            }
+
+            var response = Org_Hkserver_EnableDisableTriggerResponse()
+            response.home = HomeKitServiceProvider.nameUuidPair(obj: home)
+            response.trigger = HomeKitServiceProvider.nameUuidPair(obj: trigger)
+            promise.succeed(response)
+        })
+        return promise.futureResult
     }
     
     func changeActionSetMembership(request: Org_Hkserver_ChangeActionSetMembershipRequest, context: StatusOnlyCallContext) -> EventLoopFuture<Org_Hkserver_ChangeActionSetMembershipResponse> {
-        return context.eventLoop.makeFailedFuture(HomeKitServiceError.nyi)
+        guard let home = self.findHome(pattern: request.home) else {
+            return context.eventLoop.makeFailedFuture(HomeKitServiceError.homeNotFound(pattern: request.home))
+        }
+
+        guard let actionSet = home.actionSets.first(where: { $0.matchesExactly(nameOrUuid: request.name) }) else {
+            return context.eventLoop.makeFailedFuture(HomeKitServiceError.notFound(objectType: "action set", pattern: request.name))
+        }
+
+        let promise = context.eventLoop.makePromise(of: Void.self)
+        let completionHandler = { (error: Error?) in
+            if let error = error {
+                promise.fail(HomeKitServiceError(other: error))
+                return
+            }
+            promise.succeed(())
+        }
+        switch (request.operation, request.actions.action) {
+        case (.add, .definition(let definition)?):
+            switch HomeKitServiceProvider.characteristicWriteAction(home: home, definition: definition) {
+            case .success(let action):
+                actionSet.addAction(action, completionHandler: completionHandler)
+            case .failure(let error):
+                return context.eventLoop.makeFailedFuture(error)
+            }
+        case (.remove, .uuid(let uuid)?):
+            guard let action = actionSet.actions.first(where: { $0.uuid == uuid }) else {
+                return context.eventLoop.makeFailedFuture(HomeKitServiceError.notFound(objectType: "action", pattern: uuid))
+            }
+            actionSet.removeAction(action, completionHandler: completionHandler)
+        case (.add, _):
+            return context.eventLoop.makeFailedFuture(HomeKitServiceError(code: .invalidArgument, message: "Adding an action requires an action definition"))
+        case (.remove, _):
+            return context.eventLoop.makeFailedFuture(HomeKitServiceError(code: .invalidArgument, message: "Removing an action requires its uuid"))
+        case (.UNRECOGNIZED(_), _):
+            return context.eventLoop.makeFailedFuture(HomeKitServiceError(code: .invalidArgument, message: "Invalid value for operation"))
+        }
+        return promise.futureResult.map({ () -> Org_Hkserver_ChangeActionSetMembershipResponse in
+            var response = Org_Hkserver_ChangeActionSetMembershipResponse()
+            response.home = HomeKitServiceProvider.nameUuidPair(obj: home)
+            response.actionSet = HomeKitServiceProvider.nameUuidPair(obj: actionSet)
+            return response
+        })
     }
     
     func changeTriggerMembership(request: Org_Hkserver_ChangeTriggerMembershipRequest, context: StatusOnlyCallContext) -> EventLoopFuture<Org_Hkserver_ChangeTriggerMembershipResponse> {
-        return context.eventLoop.makeFailedFuture(HomeKitServiceError.nyi)
+        guard let home = self.findHome(pattern: request.home) else {
+            return context.eventLoop.makeFailedFuture(HomeKitServiceError.homeNotFound(pattern: request.home))
+        }
+        guard let trigger = home.triggers.first(where: { $0.matchesExactly(nameOrUuid: request.name) }) else {
+            return context.eventLoop.makeFailedFuture(HomeKitServiceError.notFound(objectType: "trigger", pattern: request.name))
+        }
+        var actionSets: [HMActionSet] = []
+        for nameOrUuid in request.actionSets {
+            guard let actionSet = home.actionSets.first(where: { $0.matchesExactly(nameOrUuid: nameOrUuid) }) else {
+                return context.eventLoop.makeFailedFuture(HomeKitServiceError.notFound(objectType: "action set", pattern: nameOrUuid))
+            }
+            actionSets.append(actionSet)
+        }
+
+        let futures = actionSets.map({ actionSet -> EventLoopFuture<Void> in
+            let changePromise: EventLoopPromise<Void> = context.eventLoop.makePromise(of: Void.self)
+            let completionHandler = { (error: Error?) in
+                if let error = error {
+                    changePromise.fail(HomeKitServiceError(other: error))
+                    return
+                }
+                changePromise.succeed(())
+            }
+            switch request.operation {
+            case .add:
+                // Adding an action set that is already there is not an error, as in hkserver-rs
+                if trigger.actionSets.contains(actionSet) {
+                    changePromise.succeed(())
+                } else {
+                    trigger.addActionSet(actionSet, completionHandler: completionHandler)
+                }
+            case .remove:
+                if trigger.actionSets.contains(actionSet) {
+                    trigger.removeActionSet(actionSet, completionHandler: completionHandler)
+                } else {
+                    changePromise.succeed(())
+                }
+            case .UNRECOGNIZED(_):
+                changePromise.fail(HomeKitServiceError(code: .invalidArgument, message: "Invalid value for operation"))
+            }
+            return changePromise.futureResult
+        })
+        return EventLoopFuture.andAllSucceed(futures, on: context.eventLoop)
+            .map({ () -> Org_Hkserver_ChangeTriggerMembershipResponse in
+                var response = Org_Hkserver_ChangeTriggerMembershipResponse()
+                response.home = HomeKitServiceProvider.nameUuidPair(obj: home)
+                response.trigger = HomeKitServiceProvider.nameUuidPair(obj: trigger)
+                return response
+            })
     }
     
+    func updateEventTrigger(request: Org_Hkserver_UpdateEventTriggerRequest, context: StatusOnlyCallContext) -> EventLoopFuture<Org_Hkserver_UpdateEventTriggerResponse> {
+        guard let home = self.findHome(pattern: request.home) else {
+            return context.eventLoop.makeFailedFuture(HomeKitServiceError.homeNotFound(pattern: request.home))
+        }
+
+        guard let trigger = home.triggers.first(where: { $0.matchesExactly(nameOrUuid: request.name) }) else {
+            return context.eventLoop.makeFailedFuture(HomeKitServiceError.notFound(objectType: "trigger", pattern: request.name))
+        }
+        guard let eventTrigger = trigger as? HMEventTrigger else {
+            return context.eventLoop.makeFailedFuture(HomeKitServiceError(code: .invalidArgument, message: "Trigger \(trigger.name) is not an event trigger"))
+        }
+
+        let events: [HMEvent]
+        let endEvents: [HMEvent]
+        let recurrences: [DateComponents]?
+        switch HomeKitServiceProvider.eventTriggerEvents(home: home, definition: request.definition) {
+        case .success(let result):
+            (events, endEvents, recurrences) = result
+        case .failure(let error):
+            return context.eventLoop.makeFailedFuture(error)
+        }
+        let predicate: NSPredicate?
+        switch HomeKitServiceProvider.eventTriggerPredicate(home: home, definition: request.definition) {
+        case .success(let p):
+            predicate = p
+        case .failure(let error):
+            return context.eventLoop.makeFailedFuture(error)
+        }
+
+        // HomeKit updates each part separately, so apply them one after the other
+        let updates: [(@escaping (Error?) -> Void) -> Void] = [
+            { eventTrigger.updateEvents(events, completionHandler: $0) },
+            { eventTrigger.updateEndEvents(endEvents, completionHandler: $0) },
+            { eventTrigger.updateRecurrences(recurrences, completionHandler: $0) },
+            { eventTrigger.updateExecuteOnce(request.definition.executesOnce, completionHandler: $0) },
+            { eventTrigger.updatePredicate(predicate, completionHandler: $0) },
+        ]
+        var future = context.eventLoop.makeSucceededFuture(())
+        for update in updates {
+            future = future.flatMap({ () -> EventLoopFuture<Void> in
+                let updatePromise: EventLoopPromise<Void> = context.eventLoop.makePromise(of: Void.self)
+                update({ error in
+                    if let error = error {
+                        updatePromise.fail(HomeKitServiceError(other: error))
+                        return
+                    }
+                    updatePromise.succeed(())
+                })
+                return updatePromise.futureResult
+            })
+        }
+        return future.map({ () -> Org_Hkserver_UpdateEventTriggerResponse in
+            var response = Org_Hkserver_UpdateEventTriggerResponse()
+            response.home = HomeKitServiceProvider.nameUuidPair(obj: home)
+            response.trigger = HomeKitServiceProvider.nameUuidPair(obj: eventTrigger)
+            return response
+        })
+    }
+
     func runActionSet(request: Org_Hkserver_RunActionSetRequest, context: StatusOnlyCallContext) -> EventLoopFuture<Org_Hkserver_RunActionSetResponse> {
-        return context.eventLoop.makeFailedFuture(HomeKitServiceError.nyi)
+        guard let home = self.findHome(pattern: request.home) else {
+            return context.eventLoop.makeFailedFuture(HomeKitServiceError.homeNotFound(pattern: request.home))
+        }
+
+        guard let actionSet = home.actionSets.first(where: { $0.matchesExactly(nameOrUuid: request.name) }) else {
+            return context.eventLoop.makeFailedFuture(HomeKitServiceError.notFound(objectType: "action set", pattern: request.name))
+        }
+
+        return HomeKitServiceProvider.executeActionSet(home: home, actionSet: actionSet, eventLoop: context.eventLoop)
+            .map({ results -> Org_Hkserver_RunActionSetResponse in
+                var response = Org_Hkserver_RunActionSetResponse()
+                response.home = HomeKitServiceProvider.nameUuidPair(obj: home)
+                response.actionSet = HomeKitServiceProvider.nameUuidPair(obj: actionSet)
+                response.results = results
+                return response
+            })
     }
     
     func runTrigger(request: Org_Hkserver_RunTriggerRequest, context: StatusOnlyCallContext) -> EventLoopFuture<Org_Hkserver_RunTriggerResponse> {
-        return context.eventLoop.makeFailedFuture(HomeKitServiceError.nyi)
+        guard let home = self.findHome(pattern: request.home) else {
+            return context.eventLoop.makeFailedFuture(HomeKitServiceError.homeNotFound(pattern: request.home))
+        }
+
+        guard let trigger = home.triggers.first(where: { $0.matchesExactly(nameOrUuid: request.name) }) else {
+            return context.eventLoop.makeFailedFuture(HomeKitServiceError.notFound(objectType: "trigger", pattern: request.name))
+        }
+
+        // Running a trigger executes its action sets one after the other, as
+        // if it had fired
+        var future = context.eventLoop.makeSucceededFuture([Org_Hkserver_ActionSetResult]())
+        for actionSet in trigger.actionSets {
+            future = future.flatMap({ actionSetResults in
+                HomeKitServiceProvider.executeActionSet(home: home, actionSet: actionSet, eventLoop: context.eventLoop)
+                    .map({ results -> [Org_Hkserver_ActionSetResult] in
+                        var actionSetResult = Org_Hkserver_ActionSetResult()
+                        actionSetResult.actionSet = HomeKitServiceProvider.nameUuidPair(obj: actionSet)
+                        actionSetResult.results = results
+                        return actionSetResults + [actionSetResult]
+                    })
+            })
+        }
+        return future.map({ actionSetResults -> Org_Hkserver_RunTriggerResponse in
+            var response = Org_Hkserver_RunTriggerResponse()
+            response.home = HomeKitServiceProvider.nameUuidPair(obj: home)
+            response.trigger = HomeKitServiceProvider.nameUuidPair(obj: trigger)
+            response.actionSets = actionSetResults
+            return response
+        })
+    }
+
+    func readCharacteristic(request: Org_Hkserver_ReadCharacteristicRequest, context: StatusOnlyCallContext) -> EventLoopFuture<Org_Hkserver_ReadCharacteristicResponse> {
+        guard let home = self.findHome(pattern: request.home) else {
+            return context.eventLoop.makeFailedFuture(HomeKitServiceError.homeNotFound(pattern: request.home))
+        }
+
+        let found = HomeKitServiceProvider.findCharacteristic(home: home, accessory: request.accessory, service: request.service, characteristic: request.characteristic, characteristicType: request.characteristicType)
+        guard case let .success((accessory, service, characteristic)) = found else {
+            if case let .failure(error) = found {
+                return context.eventLoop.makeFailedFuture(error)
+            }
+            return context.eventLoop.makeFailedFuture(HomeKitServiceError.unexpected)
+        }
+
+        guard characteristic.properties.contains(HMCharacteristicPropertyReadable) else {
+            return context.eventLoop.makeFailedFuture(HomeKitServiceError(code: .invalidArgument, message: "Characteristic \(characteristic.uuid) is not readable"))
+        }
+
+        let promise = context.eventLoop.makePromise(of: Org_Hkserver_ReadCharacteristicResponse.self)
+        characteristic.readValue(completionHandler: { error in
+            if let error = error {
+                promise.fail(HomeKitServiceError(other: error))
+                return
+            }
+
+            var response = Org_Hkserver_ReadCharacteristicResponse()
+            response.home = HomeKitServiceProvider.nameUuidPair(obj: home)
+            response.accessory = HomeKitServiceProvider.nameUuidPair(obj: accessory)
+            response.service = HomeKitServiceProvider.nameUuidPair(obj: service)
+            response.characteristic = HomeKitServiceProvider.characteristicInfo(characteristic: characteristic)
+            promise.succeed(response)
+        })
+        return promise.futureResult
+    }
+
+    func writeCharacteristic(request: Org_Hkserver_WriteCharacteristicRequest, context: StatusOnlyCallContext) -> EventLoopFuture<Org_Hkserver_WriteCharacteristicResponse> {
+        guard let home = self.findHome(pattern: request.home) else {
+            return context.eventLoop.makeFailedFuture(HomeKitServiceError.homeNotFound(pattern: request.home))
+        }
+
+        let found = HomeKitServiceProvider.findCharacteristic(home: home, accessory: request.accessory, service: request.service, characteristic: request.characteristic, characteristicType: request.characteristicType)
+        guard case let .success((accessory, service, characteristic)) = found else {
+            if case let .failure(error) = found {
+                return context.eventLoop.makeFailedFuture(error)
+            }
+            return context.eventLoop.makeFailedFuture(HomeKitServiceError.unexpected)
+        }
+
+        guard characteristic.properties.contains(HMCharacteristicPropertyWritable) else {
+            return context.eventLoop.makeFailedFuture(HomeKitServiceError(code: .invalidArgument, message: "Characteristic \(characteristic.uuid) is not writable"))
+        }
+
+        let value: Any
+        switch HomeKitServiceProvider.characteristicValueFromValue(characteristic: characteristic, value: request.value) {
+        case .success(let v):
+            value = v
+        case .failure(let error):
+            return context.eventLoop.makeFailedFuture(error)
+        }
+
+        let promise = context.eventLoop.makePromise(of: Org_Hkserver_WriteCharacteristicResponse.self)
+        characteristic.writeValue(value, completionHandler: { error in
+            if let error = error {
+                promise.fail(HomeKitServiceError(other: error))
+                return
+            }
+
+            var response = Org_Hkserver_WriteCharacteristicResponse()
+            response.home = HomeKitServiceProvider.nameUuidPair(obj: home)
+            response.accessory = HomeKitServiceProvider.nameUuidPair(obj: accessory)
+            response.service = HomeKitServiceProvider.nameUuidPair(obj: service)
+            response.characteristic = HomeKitServiceProvider.characteristicInfo(characteristic: characteristic)
+            promise.succeed(response)
+        })
+        return promise.futureResult
+    }
+
+    func subscribeCharacteristics(request: Org_Hkserver_SubscribeCharacteristicsRequest, context: StreamingResponseCallContext<Org_Hkserver_CharacteristicChangeEvent>) -> EventLoopFuture<GRPCStatus> {
+        guard let home = self.findHome(pattern: request.home) else {
+            return context.eventLoop.makeFailedFuture(HomeKitServiceError.homeNotFound(pattern: request.home))
+        }
+
+        let serviceTypes = request.serviceTypes.compactMap { HomeKitServiceProvider.fromServiceType(serviceType: $0) }
+        let accessories = home.accessories
+            .filter { $0.matches(pattern: request.accessoryFilter) }
+            .filter { request.roomFilter.count == 0 || ($0.room?.matches(pattern: request.roomFilter) ?? false) }
+        var subscribed = [(HMAccessory, HMService, HMCharacteristic)]()
+        for accessory in accessories {
+            let services = accessory.services
+                .filter { $0.matches(pattern: request.serviceFilter) }
+                .filter { serviceTypes.count == 0 || serviceTypes.contains($0.serviceType) }
+            for service in services {
+                for characteristic in service.characteristics {
+                    guard characteristic.properties.contains(HMCharacteristicPropertySupportsEventNotification) else {
+                        continue
+                    }
+                    let type = HomeKitServiceProvider.characteristicType(type: characteristic.characteristicType)
+                    if request.characteristicTypes.count != 0 && !request.characteristicTypes.contains(type) {
+                        continue
+                    }
+                    subscribed.append((accessory, service, characteristic))
+                }
+            }
+        }
+
+        let makeEvent = { (accessory: HMAccessory, service: HMService, characteristic: HMCharacteristic, isSnapshot: Bool) -> Org_Hkserver_CharacteristicChangeEvent in
+            var event = Org_Hkserver_CharacteristicChangeEvent()
+            event.home = HomeKitServiceProvider.nameUuidPair(obj: home)
+            event.accessory = HomeKitServiceProvider.nameUuidPair(obj: accessory)
+            event.service = HomeKitServiceProvider.nameUuidPair(obj: service)
+            event.characteristic = HomeKitServiceProvider.characteristicInfo(characteristic: characteristic)
+            event.timestamp = UInt64(Date().timeIntervalSince1970)
+            event.isSnapshot = isSnapshot
+            return event
+        }
+
+        subscribed.forEach { (_, _, characteristic) in
+            characteristic.enableNotification(true, completionHandler: { _ in })
+        }
+        if request.initialSnapshot {
+            subscribed.forEach { (accessory, service, characteristic) in
+                _ = context.sendResponse(makeEvent(accessory, service, characteristic, true))
+            }
+        }
+
+        // The stream stays open until the client goes away, which shows up
+        // as a failure to send the next event.
+        let promise = context.eventLoop.makePromise(of: GRPCStatus.self)
+        let uuids = Set(subscribed.map { $0.2.uniqueIdentifier })
+        var token: UUID?
+        var finished = false
+        token = homeController.addCharacteristicObserver { (accessory, service, characteristic) in
+            guard uuids.contains(characteristic.uniqueIdentifier) else {
+                return
+            }
+            context.eventLoop.execute {
+                context.sendResponse(makeEvent(accessory, service, characteristic, false)).whenFailure { _ in
+                    if finished {
+                        return
+                    }
+                    finished = true
+                    if let token = token {
+                        self.homeController.removeCharacteristicObserver(token)
+                    }
+                    promise.succeed(.ok)
+                }
+            }
+        }
+        return promise.futureResult
+    }
+
+    func watchHome(request: Org_Hkserver_WatchHomeRequest, context: StreamingResponseCallContext<Org_Hkserver_HomeEvent>) -> EventLoopFuture<GRPCStatus> {
+        guard let home = self.findHome(pattern: request.home) else {
+            return context.eventLoop.makeFailedFuture(HomeKitServiceError.homeNotFound(pattern: request.home))
+        }
+
+        // Like subscribeCharacteristics, the stream ends when sending fails
+        let promise = context.eventLoop.makePromise(of: GRPCStatus.self)
+        let homeIdentifier = home.uniqueIdentifier
+        var token: UUID?
+        var finished = false
+        token = homeController.addHomeObserver { (changedHome, change) in
+            guard changedHome.uniqueIdentifier == homeIdentifier else {
+                return
+            }
+            var event = Org_Hkserver_HomeEvent()
+            event.home = HomeKitServiceProvider.nameUuidPair(obj: changedHome)
+            event.timestamp = UInt64(Date().timeIntervalSince1970)
+            event.event = change
+            context.eventLoop.execute {
+                context.sendResponse(event).whenFailure { _ in
+                    if finished {
+                        return
+                    }
+                    finished = true
+                    if let token = token {
+                        self.homeController.removeHomeObserver(token)
+                    }
+                    promise.succeed(.ok)
+                }
+            }
+        }
+        return promise.futureResult
     }
 
     func setName(request: Org_Hkserver_SetNameRequest, context: StatusOnlyCallContext) -> EventLoopFuture<Org_Hkserver_SetNameResponse> {
+        guard let home = self.findHome(pattern: request.home) else {
+            return context.eventLoop.makeFailedFuture(HomeKitServiceError.homeNotFound(pattern: request.home))
+        }
+        guard request.newName.trimmingCharacters(in: .whitespaces).count != 0 else {
+            return context.eventLoop.makeFailedFuture(HomeKitServiceError(code: .invalidArgument, message: "The new name cannot be empty"))
+        }
+
+        // Every renamable object has updateName(_:completionHandler:), but no protocol declares it
+        let object: NameOrUuidFilterable?
+        let updateName: ((@escaping (Error?) -> Void) -> Void)?
+        let notFound = { (objectType: String) in HomeKitServiceError.notFound(objectType: objectType, pattern: request.name) }
+        let error: HomeKitServiceError
+        switch request.objectType {
+        case .home:
+            let matches = request.name.count == 0 || home.matchesExactly(nameOrUuid: request.name)
+            object = matches ? home : nil
+            updateName = matches ? { home.updateName(request.newName, completionHandler: $0) } : nil
+            error = notFound("home")
+        case .room:
+            let room = home.rooms.first(where: { $0.matchesExactly(nameOrUuid: request.name) })
+            object = room
+            updateName = room.map { room in { room.updateName(request.newName, completionHandler: $0) } }
+            error = notFound("room")
+        case .zone:
+            let zone = home.zones.first(where: { $0.matchesExactly(nameOrUuid: request.name) })
+            object = zone
+            updateName = zone.map { zone in { zone.updateName(request.newName, completionHandler: $0) } }
+            error = notFound("zone")
+        case .accessory:
+            let accessory = home.accessories.first(where: { $0.matchesExactly(nameOrUuid: request.name) })
+            object = accessory
+            updateName = accessory.map { accessory in { accessory.updateName(request.newName, completionHandler: $0) } }
+            error = notFound("accessory")
+        case .serviceGroup:
+            let serviceGroup = home.serviceGroups.first(where: { $0.matchesExactly(nameOrUuid: request.name) })
+            object = serviceGroup
+            updateName = serviceGroup.map { serviceGroup in { serviceGroup.updateName(request.newName, completionHandler: $0) } }
+            error = notFound("service group")
+        case .actionSet:
+            let actionSet = home.actionSets.first(where: { $0.matchesExactly(nameOrUuid: request.name) })
+            object = actionSet
+            updateName = actionSet.map { actionSet in { actionSet.updateName(request.newName, completionHandler: $0) } }
+            error = notFound("action set")
+        case .trigger:
+            let trigger = home.triggers.first(where: { $0.matchesExactly(nameOrUuid: request.name) })
+            object = trigger
+            updateName = trigger.map { trigger in { trigger.updateName(request.newName, completionHandler: $0) } }
+            error = notFound("trigger")
+        case .unknown, .UNRECOGNIZED(_):
+            return context.eventLoop.makeFailedFuture(HomeKitServiceError(code: .invalidArgument, message: "Invalid value for object type"))
+        }
+        guard let renamed = object, let update = updateName else {
+            return context.eventLoop.makeFailedFuture(error)
+        }
+
         let promise = context.eventLoop.makePromise(of: Org_Hkserver_SetNameResponse.self)
+        update({ error in
+            if let error = error {
+                promise.fail(HomeKitServiceError(other: error))
+                return
+            }
+
+            var response = Org_Hkserver_SetNameResponse()
+            response.home = HomeKitServiceProvider.nameUuidPair(obj: home)
+            response.object = HomeKitServiceProvider.nameUuidPair(obj: renamed)
+            promise.succeed(response)
+        })
         return promise.futureResult
     }
 
@@ -913,6 +1550,44 @@ class HomeKitServiceProvider : Org_Hkserver_HomeKitServiceProvider {
         return a
     }
 
+    /// Executes an action set and reports the outcome of each of its actions.
+    /// HomeKit reports a single error for the whole action set, so when it
+    /// fails the actions on unreachable accessories are blamed, or every
+    /// action when all accessories are reachable.
+    internal class func executeActionSet(home: HMHome, actionSet: HMActionSet, eventLoop: EventLoop) -> EventLoopFuture<[Org_Hkserver_ActionResult]> {
+        let promise = eventLoop.makePromise(of: [Org_Hkserver_ActionResult].self)
+        home.executeActionSet(actionSet, completionHandler: { error in
+            let actions = actionSet.actions.compactMap({ $0 as? HMCharacteristicWriteAction<NSCopying> })
+            let anyUnreachable = actions.contains(where: { !($0.characteristic.service?.accessory?.isReachable ?? false) })
+            promise.succeed(actions.map({ action -> Org_Hkserver_ActionResult in
+                let characteristic = action.characteristic
+                var result = Org_Hkserver_ActionResult()
+                result.uuid = (action as HMAction).uuid
+                if let service = characteristic.service {
+                    result.service = HomeKitServiceProvider.nameUuidPair(obj: service)
+                    if let accessory = service.accessory {
+                        result.accessory = HomeKitServiceProvider.nameUuidPair(obj: accessory)
+                    }
+                }
+                result.characteristic = characteristic.uuid
+                result.characteristicType = HomeKitServiceProvider.characteristicType(type: characteristic.characteristicType)
+                let format = HomeKitServiceProvider.formatFromCharacteristicTypeAndMetadata(type: result.characteristicType, metadata: characteristic.metadata)
+                if let targetValue = HomeKitServiceProvider.valueFromCharacteristicValue(format: format, value: action.targetValue) {
+                    result.targetValue = targetValue
+                }
+                let reachable = characteristic.service?.accessory?.isReachable ?? false
+                if let error = error, !reachable || !anyUnreachable {
+                    result.success = false
+                    result.error = reachable ? error.localizedDescription : "Accessory is not reachable"
+                } else {
+                    result.success = true
+                }
+                return result
+            }))
+        })
+        return promise.futureResult
+    }
+
     internal class func genericActionInformation(action: HMAction) -> Org_Hkserver_ActionSetInformation.Action {
         var ga = Org_Hkserver_ActionSetInformation.GenericAction()
         ga.uuid = action.uuid
@@ -942,6 +1617,9 @@ class HomeKitServiceProvider : Org_Hkserver_HomeKitServiceProvider {
                 eti.recurrences = recurrences.map { weekday(from: $0) }
             }
             eti.executesOnce = event.executeOnce
+            if let predicate = event.predicate {
+                eti.predicate = predicateInformation(predicate: predicate)
+            }
             ti.event = eti
         } else if let timer = trigger as? HMTimerTrigger {
             var tti = Org_Hkserver_TimerTriggerInformation()
@@ -951,11 +1629,379 @@ class HomeKitServiceProvider : Org_Hkserver_HomeKitServiceProvider {
                 let next = calendar.date(byAdding: recurrence, to: timer.fireDate)
                 tti.recurrence = UInt64(next?.timeIntervalSince(timer.fireDate) ?? 0.0)
             }
+            if let timeZone = timer.timeZone {
+                tti.timeZone = timeZone.identifier
+            }
+            if let recurrence = timer.recurrence {
+                tti.recurrenceComponents = HomeKitServiceProvider.timerRecurrence(components: recurrence)
+            }
             ti.timer = tti
         }
         return ti
     }
 
+    internal class func timerRecurrence(components: DateComponents) -> Org_Hkserver_TimerRecurrence {
+        var recurrence = Org_Hkserver_TimerRecurrence()
+        if let weeks = components.weekOfYear, weeks != 0 {
+            recurrence.unit = .week
+            recurrence.interval = UInt32(weeks)
+        } else if let days = components.day, days != 0 {
+            recurrence.unit = .day
+            recurrence.interval = UInt32(days)
+        } else if let hours = components.hour, hours != 0 {
+            recurrence.unit = .hour
+            recurrence.interval = UInt32(hours)
+        } else if let minutes = components.minute, minutes != 0 {
+            recurrence.unit = .minute
+            recurrence.interval = UInt32(minutes)
+        }
+        return recurrence
+    }
+
+    internal class func timerTrigger(name: String, definition: Org_Hkserver_TimerTriggerDefinition) -> Result<HMTimerTrigger, HomeKitServiceError> {
+        // HomeKit rejects fire dates with seconds
+        guard definition.fireDate != 0, definition.fireDate % 60 == 0 else {
+            return .failure(HomeKitServiceError(code: .invalidArgument, message: "Timer triggers can only fire on whole minutes"))
+        }
+        var timeZone: TimeZone? = nil
+        if definition.timeZone.count != 0 {
+            guard let zone = TimeZone(identifier: definition.timeZone) else {
+                return .failure(HomeKitServiceError(code: .invalidArgument, message: "Unknown time zone \(definition.timeZone)"))
+            }
+            timeZone = zone
+        }
+        var recurrence: DateComponents? = nil
+        if definition.hasRecurrence {
+            let interval = Int(definition.recurrence.interval)
+            guard interval != 0 else {
+                return .failure(HomeKitServiceError(code: .invalidArgument, message: "Recurrence interval must be at least 1"))
+            }
+            var components = DateComponents()
+            switch definition.recurrence.unit {
+            case .minute:
+                components.minute = interval
+            case .hour:
+                components.hour = interval
+            case .day:
+                components.day = interval
+            case .week:
+                components.weekOfYear = interval
+            case .invalidUnit, .UNRECOGNIZED(_):
+                return .failure(HomeKitServiceError(code: .invalidArgument, message: "Invalid recurrence unit"))
+            }
+            recurrence = components
+        }
+        let fireDate = Date(timeIntervalSince1970: TimeInterval(definition.fireDate))
+        return .success(HMTimerTrigger(name: name, fireDate: fireDate, timeZone: timeZone, recurrence: recurrence, recurrenceCalendar: nil))
+    }
+
+    // Converts the events of an event trigger definition to HomeKit events, checking them against the home
+    internal class func eventTriggerEvents(home: HMHome, definition: Org_Hkserver_EventTriggerDefinition) -> Result<([HMEvent], [HMEvent], [DateComponents]?), HomeKitServiceError> {
+        guard definition.events.count != 0 else {
+            return .failure(HomeKitServiceError(code: .invalidArgument, message: "Event triggers need at least one event"))
+        }
+        var events: [HMEvent] = []
+        for e in definition.events {
+            switch event(home: home, event: e, isEndEvent: false) {
+            case .success(let event):
+                events.append(event)
+            case .failure(let error):
+                return .failure(error)
+            }
+        }
+        var endEvents: [HMEvent] = []
+        for e in definition.endEvents {
+            switch event(home: home, event: e, isEndEvent: true) {
+            case .success(let event):
+                endEvents.append(event)
+            case .failure(let error):
+                return .failure(error)
+            }
+        }
+        var recurrences: [DateComponents] = []
+        for weekday in definition.recurrences {
+            guard let index = Weekdays.firstIndex(of: weekday) else {
+                return .failure(HomeKitServiceError(code: .invalidArgument, message: "Invalid weekday \(weekday)"))
+            }
+            var components = DateComponents()
+            components.weekday = index + 1
+            recurrences.append(components)
+        }
+        return .success((events, endEvents, recurrences.count == 0 ? nil : recurrences))
+    }
+
+    internal class func eventTriggerPredicate(home: HMHome, definition: Org_Hkserver_EventTriggerDefinition) -> Result<NSPredicate?, HomeKitServiceError> {
+        guard definition.hasPredicate else {
+            return .success(nil)
+        }
+        return predicate(home: home, predicate: definition.predicate).map { $0 as NSPredicate? }
+    }
+
+    // Builds the NSPredicate for a predicate tree with the HMEventTrigger helpers, so HomeKit can evaluate it
+    internal class func predicate(home: HMHome, predicate: Org_Hkserver_PredicateInformation) -> Result<NSPredicate, HomeKitServiceError> {
+        let invalid = { (message: String) in Result<NSPredicate, HomeKitServiceError>.failure(HomeKitServiceError(code: .invalidArgument, message: message)) }
+        let timeOfDay = { (time: Org_Hkserver_TimeOfDay) -> DateComponents in
+            var components = DateComponents()
+            components.hour = Int(time.hour)
+            components.minute = Int(time.minute)
+            return components
+        }
+
+        switch predicate.predicate {
+        case .characteristic(let p)?:
+            let characteristics = home.accessories.flatMap { $0.services }.flatMap { $0.characteristics }
+            guard let characteristic = characteristics.first(where: { $0.uuid == p.characteristic.uuid }) else {
+                return .failure(HomeKitServiceError.notFound(objectType: "characteristic", pattern: p.characteristic.uuid))
+            }
+            guard characteristic.properties.contains(HMCharacteristicPropertyReadable) else {
+                return invalid("Characteristic \(characteristic.uuid) is not readable")
+            }
+            let comparison: NSComparisonPredicate.Operator
+            switch p.comparison {
+            case .equal:
+                comparison = .equalTo
+            case .notEqual:
+                comparison = .notEqualTo
+            case .lessThan:
+                comparison = .lessThan
+            case .lessThanOrEqual:
+                comparison = .lessThanOrEqualTo
+            case .greaterThan:
+                comparison = .greaterThan
+            case .greaterThanOrEqual:
+                comparison = .greaterThanOrEqualTo
+            default:
+                return invalid("Invalid comparison operator \(p.comparison)")
+            }
+            switch characteristicValueFromValue(characteristic: characteristic, value: p.value) {
+            case .success(let value):
+                return .success(HMEventTrigger.predicateForEvaluatingTrigger(characteristic, relatedBy: comparison, toValue: value))
+            case .failure(let error):
+                return .failure(error)
+            }
+        case .significantTime(let p)?:
+            let significantEvent: HMSignificantEvent
+            switch p.significantEvent {
+            case .sunrise:
+                significantEvent = .sunrise
+            case .sunset:
+                significantEvent = .sunset
+            default:
+                return invalid("Invalid significant event")
+            }
+            var offset: DateComponents? = nil
+            if p.offset != 0 {
+                var components = DateComponents()
+                components.second = Int(p.offset)
+                offset = components
+            }
+            let event = HMSignificantTimeEvent(significantEvent: significantEvent, offset: offset)
+            switch p.relation {
+            case .before:
+                return .success(HMEventTrigger.predicateForEvaluatingTriggerOccurringBeforeSignificantEvent(event))
+            case .after:
+                return .success(HMEventTrigger.predicateForEvaluatingTriggerOccurringAfterSignificantEvent(event))
+            default:
+                return invalid("Invalid relation")
+            }
+        case .timeOfDay(let p)?:
+            for time in [p.after, p.before] where time.hour > 23 || time.minute > 59 {
+                return invalid("Invalid time of day \(time.hour):\(time.minute)")
+            }
+            switch (p.hasAfter, p.hasBefore) {
+            case (true, true):
+                return .success(HMEventTrigger.predicateForEvaluatingTriggerOccurringBetweenDate(with: timeOfDay(p.after), with: timeOfDay(p.before)))
+            case (true, false):
+                return .success(HMEventTrigger.predicateForEvaluatingTriggerOccurringAfterDate(with: timeOfDay(p.after)))
+            case (false, true):
+                return .success(HMEventTrigger.predicateForEvaluatingTriggerOccurringBeforeDate(with: timeOfDay(p.before)))
+            case (false, false):
+                return invalid("A time of day predicate needs a start, an end or both")
+            }
+        case .presence(let p)?:
+            // atHome and notAtHome are the first entry and last exit event types
+            let presenceEventType: HMPresenceEventType
+            switch p.presenceEvent {
+            case .firstEntry:
+                presenceEventType = .atHome
+            case .lastExit:
+                presenceEventType = .notAtHome
+            default:
+                return invalid("Invalid presence: expected first entry (at home) or last exit (not at home)")
+            }
+            let presenceUserType: HMPresenceEventUserType
+            switch p.presenceUser {
+            case .currentUser:
+                presenceUserType = .currentUser
+            case .homeUsers:
+                presenceUserType = .homeUsers
+            default:
+                return invalid("Invalid presence user")
+            }
+            let event = HMPresenceEvent(presenceEventType: presenceEventType, presenceUserType: presenceUserType)
+            return .success(HMEventTrigger.predicateForEvaluatingTrigger(withPresence: event))
+        case .compound(let p)?:
+            var subpredicates: [NSPredicate] = []
+            for subpredicate in p.predicates {
+                switch self.predicate(home: home, predicate: subpredicate) {
+                case .success(let s):
+                    subpredicates.append(s)
+                case .failure(let error):
+                    return .failure(error)
+                }
+            }
+            switch p.kind {
+            case .and where subpredicates.count != 0:
+                return .success(NSCompoundPredicate(andPredicateWithSubpredicates: subpredicates))
+            case .or where subpredicates.count != 0:
+                return .success(NSCompoundPredicate(orPredicateWithSubpredicates: subpredicates))
+            case .not where subpredicates.count == 1:
+                return .success(NSCompoundPredicate(notPredicateWithSubpredicate: subpredicates[0]))
+            case .not:
+                return invalid("A not predicate takes exactly one predicate")
+            case .and, .or:
+                return invalid("A compound predicate needs at least one predicate")
+            default:
+                return invalid("Invalid compound predicate kind")
+            }
+        case .format?:
+            return invalid("Predicates given as a format cannot be used in definitions")
+        case nil:
+            return invalid("Missing predicate")
+        }
+    }
+
+    internal class func event(home: HMHome, event: Org_Hkserver_EventInformation, isEndEvent: Bool) -> Result<HMEvent, HomeKitServiceError> {
+        let invalid = { (message: String) in Result<HMEvent, HomeKitServiceError>.failure(HomeKitServiceError(code: .invalidArgument, message: message)) }
+        let characteristics = home.accessories.flatMap { $0.services }.flatMap { $0.characteristics }
+        let eventCharacteristic = { (pair: Org_Hkserver_NameUuidPair) -> Result<HMCharacteristic, HomeKitServiceError> in
+            guard let characteristic = characteristics.first(where: { $0.uuid == pair.uuid }) else {
+                return .failure(HomeKitServiceError.notFound(objectType: "characteristic", pattern: pair.uuid))
+            }
+            guard characteristic.properties.contains(HMCharacteristicPropertySupportsEventNotification) else {
+                return .failure(HomeKitServiceError(code: .invalidArgument, message: "Characteristic \(characteristic.uuid) does not support events"))
+            }
+            return .success(characteristic)
+        }
+
+        switch event.event {
+        case .characteristicEvent(let e)?:
+            let characteristic: HMCharacteristic
+            switch eventCharacteristic(e.characteristic) {
+            case .success(let c):
+                characteristic = c
+            case .failure(let error):
+                return .failure(error)
+            }
+            var triggerValue: NSCopying? = nil
+            if e.hasTriggerValue {
+                switch characteristicValueFromValue(characteristic: characteristic, value: e.triggerValue) {
+                case .success(let value):
+                    triggerValue = value as? NSCopying
+                case .failure(let error):
+                    return .failure(error)
+                }
+            }
+            return .success(HMCharacteristicEvent(characteristic: characteristic, triggerValue: triggerValue))
+        case .characteristicThresholdRangeEvent(let e)?:
+            let characteristic: HMCharacteristic
+            switch eventCharacteristic(e.characteristic) {
+            case .success(let c):
+                characteristic = c
+            case .failure(let error):
+                return .failure(error)
+            }
+            let minValue = e.range.hasMinValue ? doubleFromNumber(number: e.range.minValue).map { NSNumber(value: $0) } : nil
+            let maxValue = e.range.hasMaxValue ? doubleFromNumber(number: e.range.maxValue).map { NSNumber(value: $0) } : nil
+            let range: HMNumberRange
+            switch (minValue, maxValue) {
+            case (let min?, let max?):
+                guard min.doubleValue <= max.doubleValue else {
+                    return invalid("Threshold minimum \(min) is above the maximum \(max)")
+                }
+                range = HMNumberRange(minValue: min, maxValue: max)
+            case (let min?, nil):
+                range = HMNumberRange(minValue: min)
+            case (nil, let max?):
+                range = HMNumberRange(maxValue: max)
+            case (nil, nil):
+                return invalid("A threshold range needs a minimum or a maximum")
+            }
+            return .success(HMCharacteristicThresholdRangeEvent(characteristic: characteristic, thresholdRange: range))
+        case .significantTimeEvent(let e)?:
+            let significantEvent: HMSignificantEvent
+            switch e.significantEvent {
+            case .sunrise:
+                significantEvent = .sunrise
+            case .sunset:
+                significantEvent = .sunset
+            default:
+                return invalid("Invalid significant event")
+            }
+            var offset: DateComponents? = nil
+            if e.offset != 0 {
+                var components = DateComponents()
+                components.second = Int(e.offset)
+                offset = components
+            }
+            return .success(HMSignificantTimeEvent(significantEvent: significantEvent, offset: offset))
+        case .durationEvent(let e)?:
+            // HomeKit only ends triggers after a duration, it cannot start them
+            guard isEndEvent else {
+                return invalid("Duration events can only be end events")
+            }
+            guard e.duration > 0 else {
+                return invalid("Invalid duration \(e.duration)")
+            }
+            return .success(HMDurationEvent(duration: e.duration))
+        case .presenceEvent(let e)?:
+            let presenceEventType: HMPresenceEventType
+            switch e.presenceEvent {
+            case .everyEntry:
+                presenceEventType = .everyEntry
+            case .everyExit:
+                presenceEventType = .everyExit
+            case .firstEntry:
+                presenceEventType = .firstEntry
+            case .lastExit:
+                presenceEventType = .lastExit
+            default:
+                return invalid("Invalid presence event")
+            }
+            let presenceUserType: HMPresenceEventUserType
+            switch e.presenceUser {
+            case .currentUser:
+                presenceUserType = .currentUser
+            case .homeUsers:
+                presenceUserType = .homeUsers
+            default:
+                return invalid("Invalid presence user")
+            }
+            return .success(HMPresenceEvent(presenceEventType: presenceEventType, presenceUserType: presenceUserType))
+        case .locationEvent(let e)?:
+            guard e.hasRegion, e.region.hasCenter, e.region.radius > 0 else {
+                return invalid("A location event needs a region with a center and a positive radius")
+            }
+            guard e.notifyOnEntry || e.notifyOnExit else {
+                return invalid("A location event needs to notify on entry, exit or both")
+            }
+            let center = CLLocationCoordinate2D(latitude: e.region.center.latitude, longitude: e.region.center.longitude)
+            let region = CLCircularRegion(center: center, radius: e.region.radius, identifier: UUID().uuidString)
+            region.notifyOnEntry = e.notifyOnEntry
+            region.notifyOnExit = e.notifyOnExit
+            return .success(HMLocationEvent(region: region))
+        case .calendarEvent(let e)?:
+            guard e.fireDate != 0 else {
+                return invalid("A calendar event needs a fire date")
+            }
+            let fireDate = Date(timeIntervalSince1970: TimeInterval(e.fireDate))
+            let components = Calendar.current.dateComponents([.year, .month, .day, .hour, .minute], from: fireDate)
+            return .success(HMCalendarEvent(fire: components))
+        case nil:
+            return invalid("Missing event")
+        }
+    }
+
     internal class func nameUuidPair(obj: NameOrUuidFilterable) -> Org_Hkserver_NameUuidPair {
         var nup = Org_Hkserver_NameUuidPair()
         nup.name = obj.filterableName ?? ""
@@ -1074,7 +2120,9 @@ class HomeKitServiceProvider : Org_Hkserver_HomeKitServiceProvider {
         }
         if let value = characteristic.value {
             let format = formatFromCharacteristicTypeAndMetadata(type: ci.characteristicType, metadata: characteristic.metadata)
-            ci.value = valueFromCharacteristicValue(format: format, value: value)!
+            if let v = valueFromCharacteristicValue(format: format, value: value) {
+                ci.value = v
+            }
         }
         return ci
     }
@@ -1317,14 +2365,206 @@ class HomeKitServiceProvider : Org_Hkserver_HomeKitServiceProvider {
         case .array: // = 5
             fallthrough
         case .dictionary: // = 6
-            // TODO: figure out arrays and dictionaries
+            return valueFromObject(value: value)
+        default:
             return nil
+        }
+        return v
+    }
+
+    // Array and dictionary elements carry no format, so each is converted according to its own type
+    internal class func valueFromObject(value: Any) -> Org_Hkserver_Value? {
+        var v = Org_Hkserver_Value()
+        switch value {
+        case let number as NSNumber:
+            var n = Org_Hkserver_Number()
+            if CFGetTypeID(number) == CFBooleanGetTypeID() {
+                v.boolValue = number.boolValue
+                return v
+            } else if CFNumberIsFloatType(number) {
+                n.doubleValue = number.doubleValue
+            } else {
+                n.signedIntegerValue = number.int64Value
+            }
+            v.numberValue = n
+        case let string as String:
+            v.stringValue = string
+        case let data as Data:
+            v.dataValue = data
+        case let array as [Any]:
+            var list = Org_Hkserver_ListValue()
+            list.values = array.compactMap { valueFromObject(value: $0) }
+            v.listValue = list
+        case let dictionary as [String: Any]:
+            var map = Org_Hkserver_MapValue()
+            map.entries = dictionary.compactMapValues { valueFromObject(value: $0) }
+            v.mapValue = map
         default:
             return nil
         }
         return v
     }
+
+    // The inverse of valueFromObject, for writing array and dictionary values
+    internal class func objectFromValue(value: Org_Hkserver_Value) -> Result<Any, HomeKitServiceError> {
+        switch value.value {
+        case .boolValue(let b):
+            return .success(NSNumber(value: b))
+        case .stringValue(let s):
+            return .success(s)
+        case .numberValue(let n):
+            switch n.value {
+            case .signedIntegerValue(let i): return .success(NSNumber(value: i))
+            case .unsignedIntegerValue(let u): return .success(NSNumber(value: u))
+            case .floatValue(let f): return .success(NSNumber(value: f))
+            case .doubleValue(let d): return .success(NSNumber(value: d))
+            case .none: return .failure(HomeKitServiceError(code: .invalidArgument, message: "Missing number value"))
+            }
+        case .dataValue(let d):
+            return .success(d)
+        case .listValue(let l):
+            var array: [Any] = []
+            for element in l.values {
+                switch objectFromValue(value: element) {
+                case .success(let object): array.append(object)
+                case .failure(let error): return .failure(error)
+                }
+            }
+            return .success(array as NSArray)
+        case .mapValue(let m):
+            var dictionary: [String: Any] = [:]
+            for (key, element) in m.entries {
+                switch objectFromValue(value: element) {
+                case .success(let object): dictionary[key] = object
+                case .failure(let error): return .failure(error)
+                }
+            }
+            return .success(dictionary as NSDictionary)
+        case .none:
+            return .failure(HomeKitServiceError(code: .invalidArgument, message: "Missing value in array or dictionary"))
+        }
+    }
     
+    internal class func findCharacteristic(home: HMHome, accessory: String, service: String, characteristic: String, characteristicType: Org_Hkserver_CharacteristicInformation.CharacteristicType) -> Result<(HMAccessory, HMService, HMCharacteristic), HomeKitServiceError> {
+        if characteristic.count == 0 && characteristicType == .invalidCharacteristicType {
+            return .failure(HomeKitServiceError(code: .invalidArgument, message: "A characteristic or characteristic type is required"))
+        }
+
+        var matches: [(HMAccessory, HMService, HMCharacteristic)] = []
+        for a in home.accessories where accessory.count == 0 || a.matchesExactly(nameOrUuid: accessory) {
+            for s in a.services where service.count == 0 || s.matchesExactly(nameOrUuid: service) {
+                for c in s.characteristics {
+                    let found = characteristic.count != 0
+                        ? c.uuid == characteristic || c.localizedDescription == characteristic
+                        : HomeKitServiceProvider.characteristicType(type: c.characteristicType) == characteristicType
+                    if found {
+                        matches.append((a, s, c))
+                    }
+                }
+            }
+        }
+
+        var address = [accessory, service].filter { $0.count != 0 }
+        address.append(characteristic.count != 0 ? characteristic : "\(characteristicType)")
+        let pattern = address.joined(separator: "/")
+        switch matches.count {
+        case 0:
+            return .failure(HomeKitServiceError.notFound(objectType: "characteristic", pattern: pattern))
+        case 1:
+            return .success(matches[0])
+        default:
+            return .failure(HomeKitServiceError(code: .invalidArgument, message: "'\(pattern)' matches \(matches.count) characteristics. Specify an accessory or service"))
+        }
+    }
+
+    // Builds a write action from its definition, checking the target value the same way a write would be checked
+    internal class func characteristicWriteAction(home: HMHome, definition: Org_Hkserver_ActionDefinition) -> Result<HMCharacteristicWriteAction<NSCopying>, HomeKitServiceError> {
+        guard case let .characteristicAction(action)? = definition.actionDefinition else {
+            return .failure(HomeKitServiceError(code: .invalidArgument, message: "Missing action definition"))
+        }
+        guard action.hasTargetValue else {
+            return .failure(HomeKitServiceError(code: .invalidArgument, message: "Missing target value"))
+        }
+
+        let characteristics = home.accessories.flatMap { $0.services }.flatMap { $0.characteristics }
+        guard let characteristic = characteristics.first(where: { $0.uuid == action.characteristic }) else {
+            return .failure(HomeKitServiceError.notFound(objectType: "characteristic", pattern: action.characteristic))
+        }
+        guard characteristic.properties.contains(HMCharacteristicPropertyWritable) else {
+            return .failure(HomeKitServiceError(code: .invalidArgument, message: "Characteristic \(characteristic.uuid) is not writable"))
+        }
+
+        switch characteristicValueFromValue(characteristic: characteristic, value: action.targetValue) {
+        case .success(let value):
+            guard let targetValue = value as? NSCopying else {
+                return .failure(HomeKitServiceError.unexpected)
+            }
+            return .success(HMCharacteristicWriteAction(characteristic: characteristic, targetValue: targetValue))
+        case .failure(let error):
+            return .failure(error)
+        }
+    }
+
+    internal class func doubleFromNumber(number: Org_Hkserver_Number) -> Double? {
+        switch number.value {
+        case .signedIntegerValue(let i): return Double(i)
+        case .unsignedIntegerValue(let u): return Double(u)
+        case .floatValue(let f): return Double(f)
+        case .doubleValue(let d): return d
+        case .none: return nil
+        }
+    }
+
+    // Converts a proto value to the object HomeKit expects for the characteristic, checking it against the characteristic's metadata
+    internal class func characteristicValueFromValue(characteristic: HMCharacteristic, value: Org_Hkserver_Value) -> Result<Any, HomeKitServiceError> {
+        let invalid = { (message: String) in Result<Any, HomeKitServiceError>.failure(HomeKitServiceError(code: .invalidArgument, message: message)) }
+        let type = characteristicType(type: characteristic.characteristicType)
+        let format = formatFromCharacteristicTypeAndMetadata(type: type, metadata: characteristic.metadata)
+        let metadata = characteristic.metadata
+
+        switch (format, value.value) {
+        case (.bool, .boolValue(let b)):
+            return .success(NSNumber(value: b))
+        case (.string, .stringValue(let s)):
+            if let maxLength = metadata?.maxLength, s.count > maxLength.intValue {
+                return invalid("Length \(s.count) exceeds the maximum length \(maxLength)")
+            }
+            return .success(s)
+        case (.data, .dataValue(let d)), (.tlv8, .dataValue(let d)):
+            if let maxLength = metadata?.maxLength, d.count > maxLength.intValue {
+                return invalid("Length \(d.count) exceeds the maximum length \(maxLength)")
+            }
+            return .success(d)
+        case (.int, .numberValue(let n)), (.float, .numberValue(let n)), (.uint8, .numberValue(let n)), (.uint16, .numberValue(let n)), (.uint32, .numberValue(let n)), (.uint64, .numberValue(let n)):
+            guard let d = doubleFromNumber(number: n) else {
+                return invalid("Missing number value")
+            }
+            if format != .float && d.rounded() != d {
+                return invalid("\(d) is not a valid \(format) value")
+            }
+            if let minimum = metadata?.minimumValue, d < minimum.doubleValue {
+                return invalid("\(d) is less than the minimum value \(minimum)")
+            }
+            if let maximum = metadata?.maximumValue, d > maximum.doubleValue {
+                return invalid("\(d) is greater than the maximum value \(maximum)")
+            }
+            if let step = metadata?.stepValue, step.doubleValue > 0 {
+                let steps = (d - (metadata?.minimumValue?.doubleValue ?? 0)) / step.doubleValue
+                if abs(steps - steps.rounded()) > 1e-6 {
+                    return invalid("\(d) is not a multiple of the step value \(step)")
+                }
+            }
+            if let validValues = metadata?.validValues, validValues.count != 0, !validValues.contains(where: { $0.doubleValue == d }) {
+                return invalid("\(d) is not one of the valid values: \(validValues.map { $0.stringValue }.joined(separator: ", "))")
+            }
+            return .success(format == .float ? NSNumber(value: d) : NSNumber(value: Int64(d)))
+        case (.array, .listValue(_)), (.dictionary, .mapValue(_)):
+            return objectFromValue(value: value)
+        default:
+            return invalid("Value does not match characteristic format \(format)")
+        }
+    }
+
     internal class func formatFromCharacteristicTypeAndMetadata(type: Org_Hkserver_CharacteristicInformation.CharacteristicType, metadata: HMCharacteristicMetadata?) -> Org_Hkserver_CharacteristicInformation.Format {
         if let format = metadata?.format {
             return formatFromMetadataFormat(format: format)
@@ -1668,8 +2908,7 @@ class HomeKitServiceProvider : Org_Hkserver_HomeKitServiceProvider {
             ste.uuid = event.uuid
             ste.significantEvent = significantEvent(significantEvent: event.significantEvent)
             if let offset = event.offset {
-                let calendar = offset.calendar ?? Calendar.current
-                ste.offset = UInt64(calendar.date(from: offset)?.timeIntervalSince1970 ?? 0.0)
+                ste.offset = Int64((offset.hour ?? 0) * 3600 + (offset.minute ?? 0) * 60 + (offset.second ?? 0))
             }
             ei.significantTimeEvent = ste
         } else if let event = event as? HMDurationEvent {
@@ -1704,6 +2943,143 @@ class HomeKitServiceProvider : Org_Hkserver_HomeKitServiceProvider {
         return ei
     }
     
+    // Recognizes the predicates built by the HMEventTrigger helpers from the constants they compare against,
+    // reporting anything else by its format
+    internal class func predicateInformation(predicate: NSPredicate) -> Org_Hkserver_PredicateInformation {
+        var pi = Org_Hkserver_PredicateInformation()
+        if let compound = predicate as? NSCompoundPredicate {
+            if let characteristic = characteristicPredicate(compound: compound) {
+                pi.characteristic = characteristic
+                return pi
+            }
+            var cp = Org_Hkserver_CompoundPredicate()
+            switch compound.compoundPredicateType {
+            case .and:
+                cp.kind = .and
+            case .or:
+                cp.kind = .or
+            case .not:
+                cp.kind = .not
+            @unknown default:
+                pi.format = predicate.predicateFormat
+                return pi
+            }
+            cp.predicates = compound.subpredicates.compactMap { $0 as? NSPredicate }.map { predicateInformation(predicate: $0) }
+            pi.compound = cp
+            return pi
+        }
+        guard let comparison = predicate as? NSComparisonPredicate, let (constant, comparisonOperator) = constantComparison(comparison: comparison) else {
+            pi.format = predicate.predicateFormat
+            return pi
+        }
+        let timeOfDay = { (components: DateComponents) -> Org_Hkserver_TimeOfDay in
+            var time = Org_Hkserver_TimeOfDay()
+            time.hour = UInt32(components.hour ?? 0)
+            time.minute = UInt32(components.minute ?? 0)
+            return time
+        }
+        switch (constant, comparisonOperator) {
+        case (let event as HMSignificantTimeEvent, .lessThan), (let event as HMSignificantTimeEvent, .lessThanOrEqualTo),
+             (let event as HMSignificantTimeEvent, .greaterThan), (let event as HMSignificantTimeEvent, .greaterThanOrEqualTo):
+            var stp = Org_Hkserver_SignificantTimePredicate()
+            stp.relation = (comparisonOperator == .lessThan || comparisonOperator == .lessThanOrEqualTo) ? .before : .after
+            stp.significantEvent = significantEvent(significantEvent: event.significantEvent)
+            if let offset = event.offset {
+                stp.offset = Int64((offset.hour ?? 0) * 3600 + (offset.minute ?? 0) * 60 + (offset.second ?? 0))
+            }
+            pi.significantTime = stp
+        case (let components as DateComponents, .lessThan), (let components as DateComponents, .lessThanOrEqualTo):
+            var todp = Org_Hkserver_TimeOfDayPredicate()
+            todp.before = timeOfDay(components)
+            pi.timeOfDay = todp
+        case (let components as DateComponents, .greaterThan), (let components as DateComponents, .greaterThanOrEqualTo):
+            var todp = Org_Hkserver_TimeOfDayPredicate()
+            todp.after = timeOfDay(components)
+            pi.timeOfDay = todp
+        case (let window as [DateComponents], .between) where window.count == 2:
+            var todp = Org_Hkserver_TimeOfDayPredicate()
+            todp.after = timeOfDay(window[0])
+            todp.before = timeOfDay(window[1])
+            pi.timeOfDay = todp
+        case (let event as HMPresenceEvent, _):
+            var pp = Org_Hkserver_PresencePredicate()
+            pp.presenceEvent = presenceEventType(presenceEventType: event.presenceEventType)
+            pp.presenceUser = presenceEventUserType(presenceEventUserType: event.presenceUserType)
+            pi.presence = pp
+        default:
+            pi.format = predicate.predicateFormat
+        }
+        return pi
+    }
+
+    // The constant a comparison tests against, with the operator as if the constant were on the right
+    internal class func constantComparison(comparison: NSComparisonPredicate) -> (Any, NSComparisonPredicate.Operator)? {
+        if comparison.rightExpression.expressionType == .constantValue, let constant = comparison.rightExpression.constantValue {
+            return (constant, comparison.predicateOperatorType)
+        }
+        guard comparison.leftExpression.expressionType == .constantValue, let constant = comparison.leftExpression.constantValue else {
+            return nil
+        }
+        switch comparison.predicateOperatorType {
+        case .lessThan:
+            return (constant, .greaterThan)
+        case .lessThanOrEqualTo:
+            return (constant, .greaterThanOrEqualTo)
+        case .greaterThan:
+            return (constant, .lessThan)
+        case .greaterThanOrEqualTo:
+            return (constant, .lessThanOrEqualTo)
+        default:
+            return (constant, comparison.predicateOperatorType)
+        }
+    }
+
+    // HomeKit expresses a characteristic comparison as the conjunction of the characteristic's type, its service
+    // and the comparison of its value
+    internal class func characteristicPredicate(compound: NSCompoundPredicate) -> Org_Hkserver_CharacteristicPredicate? {
+        guard compound.compoundPredicateType == .and else {
+            return nil
+        }
+        let comparisons = compound.subpredicates.compactMap { $0 as? NSComparisonPredicate }
+        let keyPath = { (comparison: NSComparisonPredicate) -> String? in
+            comparison.leftExpression.expressionType == .keyPath ? comparison.leftExpression.keyPath : nil
+        }
+        guard comparisons.count == compound.subpredicates.count,
+              let typeComparison = comparisons.first(where: { keyPath($0) == "characteristic.characteristicType" }),
+              let serviceComparison = comparisons.first(where: { keyPath($0) == "characteristic.service" }),
+              let valueComparison = comparisons.first(where: { keyPath($0) == "characteristic.value" }),
+              let type = typeComparison.rightExpression.constantValue as? String,
+              let service = serviceComparison.rightExpression.constantValue as? HMService,
+              let characteristic = service.characteristics.first(where: { $0.characteristicType == type }),
+              let value = valueComparison.rightExpression.constantValue else {
+            return nil
+        }
+        var cp = Org_Hkserver_CharacteristicPredicate()
+        switch valueComparison.predicateOperatorType {
+        case .equalTo:
+            cp.comparison = .equal
+        case .notEqualTo:
+            cp.comparison = .notEqual
+        case .lessThan:
+            cp.comparison = .lessThan
+        case .lessThanOrEqualTo:
+            cp.comparison = .lessThanOrEqual
+        case .greaterThan:
+            cp.comparison = .greaterThan
+        case .greaterThanOrEqualTo:
+            cp.comparison = .greaterThanOrEqual
+        default:
+            return nil
+        }
+        let format = formatFromCharacteristicTypeAndMetadata(type: characteristicType(type: characteristic.characteristicType), metadata: characteristic.metadata)
+        guard let v = valueFromCharacteristicValue(format: format, value: value) else {
+            return nil
+        }
+        cp.characteristic = nameUuidPair(obj: characteristic)
+        cp.value = v
+        return cp
+    }
+
     internal class func regionInformation(region: CLRegion?) -> Org_Hkserver_CircularRegion? {
         guard let region = region as? CLCircularRegion else {
             return nil
@@ -1,9 +1,134 @@
 use build_deps;
+use prost::Message;
+use prost_types::{DescriptorProto, EnumDescriptorProto, FileDescriptorSet};
+use std::path::Path;
+use std::process::Command;
 use tonic_build;
 
+const PROTOS: &[&str] = &["../protos/hkserver.proto", "../protos/grpc/health/v1/health.proto"];
+
+// Every enum, rendered by name in --output json and yaml
+const ENUMS: &[&str] = &[
+    ".org.hkserver.AccessoryInformation.Category",
+    ".org.hkserver.ActionSetInformation.ActionSetType",
+    ".org.hkserver.CharacteristicInformation.CharacteristicType",
+    ".org.hkserver.CharacteristicInformation.Format",
+    ".org.hkserver.CharacteristicInformation.Property",
+    ".org.hkserver.CharacteristicInformation.Units",
+    ".org.hkserver.ComparisonOperator",
+    ".org.hkserver.CompoundPredicate.Kind",
+    ".org.hkserver.EnumerateTriggersRequest.EnabledFilter",
+    ".org.hkserver.EventTriggerInformation.ActivationState",
+    ".org.hkserver.HomeInformation.HomeHubState",
+    ".org.hkserver.Operation",
+    ".org.hkserver.PresenceEventType",
+    ".org.hkserver.PresenceEventUserType",
+    ".org.hkserver.ServiceType",
+    ".org.hkserver.SetNameRequest.ObjectType",
+    ".org.hkserver.SignificantEvent",
+    ".org.hkserver.SignificantTimePredicate.Relation",
+    ".org.hkserver.TimerRecurrence.Unit",
+    ".org.hkserver.Weekday",
+];
+
+// Oneofs are flattened into their message, so that for example a Value
+// reads {"bool_value": true}
+const ONEOFS: &[&str] = &[
+    ".org.hkserver.Number.value",
+    ".org.hkserver.Value.value",
+    ".org.hkserver.ActionSetInformation.Action.action",
+    ".org.hkserver.EventInformation.event",
+    ".org.hkserver.PredicateInformation.predicate",
+    ".org.hkserver.TriggerInformation.trigger",
+    ".org.hkserver.ActionDefinition.action_definition",
+    ".org.hkserver.TriggerDefinition.trigger_definition",
+    ".org.hkserver.ChangeActionSetMembershipRequest.NameOrActionDefinition.action",
+    ".org.hkserver.HomeEvent.event",
+];
+
+// prost represents enum fields as i32, so the fields found in responses
+// need to be told which enum they hold
+const ENUM_FIELDS: &[(&str, &str)] = &[
+    (".org.hkserver.CharacteristicInformation.Metadata.format", "characteristic_information::Format"),
+    (".org.hkserver.CharacteristicInformation.Metadata.units", "characteristic_information::Units"),
+    (".org.hkserver.CharacteristicInformation.characteristic_type", "characteristic_information::CharacteristicType"),
+    (".org.hkserver.ServiceInformation.service_type", "ServiceType"),
+    (".org.hkserver.ServiceInformation.associated_service_type", "ServiceType"),
+    (".org.hkserver.AccessoryInformation.category", "accessory_information::Category"),
+    (".org.hkserver.ActionSetInformation.action_set_type", "action_set_information::ActionSetType"),
+    (".org.hkserver.ActionResult.characteristic_type", "characteristic_information::CharacteristicType"),
+    (".org.hkserver.SignificantTimeEventInformation.significant_event", "SignificantEvent"),
+    (".org.hkserver.PresenceEventInformation.presence_event", "PresenceEventType"),
+    (".org.hkserver.PresenceEventInformation.presence_user", "PresenceEventUserType"),
+    (".org.hkserver.CharacteristicPredicate.comparison", "ComparisonOperator"),
+    (".org.hkserver.SignificantTimePredicate.relation", "significant_time_predicate::Relation"),
+    (".org.hkserver.SignificantTimePredicate.significant_event", "SignificantEvent"),
+    (".org.hkserver.PresencePredicate.presence_event", "PresenceEventType"),
+    (".org.hkserver.PresencePredicate.presence_user", "PresenceEventUserType"),
+    (".org.hkserver.CompoundPredicate.kind", "compound_predicate::Kind"),
+    (".org.hkserver.EventTriggerInformation.activation_state", "event_trigger_information::ActivationState"),
+    (".org.hkserver.HomeInformation.hub_state", "home_information::HomeHubState"),
+    (".org.hkserver.TimerRecurrence.unit", "timer_recurrence::Unit"),
+];
+
+const REPEATED_ENUM_FIELDS: &[(&str, &str)] = &[
+    (".org.hkserver.CharacteristicInformation.properties", "characteristic_information::Property"),
+    (".org.hkserver.EventTriggerInformation.recurrences", "Weekday"),
+];
+
+/// Finds the value names of the enum with the fully qualified name `path`
+/// among `enums` and the enums nested in `messages`.
+fn enum_values(scope: &str, messages: &[DescriptorProto], enums: &[EnumDescriptorProto], path: &str) -> Option<Vec<String>> {
+    let found = enums.iter()
+        .find(|e| format!("{}.{}", scope, e.name()) == path)
+        .map(|e| e.value.iter().map(|v| v.name().to_string()).collect());
+    found.or_else(|| messages.iter().find_map(|m| {
+        let scope = format!("{}.{}", scope, m.name());
+        enum_values(&scope, &m.nested_type, &m.enum_type, path)
+    }))
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    tonic_build::compile_protos("../protos/hkserver.proto")?;
+    let descriptors = Path::new(&std::env::var("OUT_DIR")?).join("descriptors.bin");
+    let status = Command::new(prost_build::protoc())
+        .arg(format!("--descriptor_set_out={}", descriptors.display()))
+        .arg("-I../protos")
+        .args(PROTOS)
+        .status()?;
+    if !status.success() {
+        return Err(format!("protoc failed to write descriptors: {}", status).into());
+    }
+    let set = FileDescriptorSet::decode(std::fs::read(&descriptors)?.as_slice())?;
+
+    let mut builder = tonic_build::configure()
+        .type_attribute(".", "#[derive(serde::Serialize)]");
+    for path in ENUMS {
+        // prost renames values to CamelCase, which does not convert back to
+        // names such as PM2_5_DENSITY, so every value is renamed explicitly
+        let values = set.file.iter()
+            .find_map(|f| enum_values(&format!(".{}", f.package()), &f.message_type, &f.enum_type, path))
+            .ok_or_else(|| format!("No enum {} in the protos", path))?;
+        for value in values {
+            builder = builder.field_attribute(format!("{}.{}", path, value), format!("#[serde(rename = \"{}\")]", value));
+        }
+    }
+    for path in ONEOFS {
+        // Without the leading dot the field path is matched as a suffix,
+        // which keeps it from applying to the fields inside the oneof
+        builder = builder.type_attribute(path, "#[serde(rename_all = \"snake_case\")]")
+            .field_attribute(&path[1..], "#[serde(flatten)]");
+    }
+    for (path, enumeration) in ENUM_FIELDS {
+        builder = builder.field_attribute(path, format!(
+            "#[serde(serialize_with = \"crate::output::enumeration::<crate::hkservice::{}, _>\")]", enumeration));
+    }
+    for (path, enumeration) in REPEATED_ENUM_FIELDS {
+        builder = builder.field_attribute(path, format!(
+            "#[serde(serialize_with = \"crate::output::enumerations::<crate::hkservice::{}, _>\")]", enumeration));
+    }
+    builder.compile(PROTOS, &["../protos"])?;
     build_deps::rerun_if_changed_paths("../protos/hkserver.proto").unwrap();
+    build_deps::rerun_if_changed_paths("../protos/grpc/health/v1/health.proto").unwrap();
 
     // Inject build project as cfg "profile" key
     println!("cargo:rustc-cfg=profile=\"{}\"", std::env::var("PROFILE").unwrap());
@@ -14,6 +14,8 @@ use crate::hkservice::characteristic_information::Format as CharacteristicFormat
 use crate::hkservice::characteristic_information::Units as CharacteristicUnits;
 use crate::hkservice::{Number, number::Value, Value as SampledValue, value::Value as SampledValueEnum};
 use crate::services::print_service;
+use crate::output;
+use crate::table::{Column, Table};
 
 impl std::fmt::Display for Category {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
@@ -59,6 +61,17 @@ impl std::fmt::Display for SampledValue {
                 SampledValueEnum::StringValue(s) => write!(f, "{}", s),
                 SampledValueEnum::NumberValue(n) => write!(f, "{}", n),
                 SampledValueEnum::DataValue(d) => write!(f, "{{{}, b'{}'}}", d.len(), hex::encode(d)),
+                SampledValueEnum::ListValue(l) => {
+                    let values = l.values.iter().map(|v| v.to_string()).collect::<Vec<String>>();
+                    write!(f, "[{}]", values.join(", "))
+                },
+                SampledValueEnum::MapValue(m) => {
+                    // Sorted, since the entries arrive in no particular order
+                    let mut entries = m.entries.iter().collect::<Vec<_>>();
+                    entries.sort_by(|a, b| a.0.cmp(b.0));
+                    let entries = entries.iter().map(|(k, v)| format!("{}: {}", k, v)).collect::<Vec<String>>();
+                    write!(f, "{{{}}}", entries.join(", "))
+                },
             }
         } else {
             write!(f, "<None>")
@@ -81,7 +94,7 @@ impl std::fmt::Display for Number {
     }
 }
 
-fn print_response(response: &EnumerateAccessoriesResponse) {
+fn print_response(response: &EnumerateAccessoriesResponse, decode_tlv: bool) {
     println!("Accessories: ({})", response.accessories.len());
     response.accessories.iter().for_each(|accessory| {
         println!("  Accessory: {}", accessory.name);
@@ -111,7 +124,7 @@ fn print_response(response: &EnumerateAccessoriesResponse) {
         });
         println!("    Services: ({})", accessory.services.len());
         accessory.services.iter().for_each(|service| {
-            print_service(service, 6);
+            print_service(service, 6, decode_tlv);
         });
         if accessory.category() == Category::Bridge {
             println!("    Bridged Accessories: ({})", accessory.bridged_accessory_uuids.len());
@@ -122,6 +135,38 @@ fn print_response(response: &EnumerateAccessoriesResponse) {
     })
 }
 
+impl Table for EnumerateAccessoriesResponse {
+    const COLUMNS: &'static [Column] = &[
+        Column { name: "name", default: true },
+        Column { name: "uuid", default: false },
+        Column { name: "room", default: true },
+        Column { name: "category", default: true },
+        Column { name: "reachable", default: true },
+        Column { name: "blocked", default: false },
+        Column { name: "bridged", default: false },
+        Column { name: "manufacturer", default: false },
+        Column { name: "model", default: false },
+        Column { name: "firmware", default: false },
+        Column { name: "services", default: true },
+    ];
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        self.accessories.iter().map(|accessory| vec![
+            accessory.name.clone(),
+            accessory.uuid.clone(),
+            accessory.room.as_ref().map_or(String::new(), |room| room.name.clone()),
+            accessory.category().to_string(),
+            accessory.is_reachable.to_string(),
+            accessory.is_blocked.to_string(),
+            accessory.is_bridged.to_string(),
+            accessory.manufacturer.clone(),
+            accessory.model.clone(),
+            accessory.firmware_version.clone(),
+            accessory.services.iter().map(|service| service.name.as_str()).collect::<Vec<&str>>().join(", "),
+        ]).collect()
+    }
+}
+
 async fn _run(matches: ArgMatches, mut client: HomeKitServiceClient<Channel>) -> Result<(), Box<dyn std::error::Error>> {
     let response = client.enumerate_accessories(
         EnumerateAccessoriesRequest {
@@ -130,8 +175,7 @@ async fn _run(matches: ArgMatches, mut client: HomeKitServiceClient<Channel>) ->
             room_filter: matches.value_of("room").unwrap_or("").to_string(),
             name_filter: matches.value_of("name").unwrap_or("").to_string(),
     }).await?.into_inner();
-    print_response(&response);
-    Ok(())
+    output::print_listing(&matches, &response, |r| print_response(r, matches.is_present("decode_tlv")))
 }
 
 pub fn run(matches: ArgMatches, client: HomeKitServiceClient<Channel>) -> Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error>>>>> {
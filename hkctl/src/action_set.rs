@@ -0,0 +1,166 @@
+use clap::{ArgMatches};
+use simple_error::SimpleError;
+use std::boxed::Box;
+use std::future::Future;
+use std::pin::Pin;
+use std::str::FromStr;
+use tonic::transport::Channel;
+use crate::characteristic::{check_write, parse_characteristic_value, Address};
+use crate::hkservice::home_kit_service_client::HomeKitServiceClient;
+use crate::hkservice::{
+    ActionDefinition,
+    AddRemoveActionSetRequest,
+    AddRemoveActionSetResponse,
+    ChangeActionSetMembershipRequest,
+    ChangeActionSetMembershipResponse,
+    CharacteristicActionDefinition,
+    Operation,
+};
+use crate::hkservice::action_definition::ActionDefinition as ActionDefinitionEnum;
+use crate::hkservice::action_set_information::action::Action;
+use crate::hkservice::change_action_set_membership_request::NameOrActionDefinition;
+use crate::hkservice::change_action_set_membership_request::name_or_action_definition::Action as NameOrActionDefinitionEnum;
+use crate::output;
+use crate::resolve;
+
+/// A characteristic write action given as `ACCESSORY/SERVICE/CHARACTERISTIC=VALUE`.
+struct ActionSpec {
+    spec: String,
+    definition: ActionDefinition,
+}
+
+impl ActionSpec {
+    /// Resolves the characteristic and parses the value according to its
+    /// format, checking it against the characteristic's metadata.
+    async fn parse(client: &mut HomeKitServiceClient<Channel>, home: &str, spec: &str) -> Result<ActionSpec, Box<dyn std::error::Error>> {
+        let invalid = |e: &dyn std::fmt::Display| SimpleError::new(format!("Invalid action '{}': {}", spec, e));
+        let split = spec.find('=')
+            .ok_or_else(|| invalid(&"expected ACCESSORY/SERVICE/CHARACTERISTIC=VALUE"))?;
+        let address = Address::parse(home, &spec[..split]).map_err(|e| invalid(&e))?;
+        let (_, _, characteristic) = address.resolve(client).await.map_err(|e| invalid(&e))?;
+        let value = parse_characteristic_value(&characteristic, &spec[split + 1..]).map_err(|e| invalid(&e))?;
+        check_write(&characteristic, &value).map_err(|e| invalid(&e))?;
+        Ok(ActionSpec {
+            spec: spec.to_string(),
+            definition: ActionDefinition {
+                action_definition: Some(ActionDefinitionEnum::CharacteristicAction(CharacteristicActionDefinition {
+                    characteristic: characteristic.uuid,
+                    target_value: Some(value),
+                })),
+            },
+        })
+    }
+
+    async fn parse_all(client: &mut HomeKitServiceClient<Channel>, home: &str, matches: &ArgMatches) -> Result<Vec<ActionSpec>, Box<dyn std::error::Error>> {
+        let mut actions = vec![];
+        for spec in matches.values_of("action").map_or(vec![], |values| values.collect()) {
+            actions.push(ActionSpec::parse(client, home, spec).await?);
+        }
+        Ok(actions)
+    }
+}
+
+fn print_response(response: &AddRemoveActionSetResponse, actions: &[ActionSpec]) {
+    println!("Home: {}, Action Set {}", response.home.as_ref().unwrap().name, response.action_set.as_ref().unwrap().name);
+    actions.iter().for_each(|action| println!("  {}", action.spec));
+}
+
+fn print_membership_response(response: &ChangeActionSetMembershipResponse, changes: &[String]) {
+    println!("Home: {}, Action Set {}", response.home.as_ref().unwrap().name, response.action_set.as_ref().unwrap().name);
+    changes.iter().for_each(|change| println!("  {}", change));
+}
+
+async fn _create(matches: &ArgMatches, mut client: HomeKitServiceClient<Channel>) -> Result<(), Box<dyn std::error::Error>> {
+    let home = matches.value_of("home").unwrap_or("");
+    let actions = ActionSpec::parse_all(&mut client, home, matches).await?;
+    let response = client.add_remove_actions(AddRemoveActionSetRequest {
+        home: home.to_string(),
+        name: matches.value_of("name").unwrap().to_string(),
+        operation: Operation::Add as i32,
+        action_definition: actions.iter().map(|action| action.definition.clone()).collect(),
+    }).await?.into_inner();
+    output::print(matches, &response, |r| print_response(r, &actions))
+}
+
+async fn _remove(matches: &ArgMatches, mut client: HomeKitServiceClient<Channel>) -> Result<(), Box<dyn std::error::Error>> {
+    let home = matches.value_of("home").unwrap_or("");
+    // The server removes the first action set matching the pattern, so make
+    // sure it is the only one
+    let name = matches.value_of("name").unwrap();
+    let action_set = resolve::action_set(&mut client, home, name).await?;
+    if !resolve::confirm_removal(matches, "action set", name, &action_set)? {
+        return Ok(());
+    }
+    let response = client.add_remove_actions(AddRemoveActionSetRequest {
+        home: home.to_string(),
+        name: resolve::exactly(&action_set.uuid),
+        operation: Operation::Remove as i32,
+        action_definition: vec![],
+    }).await?.into_inner();
+    output::print(matches, &response, |r| print_response(r, &[]))
+}
+
+async fn _change_membership(matches: &ArgMatches, mut client: HomeKitServiceClient<Channel>) -> Result<(), Box<dyn std::error::Error>> {
+    let home = matches.value_of("home").unwrap_or("");
+    let operation = Operation::from_str(matches.value_of("operation").unwrap())?;
+    let action_set = resolve::action_set(&mut client, home, matches.value_of("action_set").unwrap()).await?;
+    let mut changes = vec![];
+    match operation {
+        Operation::Add => {
+            for action in ActionSpec::parse_all(&mut client, home, matches).await? {
+                changes.push((NameOrActionDefinitionEnum::Definition(action.definition), format!("+ {}", action.spec)));
+            }
+        },
+        // Actions are removed by UUID, or by the characteristic they write
+        Operation::Remove => {
+            for name in matches.values_of("action").map_or(vec![], |values| values.collect()) {
+                let uuid = match action_set.actions.iter().filter_map(|a| a.action.as_ref()).find(|a| action_uuid(a) == name) {
+                    Some(action) => action_uuid(action).to_string(),
+                    None => {
+                        let (_, _, characteristic) = Address::parse(home, name)?.resolve(&mut client).await?;
+                        action_set.actions.iter()
+                            .filter_map(|a| a.action.as_ref())
+                            .find(|a| match a {
+                                Action::CharacteristicAction(ca) => ca.characteristic.as_ref().map_or(false, |c| c.uuid == characteristic.uuid),
+                                Action::GenericAction(_) => false,
+                            })
+                            .map(|a| action_uuid(a).to_string())
+                            .ok_or_else(|| SimpleError::new(format!("Action set {} does not write '{}'", action_set.name, name)))?
+                    },
+                };
+                changes.push((NameOrActionDefinitionEnum::Uuid(uuid), format!("- {}", name)));
+            }
+        },
+    };
+    let mut response = ChangeActionSetMembershipResponse::default();
+    for (action, _) in changes.iter() {
+        response = client.change_action_set_membership(ChangeActionSetMembershipRequest {
+            home: home.to_string(),
+            name: action_set.uuid.clone(),
+            operation: operation as i32,
+            actions: Some(NameOrActionDefinition { action: Some(action.clone()) }),
+        }).await?.into_inner();
+    }
+    let changes = changes.into_iter().map(|(_, change)| change).collect::<Vec<String>>();
+    output::print(matches, &response, |r| print_membership_response(r, &changes))
+}
+
+fn action_uuid(action: &Action) -> &str {
+    match action {
+        Action::CharacteristicAction(ca) => &ca.uuid,
+        Action::GenericAction(ga) => &ga.uuid,
+    }
+}
+
+async fn _run(matches: ArgMatches, client: HomeKitServiceClient<Channel>) -> Result<(), Box<dyn std::error::Error>> {
+    match matches.subcommand() {
+        Some(("create", create_matches)) => _create(create_matches, client).await,
+        Some(("remove", remove_matches)) => _remove(remove_matches, client).await,
+        Some(("actions", actions_matches)) => _change_membership(actions_matches, client).await,
+        _ => Err(Box::new(SimpleError::new("Expected one of create, remove or actions"))),
+    }
+}
+
+pub fn run(matches: ArgMatches, client: HomeKitServiceClient<Channel>) -> Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error>>>>> {
+    Box::pin(_run(matches, client))
+}
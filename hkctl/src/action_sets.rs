@@ -8,6 +8,9 @@ use crate::hkservice::{EnumerateActionSetsRequest, EnumerateActionSetsResponse};
 use crate::hkservice::action_set_information::ActionSetType;
 use crate::hkservice::action_set_information::action::Action;
 use crate::services::print_characteristic;
+use crate::output;
+use crate::tlv;
+use crate::table::{Column, Table};
 
 impl std::fmt::Display for ActionSetType {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
@@ -15,7 +18,7 @@ impl std::fmt::Display for ActionSetType {
     }
 }
 
-fn print_response(response: &EnumerateActionSetsResponse) {
+fn print_response(response: &EnumerateActionSetsResponse, decode_tlv: bool) {
     if let Some(ref home) = &response.home {
         println!("Home: {}", home.name);
     }
@@ -35,8 +38,12 @@ fn print_response(response: &EnumerateActionSetsResponse) {
                     },
                     Action::CharacteristicAction(ca) => {
                         println!("        UUID: {}", ca.uuid);
+                        if let Some(ref value) = ca.target_value {
+                            let characteristic = ca.characteristic.clone().unwrap_or_default();
+                            println!("        Target Value:{}", tlv::format_value(&characteristic, value, decode_tlv, 10));
+                        }
                         if let Some(ref c) = ca.characteristic {
-                            print_characteristic(&c, 8);
+                            print_characteristic(c, 8, decode_tlv);
                         }
                     },
                 };
@@ -45,14 +52,33 @@ fn print_response(response: &EnumerateActionSetsResponse) {
     });
 }
 
+impl Table for EnumerateActionSetsResponse {
+    const COLUMNS: &'static [Column] = &[
+        Column { name: "name", default: true },
+        Column { name: "uuid", default: false },
+        Column { name: "type", default: true },
+        Column { name: "actions", default: true },
+        Column { name: "executing", default: false },
+    ];
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        self.action_sets.iter().map(|action_set| vec![
+            action_set.name.clone(),
+            action_set.uuid.clone(),
+            action_set.action_set_type().to_string(),
+            action_set.actions.len().to_string(),
+            action_set.is_executing.to_string(),
+        ]).collect()
+    }
+}
+
 async fn _run(matches: ArgMatches, mut client: HomeKitServiceClient<Channel>) -> Result<(), Box<dyn std::error::Error>> {
     let response = client.enumerate_action_sets(
         EnumerateActionSetsRequest {
             home: matches.value_of("home").unwrap_or("").to_string(),
             name_filter: matches.value_of("name").unwrap_or("").to_string(),
         }).await?.into_inner();
-    print_response(&response);
-    Ok(())
+    output::print_listing(&matches, &response, |r| print_response(r, matches.is_present("decode_tlv")))
 }
 
 pub fn run(matches: ArgMatches, client: HomeKitServiceClient<Channel>) -> Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error>>>>> {
@@ -0,0 +1,353 @@
+use clap::ArgMatches;
+use hex;
+use simple_error::{SimpleError, SimpleResult};
+use std::boxed::Box;
+use std::future::Future;
+use std::pin::Pin;
+use tonic::transport::Channel;
+use crate::hkservice::home_kit_service_client::HomeKitServiceClient;
+use crate::hkservice::{
+    CharacteristicInformation,
+    EnumerateServicesRequest,
+    NameUuidPair,
+    Number,
+    ReadCharacteristicRequest,
+    Value,
+    WriteCharacteristicRequest,
+};
+use crate::hkservice::characteristic_information::{CharacteristicType, Format, Metadata, Property};
+use crate::hkservice::number::Value as NumberValue;
+use crate::hkservice::value::Value as ValueEnum;
+use crate::output;
+use crate::tlv;
+
+/// Parses a characteristic type, ignoring case and underscores so that
+/// `PowerState`, `power_state` and `POWER_STATE` are all accepted.
+pub fn characteristictype_from_str(s: &str) -> CharacteristicType {
+    let normalized = s.replace("_", "").to_lowercase();
+    (0..512)
+        .filter_map(CharacteristicType::from_i32)
+        .find(|t| format!("{:?}", t).to_lowercase() == normalized)
+        .unwrap_or(CharacteristicType::InvalidCharacteristicType)
+}
+
+/// Parses the characteristic types given with `name`, failing on unknown
+/// types rather than silently matching nothing.
+pub fn characteristictypes_from_matches(matches: &ArgMatches, name: &str) -> SimpleResult<Vec<i32>> {
+    matches.values_of(name).map_or(Ok(vec![]), |values| values
+        .map(|s| match characteristictype_from_str(s) {
+            CharacteristicType::InvalidCharacteristicType => Err(SimpleError::new(format!("Unknown characteristic type '{}'", s))),
+            characteristic_type => Ok(characteristic_type as i32),
+        })
+        .collect())
+}
+
+fn bool_from_str(s: &str) -> Option<bool> {
+    match s.to_lowercase().as_str() {
+        "true" | "on" | "yes" | "1" => Some(true),
+        "false" | "off" | "no" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+fn number_value(value: NumberValue) -> Value {
+    Value {
+        value: Some(ValueEnum::NumberValue(Number { value: Some(value) })),
+    }
+}
+
+/// Parses a value for a characteristic with the given format. Without a
+/// format, the value is inferred from its spelling.
+pub fn parse_value(format: Option<Format>, s: &str) -> SimpleResult<Value> {
+    let invalid = |format: Format| SimpleError::new(format!("'{}' is not a valid {} value", s, format));
+    let value = match format {
+        Some(Format::Bool) => Value { value: Some(ValueEnum::BoolValue(bool_from_str(s).ok_or_else(|| invalid(Format::Bool))?)) },
+        Some(Format::Int) => number_value(NumberValue::SignedIntegerValue(s.parse::<i64>().map_err(|_| invalid(Format::Int))?)),
+        Some(format @ Format::Uint8) |
+        Some(format @ Format::Uint16) |
+        Some(format @ Format::Uint32) |
+        Some(format @ Format::Uint64) => number_value(NumberValue::UnsignedIntegerValue(s.parse::<u64>().map_err(|_| invalid(format))?)),
+        Some(Format::Float) => number_value(NumberValue::FloatValue(s.parse::<f32>().map_err(|_| invalid(Format::Float))?)),
+        Some(Format::String) => Value { value: Some(ValueEnum::StringValue(s.to_string())) },
+        Some(format @ Format::Data) |
+        Some(format @ Format::Tlv8) => Value {
+            value: Some(ValueEnum::DataValue(hex::decode(s.trim_start_matches("0x")).map_err(|_| invalid(format))?)),
+        },
+        Some(format @ Format::Array) |
+        Some(format @ Format::Dictionary) => return Err(SimpleError::new(format!("Writing {} values is not supported", format))),
+        Some(Format::InvalidFormat) | None => {
+            if let Some(b) = bool_from_str(s).filter(|_| s.parse::<i64>().is_err()) {
+                Value { value: Some(ValueEnum::BoolValue(b)) }
+            } else if let Ok(i) = s.parse::<i64>() {
+                number_value(NumberValue::SignedIntegerValue(i))
+            } else if let Ok(f) = s.parse::<f32>() {
+                number_value(NumberValue::FloatValue(f))
+            } else {
+                Value { value: Some(ValueEnum::StringValue(s.to_string())) }
+            }
+        },
+    };
+    Ok(value)
+}
+
+/// Parses a value for `characteristic`. TLV8 values may also be written as
+/// JSON, which is encoded according to the characteristic's schema.
+pub fn parse_characteristic_value(characteristic: &CharacteristicInformation, s: &str) -> SimpleResult<Value> {
+    let format = characteristic.metadata.as_ref().map(|m| m.format());
+    if format == Some(Format::Tlv8) && s.trim_start().starts_with('{') {
+        return tlv::parse_value(characteristic.characteristic_type(), s);
+    }
+    parse_value(format, s)
+}
+
+pub struct Address {
+    home: String,
+    accessory: String,
+    service: String,
+    characteristic: String,
+    characteristic_type: CharacteristicType,
+}
+
+impl Address {
+    fn new(home: &str, accessory: &str, service: &str, characteristic: &str) -> Address {
+        let characteristic_type = characteristictype_from_str(characteristic);
+        Address {
+            home: home.to_string(),
+            accessory: accessory.to_string(),
+            service: service.to_string(),
+            characteristic: if characteristic_type == CharacteristicType::InvalidCharacteristicType {
+                characteristic.to_string()
+            } else {
+                String::new()
+            },
+            characteristic_type: characteristic_type,
+        }
+    }
+
+    fn from_matches(matches: &ArgMatches) -> Address {
+        Address::new(matches.value_of("home").unwrap_or(""),
+                     matches.value_of("accessory").unwrap_or(""),
+                     matches.value_of("service").unwrap_or(""),
+                     matches.value_of("characteristic").unwrap_or(""))
+    }
+
+    /// Parses `ACCESSORY/SERVICE/CHARACTERISTIC`, or `ACCESSORY/CHARACTERISTIC`
+    /// when the accessory has only one such characteristic.
+    pub fn parse(home: &str, s: &str) -> SimpleResult<Address> {
+        let mut parts = s.rsplitn(3, '/').collect::<Vec<&str>>();
+        parts.reverse();
+        match parts.as_slice() {
+            [accessory, characteristic] if accessory.len() != 0 && characteristic.len() != 0 => {
+                Ok(Address::new(home, accessory, "", characteristic))
+            },
+            [accessory, service, characteristic] if accessory.len() != 0 && characteristic.len() != 0 => {
+                Ok(Address::new(home, accessory, service, characteristic))
+            },
+            _ => Err(SimpleError::new(format!("'{}' is not of the form ACCESSORY/SERVICE/CHARACTERISTIC", s))),
+        }
+    }
+
+    fn matches(&self, accessory: &NameUuidPair, service: &NameUuidPair, characteristic: &CharacteristicInformation) -> bool {
+        let exactly = |pattern: &str, pair: &NameUuidPair| pattern.len() == 0 || pattern == pair.name || pattern == pair.uuid;
+        exactly(&self.accessory, accessory) && exactly(&self.service, service) && if self.characteristic.len() != 0 {
+            self.characteristic == characteristic.uuid || self.characteristic == characteristic.description
+        } else {
+            characteristic.characteristic_type == self.characteristic_type as i32
+        }
+    }
+
+    /// Looks up the addressed characteristic so that the value can be parsed
+    /// according to its format. The server resolves the address again when
+    /// writing, and reports missing or ambiguous characteristics.
+    async fn characteristic(&self, client: &mut HomeKitServiceClient<Channel>) -> Result<Option<CharacteristicInformation>, Box<dyn std::error::Error>> {
+        let response = client.enumerate_services(EnumerateServicesRequest {
+            home: self.home.clone(),
+            types: vec![],
+            name_filter: String::new(),
+        }).await?.into_inner();
+        let no_accessory = NameUuidPair::default();
+        let characteristic = response.services.iter()
+            .flat_map(|service| {
+                let accessory = service.accessory.as_ref().unwrap_or(&no_accessory);
+                let pair = NameUuidPair { name: service.name.clone(), uuid: service.uuid.clone() };
+                service.characteristics.iter()
+                    .filter(move |c| self.matches(accessory, &pair, c))
+            })
+            .next()
+            .cloned();
+        Ok(characteristic)
+    }
+
+    /// Finds the one characteristic the address refers to.
+    pub async fn resolve(&self, client: &mut HomeKitServiceClient<Channel>) -> Result<(NameUuidPair, NameUuidPair, CharacteristicInformation), Box<dyn std::error::Error>> {
+        let response = client.enumerate_services(EnumerateServicesRequest {
+            home: self.home.clone(),
+            types: vec![],
+            name_filter: String::new(),
+        }).await?.into_inner();
+        let mut found = vec![];
+        for service in response.services {
+            let accessory = service.accessory.unwrap_or_default();
+            let pair = NameUuidPair { name: service.name, uuid: service.uuid };
+            for characteristic in service.characteristics {
+                if self.matches(&accessory, &pair, &characteristic) {
+                    found.push((accessory.clone(), pair.clone(), characteristic));
+                }
+            }
+        }
+        match found.len() {
+            0 => Err(Box::new(SimpleError::new(format!("No characteristic matches '{}'", self)))),
+            1 => Ok(found.remove(0)),
+            n => {
+                let candidates = found.iter()
+                    .map(|(a, s, c)| format!("{}/{}/{} ({})", a.name, s.name, c.characteristic_type(), c.uuid))
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                Err(Box::new(SimpleError::new(format!("'{}' matches {} characteristics: {}. Specify a service or use a UUID", self, n, candidates))))
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for Address {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for part in [&self.accessory, &self.service].iter().filter(|p| p.len() != 0) {
+            write!(f, "{}/", part)?;
+        }
+        if self.characteristic.len() != 0 {
+            write!(f, "{}", self.characteristic)
+        } else {
+            write!(f, "{}", self.characteristic_type)
+        }
+    }
+}
+
+fn number_as_f64(number: &Number) -> Option<f64> {
+    match number.value.as_ref()? {
+        NumberValue::SignedIntegerValue(i) => Some(*i as f64),
+        NumberValue::UnsignedIntegerValue(u) => Some(*u as f64),
+        NumberValue::FloatValue(f) => Some(*f as f64),
+        NumberValue::DoubleValue(d) => Some(*d),
+    }
+}
+
+fn check_number(metadata: &Metadata, value: f64) -> SimpleResult<()> {
+    let format = metadata.format();
+    let range = match format {
+        Format::Int => Some((i32::MIN as f64, i32::MAX as f64)),
+        Format::Uint8 => Some((0.0, u8::MAX as f64)),
+        Format::Uint16 => Some((0.0, u16::MAX as f64)),
+        Format::Uint32 => Some((0.0, u32::MAX as f64)),
+        _ => None,
+    };
+    if let Some((min, max)) = range {
+        if value < min || value > max {
+            return Err(SimpleError::new(format!("{} is not a valid {} value", value, format)));
+        }
+    }
+    let minimum = metadata.minimum_value.as_ref().and_then(number_as_f64);
+    if let Some(minimum) = minimum {
+        if value < minimum {
+            return Err(SimpleError::new(format!("{} is less than the minimum value {}", value, minimum)));
+        }
+    }
+    if let Some(maximum) = metadata.maximum_value.as_ref().and_then(number_as_f64) {
+        if value > maximum {
+            return Err(SimpleError::new(format!("{} is greater than the maximum value {}", value, maximum)));
+        }
+    }
+    if let Some(step) = metadata.step_value.as_ref().and_then(number_as_f64).filter(|step| *step > 0.0) {
+        let steps = (value - minimum.unwrap_or(0.0)) / step;
+        if (steps - steps.round()).abs() > 1e-6 {
+            return Err(SimpleError::new(format!("{} is not a multiple of the step value {}", value, step)));
+        }
+    }
+    if metadata.valid_values.len() != 0 && !metadata.valid_values.iter().any(|v| number_as_f64(v) == Some(value)) {
+        let valid_values = metadata.valid_values.iter()
+            .map(|v| v.to_string())
+            .collect::<Vec<String>>()
+            .join(", ");
+        return Err(SimpleError::new(format!("{} is not one of the valid values: {}", value, valid_values)));
+    }
+    Ok(())
+}
+
+/// Checks a value parsed with `parse_value` against the characteristic it
+/// is meant for, the way the server checks writes, so that mistakes are
+/// reported before anything is changed.
+pub fn check_write(characteristic: &CharacteristicInformation, value: &Value) -> SimpleResult<()> {
+    if !characteristic.properties.contains(&(Property::Writable as i32)) {
+        return Err(SimpleError::new(format!("{} is not writable", characteristic.characteristic_type())));
+    }
+    let metadata = match characteristic.metadata {
+        Some(ref metadata) => metadata,
+        None => return Ok(()),
+    };
+    let length = match value.value {
+        Some(ValueEnum::NumberValue(ref number)) => {
+            return check_number(metadata, number_as_f64(number).unwrap_or_default());
+        },
+        Some(ValueEnum::StringValue(ref s)) => s.chars().count(),
+        Some(ValueEnum::DataValue(ref d)) => d.len(),
+        _ => return Ok(()),
+    };
+    match metadata.max_length.as_ref().and_then(number_as_f64) {
+        Some(max_length) if length as f64 > max_length => {
+            Err(SimpleError::new(format!("Length {} exceeds the maximum length {}", length, max_length)))
+        },
+        _ => Ok(()),
+    }
+}
+
+fn print_characteristic(accessory: &Option<NameUuidPair>, service: &Option<NameUuidPair>, characteristic: &Option<CharacteristicInformation>, decode_tlv: bool) {
+    if let Some(ref accessory) = accessory {
+        println!("Accessory: {} ({})", accessory.name, accessory.uuid);
+    }
+    if let Some(ref service) = service {
+        println!("Service: {} ({})", service.name, service.uuid);
+    }
+    if let Some(ref characteristic) = characteristic {
+        println!("Characteristic: {} ({})", characteristic.characteristic_type(), characteristic.uuid);
+        match characteristic.value {
+            Some(ref value) => println!("Value:{}", tlv::format_value(characteristic, value, decode_tlv, 2)),
+            None => println!("Value: <None>"),
+        }
+    }
+}
+
+async fn _get(matches: ArgMatches, mut client: HomeKitServiceClient<Channel>) -> Result<(), Box<dyn std::error::Error>> {
+    let address = Address::from_matches(&matches);
+    let response = client.read_characteristic(ReadCharacteristicRequest {
+        home: address.home,
+        accessory: address.accessory,
+        service: address.service,
+        characteristic: address.characteristic,
+        characteristic_type: address.characteristic_type as i32,
+    }).await?.into_inner();
+    output::print(&matches, &response, |r| print_characteristic(&r.accessory, &r.service, &r.characteristic, matches.is_present("decode_tlv")))
+}
+
+async fn _set(matches: ArgMatches, mut client: HomeKitServiceClient<Channel>) -> Result<(), Box<dyn std::error::Error>> {
+    let address = Address::from_matches(&matches);
+    let value = match address.characteristic(&mut client).await? {
+        Some(characteristic) => parse_characteristic_value(&characteristic, matches.value_of("value").unwrap())?,
+        None => parse_value(None, matches.value_of("value").unwrap())?,
+    };
+    let response = client.write_characteristic(WriteCharacteristicRequest {
+        home: address.home,
+        accessory: address.accessory,
+        service: address.service,
+        characteristic: address.characteristic,
+        characteristic_type: address.characteristic_type as i32,
+        value: Some(value),
+    }).await?.into_inner();
+    output::print(&matches, &response, |r| print_characteristic(&r.accessory, &r.service, &r.characteristic, matches.is_present("decode_tlv")))
+}
+
+pub fn get(matches: ArgMatches, client: HomeKitServiceClient<Channel>) -> Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error>>>>> {
+    Box::pin(_get(matches, client))
+}
+
+pub fn set(matches: ArgMatches, client: HomeKitServiceClient<Channel>) -> Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error>>>>> {
+    Box::pin(_set(matches, client))
+}
@@ -0,0 +1,83 @@
+use chrono::Utc;
+use clap::ArgMatches;
+use serde::Serialize;
+use std::time::Instant;
+use tonic::transport::Channel;
+use crate::output;
+
+pub mod proto {
+    tonic::include_proto!("grpc.health.v1");
+}
+
+use proto::HealthCheckRequest;
+use proto::health_check_response::ServingStatus;
+use proto::health_client::HealthClient;
+
+/// The services whose status is printed. The empty name is the server as a
+/// whole.
+const SERVICES: &[&str] = &["", "org.hkserver.HomeKitService"];
+
+fn status_name(status: i32) -> &'static str {
+    match ServingStatus::from_i32(status) {
+        Some(ServingStatus::Serving) => "SERVING",
+        Some(ServingStatus::NotServing) => "NOT_SERVING",
+        Some(ServingStatus::ServiceUnknown) => "SERVICE_UNKNOWN",
+        Some(ServingStatus::Unknown) | None => "UNKNOWN",
+    }
+}
+
+fn service_name(service: &str) -> &str {
+    if service.is_empty() { "(server)" } else { service }
+}
+
+#[derive(Serialize)]
+struct Ping {
+    status: &'static str,
+    milliseconds: f64,
+}
+
+pub async fn ping(matches: ArgMatches, channel: Channel) -> Result<(), Box<dyn std::error::Error>> {
+    let mut client = HealthClient::new(channel);
+    let start = Instant::now();
+    let response = client.check(HealthCheckRequest { service: "".to_string() }).await?.into_inner();
+    let ping = Ping {
+        status: status_name(response.status),
+        milliseconds: start.elapsed().as_secs_f64() * 1000.0,
+    };
+    output::print(&matches, &ping, |ping| println!("{} in {:.1} ms", ping.status, ping.milliseconds))?;
+    if response.status != ServingStatus::Serving as i32 {
+        return Err("The server is not serving".into());
+    }
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct ServiceStatus {
+    service: String,
+    status: &'static str,
+}
+
+async fn run_watch(mut client: HealthClient<Channel>) -> Result<(), Box<dyn std::error::Error>> {
+    let mut stream = client.watch(HealthCheckRequest { service: "".to_string() }).await?.into_inner();
+    while let Some(response) = stream.message().await? {
+        println!("{} {}", Utc::now().naive_utc(), status_name(response.status));
+    }
+    Ok(())
+}
+
+pub async fn status(matches: ArgMatches, channel: Channel) -> Result<(), Box<dyn std::error::Error>> {
+    let mut client = HealthClient::new(channel);
+    if matches.is_present("watch") {
+        return run_watch(client).await;
+    }
+    let mut statuses = vec![];
+    for service in SERVICES {
+        let response = client.check(HealthCheckRequest { service: service.to_string() }).await?.into_inner();
+        statuses.push(ServiceStatus { service: service.to_string(), status: status_name(response.status) });
+    }
+    output::print(&matches, &statuses, |statuses| {
+        for status in statuses.iter() {
+            println!("{}: {}", service_name(&status.service), status.status);
+        }
+    })
+}
@@ -0,0 +1,875 @@
+use clap::ArgMatches;
+use serde::{Deserialize, Deserializer, Serialize};
+use simple_error::{SimpleError, SimpleResult};
+use std::boxed::Box;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::pin::Pin;
+use tonic::transport::Channel;
+use crate::hkservice::home_kit_service_client::HomeKitServiceClient;
+use crate::hkservice::{
+    AccessoryInformation,
+    ActionSetInformation,
+    AddRemoveRoomRequest,
+    AddRemoveServiceGroupRequest,
+    AddRemoveZoneRequest,
+    ChangeRoomZoneMembershipRequest,
+    ChangeServiceGroupMembershipRequest,
+    ChangeTriggerMembershipRequest,
+    CommonTriggerInformation,
+    EnableDisableTriggerRequest,
+    EnumerateAccessoriesRequest,
+    EnumerateActionSetsRequest,
+    EnumerateHomesRequest,
+    EnumerateRoomsRequest,
+    EnumerateServiceGroupsRequest,
+    EnumerateServicesRequest,
+    EnumerateTriggersRequest,
+    EnumerateZonesRequest,
+    MoveAccessoryToRoomRequest,
+    NameUuidPair,
+    Operation,
+    ServiceGroupInformation,
+    ServiceInformation,
+    SetNameRequest,
+    ZoneInformation,
+};
+use crate::hkservice::set_name_request::ObjectType;
+use crate::hkservice::trigger_information::Trigger;
+use crate::resolve::{confirm, exactly, Named};
+
+/// A home as written by `hkctl export` and read by `hkctl plan` and
+/// `hkctl apply`. Objects with a UUID are matched by UUID, so that they can
+/// be renamed; objects without one are matched by name. Everything else
+/// refers to other objects by name.
+///
+/// Rooms, zones and service groups missing from the file are removed.
+/// Accessories, action sets and triggers cannot be created or removed, so
+/// the ones missing from the file are left alone.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct HomeConfig {
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub home: ObjectConfig,
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub rooms: Vec<ObjectConfig>,
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub zones: Vec<ZoneConfig>,
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub accessories: Vec<AccessoryConfig>,
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub service_groups: Vec<ServiceGroupConfig>,
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub action_sets: Vec<ObjectConfig>,
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub triggers: Vec<TriggerConfig>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ObjectConfig {
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub name: String,
+    #[serde(default, deserialize_with = "null_as_default", skip_serializing_if = "String::is_empty")]
+    pub uuid: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ZoneConfig {
+    pub name: String,
+    #[serde(default, deserialize_with = "null_as_default", skip_serializing_if = "String::is_empty")]
+    pub uuid: String,
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub rooms: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct AccessoryConfig {
+    pub name: String,
+    #[serde(default, deserialize_with = "null_as_default", skip_serializing_if = "String::is_empty")]
+    pub uuid: String,
+    // Empty to leave the accessory where it is
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub room: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ServiceGroupConfig {
+    pub name: String,
+    #[serde(default, deserialize_with = "null_as_default", skip_serializing_if = "String::is_empty")]
+    pub uuid: String,
+    // Services are spelled "Accessory/Service"
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub services: Vec<String>,
+}
+
+/// Reads an empty key, which YAML parses as null, as the default value, so
+/// that `action_sets:` with nothing under it means no action sets and
+/// `room:` leaves an accessory where it is.
+fn null_as_default<'de, D: Deserializer<'de>, T: Default + Deserialize<'de>>(deserializer: D) -> Result<T, D::Error> {
+    Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct TriggerConfig {
+    pub name: String,
+    #[serde(default, deserialize_with = "null_as_default", skip_serializing_if = "String::is_empty")]
+    pub uuid: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub action_sets: Vec<String>,
+}
+
+crate::impl_named!(
+    AccessoryConfig,
+    ObjectConfig,
+    ServiceGroupConfig,
+    TriggerConfig,
+    ZoneConfig
+);
+
+/// The current state of a home, as far as a `HomeConfig` is concerned.
+struct LiveHome {
+    home: NameUuidPair,
+    room_for_entire_home: NameUuidPair,
+    rooms: Vec<NameUuidPair>,
+    zones: Vec<ZoneInformation>,
+    accessories: Vec<AccessoryInformation>,
+    services: Vec<ServiceInformation>,
+    service_groups: Vec<ServiceGroupInformation>,
+    action_sets: Vec<ActionSetInformation>,
+    triggers: Vec<CommonTriggerInformation>,
+}
+
+impl LiveHome {
+    /// Fetches the home selected with `--home`, else the home named in the
+    /// configuration, else the primary home.
+    async fn fetch(client: &mut HomeKitServiceClient<Channel>, home_opt: Option<&str>, config: Option<&ObjectConfig>) -> Result<LiveHome, Box<dyn std::error::Error>> {
+        let homes = client.enumerate_homes(EnumerateHomesRequest {
+            name_filter: home_opt.unwrap_or("").to_string(),
+        }).await?.into_inner().homes;
+        let home = match (home_opt, config) {
+            (Some(pattern), _) => homes.into_iter().next()
+                .ok_or_else(|| SimpleError::new(format!("Could not find home matching '{}'", pattern)))?,
+            (None, Some(config)) if !config.uuid.is_empty() => homes.into_iter().find(|h| h.uuid == config.uuid)
+                .ok_or_else(|| SimpleError::new(format!("Could not find home with UUID {}", config.uuid)))?,
+            (None, Some(config)) if !config.name.is_empty() => homes.into_iter().find(|h| h.name == config.name)
+                .ok_or_else(|| SimpleError::new(format!("Could not find home named '{}'", config.name)))?,
+            _ => homes.into_iter().find(|h| h.is_primary)
+                .ok_or_else(|| SimpleError::new("There is no primary home"))?,
+        };
+        let pattern = exactly(&home.uuid);
+
+        // EnumerateRooms includes the room for the entire home, which
+        // HomeInformation does not list
+        let room_for_entire_home = client.enumerate_rooms(EnumerateRoomsRequest {
+            home: pattern.clone(),
+            name_filter: String::new(),
+        }).await?.into_inner().rooms.into_iter()
+            .find(|room| !home.rooms.iter().any(|r| r.uuid == room.uuid))
+            .map(|room| NameUuidPair { name: room.name, uuid: room.uuid })
+            .unwrap_or_default();
+        let zones = client.enumerate_zones(EnumerateZonesRequest {
+            home: pattern.clone(),
+            ..Default::default()
+        }).await?.into_inner().zones;
+        let accessories = client.enumerate_accessories(EnumerateAccessoriesRequest {
+            home: pattern.clone(),
+            ..Default::default()
+        }).await?.into_inner().accessories;
+        let services = client.enumerate_services(EnumerateServicesRequest {
+            home: pattern.clone(),
+            ..Default::default()
+        }).await?.into_inner().services;
+        let service_groups = client.enumerate_service_groups(EnumerateServiceGroupsRequest {
+            home: pattern.clone(),
+            ..Default::default()
+        }).await?.into_inner().service_groups;
+        let action_sets = client.enumerate_action_sets(EnumerateActionSetsRequest {
+            home: pattern.clone(),
+            ..Default::default()
+        }).await?.into_inner().action_sets;
+        let triggers = client.enumerate_triggers(EnumerateTriggersRequest {
+            home: pattern.clone(),
+            ..Default::default()
+        }).await?.into_inner().triggers.into_iter()
+            .filter_map(|trigger| match trigger.trigger {
+                Some(Trigger::Event(event)) => event.trigger,
+                Some(Trigger::Timer(timer)) => timer.trigger,
+                None => None,
+            })
+            .collect();
+        Ok(LiveHome {
+            home: NameUuidPair { name: home.name, uuid: home.uuid },
+            room_for_entire_home: room_for_entire_home,
+            rooms: home.rooms,
+            zones: zones,
+            accessories: accessories,
+            services: services,
+            service_groups: service_groups,
+            action_sets: action_sets,
+            triggers: triggers,
+        })
+    }
+
+    fn service_path(&self, uuid: &str) -> Option<String> {
+        self.services.iter()
+            .find(|service| service.uuid == uuid)
+            .map(|service| format!("{}/{}", service.accessory.as_ref().map_or("", |a| a.name.as_str()), service.name))
+    }
+
+    fn export(&self) -> HomeConfig {
+        let object = |o: &dyn Named| ObjectConfig { name: o.name().to_string(), uuid: o.uuid().to_string() };
+        HomeConfig {
+            home: object(&self.home),
+            rooms: self.rooms.iter().map(|room| object(room)).collect(),
+            zones: self.zones.iter()
+                .map(|zone| ZoneConfig {
+                    name: zone.name.clone(),
+                    uuid: zone.uuid.clone(),
+                    rooms: zone.rooms.iter().map(|room| room.name.clone()).collect(),
+                })
+                .collect(),
+            accessories: self.accessories.iter()
+                .map(|accessory| AccessoryConfig {
+                    name: accessory.name.clone(),
+                    uuid: accessory.uuid.clone(),
+                    room: accessory.room.as_ref().unwrap_or(&self.room_for_entire_home).name.clone(),
+                })
+                .collect(),
+            service_groups: self.service_groups.iter()
+                .map(|service_group| ServiceGroupConfig {
+                    name: service_group.name.clone(),
+                    uuid: service_group.uuid.clone(),
+                    services: service_group.services.iter()
+                        .filter_map(|service| self.service_path(&service.uuid))
+                        .collect(),
+                })
+                .collect(),
+            action_sets: self.action_sets.iter().map(|action_set| object(action_set)).collect(),
+            triggers: self.triggers.iter()
+                .map(|trigger| TriggerConfig {
+                    name: trigger.name.clone(),
+                    uuid: trigger.uuid.clone(),
+                    enabled: trigger.is_enabled,
+                    action_sets: trigger.action_sets.iter().map(|a| a.name.clone()).collect(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// An object referred to by a plan, by its name once the plan is applied and
+/// its UUID if it already exists.
+#[derive(Clone, Debug)]
+struct Ref {
+    name: String,
+    uuid: Option<String>,
+}
+
+impl Ref {
+    fn existing<T: Named>(name: &str, live: &T) -> Ref {
+        Ref { name: name.to_string(), uuid: Some(live.uuid().to_string()) }
+    }
+
+    fn new(name: &str) -> Ref {
+        Ref { name: name.to_string(), uuid: None }
+    }
+
+    /// How RPCs that look objects up by name or UUID should refer to it.
+    fn id(&self) -> String {
+        self.uuid.clone().unwrap_or_else(|| self.name.clone())
+    }
+
+    /// How RPCs that look objects up by pattern should refer to it. Only
+    /// existing objects are looked up by pattern.
+    fn pattern(&self) -> String {
+        match self.uuid {
+            Some(ref uuid) => exactly(uuid),
+            None => self.name.clone(),
+        }
+    }
+
+    fn key(&self) -> String {
+        match self.uuid {
+            Some(ref uuid) => uuid.clone(),
+            None => format!("+{}", self.name),
+        }
+    }
+}
+
+impl std::fmt::Display for Ref {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "'{}'", self.name)
+    }
+}
+
+#[derive(Debug)]
+enum Step {
+    Rename { object_type: ObjectType, object: Ref, old_name: String },
+    AddRoom(Ref),
+    RemoveRoom(Ref),
+    AddZone(Ref),
+    RemoveZone(Ref),
+    AddRoomToZone { room: Ref, zone: Ref },
+    RemoveRoomFromZone { room: Ref, zone: Ref },
+    MoveAccessory { accessory: Ref, room: Ref },
+    AddServiceGroup(Ref),
+    RemoveServiceGroup(Ref),
+    AddServiceToGroup { service: Ref, service_group: Ref },
+    RemoveServiceFromGroup { service: Ref, service_group: Ref },
+    EnableTrigger { trigger: Ref, enable: bool },
+    AddActionSetToTrigger { action_set: Ref, trigger: Ref },
+    RemoveActionSetFromTrigger { action_set: Ref, trigger: Ref },
+}
+
+fn object_type_name(object_type: ObjectType) -> &'static str {
+    match object_type {
+        ObjectType::Home => "home",
+        ObjectType::Room => "room",
+        ObjectType::Zone => "zone",
+        ObjectType::Accessory => "accessory",
+        ObjectType::ServiceGroup => "service group",
+        ObjectType::ActionSet => "action set",
+        ObjectType::Trigger => "trigger",
+        ObjectType::Unknown => "object",
+    }
+}
+
+impl std::fmt::Display for Step {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Step::Rename { object_type, object, old_name } => write!(f, "~ rename {} '{}' to {}", object_type_name(*object_type), old_name, object),
+            Step::AddRoom(room) => write!(f, "+ add room {}", room),
+            Step::RemoveRoom(room) => write!(f, "- remove room {}", room),
+            Step::AddZone(zone) => write!(f, "+ add zone {}", zone),
+            Step::RemoveZone(zone) => write!(f, "- remove zone {}", zone),
+            Step::AddRoomToZone { room, zone } => write!(f, "+ add room {} to zone {}", room, zone),
+            Step::RemoveRoomFromZone { room, zone } => write!(f, "- remove room {} from zone {}", room, zone),
+            Step::MoveAccessory { accessory, room } => write!(f, "~ move accessory {} to room {}", accessory, room),
+            Step::AddServiceGroup(service_group) => write!(f, "+ add service group {}", service_group),
+            Step::RemoveServiceGroup(service_group) => write!(f, "- remove service group {}", service_group),
+            Step::AddServiceToGroup { service, service_group } => write!(f, "+ add service {} to service group {}", service, service_group),
+            Step::RemoveServiceFromGroup { service, service_group } => write!(f, "- remove service {} from service group {}", service, service_group),
+            Step::EnableTrigger { trigger, enable: true } => write!(f, "~ enable trigger {}", trigger),
+            Step::EnableTrigger { trigger, enable: false } => write!(f, "~ disable trigger {}", trigger),
+            Step::AddActionSetToTrigger { action_set, trigger } => write!(f, "+ add action set {} to trigger {}", action_set, trigger),
+            Step::RemoveActionSetFromTrigger { action_set, trigger } => write!(f, "- remove action set {} from trigger {}", action_set, trigger),
+        }
+    }
+}
+
+/// Pairs every desired object with the live object it describes, if any.
+fn match_objects<'a, D: Named, L: Named>(object_type: &str, desired: &[D], live: &'a [L]) -> SimpleResult<Vec<Option<&'a L>>> {
+    let mut names = HashSet::new();
+    let mut matched = HashSet::new();
+    desired.iter()
+        .map(|d| {
+            if !names.insert(d.name()) {
+                return Err(SimpleError::new(format!("There is more than one {} named '{}'", object_type, d.name())));
+            }
+            let found = if !d.uuid().is_empty() {
+                Some(live.iter().find(|l| l.uuid() == d.uuid())
+                     .ok_or_else(|| SimpleError::new(format!("Could not find {} with UUID {}", object_type, d.uuid())))?)
+            } else {
+                live.iter().find(|l| l.name() == d.name())
+            };
+            if let Some(l) = found {
+                if !matched.insert(l.uuid()) {
+                    return Err(SimpleError::new(format!("{} {} is listed more than once", object_type, l.uuid())));
+                }
+            }
+            Ok(found)
+        })
+        .collect()
+}
+
+/// The steps needed to bring `live` in line with `config`, in the order they
+/// are applied: renames first, so that later steps can use the new names,
+/// and removals last, after accessories have been moved out of removed
+/// rooms.
+fn plan(config: &HomeConfig, live: &LiveHome) -> SimpleResult<Vec<Step>> {
+    let mut renames = vec![];
+    let mut additions = vec![];
+    let mut changes = vec![];
+    let mut removals = vec![];
+
+    let mut rename = |object_type: ObjectType, desired: &dyn Named, live: &dyn Named| {
+        if desired.name() != live.name() {
+            renames.push(Step::Rename {
+                object_type: object_type,
+                object: Ref { name: desired.name().to_string(), uuid: Some(live.uuid().to_string()) },
+                old_name: live.name().to_string(),
+            });
+        }
+    };
+
+    if !config.home.name.is_empty() {
+        rename(ObjectType::Home, &config.home, &live.home);
+    }
+
+    // Rooms, by their name in the configuration
+    let mut rooms = HashMap::new();
+    rooms.insert(live.room_for_entire_home.name.clone(), Ref::existing(&live.room_for_entire_home.name, &live.room_for_entire_home));
+    for (desired, found) in config.rooms.iter().zip(match_objects("room", &config.rooms, &live.rooms)?) {
+        let room = match found {
+            Some(found) => {
+                rename(ObjectType::Room, desired, found);
+                Ref::existing(&desired.name, found)
+            },
+            None => {
+                additions.push(Step::AddRoom(Ref::new(&desired.name)));
+                Ref::new(&desired.name)
+            },
+        };
+        rooms.insert(desired.name.clone(), room);
+    }
+    let kept_rooms = rooms.values().map(|room| room.key()).collect::<HashSet<String>>();
+    for room in live.rooms.iter().filter(|room| !kept_rooms.contains(&room.uuid)) {
+        removals.push(Step::RemoveRoom(Ref::existing(&room.name, room)));
+    }
+    let room = |name: &str| rooms.get(name).cloned()
+        .ok_or_else(|| SimpleError::new(format!("Could not find room named '{}'", name)));
+    let room_name = |uuid: &str| rooms.values()
+        .find(|room| room.uuid.as_ref().map_or(false, |u| u == uuid))
+        .cloned();
+
+    for (desired, found) in config.zones.iter().zip(match_objects("zone", &config.zones, &live.zones)?) {
+        let (zone, current) = match found {
+            Some(found) => {
+                rename(ObjectType::Zone, desired, found);
+                (Ref::existing(&desired.name, found), found.rooms.iter().map(|r| r.uuid.clone()).collect::<Vec<String>>())
+            },
+            None => {
+                additions.push(Step::AddZone(Ref::new(&desired.name)));
+                (Ref::new(&desired.name), vec![])
+            },
+        };
+        let wanted = desired.rooms.iter().map(|name| room(name)).collect::<SimpleResult<Vec<Ref>>>()?;
+        for r in wanted.iter().filter(|r| !current.contains(&r.key())) {
+            changes.push(Step::AddRoomToZone { room: r.clone(), zone: zone.clone() });
+        }
+        // Rooms that are removed leave their zones anyway
+        for r in current.iter().filter(|uuid| !wanted.iter().any(|w| &w.key() == *uuid)).filter_map(|uuid| room_name(uuid)) {
+            changes.push(Step::RemoveRoomFromZone { room: r, zone: zone.clone() });
+        }
+    }
+    let kept_zones = config.zones.iter().zip(match_objects("zone", &config.zones, &live.zones)?)
+        .filter_map(|(_, found)| found.map(|f| f.uuid.clone()))
+        .collect::<HashSet<String>>();
+    for zone in live.zones.iter().filter(|zone| !kept_zones.contains(&zone.uuid)) {
+        removals.push(Step::RemoveZone(Ref::existing(&zone.name, zone)));
+    }
+
+    // Accessories, by their name once renamed
+    let mut accessories = live.accessories.iter()
+        .map(|accessory| (accessory.name.clone(), accessory.uuid.clone()))
+        .collect::<HashMap<String, String>>();
+    for (desired, found) in config.accessories.iter().zip(match_objects("accessory", &config.accessories, &live.accessories)?) {
+        let found = found.ok_or_else(|| SimpleError::new(format!("Could not find accessory named '{}'", desired.name)))?;
+        rename(ObjectType::Accessory, desired, found);
+        accessories.remove(&found.name);
+        accessories.insert(desired.name.clone(), found.uuid.clone());
+        if !desired.room.is_empty() {
+            let wanted = room(&desired.room)?;
+            let current = found.room.as_ref().unwrap_or(&live.room_for_entire_home);
+            if wanted.key() != current.uuid {
+                changes.push(Step::MoveAccessory { accessory: Ref::existing(&desired.name, found), room: wanted });
+            }
+        }
+    }
+    let service = |path: &str| -> SimpleResult<Ref> {
+        let not_found = || SimpleError::new(format!("Could not find service '{}'", path));
+        let split = path.rfind('/').ok_or_else(not_found)?;
+        let accessory = accessories.get(&path[..split]).ok_or_else(not_found)?;
+        live.services.iter()
+            .find(|s| s.name == path[split + 1..] && s.accessory.as_ref().map_or(false, |a| &a.uuid == accessory))
+            .map(|s| Ref { name: path.to_string(), uuid: Some(s.uuid.clone()) })
+            .ok_or_else(not_found)
+    };
+
+    let service_group_matches = match_objects("service group", &config.service_groups, &live.service_groups)?;
+    for (desired, found) in config.service_groups.iter().zip(service_group_matches.iter()) {
+        let (service_group, current) = match found {
+            Some(found) => {
+                rename(ObjectType::ServiceGroup, desired, *found);
+                (Ref::existing(&desired.name, *found), found.services.iter().map(|s| s.uuid.clone()).collect::<Vec<String>>())
+            },
+            None => {
+                additions.push(Step::AddServiceGroup(Ref::new(&desired.name)));
+                (Ref::new(&desired.name), vec![])
+            },
+        };
+        let wanted = desired.services.iter().map(|path| service(path)).collect::<SimpleResult<Vec<Ref>>>()?;
+        for s in wanted.iter().filter(|s| !current.contains(&s.key())) {
+            changes.push(Step::AddServiceToGroup { service: s.clone(), service_group: service_group.clone() });
+        }
+        for uuid in current.iter().filter(|uuid| !wanted.iter().any(|w| &w.key() == *uuid)) {
+            let name = live.service_path(uuid).unwrap_or_else(|| uuid.clone());
+            changes.push(Step::RemoveServiceFromGroup {
+                service: Ref { name: name, uuid: Some(uuid.clone()) },
+                service_group: service_group.clone(),
+            });
+        }
+    }
+    for service_group in live.service_groups.iter().filter(|g| !service_group_matches.iter().any(|m| m.map_or(false, |m| m.uuid == g.uuid))) {
+        removals.push(Step::RemoveServiceGroup(Ref::existing(&service_group.name, service_group)));
+    }
+
+    // Action sets, by their name once renamed
+    let mut action_sets = live.action_sets.iter()
+        .map(|action_set| (action_set.name.clone(), Ref::existing(&action_set.name, action_set)))
+        .collect::<HashMap<String, Ref>>();
+    for (desired, found) in config.action_sets.iter().zip(match_objects("action set", &config.action_sets, &live.action_sets)?) {
+        let found = found.ok_or_else(|| SimpleError::new(format!("Could not find action set named '{}'", desired.name)))?;
+        rename(ObjectType::ActionSet, desired, found);
+        action_sets.remove(&found.name);
+        action_sets.insert(desired.name.clone(), Ref::existing(&desired.name, found));
+    }
+
+    for (desired, found) in config.triggers.iter().zip(match_objects("trigger", &config.triggers, &live.triggers)?) {
+        let found = found.ok_or_else(|| SimpleError::new(format!("Could not find trigger named '{}'", desired.name)))?;
+        rename(ObjectType::Trigger, desired, found);
+        let trigger = Ref::existing(&desired.name, found);
+        if desired.enabled != found.is_enabled {
+            changes.push(Step::EnableTrigger { trigger: trigger.clone(), enable: desired.enabled });
+        }
+        let wanted = desired.action_sets.iter()
+            .map(|name| action_sets.get(name).cloned()
+                 .ok_or_else(|| SimpleError::new(format!("Could not find action set named '{}'", name))))
+            .collect::<SimpleResult<Vec<Ref>>>()?;
+        for action_set in wanted.iter().filter(|a| !found.action_sets.iter().any(|f| a.key() == f.uuid)) {
+            changes.push(Step::AddActionSetToTrigger { action_set: action_set.clone(), trigger: trigger.clone() });
+        }
+        for current in found.action_sets.iter().filter(|f| !wanted.iter().any(|a| a.key() == f.uuid)) {
+            let action_set = action_sets.values()
+                .find(|a| a.key() == current.uuid)
+                .cloned()
+                .unwrap_or_else(|| Ref::existing(&current.name, current));
+            changes.push(Step::RemoveActionSetFromTrigger { action_set: action_set, trigger: trigger.clone() });
+        }
+    }
+
+    Ok(renames.into_iter()
+       .chain(additions)
+       .chain(changes)
+       .chain(removals)
+       .collect())
+}
+
+async fn apply_step(client: &mut HomeKitServiceClient<Channel>, home: &str, step: &Step) -> Result<(), Box<dyn std::error::Error>> {
+    let home = home.to_string();
+    match step {
+        Step::Rename { object_type, object, .. } => {
+            client.set_name(SetNameRequest {
+                home: home,
+                name: object.id(),
+                new_name: object.name.clone(),
+                object_type: *object_type as i32,
+            }).await?;
+        },
+        Step::AddRoom(room) | Step::RemoveRoom(room) => {
+            let add = matches!(step, Step::AddRoom(_));
+            client.add_remove_room(AddRemoveRoomRequest {
+                home: home,
+                name: if add { room.name.clone() } else { room.pattern() },
+                accessories: vec![],
+                operation: if add { Operation::Add } else { Operation::Remove } as i32,
+            }).await?;
+        },
+        Step::AddZone(zone) | Step::RemoveZone(zone) => {
+            let add = matches!(step, Step::AddZone(_));
+            client.add_remove_zone(AddRemoveZoneRequest {
+                home: home,
+                name: if add { zone.name.clone() } else { zone.pattern() },
+                rooms: vec![],
+                operation: if add { Operation::Add } else { Operation::Remove } as i32,
+            }).await?;
+        },
+        Step::AddRoomToZone { room, zone } | Step::RemoveRoomFromZone { room, zone } => {
+            let add = matches!(step, Step::AddRoomToZone { .. });
+            client.change_room_zone_membership(ChangeRoomZoneMembershipRequest {
+                home: home,
+                name: room.id(),
+                zone: zone.id(),
+                operation: if add { Operation::Add } else { Operation::Remove } as i32,
+            }).await?;
+        },
+        Step::MoveAccessory { accessory, room } => {
+            client.move_accessory_to_room(MoveAccessoryToRoomRequest {
+                home: home,
+                name: accessory.id(),
+                room: room.id(),
+            }).await?;
+        },
+        Step::AddServiceGroup(service_group) | Step::RemoveServiceGroup(service_group) => {
+            let add = matches!(step, Step::AddServiceGroup(_));
+            client.add_remove_service_group(AddRemoveServiceGroupRequest {
+                home: home,
+                name: if add { service_group.name.clone() } else { service_group.pattern() },
+                services: vec![],
+                operation: if add { Operation::Add } else { Operation::Remove } as i32,
+            }).await?;
+        },
+        Step::AddServiceToGroup { service, service_group } | Step::RemoveServiceFromGroup { service, service_group } => {
+            let add = matches!(step, Step::AddServiceToGroup { .. });
+            client.change_service_group_membership(ChangeServiceGroupMembershipRequest {
+                home: home,
+                name: service_group.id(),
+                service_filter: service.pattern(),
+                operation: if add { Operation::Add } else { Operation::Remove } as i32,
+            }).await?;
+        },
+        Step::EnableTrigger { trigger, enable } => {
+            client.enable_disable_trigger(EnableDisableTriggerRequest {
+                home: home,
+                name: trigger.id(),
+                enable: *enable,
+            }).await?;
+        },
+        Step::AddActionSetToTrigger { action_set, trigger } | Step::RemoveActionSetFromTrigger { action_set, trigger } => {
+            let add = matches!(step, Step::AddActionSetToTrigger { .. });
+            client.change_trigger_membership(ChangeTriggerMembershipRequest {
+                home: home,
+                name: trigger.id(),
+                operation: if add { Operation::Add } else { Operation::Remove } as i32,
+                action_sets: vec![action_set.id()],
+            }).await?;
+        },
+    };
+    Ok(())
+}
+
+fn read_config(path: &str) -> Result<HomeConfig, Box<dyn std::error::Error>> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| SimpleError::new(format!("Unable to read {}: {}", path, e)))?;
+    let config = serde_yaml::from_str(&contents)
+        .map_err(|e| SimpleError::new(format!("Unable to parse {}: {}", path, e)))?;
+    Ok(config)
+}
+
+fn print_plan(home: &NameUuidPair, steps: &[Step]) {
+    if steps.is_empty() {
+        println!("Home {} is up to date", home.name);
+        return;
+    }
+    println!("Changes to home {} ({}):", home.name, steps.len());
+    steps.iter().for_each(|step| println!("  {}", step));
+}
+
+async fn _export(matches: ArgMatches, mut client: HomeKitServiceClient<Channel>) -> Result<(), Box<dyn std::error::Error>> {
+    let live = LiveHome::fetch(&mut client, matches.value_of("home"), None).await?;
+    let yaml = serde_yaml::to_string(&live.export())?;
+    match matches.value_of("file") {
+        Some(path) => std::fs::write(path, yaml)?,
+        None => print!("{}", yaml),
+    }
+    Ok(())
+}
+
+async fn _plan(matches: ArgMatches, mut client: HomeKitServiceClient<Channel>) -> Result<(), Box<dyn std::error::Error>> {
+    let config = read_config(matches.value_of("file").unwrap())?;
+    let live = LiveHome::fetch(&mut client, matches.value_of("home"), Some(&config.home)).await?;
+    print_plan(&live.home, &plan(&config, &live)?);
+    Ok(())
+}
+
+async fn _apply(matches: ArgMatches, mut client: HomeKitServiceClient<Channel>) -> Result<(), Box<dyn std::error::Error>> {
+    let config = read_config(matches.value_of("file").unwrap())?;
+    let live = LiveHome::fetch(&mut client, matches.value_of("home"), Some(&config.home)).await?;
+    let steps = plan(&config, &live)?;
+    print_plan(&live.home, &steps);
+    if steps.is_empty() {
+        return Ok(());
+    }
+    if !matches.is_present("yes") && !confirm("Apply these changes?")? {
+        println!("Nothing was changed");
+        return Ok(());
+    }
+    let home = exactly(&live.home.uuid);
+    for step in steps.iter() {
+        apply_step(&mut client, &home, step).await?;
+        println!("Applied: {}", step);
+    }
+    Ok(())
+}
+
+pub fn export(matches: ArgMatches, client: HomeKitServiceClient<Channel>) -> Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error>>>>> {
+    Box::pin(_export(matches, client))
+}
+
+pub fn plan_changes(matches: ArgMatches, client: HomeKitServiceClient<Channel>) -> Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error>>>>> {
+    Box::pin(_plan(matches, client))
+}
+
+pub fn apply(matches: ArgMatches, client: HomeKitServiceClient<Channel>) -> Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error>>>>> {
+    Box::pin(_apply(matches, client))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pair(name: &str, uuid: &str) -> NameUuidPair {
+        NameUuidPair { name: name.to_string(), uuid: uuid.to_string() }
+    }
+
+    /// A home with a room in a zone, a lamp in that room whose light is in a
+    /// service group, and a trigger running an action set.
+    fn live_home() -> LiveHome {
+        LiveHome {
+            home: pair("Cabin", "H"),
+            room_for_entire_home: pair("Cabin", "R0"),
+            rooms: vec![pair("Kitchen", "R1"), pair("Hallway", "R2")],
+            zones: vec![ZoneInformation {
+                name: "Downstairs".to_string(),
+                uuid: "Z1".to_string(),
+                rooms: vec![pair("Kitchen", "R1")],
+            }],
+            accessories: vec![AccessoryInformation {
+                name: "Lamp".to_string(),
+                uuid: "A1".to_string(),
+                room: Some(pair("Kitchen", "R1")),
+                ..Default::default()
+            }],
+            services: vec![ServiceInformation {
+                name: "Light".to_string(),
+                uuid: "S1".to_string(),
+                accessory: Some(pair("Lamp", "A1")),
+                ..Default::default()
+            }],
+            service_groups: vec![ServiceGroupInformation {
+                name: "Lights".to_string(),
+                uuid: "G1".to_string(),
+                services: vec![pair("Light", "S1")],
+            }],
+            action_sets: vec![ActionSetInformation {
+                name: "Evening".to_string(),
+                uuid: "AS1".to_string(),
+                ..Default::default()
+            }],
+            triggers: vec![CommonTriggerInformation {
+                name: "Dusk".to_string(),
+                uuid: "T1".to_string(),
+                is_enabled: true,
+                action_sets: vec![pair("Evening", "AS1")],
+                ..Default::default()
+            }],
+        }
+    }
+
+    fn plan_yaml(yaml: &str) -> Vec<String> {
+        let config: HomeConfig = serde_yaml::from_str(yaml).unwrap();
+        plan(&config, &live_home()).unwrap().iter().map(|step| step.to_string()).collect()
+    }
+
+    #[test]
+    fn exported_home_is_up_to_date() {
+        let live = live_home();
+        assert!(plan(&live.export(), &live).unwrap().is_empty());
+    }
+
+    #[test]
+    fn creates_missing_objects() {
+        let steps = plan_yaml(r#"
+rooms:
+  - name: Kitchen
+  - name: Hallway
+  - name: Bedroom
+zones:
+  - name: Downstairs
+    rooms: [Kitchen]
+  - name: Upstairs
+    rooms: [Bedroom]
+service_groups:
+  - name: Lights
+    services: [Lamp/Light]
+  - name: Lamps
+    services: [Lamp/Light]
+"#);
+        assert_eq!(steps, vec![
+            "+ add room 'Bedroom'",
+            "+ add zone 'Upstairs'",
+            "+ add service group 'Lamps'",
+            "+ add room 'Bedroom' to zone 'Upstairs'",
+            "+ add service 'Lamp/Light' to service group 'Lamps'",
+        ]);
+    }
+
+    #[test]
+    fn removes_objects_missing_from_the_config() {
+        let steps = plan_yaml(r#"
+rooms:
+  - name: Kitchen
+service_groups:
+"#);
+        assert_eq!(steps, vec![
+            "- remove room 'Hallway'",
+            "- remove zone 'Downstairs'",
+            "- remove service group 'Lights'",
+        ]);
+    }
+
+    #[test]
+    fn renames_objects_matched_by_uuid() {
+        let steps = plan_yaml(r#"
+home: {name: Chalet, uuid: H}
+rooms:
+  - {name: Cuisine, uuid: R1}
+  - {name: Hallway}
+zones:
+  - {name: Downstairs, rooms: [Cuisine]}
+accessories:
+  - {name: Lampe, uuid: A1, room: Cuisine}
+service_groups:
+  - {name: Lights, services: [Lampe/Light]}
+action_sets:
+  - {name: Soir, uuid: AS1}
+triggers:
+  - {name: Dusk, action_sets: [Soir]}
+"#);
+        assert_eq!(steps, vec![
+            "~ rename home 'Cabin' to 'Chalet'",
+            "~ rename room 'Kitchen' to 'Cuisine'",
+            "~ rename accessory 'Lamp' to 'Lampe'",
+            "~ rename action set 'Evening' to 'Soir'",
+        ]);
+    }
+
+    #[test]
+    fn empty_keys_are_empty_lists() {
+        let steps = plan_yaml(r#"
+rooms:
+  - name: Kitchen
+  - name: Hallway
+zones:
+  - name: Downstairs
+    rooms: [Kitchen]
+service_groups:
+  - name: Lights
+    services: [Lamp/Light]
+accessories:
+  - name: Lamp
+    room:
+triggers:
+  - name: Dusk
+    action_sets:
+"#);
+        assert_eq!(steps, vec!["- remove action set 'Evening' from trigger 'Dusk'"]);
+    }
+
+    #[test]
+    fn rejects_unknown_room() {
+        let config: HomeConfig = serde_yaml::from_str("accessories:\n  - {name: Lamp, room: Attic}\n").unwrap();
+        assert_eq!(plan(&config, &live_home()).unwrap_err().as_str(), "Could not find room named 'Attic'");
+    }
+}
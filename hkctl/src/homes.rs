@@ -6,6 +6,8 @@ use tonic::transport::Channel;
 use crate::hkservice::home_kit_service_client::HomeKitServiceClient;
 use crate::hkservice::{EnumerateHomesRequest, EnumerateHomesResponse};
 use crate::hkservice::home_information::HomeHubState;
+use crate::output;
+use crate::table::{Column, Table};
 
 fn print_response(response: &EnumerateHomesResponse) {
     response.homes.iter().for_each(|home| {
@@ -22,14 +24,43 @@ fn print_response(response: &EnumerateHomesResponse) {
     });
 }
 
+impl Table for EnumerateHomesResponse {
+    const COLUMNS: &'static [Column] = &[
+        Column { name: "name", default: true },
+        Column { name: "uuid", default: false },
+        Column { name: "primary", default: true },
+        Column { name: "hub_state", default: true },
+        Column { name: "rooms", default: true },
+        Column { name: "zones", default: false },
+        Column { name: "accessories", default: true },
+        Column { name: "service_groups", default: false },
+        Column { name: "action_sets", default: false },
+        Column { name: "triggers", default: false },
+    ];
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        self.homes.iter().map(|home| vec![
+            home.name.clone(),
+            home.uuid.clone(),
+            home.is_primary.to_string(),
+            format!("{:?}", home.hub_state()),
+            home.rooms.len().to_string(),
+            home.zones.len().to_string(),
+            home.accessories.len().to_string(),
+            home.service_groups.len().to_string(),
+            home.action_sets.len().to_string(),
+            home.triggers.len().to_string(),
+        ]).collect()
+    }
+}
+
 async fn _run(matches: ArgMatches, mut client: HomeKitServiceClient<Channel>) -> Result<(), Box<dyn std::error::Error>> {
     let response = client.enumerate_homes(
         EnumerateHomesRequest {
             name_filter: matches.value_of("home").unwrap_or("").to_string(),
         }
     ).await?.into_inner();
-    print_response(&response);
-    Ok(())
+    output::print_listing(&matches, &response, print_response)
 }
 
 pub fn run(matches: ArgMatches, client: HomeKitServiceClient<Channel>) -> Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error>>>>> {
@@ -8,28 +8,144 @@ mod service_groups;
 mod action_sets;
 mod triggers;
 mod room;
+mod zone;
+mod service_group;
+mod action_set;
+mod trigger;
+mod run;
+mod rename;
+mod move_accessories;
+mod home_config;
+mod characteristic;
+mod watch;
+mod output;
+mod table;
+mod resolve;
+mod tlv;
+mod health;
 
-use clap::{App, Arg, crate_version};
-use tonic::transport::{Channel, Uri};
+use clap::{App, AppSettings, Arg, ArgMatches, crate_version};
+use tonic::metadata::MetadataValue;
+use tonic::transport::{Certificate, Channel, ClientTlsConfig, Identity, Uri};
 use tokio;
 use hkservice::home_kit_service_client::HomeKitServiceClient;
 use std::error::Error;
+use std::path::PathBuf;
+use tokio::net::UnixStream;
+
+/// Where the server listens: a host and port, or a unix socket given as
+/// unix:///path.
+enum ServerAddress {
+    Tcp { host: String, port: u32 },
+    Unix(PathBuf),
+}
+
+impl ServerAddress {
+    /// Parses --host, which may also carry a port, as in host:port or
+    /// [::1]:port. Otherwise `port` is used.
+    fn parse(host: &str, port: u32) -> Result<ServerAddress, Box<dyn Error>> {
+        if let Some(path) = host.strip_prefix("unix://") {
+            if path.is_empty() {
+                return Err("A unix socket address needs a path, as in unix:///tmp/hkserver.sock".into());
+            }
+            return Ok(ServerAddress::Unix(PathBuf::from(path)));
+        }
+        let parse_port = |p: &str| p.parse::<u32>().map_err(|_| format!("'{}' is not a valid port", p));
+        let (host, port) = match (host.strip_prefix('['), host.rfind(':')) {
+            (Some(rest), _) => match rest.find(']') {
+                Some(end) if end + 1 == rest.len() => (&rest[..end], port),
+                Some(end) if rest[end + 1..].starts_with(':') => (&rest[..end], parse_port(&rest[end + 2..])?),
+                _ => return Err(format!("'{}' is not a valid host", host).into()),
+            },
+            // More than one colon is an IPv6 address without a port
+            (None, Some(colon)) if host.matches(':').count() == 1 => (&host[..colon], parse_port(&host[colon + 1..])?),
+            _ => (host, port),
+        };
+        Ok(ServerAddress::Tcp { host: host.to_string(), port })
+    }
+}
+
+impl std::fmt::Display for ServerAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            // IPv6 addresses are bracketed in URIs
+            ServerAddress::Tcp { host, port } if host.contains(':') => write!(f, "[{}]:{}", host, port),
+            ServerAddress::Tcp { host, port } => write!(f, "{}:{}", host, port),
+            ServerAddress::Unix(path) => write!(f, "unix://{}", path.display()),
+        }
+    }
+}
+
+async fn connect(address: &ServerAddress, tls: Option<ClientTlsConfig>) -> Result<Channel, Box<dyn Error>> {
+    match address {
+        ServerAddress::Tcp { .. } => {
+            let authority = address.to_string();
+            let endpoint = Uri::builder()
+                .scheme(if tls.is_some() { "https" } else { "http" })
+                .authority(authority.as_str())
+                .path_and_query("/")
+                .build();
+            let mut builder = tonic::transport::Channel::builder(endpoint.unwrap());
+            if let Some(tls) = tls {
+                builder = builder.tls_config(tls)?;
+            }
+            Ok(builder.connect().await?)
+        },
+        ServerAddress::Unix(path) => {
+            if tls.is_some() {
+                return Err("TLS is not used on unix sockets".into());
+            }
+            // The URI is required but unused, since the connector ignores it
+            let path = path.clone();
+            Ok(tonic::transport::Channel::from_static("http://localhost")
+               .connect_with_connector(tower::service_fn(move |_: Uri| UnixStream::connect(path.clone())))
+               .await?)
+        },
+    }
+}
 
 impl HomeKitServiceClient<Channel> {
-    async fn create(host: &str, port: u32) -> Result<HomeKitServiceClient<Channel>, Box<dyn Error>> {
-        let authority = format!("{}:{}", host, port);
-        let endpoint = Uri::builder()
-            .scheme("http")
-            .authority(authority.as_str())
-            .path_and_query("/")
-            .build();
-        let channel = tonic::transport::Channel::builder(endpoint.unwrap())
-            .connect()
-            .await?;
-        Ok(HomeKitServiceClient::new(channel))
+    fn create(channel: Channel, token: Option<&str>) -> Result<HomeKitServiceClient<Channel>, Box<dyn Error>> {
+        match token {
+            Some(token) => {
+                let authorization = MetadataValue::from_str(&format!("Bearer {}", token))
+                    .map_err(|_| "The API token contains invalid characters")?;
+                Ok(HomeKitServiceClient::with_interceptor(channel, move |mut request: tonic::Request<()>| {
+                    request.metadata_mut().insert("authorization", authorization.clone());
+                    Ok(request)
+                }))
+            },
+            None => Ok(HomeKitServiceClient::new(channel)),
+        }
     }
 }
 
+fn read(path: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    std::fs::read(path).map_err(|e| format!("Could not read {}: {}", path, e).into())
+}
+
+/// TLS settings from the command line. Only the CA given with --ca-cert is
+/// trusted. Certificates cannot name IP addresses the way hkctl checks
+/// them, so the server is expected to be named localhost when connecting to
+/// an address, unless --server-name says otherwise.
+fn tls_config(matches: &ArgMatches, address: &ServerAddress) -> Result<Option<ClientTlsConfig>, Box<dyn Error>> {
+    if !matches.is_present("tls") {
+        return Ok(None);
+    }
+    let server_name = match (matches.value_of("server_name"), address) {
+        (Some(name), _) => name,
+        (None, ServerAddress::Tcp { host, .. }) if host.parse::<std::net::IpAddr>().is_err() => host,
+        (None, _) => "localhost",
+    };
+    let mut config = ClientTlsConfig::new()
+        .domain_name(server_name)
+        .ca_certificate(Certificate::from_pem(read(matches.value_of("ca_cert").unwrap())?));
+    if let Some(cert) = matches.value_of("client_cert") {
+        config = config.identity(Identity::from_pem(read(cert)?, read(matches.value_of("client_key").unwrap())?));
+    }
+    Ok(Some(config))
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
     let room_opt = Arg::new("room")
@@ -51,6 +167,64 @@ async fn main() -> Result<(), Box<dyn Error>> {
         .value_name("OPERATION")
         .about("Operation to be performed")
         .possible_values(&["add", "remove"]);
+    let accessory_arg = Arg::new("accessory")
+        .value_name("ACCESSORY")
+        .about("Accessory name or UUID");
+    let action_opt = Arg::new("action")
+        .long("action")
+        .short('a')
+        .value_name("ACCESSORY/SERVICE/CHARACTERISTIC=VALUE")
+        .about("Characteristic write action. The value is checked against the characteristic's format and metadata")
+        .multiple(true)
+        .number_of_values(1);
+    let action_set_opt = Arg::new("action_set")
+        .long("actionset")
+        .value_name("ACTION SET")
+        .about("Action set name or UUID")
+        .multiple(true)
+        .number_of_values(1);
+    let when_opt = Arg::new("when")
+        .long("when")
+        .value_name("EVENT")
+        .about("Event that fires the trigger: ACCESSORY/SERVICE/CHARACTERISTIC[=VALUE|>=MIN|<=MAX|=MIN..MAX], \
+                sunrise or sunset[+-OFFSET], arrive, leave, first-arrive or last-leave[@me], \
+                enter or exit LAT,LON,RADIUS, or at DATE")
+        .multiple(true)
+        .number_of_values(1);
+    let end_when_opt = Arg::new("end_when")
+        .long("end-when")
+        .value_name("EVENT")
+        .about("Event that ends the trigger, restoring what its action sets changed. Same syntax as --when")
+        .multiple(true)
+        .number_of_values(1);
+    let end_after_opt = Arg::new("end_after")
+        .long("end-after")
+        .value_name("DURATION")
+        .about("End the trigger after a duration, such as 5m or 1h30m");
+    let on_opt = Arg::new("on")
+        .long("on")
+        .value_name("DAYS")
+        .about("Days the trigger may fire on: comma separated days (mon,tue,...), weekdays, weekends or every-day");
+    let if_opt = Arg::new("if")
+        .long("if")
+        .value_name("CONDITION")
+        .about("Condition that must hold for the trigger to fire: ACCESSORY/SERVICE/CHARACTERISTIC(=|!=|<|<=|>|>=)VALUE, \
+                before or after sunrise, sunset[+-OFFSET] or HH:MM, between HH:MM-HH:MM, or home or away[@me]. \
+                Conditions can start with not and be combined with or. Repeated conditions must all hold")
+        .multiple(true)
+        .number_of_values(1);
+    let yes_opt = Arg::new("yes")
+        .long("yes")
+        .short('y')
+        .about("Apply the changes without asking for confirmation");
+    let characteristic_arg = Arg::new("characteristic")
+        .value_name("CHARACTERISTIC")
+        .about("Characteristic type (e.g. power_state), UUID or description");
+    let service_opt = Arg::new("service")
+        .long("service")
+        .short('s')
+        .value_name("NAME OR UUID")
+        .about("Service containing the characteristic, if the accessory has several");
     let mut app = App::new("hkctl")
         .version(crate_version!())
         .about("Command line porcelain for HomeKit")
@@ -62,12 +236,67 @@ async fn main() -> Result<(), Box<dyn Error>> {
              .long("port")
              .short('p')
              .value_name("PORT")
-             .about("Local port to connect to"))
+             .about("Port to connect to"))
+        .arg(Arg::new("host")
+             .long("host")
+             .value_name("HOST")
+             .about("Host running the server, optionally with a port as in host:port or [::1]:port, or unix:///path of its unix socket")
+             .default_value("127.0.0.1"))
+        .arg(Arg::new("token")
+             .long("token")
+             .value_name("TOKEN")
+             .about("API token for servers that require one")
+             .env("HKCTL_TOKEN"))
+        .arg(Arg::new("tls")
+             .long("tls")
+             .about("Connect with TLS")
+             .requires("ca_cert"))
+        .arg(Arg::new("ca_cert")
+             .long("ca-cert")
+             .value_name("FILE")
+             .about("PEM certificate of the CA that signed the server's certificate")
+             .requires("tls"))
+        .arg(Arg::new("server_name")
+             .long("server-name")
+             .value_name("NAME")
+             .about("Name the server's certificate must have. Defaults to the host, or localhost when the host is an address")
+             .requires("tls"))
+        .arg(Arg::new("client_cert")
+             .long("client-cert")
+             .value_name("FILE")
+             .about("PEM certificate to present to servers that require client certificates")
+             .requires_all(&["tls", "client_key"]))
+        .arg(Arg::new("client_key")
+             .long("client-key")
+             .value_name("FILE")
+             .about("PEM private key of the certificate given with --client-cert")
+             .requires("client_cert"))
         .arg(Arg::new("home")
              .long("home")
              .about("Specify a home. Defaults to the primary home")
              .value_name("NAME OR UUID")
              .global(true))
+        .arg(Arg::new("output")
+             .long("output")
+             .short('o')
+             .about("Output format")
+             .value_name("FORMAT")
+             .possible_values(&["text", "json", "yaml", "table"])
+             .default_value("text")
+             .global(true))
+        .arg(Arg::new("columns")
+             .long("columns")
+             .about("Comma separated columns to show with --output table")
+             .value_name("COLUMNS")
+             .global(true))
+        .arg(Arg::new("wide")
+             .long("wide")
+             .about("Show every column and do not truncate them to the terminal width with --output table")
+             .global(true))
+        .arg(Arg::new("decode_tlv")
+             .long("decode-tlv")
+             .about("Decode the values of TLV8 characteristics into their fields in text output")
+             .global(true))
         .subcommand(App::new("homes")
                     .about("Lists homes"))
         .subcommand(App::new("rooms")
@@ -121,7 +350,267 @@ async fn main() -> Result<(), Box<dyn Error>> {
                     .arg(name_arg.clone().required(true))
                     .arg(Arg::new("accessories")
                          .about("List of accessories to add/remove to/from a room. If empty, the room itself will be added or deleted")
-                         .multiple(true)));
+                         .multiple(true))
+                    .arg(yes_opt.clone()))
+        .subcommand(App::new("zone")
+                    .about("Manipulate zones")
+                    .setting(AppSettings::SubcommandsNegateReqs)
+                    .arg(operation_arg.clone().required(true))
+                    .arg(name_arg.clone().required(true))
+                    .arg(yes_opt.clone())
+                    .arg(Arg::new("rooms")
+                         .value_name("ROOM")
+                         .about("Rooms to put in a zone being added")
+                         .multiple(true))
+                    .subcommand(App::new("rooms")
+                                .about("Add or remove a room from a zone")
+                                .arg(operation_arg.clone().required(true))
+                                .arg(Arg::new("zone")
+                                     .value_name("ZONE")
+                                     .about("Zone name or UUID")
+                                     .required(true))
+                                .arg(Arg::new("room")
+                                     .value_name("ROOM")
+                                     .about("Room name or UUID")
+                                     .required(true))))
+        .subcommand(App::new("servicegroup")
+                    .about("Manipulate service groups")
+                    .setting(AppSettings::SubcommandsNegateReqs)
+                    .arg(operation_arg.clone().required(true))
+                    .arg(name_arg.clone().required(true))
+                    .arg(yes_opt.clone())
+                    .subcommand(App::new("members")
+                                .about("Add or remove services from a service group")
+                                .arg(operation_arg.clone().required(true))
+                                .arg(Arg::new("service_group")
+                                     .value_name("SERVICE GROUP")
+                                     .about("Service group name or UUID")
+                                     .required(true))
+                                .arg(Arg::new("services")
+                                     .long("services")
+                                     .value_name("PATTERN")
+                                     .about("Services to add or remove, by name or UUID pattern")
+                                     .required(true))
+                                .arg(Arg::new("dry_run")
+                                     .long("dry-run")
+                                     .about("Show the services that match without changing the service group"))))
+        .subcommand(App::new("actionset")
+                    .about("Manipulate action sets")
+                    .setting(AppSettings::SubcommandRequiredElseHelp)
+                    .subcommand(App::new("create")
+                                .about("Create an action set")
+                                .arg(name_arg.clone().required(true))
+                                .arg(action_opt.clone()))
+                    .subcommand(App::new("remove")
+                                .about("Remove an action set")
+                                .arg(name_arg.clone().required(true))
+                                .arg(yes_opt.clone()))
+                    .subcommand(App::new("actions")
+                                .about("Add or remove actions from an action set")
+                                .arg(operation_arg.clone().required(true))
+                                .arg(Arg::new("action_set")
+                                     .value_name("ACTION SET")
+                                     .about("Action set name or UUID")
+                                     .required(true))
+                                .arg(action_opt.clone()
+                                     .about("Action to add, or the UUID or ACCESSORY/SERVICE/CHARACTERISTIC of an action to remove")
+                                     .required(true))))
+        .subcommand(App::new("trigger")
+                    .about("Manipulate triggers")
+                    .setting(AppSettings::SubcommandRequiredElseHelp)
+                    .subcommand(App::new("create")
+                                .about("Create a trigger")
+                                .setting(AppSettings::SubcommandRequiredElseHelp)
+                                .subcommand(App::new("timer")
+                                            .about("Create a trigger that fires at a date, and optionally repeats")
+                                            .arg(name_arg.clone().required(true))
+                                            .arg(Arg::new("at")
+                                                 .long("at")
+                                                 .value_name("DATE")
+                                                 .about("First fire date, as YYYY-MM-DD HH:MM in UTC or RFC 3339")
+                                                 .required(true))
+                                            .arg(Arg::new("every")
+                                                 .long("every")
+                                                 .value_name("RECURRENCE")
+                                                 .about("Repeat daily, weekly, hourly or every N minutes, hours, days or weeks (e.g. 15m, 2h, 3d, 1w)"))
+                                            .arg(Arg::new("time_zone")
+                                                 .long("time-zone")
+                                                 .value_name("TIME ZONE")
+                                                 .about("Time zone the recurrence follows, such as Europe/Paris. Defaults to the home's"))
+                                            .arg(action_set_opt.clone()))
+                                .subcommand(App::new("event")
+                                            .about("Create a trigger that fires on characteristic, time, presence or location events")
+                                            .arg(name_arg.clone().required(true))
+                                            .arg(when_opt.clone().required(true))
+                                            .arg(end_when_opt.clone())
+                                            .arg(end_after_opt.clone())
+                                            .arg(on_opt.clone())
+                                            .arg(if_opt.clone())
+                                            .arg(Arg::new("once")
+                                                 .long("once")
+                                                 .about("Disable the trigger after it fires"))
+                                            .arg(action_set_opt.clone())))
+                    .subcommand(App::new("edit")
+                                .about("Change the events and conditions of an event trigger, keeping whatever is not given")
+                                .arg(name_arg.clone().required(true))
+                                .arg(when_opt.clone())
+                                .arg(end_when_opt.clone())
+                                .arg(end_after_opt.clone())
+                                .arg(Arg::new("no_end")
+                                     .long("no-end")
+                                     .about("Remove the end events")
+                                     .conflicts_with_all(&["end_when", "end_after"]))
+                                .arg(on_opt.clone())
+                                .arg(if_opt.clone())
+                                .arg(Arg::new("no_condition")
+                                     .long("no-condition")
+                                     .about("Remove the conditions")
+                                     .conflicts_with("if"))
+                                .arg(Arg::new("once")
+                                     .long("once")
+                                     .about("Disable the trigger after it fires"))
+                                .arg(Arg::new("repeat")
+                                     .long("repeat")
+                                     .about("Keep the trigger enabled after it fires")
+                                     .conflicts_with("once")))
+                    .subcommand(App::new("remove")
+                                .about("Remove a trigger")
+                                .arg(name_arg.clone().required(true))
+                                .arg(yes_opt.clone()))
+                    .subcommand(App::new("enable")
+                                .about("Enable every trigger matching a pattern")
+                                .arg(name_arg.clone().value_name("PATTERN").about("Trigger name or UUID pattern").required(true))
+                                .arg(yes_opt.clone()))
+                    .subcommand(App::new("disable")
+                                .about("Disable every trigger matching a pattern")
+                                .arg(name_arg.clone().value_name("PATTERN").about("Trigger name or UUID pattern").required(true))
+                                .arg(yes_opt.clone()))
+                    .subcommand(App::new("actionsets")
+                                .about("Add or remove action sets from a trigger")
+                                .arg(operation_arg.clone().required(true))
+                                .arg(Arg::new("trigger")
+                                     .value_name("TRIGGER")
+                                     .about("Trigger name or UUID")
+                                     .required(true))
+                                .arg(Arg::new("action_sets")
+                                     .value_name("ACTION SET")
+                                     .about("Action set names or UUIDs")
+                                     .multiple(true)
+                                     .required(true))))
+        .subcommand(App::new("rename")
+                    .about("Rename a home, room, zone, accessory, service group, action set or trigger")
+                    .arg(Arg::new("type")
+                         .value_name("TYPE")
+                         .about("Type of the object to rename")
+                         .possible_values(&["home", "room", "zone", "accessory", "servicegroup", "actionset", "trigger"])
+                         .required(true))
+                    .arg(name_arg.clone().required_unless_present("from_csv"))
+                    .arg(Arg::new("new_name")
+                         .value_name("NEW NAME")
+                         .about("New name")
+                         .required_unless_present("from_csv"))
+                    .arg(Arg::new("from_csv")
+                         .long("from-csv")
+                         .value_name("FILE")
+                         .about("Rename many objects of the type, from a CSV file of NAME-OR-UUID,NEW-NAME rows")
+                         .conflicts_with_all(&["name", "new_name"])))
+        .subcommand(App::new("move")
+                    .about("Move accessories to a room")
+                    .arg(Arg::new("accessories")
+                         .value_name("ACCESSORY")
+                         .about("Accessory names, patterns or UUIDs")
+                         .multiple(true)
+                         .required(true))
+                    .arg(Arg::new("to")
+                         .long("to")
+                         .value_name("ROOM")
+                         .about("Room name or UUID")
+                         .required(true))
+                    .arg(Arg::new("bridge")
+                         .long("bridge")
+                         .about("The accessories are bridges; also move every accessory behind them"))
+                    .arg(yes_opt.clone()))
+        .subcommand(App::new("run")
+                    .about("Run action sets and triggers, waiting for every action to complete")
+                    .setting(AppSettings::SubcommandRequiredElseHelp)
+                    .subcommand(App::new("actionset")
+                                .about("Run an action set")
+                                .arg(name_arg.clone().required(true)))
+                    .subcommand(App::new("trigger")
+                                .about("Run the action sets of a trigger as if it had fired")
+                                .arg(name_arg.clone().required(true))))
+        .subcommand(App::new("export")
+                    .about("Writes the configuration of a home as YAML")
+                    .arg(Arg::new("file")
+                         .long("file")
+                         .short('f')
+                         .value_name("FILE")
+                         .about("File to write instead of standard output")))
+        .subcommand(App::new("plan")
+                    .about("Shows the changes needed to make a home match a configuration file")
+                    .arg(Arg::new("file")
+                         .value_name("FILE")
+                         .about("Configuration written by export")
+                         .required(true)))
+        .subcommand(App::new("apply")
+                    .about("Changes a home to match a configuration file")
+                    .arg(Arg::new("file")
+                         .value_name("FILE")
+                         .about("Configuration written by export")
+                         .required(true))
+                    .arg(yes_opt.clone()))
+        .subcommand(App::new("get")
+                    .about("Reads the value of a characteristic")
+                    .arg(accessory_arg.clone().required(true))
+                    .arg(characteristic_arg.clone().required(true))
+                    .arg(service_opt.clone()))
+        .subcommand(App::new("set")
+                    .about("Writes the value of a characteristic")
+                    .arg(accessory_arg.clone().required(true))
+                    .arg(characteristic_arg.clone().required(true))
+                    .arg(Arg::new("value")
+                         .value_name("VALUE")
+                         .about("New value, parsed according to the characteristic's format. \
+                                 TLV8 values are hex, or JSON objects of field names and values")
+                         .required(true))
+                    .arg(service_opt.clone()))
+        .subcommand(App::new("watch")
+                    .about("Prints characteristic value changes as they happen")
+                    .arg(room_opt.clone())
+                    .arg(Arg::new("accessory")
+                         .long("accessory")
+                         .value_name("NAME OR UUID")
+                         .about("Accessory name pattern filter"))
+                    .arg(Arg::new("service")
+                         .long("service")
+                         .value_name("NAME OR UUID")
+                         .about("Service name pattern filter"))
+                    .arg(Arg::new("type")
+                         .long("type")
+                         .short('t')
+                         .value_name("SERVICE TYPE")
+                         .about("Only watch services of this type")
+                         .takes_value(true)
+                         .multiple(true))
+                    .arg(Arg::new("characteristic")
+                         .long("characteristic")
+                         .short('c')
+                         .value_name("CHARACTERISTIC TYPE")
+                         .about("Only watch characteristics of this type")
+                         .takes_value(true)
+                         .multiple(true))
+                    .arg(Arg::new("snapshot")
+                         .long("snapshot")
+                         .about("Print the current values before any changes")))
+        .subcommand(App::new("watchhome")
+                    .about("Prints changes to the configuration of a home as they happen"))
+        .subcommand(App::new("ping")
+                    .about("Checks that the server is up and serving"))
+        .subcommand(App::new("status")
+                    .about("Prints whether the server and its services are serving")
+                    .arg(Arg::new("watch")
+                         .long("watch")
+                         .about("Keep printing the status of the server as it changes")));
 
     let matches = app.get_matches_mut();
     let port = match matches.value_of_t::<u32>("port") {
@@ -134,9 +623,12 @@ async fn main() -> Result<(), Box<dyn Error>> {
             }
         }
     };
-    let client = HomeKitServiceClient::create("127.0.0.1", port).await?;
+    let address = ServerAddress::parse(matches.value_of("host").unwrap(), port)?;
+    let channel = connect(&address, tls_config(&matches, &address)?).await
+        .map_err(|e| format!("Could not connect to {}: {}", address, e))?;
+    let client = HomeKitServiceClient::create(channel.clone(), matches.value_of("token"))?;
 
-    let subcommand_fn = matches.subcommand_name().map(|name| {
+    let subcommand_fn = |name: &str| {
         match name {
             // Enumerate stuff
             "homes" => homes::run,
@@ -150,13 +642,34 @@ async fn main() -> Result<(), Box<dyn Error>> {
 
             // Organize a home
             "room" => room::run,
+            "zone" => zone::run,
+            "servicegroup" => service_group::run,
+            "actionset" => action_set::run,
+            "trigger" => trigger::run,
+            "run" => run::run,
+            "rename" => rename::run,
+            "move" => move_accessories::run,
+            "export" => home_config::export,
+            "plan" => home_config::plan_changes,
+            "apply" => home_config::apply,
+
+            // Read and write characteristics
+            "get" => characteristic::get,
+            "set" => characteristic::set,
+            "watch" => watch::run,
+            "watchhome" => watch::run_home,
             _ => panic!("Unrecognized subcommand name")
         }
-    });
+    };
 
-    if let Some(subcommand_fn) = subcommand_fn {
-        let args = matches.subcommand_matches(matches.subcommand_name().unwrap()).unwrap();
-        let result = subcommand_fn(args.clone(), client).await;
+    if let Some(name) = matches.subcommand_name() {
+        let args = matches.subcommand_matches(name).unwrap().clone();
+        let result = match name {
+            // Check on the server with the health service
+            "ping" => health::ping(args, channel).await,
+            "status" => health::status(args, channel).await,
+            _ => subcommand_fn(name)(args, client).await,
+        };
         if let Err(ref error) = result {
             match error.downcast_ref::<tonic::Status>() {
                 Some(e) => {
@@ -0,0 +1,86 @@
+use clap::{ArgMatches};
+use simple_error::SimpleError;
+use std::boxed::Box;
+use std::future::Future;
+use std::pin::Pin;
+use tonic::transport::Channel;
+use crate::hkservice::home_kit_service_client::HomeKitServiceClient;
+use crate::hkservice::{
+    AccessoryInformation,
+    EnumerateAccessoriesRequest,
+    MoveAccessoryToRoomRequest,
+    MoveAccessoryToRoomResponse,
+};
+use crate::output;
+use crate::resolve;
+
+fn print_response(response: &MoveAccessoryToRoomResponse) {
+    println!("Home: {}, Accessory {} moved to {}", response.home.as_ref().unwrap().name,
+             response.accessory.as_ref().unwrap().name, response.room.as_ref().unwrap().name);
+}
+
+async fn accessories(client: &mut HomeKitServiceClient<Channel>, home: &str, pattern: &str) -> Result<Vec<AccessoryInformation>, Box<dyn std::error::Error>> {
+    resolve::check_pattern(pattern)?;
+    Ok(client.enumerate_accessories(EnumerateAccessoriesRequest {
+        home: home.to_string(),
+        name_filter: pattern.to_string(),
+        ..Default::default()
+    }).await?.into_inner().accessories)
+}
+
+/// Moves the accessories named by the patterns to a room. A pattern that is
+/// the exact name or UUID of an accessory moves only that accessory, and
+/// otherwise every accessory it matches moves once confirmed. With --bridge
+/// only bridges match the patterns, and the accessories behind them move too.
+async fn _run(matches: ArgMatches, mut client: HomeKitServiceClient<Channel>) -> Result<(), Box<dyn std::error::Error>> {
+    let home = matches.value_of("home").unwrap_or("");
+    let room = resolve::room(&mut client, home, matches.value_of("to").unwrap()).await?;
+    let all = if matches.is_present("bridge") { accessories(&mut client, home, "").await? } else { vec![] };
+    let mut moving: Vec<AccessoryInformation> = vec![];
+    let mut ambiguous = false;
+    let kind = if matches.is_present("bridge") { "bridge" } else { "accessory" };
+    for pattern in matches.values_of("accessories").unwrap() {
+        let mut matched = accessories(&mut client, home, pattern).await?;
+        if matches.is_present("bridge") {
+            matched.retain(|accessory| !accessory.bridged_accessory_uuids.is_empty());
+        }
+        if matched.is_empty() {
+            return Err(Box::new(SimpleError::new(format!("No {} matches '{}'", kind, pattern))));
+        }
+        if matched.iter().any(|accessory| resolve::is_exact(accessory, pattern)) {
+            matched = vec![resolve::pick(kind, pattern, matched)?];
+        } else {
+            ambiguous = true;
+        }
+        for accessory in matched {
+            let bridged = all.iter().filter(|a| accessory.bridged_accessory_uuids.contains(&a.uuid)).cloned().collect::<Vec<_>>();
+            moving.push(accessory);
+            moving.extend(bridged);
+        }
+    }
+    let mut seen = std::collections::HashSet::new();
+    moving.retain(|accessory| seen.insert(accessory.uuid.clone()));
+
+    if ambiguous {
+        // Standard output is kept for the responses, which may be JSON or YAML
+        eprintln!("Moving {} accessories to {}:", moving.len(), room.name);
+        moving.iter().for_each(|accessory| eprintln!("  {} ({})", accessory.name, accessory.uuid));
+        if !matches.is_present("yes") && !resolve::confirm("Move them?")? {
+            eprintln!("Nothing was changed");
+            return Ok(());
+        }
+    }
+    for accessory in moving {
+        let response = client.move_accessory_to_room(MoveAccessoryToRoomRequest {
+            home: home.to_string(),
+            name: accessory.uuid,
+            room: room.uuid.clone(),
+        }).await?.into_inner();
+        output::print(&matches, &response, print_response)?;
+    }
+    Ok(())
+}
+
+pub fn run(matches: ArgMatches, client: HomeKitServiceClient<Channel>) -> Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error>>>>> {
+    Box::pin(_run(matches, client))
+}
@@ -0,0 +1,116 @@
+use clap::ArgMatches;
+use serde::{Serialize, Serializer};
+use serde::ser::SerializeSeq;
+use crate::hkservice::*;
+use crate::hkservice::accessory_information::Category;
+use crate::hkservice::action_set_information::ActionSetType;
+use crate::hkservice::characteristic_information::{CharacteristicType, Format, Property, Units};
+use crate::hkservice::compound_predicate::Kind;
+use crate::hkservice::enumerate_triggers_request::EnabledFilter;
+use crate::hkservice::event_trigger_information::ActivationState;
+use crate::hkservice::home_information::HomeHubState;
+use crate::hkservice::set_name_request::ObjectType;
+use crate::hkservice::significant_time_predicate::Relation;
+use crate::hkservice::timer_recurrence::Unit;
+use crate::table::{print_table, Table};
+
+/// Formats selectable with the global `--output` flag.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputFormat {
+    Text,
+    Json,
+    Yaml,
+    Table,
+}
+
+impl OutputFormat {
+    pub fn from_matches(matches: &ArgMatches) -> OutputFormat {
+        match matches.value_of("output").unwrap_or("text") {
+            "json" => OutputFormat::Json,
+            "yaml" => OutputFormat::Yaml,
+            "table" => OutputFormat::Table,
+            _ => OutputFormat::Text,
+        }
+    }
+}
+
+/// Prints a response in the format selected with `--output`. Text output is
+/// left to `print_text`; JSON and YAML serialize the response message itself.
+/// Responses that are not listings have no table form and print as text.
+pub fn print<T: Serialize, F: FnOnce(&T)>(matches: &ArgMatches, response: &T, print_text: F) -> Result<(), Box<dyn std::error::Error>> {
+    match OutputFormat::from_matches(matches) {
+        OutputFormat::Text | OutputFormat::Table => print_text(response),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(response)?),
+        OutputFormat::Yaml => print!("{}", serde_yaml::to_string(response)?),
+    }
+    Ok(())
+}
+
+/// Like `print`, for listings that can also be printed as a table.
+pub fn print_listing<T: Serialize + Table, F: FnOnce(&T)>(matches: &ArgMatches, response: &T, print_text: F) -> Result<(), Box<dyn std::error::Error>> {
+    match OutputFormat::from_matches(matches) {
+        OutputFormat::Table => Ok(print_table(matches, response)?),
+        _ => print(matches, response, print_text),
+    }
+}
+
+/// prost enums with a name for each of their values.
+pub trait Enumeration: Serialize + Sized {
+    fn from_i32(value: i32) -> Option<Self>;
+}
+
+macro_rules! impl_enumeration {
+    ($($t:ty),*) => {
+        $(
+            impl Enumeration for $t {
+                fn from_i32(value: i32) -> Option<$t> {
+                    <$t>::from_i32(value)
+                }
+            }
+        )*
+    }
+}
+
+impl_enumeration!(
+    ActionSetType,
+    ActivationState,
+    Category,
+    CharacteristicType,
+    ComparisonOperator,
+    EnabledFilter,
+    Format,
+    HomeHubState,
+    Kind,
+    ObjectType,
+    Operation,
+    PresenceEventType,
+    PresenceEventUserType,
+    Property,
+    Relation,
+    ServiceType,
+    SignificantEvent,
+    Unit,
+    Units,
+    Weekday
+);
+
+/// Serializes an enum field by name. Values unknown to this build of hkctl
+/// are serialized as numbers.
+pub fn enumeration<E: Enumeration, S: Serializer>(value: &i32, serializer: S) -> Result<S::Ok, S::Error> {
+    match E::from_i32(*value) {
+        Some(e) => e.serialize(serializer),
+        None => serializer.serialize_i32(*value),
+    }
+}
+
+/// Serializes a repeated enum field by name.
+pub fn enumerations<E: Enumeration, S: Serializer>(values: &Vec<i32>, serializer: S) -> Result<S::Ok, S::Error> {
+    let mut seq = serializer.serialize_seq(Some(values.len()))?;
+    for value in values {
+        match E::from_i32(*value) {
+            Some(e) => seq.serialize_element(&e)?,
+            None => seq.serialize_element(value)?,
+        }
+    }
+    seq.end()
+}
@@ -0,0 +1,97 @@
+use clap::{ArgMatches};
+use simple_error::SimpleError;
+use std::boxed::Box;
+use std::future::Future;
+use std::pin::Pin;
+use tonic::transport::Channel;
+use crate::hkservice::home_kit_service_client::HomeKitServiceClient;
+use crate::hkservice::{NameUuidPair, SetNameRequest, SetNameResponse};
+use crate::hkservice::set_name_request::ObjectType;
+use crate::output;
+use crate::resolve::{self, Named};
+
+fn print_response(response: &SetNameResponse, old_name: &str) {
+    println!("Home: {}, Renamed {} to {}", response.home.as_ref().unwrap().name, old_name, response.object.as_ref().unwrap().name);
+}
+
+fn object_type(s: &str) -> ObjectType {
+    match s {
+        "home" => ObjectType::Home,
+        "room" => ObjectType::Room,
+        "zone" => ObjectType::Zone,
+        "accessory" => ObjectType::Accessory,
+        "servicegroup" => ObjectType::ServiceGroup,
+        "actionset" => ObjectType::ActionSet,
+        "trigger" => ObjectType::Trigger,
+        _ => ObjectType::Unknown,
+    }
+}
+
+fn pair<T: Named>(object: T) -> NameUuidPair {
+    NameUuidPair { name: object.name().to_string(), uuid: object.uuid().to_string() }
+}
+
+async fn resolve(client: &mut HomeKitServiceClient<Channel>, home: &str, object_type: ObjectType, name_or_pattern: &str) -> Result<NameUuidPair, Box<dyn std::error::Error>> {
+    Ok(match object_type {
+        ObjectType::Home => pair(resolve::home(client, name_or_pattern).await?),
+        ObjectType::Room => pair(resolve::room(client, home, name_or_pattern).await?),
+        ObjectType::Zone => pair(resolve::zone(client, home, name_or_pattern).await?),
+        ObjectType::Accessory => pair(resolve::accessory(client, home, name_or_pattern).await?),
+        ObjectType::ServiceGroup => pair(resolve::service_group(client, home, name_or_pattern).await?),
+        ObjectType::ActionSet => pair(resolve::action_set(client, home, name_or_pattern).await?),
+        ObjectType::Trigger => pair(resolve::trigger(client, home, name_or_pattern).await?),
+        ObjectType::Unknown => return Err(Box::new(SimpleError::new("Unknown object type"))),
+    })
+}
+
+/// Reads the NAME-OR-UUID,NEW-NAME rows of a CSV file. A header row reading
+/// name,new_name is skipped, as are lines starting with #.
+fn read_csv(path: &str) -> Result<Vec<(String, String)>, Box<dyn std::error::Error>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .comment(Some(b'#'))
+        .trim(csv::Trim::All)
+        .from_path(path)?;
+    let mut rows = vec![];
+    for (index, record) in reader.records().enumerate() {
+        let record = record?;
+        if record.len() != 2 {
+            return Err(Box::new(SimpleError::new(format!("{}: line {} has {} columns, expected NAME-OR-UUID,NEW-NAME",
+                                                         path, record.position().map_or(0, |p| p.line()), record.len()))));
+        }
+        if index == 0 && record[0].eq_ignore_ascii_case("name") && record[1].eq_ignore_ascii_case("new_name") {
+            continue;
+        }
+        rows.push((record[0].to_string(), record[1].to_string()));
+    }
+    Ok(rows)
+}
+
+async fn _run(matches: ArgMatches, mut client: HomeKitServiceClient<Channel>) -> Result<(), Box<dyn std::error::Error>> {
+    let home = matches.value_of("home").unwrap_or("");
+    let object_type = object_type(matches.value_of("type").unwrap());
+    let renames = match matches.value_of("from_csv") {
+        Some(path) => read_csv(path)?,
+        None => vec![(matches.value_of("name").unwrap().to_string(), matches.value_of("new_name").unwrap().to_string())],
+    };
+    // Resolve every object before renaming any, so that a typo in a CSV file
+    // does not leave the renames half done
+    let mut objects = vec![];
+    for (name_or_pattern, new_name) in renames {
+        objects.push((resolve(&mut client, home, object_type, &name_or_pattern).await?, new_name));
+    }
+    for (object, new_name) in objects {
+        let response = client.set_name(SetNameRequest {
+            home: if object_type == ObjectType::Home { object.uuid.clone() } else { home.to_string() },
+            name: object.uuid.clone(),
+            new_name: new_name,
+            object_type: object_type as i32,
+        }).await?.into_inner();
+        output::print(&matches, &response, |response| print_response(response, &object.name))?;
+    }
+    Ok(())
+}
+
+pub fn run(matches: ArgMatches, client: HomeKitServiceClient<Channel>) -> Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error>>>>> {
+    Box::pin(_run(matches, client))
+}
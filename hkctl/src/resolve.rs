@@ -0,0 +1,226 @@
+use clap::ArgMatches;
+use regex::Regex;
+use simple_error::{SimpleError, SimpleResult};
+use std::boxed::Box;
+use std::io::Write;
+use tonic::transport::Channel;
+use crate::hkservice::home_kit_service_client::HomeKitServiceClient;
+use crate::hkservice::{
+    AccessoryInformation,
+    ActionSetInformation,
+    CommonTriggerInformation,
+    EnumerateAccessoriesRequest,
+    EnumerateActionSetsRequest,
+    EnumerateHomesRequest,
+    EnumerateRoomsRequest,
+    EnumerateServiceGroupsRequest,
+    EnumerateTriggersRequest,
+    EnumerateZonesRequest,
+    HomeInformation,
+    NameUuidPair,
+    RoomInformation,
+    ServiceGroupInformation,
+    ServiceInformation,
+    ZoneInformation,
+};
+use crate::hkservice::trigger_information::Trigger;
+
+pub trait Named {
+    fn name(&self) -> &str;
+    fn uuid(&self) -> &str;
+}
+
+#[macro_export]
+macro_rules! impl_named {
+    ($($t:ty),*) => {
+        $(
+            impl $crate::resolve::Named for $t {
+                fn name(&self) -> &str {
+                    &self.name
+                }
+
+                fn uuid(&self) -> &str {
+                    &self.uuid
+                }
+            }
+        )*
+    }
+}
+
+impl_named!(
+    AccessoryInformation,
+    ActionSetInformation,
+    CommonTriggerInformation,
+    HomeInformation,
+    NameUuidPair,
+    RoomInformation,
+    ServiceGroupInformation,
+    ServiceInformation,
+    ZoneInformation
+);
+
+fn describe<T: Named>(objects: &[&T]) -> String {
+    objects.iter()
+        .map(|o| format!("{} ({})", o.name(), o.uuid()))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// Picks the object the user meant out of the objects the server matched
+/// against `name_or_pattern`. A UUID or an exact name wins over other
+/// matches, but a name shared by several objects has to be disambiguated with
+/// a UUID.
+pub fn pick<T: Named>(object_type: &str, name_or_pattern: &str, candidates: Vec<T>) -> SimpleResult<T> {
+    if let Some(index) = candidates.iter().position(|c| c.uuid().eq_ignore_ascii_case(name_or_pattern)) {
+        return Ok(candidates.into_iter().nth(index).unwrap());
+    }
+    let exact = candidates.iter()
+        .enumerate()
+        .filter(|(_, c)| c.name() == name_or_pattern)
+        .map(|(index, _)| index)
+        .collect::<Vec<usize>>();
+    let index = match exact.len() {
+        1 => exact[0],
+        0 if candidates.len() == 1 => 0,
+        0 if candidates.is_empty() => {
+            return Err(SimpleError::new(format!("No {} matches '{}'", object_type, name_or_pattern)));
+        },
+        0 => {
+            let matches = candidates.iter().collect::<Vec<&T>>();
+            return Err(SimpleError::new(format!("'{}' matches {} {}s: {}. Use the exact name or a UUID",
+                                                name_or_pattern, candidates.len(), object_type, describe(&matches))));
+        },
+        _ => {
+            let matches = exact.iter().map(|index| &candidates[*index]).collect::<Vec<&T>>();
+            return Err(SimpleError::new(format!("There are {} {}s named '{}': {}. Use a UUID instead",
+                                                exact.len(), object_type, name_or_pattern, describe(&matches))));
+        },
+    };
+    Ok(candidates.into_iter().nth(index).unwrap())
+}
+
+/// Whether `name_or_pattern` names the object exactly, by name or UUID,
+/// rather than being a pattern that happens to match only it.
+pub fn is_exact<T: Named>(object: &T, name_or_pattern: &str) -> bool {
+    object.name() == name_or_pattern || object.uuid().eq_ignore_ascii_case(name_or_pattern)
+}
+
+/// Confirms removing an object that was picked with a pattern rather than by
+/// its exact name or UUID, unless --yes was given.
+pub fn confirm_removal<T: Named>(matches: &ArgMatches, object_type: &str, name_or_pattern: &str, object: &T) -> std::io::Result<bool> {
+    if is_exact(object, name_or_pattern) || matches.is_present("yes") {
+        return Ok(true);
+    }
+    eprintln!("'{}' matches {} {} ({})", name_or_pattern, object_type, object.name(), object.uuid());
+    if confirm("Remove it?")? {
+        return Ok(true);
+    }
+    eprintln!("Nothing was changed");
+    Ok(false)
+}
+
+/// Asks the user to confirm on stdin, prompting on stderr so that the prompt
+/// stays out of --output json and yaml. Anything but "y" or "yes" declines.
+pub fn confirm(prompt: &str) -> std::io::Result<bool> {
+    eprint!("{} [y/N] ", prompt);
+    std::io::stderr().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(["y", "yes"].contains(&answer.trim().to_lowercase().as_str()))
+}
+
+/// Checks that a pattern compiles before it is sent to the server, which
+/// treats an invalid pattern as matching everything.
+pub fn check_pattern(pattern: &str) -> SimpleResult<()> {
+    Regex::new(pattern)
+        .map(|_| ())
+        .map_err(|e| SimpleError::new(format!("Invalid pattern '{}': {}", pattern, e)))
+}
+
+/// The pattern that matches only the object with this UUID, for RPCs that
+/// take patterns.
+pub fn exactly(uuid: &str) -> String {
+    format!("^{}$", uuid)
+}
+
+pub async fn home(client: &mut HomeKitServiceClient<Channel>, name_or_pattern: &str) -> Result<HomeInformation, Box<dyn std::error::Error>> {
+    check_pattern(name_or_pattern)?;
+    let homes = client.enumerate_homes(EnumerateHomesRequest {
+        name_filter: name_or_pattern.to_string(),
+    }).await?.into_inner().homes;
+    Ok(pick("home", name_or_pattern, homes)?)
+}
+
+pub async fn accessory(client: &mut HomeKitServiceClient<Channel>, home: &str, name_or_pattern: &str) -> Result<AccessoryInformation, Box<dyn std::error::Error>> {
+    check_pattern(name_or_pattern)?;
+    let accessories = client.enumerate_accessories(EnumerateAccessoriesRequest {
+        home: home.to_string(),
+        name_filter: name_or_pattern.to_string(),
+        ..Default::default()
+    }).await?.into_inner().accessories;
+    Ok(pick("accessory", name_or_pattern, accessories)?)
+}
+
+pub async fn room(client: &mut HomeKitServiceClient<Channel>, home: &str, name_or_pattern: &str) -> Result<RoomInformation, Box<dyn std::error::Error>> {
+    check_pattern(name_or_pattern)?;
+    let mut rooms = client.enumerate_rooms(EnumerateRoomsRequest {
+        home: home.to_string(),
+        name_filter: name_or_pattern.to_string(),
+    }).await?.into_inner().rooms;
+    // The room for the entire home is listed first whatever the filter, so it
+    // only counts when asked for by name or UUID
+    if rooms.first().map_or(false, |room| room.name != name_or_pattern && !room.uuid.eq_ignore_ascii_case(name_or_pattern)) {
+        rooms.remove(0);
+    }
+    Ok(pick("room", name_or_pattern, rooms)?)
+}
+
+pub async fn zone(client: &mut HomeKitServiceClient<Channel>, home: &str, name_or_pattern: &str) -> Result<ZoneInformation, Box<dyn std::error::Error>> {
+    check_pattern(name_or_pattern)?;
+    let zones = client.enumerate_zones(EnumerateZonesRequest {
+        home: home.to_string(),
+        name_filter: name_or_pattern.to_string(),
+        ..Default::default()
+    }).await?.into_inner().zones;
+    Ok(pick("zone", name_or_pattern, zones)?)
+}
+
+pub async fn service_group(client: &mut HomeKitServiceClient<Channel>, home: &str, name_or_pattern: &str) -> Result<ServiceGroupInformation, Box<dyn std::error::Error>> {
+    check_pattern(name_or_pattern)?;
+    let service_groups = client.enumerate_service_groups(EnumerateServiceGroupsRequest {
+        home: home.to_string(),
+        name_filter: name_or_pattern.to_string(),
+    }).await?.into_inner().service_groups;
+    Ok(pick("service group", name_or_pattern, service_groups)?)
+}
+
+pub async fn action_set(client: &mut HomeKitServiceClient<Channel>, home: &str, name_or_pattern: &str) -> Result<ActionSetInformation, Box<dyn std::error::Error>> {
+    check_pattern(name_or_pattern)?;
+    let action_sets = client.enumerate_action_sets(EnumerateActionSetsRequest {
+        home: home.to_string(),
+        name_filter: name_or_pattern.to_string(),
+    }).await?.into_inner().action_sets;
+    Ok(pick("action set", name_or_pattern, action_sets)?)
+}
+
+/// Every trigger matching the pattern, for commands that act on several.
+pub async fn triggers(client: &mut HomeKitServiceClient<Channel>, home: &str, pattern: &str) -> Result<Vec<CommonTriggerInformation>, Box<dyn std::error::Error>> {
+    check_pattern(pattern)?;
+    Ok(client.enumerate_triggers(EnumerateTriggersRequest {
+        home: home.to_string(),
+        name_filter: pattern.to_string(),
+        ..Default::default()
+    }).await?.into_inner().triggers.into_iter()
+        .filter_map(|trigger| match trigger.trigger {
+            Some(Trigger::Event(event)) => event.trigger,
+            Some(Trigger::Timer(timer)) => timer.trigger,
+            None => None,
+        })
+        .collect())
+}
+
+pub async fn trigger(client: &mut HomeKitServiceClient<Channel>, home: &str, name_or_pattern: &str) -> Result<CommonTriggerInformation, Box<dyn std::error::Error>> {
+    check_pattern(name_or_pattern)?;
+    let triggers = triggers(client, home, name_or_pattern).await?;
+    Ok(pick("trigger", name_or_pattern, triggers)?)
+}
@@ -7,6 +7,8 @@ use std::str::FromStr;
 use tonic::transport::Channel;
 use crate::hkservice::home_kit_service_client::HomeKitServiceClient;
 use crate::hkservice::{AddRemoveRoomRequest, AddRemoveRoomResponse};
+use crate::output;
+use crate::resolve;
 
 impl FromStr for crate::hkservice::Operation {
     type Err = SimpleError;
@@ -27,15 +29,26 @@ fn print_response(response: &AddRemoveRoomResponse) {
 
 async fn _run(matches: ArgMatches, mut client: HomeKitServiceClient<Channel>) -> Result<(), Box<dyn std::error::Error>> {
     let operation_string = matches.value_of("operation").unwrap();
+    let operation = crate::hkservice::Operation::from_str(operation_string).unwrap();
+    let home = matches.value_of("home").unwrap_or("");
+    let mut name = matches.value_of("name").unwrap_or("").to_string();
+    if operation == crate::hkservice::Operation::Remove {
+        // The server removes the first room matching the pattern, so make
+        // sure it is the only one
+        let room = resolve::room(&mut client, home, &name).await?;
+        if !resolve::confirm_removal(&matches, "room", &name, &room)? {
+            return Ok(());
+        }
+        name = resolve::exactly(&room.uuid);
+    }
     let request = AddRemoveRoomRequest {
-        home: matches.value_of("home").unwrap_or("").to_string(),
-        name: matches.value_of("name").unwrap_or("").to_string(),
+        home: home.to_string(),
+        name: name,
         accessories: matches.values_of("accessories").map_or(vec![], |values| values.collect()).iter().map(|s| s.to_string()).collect(),
-        operation: crate::hkservice::Operation::from_str(operation_string).unwrap() as i32,
+        operation: operation as i32,
     };
     let response = client.add_remove_room(request.clone()).await?.into_inner();
-    print_response(&response);
-    Ok(())
+    output::print(&matches, &response, print_response)
 }
 
 pub fn run(matches: ArgMatches, client: HomeKitServiceClient<Channel>) -> Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error>>>>> {
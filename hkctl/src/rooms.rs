@@ -5,6 +5,8 @@ use std::pin::Pin;
 use tonic::transport::Channel;
 use crate::hkservice::home_kit_service_client::HomeKitServiceClient;
 use crate::hkservice::{EnumerateRoomsRequest, EnumerateRoomsResponse};
+use crate::output;
+use crate::table::{names, Column, Table};
 
 fn print_response(response: &EnumerateRoomsResponse) {
     if let Some(ref home) = &response.home {
@@ -22,14 +24,29 @@ fn print_response(response: &EnumerateRoomsResponse) {
     });
 }
 
+impl Table for EnumerateRoomsResponse {
+    const COLUMNS: &'static [Column] = &[
+        Column { name: "name", default: true },
+        Column { name: "uuid", default: false },
+        Column { name: "accessories", default: true },
+    ];
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        self.rooms.iter().map(|room| vec![
+            room.name.clone(),
+            room.uuid.clone(),
+            names(&room.accessories),
+        ]).collect()
+    }
+}
+
 async fn _run(matches: ArgMatches, mut client: HomeKitServiceClient<Channel>) -> Result<(), Box<dyn std::error::Error>> {
     let response = client.enumerate_rooms(
         EnumerateRoomsRequest {
             home: matches.value_of("home").unwrap_or("").to_string(),
             name_filter: matches.value_of("name").unwrap_or("").to_string(),
         }).await?.into_inner();
-    print_response(&response);
-    Ok(())
+    output::print_listing(&matches, &response, print_response)
 }
 
 pub fn run(matches: ArgMatches, client: HomeKitServiceClient<Channel>) -> Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error>>>>> {
@@ -0,0 +1,91 @@
+use clap::{ArgMatches};
+use simple_error::SimpleError;
+use std::boxed::Box;
+use std::future::Future;
+use std::pin::Pin;
+use tonic::transport::Channel;
+use crate::hkservice::home_kit_service_client::HomeKitServiceClient;
+use crate::hkservice::{
+    ActionResult,
+    RunActionSetRequest,
+    RunActionSetResponse,
+    RunTriggerRequest,
+    RunTriggerResponse,
+};
+use crate::output;
+use crate::resolve;
+
+fn print_result(result: &ActionResult) {
+    let mut target = vec![];
+    if let Some(ref accessory) = result.accessory {
+        target.push(accessory.name.clone());
+    }
+    if let Some(ref service) = result.service {
+        target.push(service.name.clone());
+    }
+    target.push(result.characteristic_type().to_string());
+    let value = result.target_value.as_ref().map_or("-".to_string(), |v| v.to_string());
+    if result.success {
+        println!("    OK     {} = {}", target.join("/"), value);
+    } else {
+        println!("    FAILED {} = {}: {}", target.join("/"), value, result.error);
+    }
+}
+
+fn print_action_set_response(response: &RunActionSetResponse) {
+    println!("Home: {}", response.home.as_ref().unwrap().name);
+    println!("  Action Set: {}", response.action_set.as_ref().unwrap().name);
+    response.results.iter().for_each(print_result);
+}
+
+fn print_trigger_response(response: &RunTriggerResponse) {
+    println!("Home: {}, Trigger {}", response.home.as_ref().unwrap().name, response.trigger.as_ref().unwrap().name);
+    response.action_sets.iter().for_each(|action_set| {
+        println!("  Action Set: {}", action_set.action_set.as_ref().unwrap().name);
+        action_set.results.iter().for_each(print_result);
+    });
+}
+
+/// Fails when any action failed, so that scripts can tell a partial failure
+/// from success.
+fn check_results<'a, I: Iterator<Item = &'a ActionResult>>(results: I) -> Result<(), Box<dyn std::error::Error>> {
+    let (total, failed) = results.fold((0, 0), |(total, failed), result| (total + 1, failed + if result.success { 0 } else { 1 }));
+    if failed > 0 {
+        return Err(Box::new(SimpleError::new(format!("{} of {} actions failed", failed, total))));
+    }
+    Ok(())
+}
+
+async fn _action_set(matches: &ArgMatches, mut client: HomeKitServiceClient<Channel>) -> Result<(), Box<dyn std::error::Error>> {
+    let home = matches.value_of("home").unwrap_or("");
+    let action_set = resolve::action_set(&mut client, home, matches.value_of("name").unwrap()).await?;
+    let response = client.run_action_set(RunActionSetRequest {
+        home: home.to_string(),
+        name: action_set.uuid,
+    }).await?.into_inner();
+    output::print(matches, &response, print_action_set_response)?;
+    check_results(response.results.iter())
+}
+
+async fn _trigger(matches: &ArgMatches, mut client: HomeKitServiceClient<Channel>) -> Result<(), Box<dyn std::error::Error>> {
+    let home = matches.value_of("home").unwrap_or("");
+    let trigger = resolve::trigger(&mut client, home, matches.value_of("name").unwrap()).await?;
+    let response = client.run_trigger(RunTriggerRequest {
+        home: home.to_string(),
+        name: trigger.uuid,
+    }).await?.into_inner();
+    output::print(matches, &response, print_trigger_response)?;
+    check_results(response.action_sets.iter().flat_map(|action_set| action_set.results.iter()))
+}
+
+async fn _run(matches: ArgMatches, client: HomeKitServiceClient<Channel>) -> Result<(), Box<dyn std::error::Error>> {
+    match matches.subcommand() {
+        Some(("actionset", action_set_matches)) => _action_set(action_set_matches, client).await,
+        Some(("trigger", trigger_matches)) => _trigger(trigger_matches, client).await,
+        _ => Err(Box::new(SimpleError::new("Expected one of actionset or trigger"))),
+    }
+}
+
+pub fn run(matches: ArgMatches, client: HomeKitServiceClient<Channel>) -> Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error>>>>> {
+    Box::pin(_run(matches, client))
+}
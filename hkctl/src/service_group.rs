@@ -0,0 +1,123 @@
+use clap::{ArgMatches};
+use std::boxed::Box;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::str::FromStr;
+use tonic::transport::Channel;
+use crate::hkservice::home_kit_service_client::HomeKitServiceClient;
+use crate::hkservice::{
+    AddRemoveServiceGroupRequest,
+    AddRemoveServiceGroupResponse,
+    ChangeServiceGroupMembershipRequest,
+    ChangeServiceGroupMembershipResponse,
+    EnumerateServicesRequest,
+    NameUuidPair,
+    Operation,
+    ServiceInformation,
+};
+use crate::output::{self, OutputFormat};
+use crate::resolve;
+
+fn print_response(response: &AddRemoveServiceGroupResponse) {
+    println!("Home: {}, Service Group {}", response.home.as_ref().unwrap().name, response.service_group.as_ref().unwrap().name);
+}
+
+/// Services print as "Accessory/Service", since service names are usually
+/// only unique within an accessory.
+fn describe(service: &NameUuidPair, services: &HashMap<String, ServiceInformation>) -> String {
+    let accessory = services.get(&service.uuid)
+        .and_then(|s| s.accessory.as_ref())
+        .map_or(String::new(), |a| format!("{}/", a.name));
+    format!("{}{} ({})", accessory, service.name, service.uuid)
+}
+
+fn print_services(title: &str, services: &[NameUuidPair], all: &HashMap<String, ServiceInformation>) {
+    println!("{} ({}):", title, services.len());
+    services.iter().for_each(|service| println!("  {}", describe(service, all)));
+}
+
+async fn _run(matches: ArgMatches, mut client: HomeKitServiceClient<Channel>) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(members_matches) = matches.subcommand_matches("members") {
+        return change_membership(members_matches, client).await;
+    }
+    let home = matches.value_of("home").unwrap_or("");
+    let name = matches.value_of("name").unwrap();
+    let operation = Operation::from_str(matches.value_of("operation").unwrap())?;
+    let name = match operation {
+        Operation::Add => name.to_string(),
+        // The server removes the first service group matching the pattern,
+        // so make sure it is the only one
+        Operation::Remove => {
+            let service_group = resolve::service_group(&mut client, home, name).await?;
+            if !resolve::confirm_removal(&matches, "service group", name, &service_group)? {
+                return Ok(());
+            }
+            resolve::exactly(&service_group.uuid)
+        },
+    };
+    let response = client.add_remove_service_group(AddRemoveServiceGroupRequest {
+        home: home.to_string(),
+        name: name,
+        services: vec![],
+        operation: operation as i32,
+    }).await?.into_inner();
+    output::print(&matches, &response, print_response)
+}
+
+async fn change_membership(matches: &ArgMatches, mut client: HomeKitServiceClient<Channel>) -> Result<(), Box<dyn std::error::Error>> {
+    let home = matches.value_of("home").unwrap_or("");
+    let operation = Operation::from_str(matches.value_of("operation").unwrap())?;
+    let filter = matches.value_of("services").unwrap();
+    resolve::check_pattern(filter)?;
+    let text = matches!(OutputFormat::from_matches(matches), OutputFormat::Text | OutputFormat::Table);
+    let service_group = resolve::service_group(&mut client, home, matches.value_of("service_group").unwrap()).await?;
+    let services = client.enumerate_services(EnumerateServicesRequest {
+        home: home.to_string(),
+        ..Default::default()
+    }).await?.into_inner().services.into_iter()
+        .map(|service| (service.uuid.clone(), service))
+        .collect::<HashMap<String, ServiceInformation>>();
+
+    // Resolve the filter the way the server will: against every service when
+    // adding, and against the members of the group when removing
+    let matched = client.enumerate_services(EnumerateServicesRequest {
+        home: home.to_string(),
+        name_filter: filter.to_string(),
+        ..Default::default()
+    }).await?.into_inner().services.into_iter()
+        .filter(|service| operation == Operation::Add || service_group.services.iter().any(|s| s.uuid == service.uuid))
+        .map(|service| NameUuidPair { name: service.name, uuid: service.uuid })
+        .collect::<Vec<NameUuidPair>>();
+    if text {
+        print_services(&format!("Services matching '{}'", filter), &matched, &services);
+    }
+
+    let dry_run = matches.is_present("dry_run");
+    if dry_run || matched.is_empty() {
+        let reason = if dry_run { "dry run" } else { "nothing matched" };
+        let response = ChangeServiceGroupMembershipResponse {
+            home: None,
+            service_group: Some(NameUuidPair { name: service_group.name.clone(), uuid: service_group.uuid.clone() }),
+            services: matched,
+        };
+        return output::print(matches, &response, |_| println!("Service group {} was not changed ({})", service_group.name, reason));
+    }
+
+    let response = client.change_service_group_membership(ChangeServiceGroupMembershipRequest {
+        home: home.to_string(),
+        name: service_group.uuid.clone(),
+        service_filter: filter.to_string(),
+        operation: operation as i32,
+    }).await?.into_inner();
+    let members = resolve::service_group(&mut client, home, &service_group.uuid).await?.services;
+    output::print(matches, &response, |response| {
+        let verb = if operation == Operation::Add { "Added to" } else { "Removed from" };
+        print_services(&format!("{} {}", verb, service_group.name), &response.services, &services);
+        print_services(&format!("Now in {}", service_group.name), &members, &services);
+    })
+}
+
+pub fn run(matches: ArgMatches, client: HomeKitServiceClient<Channel>) -> Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error>>>>> {
+    Box::pin(_run(matches, client))
+}
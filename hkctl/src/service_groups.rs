@@ -5,6 +5,8 @@ use std::pin::Pin;
 use tonic::transport::Channel;
 use crate::hkservice::home_kit_service_client::HomeKitServiceClient;
 use crate::hkservice::{EnumerateServiceGroupsRequest, EnumerateServiceGroupsResponse};
+use crate::output;
+use crate::table::{names, Column, Table};
 
 fn print_response(response: &EnumerateServiceGroupsResponse) {
     if let Some(ref home) = response.home {
@@ -21,14 +23,29 @@ fn print_response(response: &EnumerateServiceGroupsResponse) {
     });
 }
 
+impl Table for EnumerateServiceGroupsResponse {
+    const COLUMNS: &'static [Column] = &[
+        Column { name: "name", default: true },
+        Column { name: "uuid", default: false },
+        Column { name: "services", default: true },
+    ];
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        self.service_groups.iter().map(|service_group| vec![
+            service_group.name.clone(),
+            service_group.uuid.clone(),
+            names(&service_group.services),
+        ]).collect()
+    }
+}
+
 async fn _run(matches: ArgMatches, mut client: HomeKitServiceClient<Channel>) -> Result<(), Box<dyn std::error::Error>> {
     let response = client.enumerate_service_groups(
         EnumerateServiceGroupsRequest {
             home: matches.value_of("home").unwrap_or("").to_string(),
             name_filter: matches.value_of("name").unwrap_or("").to_string(),
         }).await?.into_inner();
-    print_response(&response);
-    Ok(())
+    output::print_listing(&matches, &response, print_response)
 }
 
 pub fn run(matches: ArgMatches, client: HomeKitServiceClient<Channel>) -> Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error>>>>> {
@@ -1,60 +1,37 @@
 use clap::{ArgMatches};
+use simple_error::{SimpleError, SimpleResult};
 use std::boxed::Box;
 use std::future::Future;
 use std::pin::Pin;
 use tonic::transport::Channel;
 use crate::hkservice::home_kit_service_client::HomeKitServiceClient;
 use crate::hkservice::{EnumerateServicesRequest, EnumerateServicesResponse, ServiceInformation, ServiceType, CharacteristicInformation};
+use crate::output;
+use crate::tlv;
+use crate::table::{Column, Table};
 
-fn servicetype_from_str(s: &str) -> ServiceType {
-    match s {
-        "LightBulb" => ServiceType::LightBulb,
-        "LightSensor" => ServiceType::LightSensor,
-        "Switch" => ServiceType::Switch,
-        "Battery" => ServiceType::Battery,
-        "Outlet" => ServiceType::Outlet,
-        "StatefulProgrammableSwitch" => ServiceType::StatefulProgrammableSwitch,
-        "StatelessProgrammableSwitch" => ServiceType::StatelessProgrammableSwitch,
-        "AirPurifier" => ServiceType::AirPurifier,
-        "AirQualitySensor" => ServiceType::AirQualitySensor,
-        "CarbonDioxideSensor" => ServiceType::CarbonDioxideSensor,
-        "CarbonMonoxideSensor" => ServiceType::CarbonMonoxideSensor,
-        "SmokeSensor" => ServiceType::SmokeSensor,
-        "HeaterCooler" => ServiceType::HeaterCooler,
-        "TemperatureSensor" => ServiceType::TemperatureSensor,
-        "Thermostat" => ServiceType::Thermostat,
-        "Fan" => ServiceType::Fan,
-        "FilterMaintenance" => ServiceType::FilterMaintenance,
-        "HumidifierDehumidifier" => ServiceType::HumidifierDehumidifier,
-        "HumiditySensor" => ServiceType::HumiditySensor,
-        "VentilationFan" => ServiceType::VentilationFan,
-        "Window" => ServiceType::Window,
-        "WindowCovering" => ServiceType::WindowCovering,
-        "Slats" => ServiceType::Slats,
-        "Faucet" => ServiceType::Faucet,
-        "Valve" => ServiceType::Valve,
-        "IrrigationSystem" => ServiceType::IrrigationSystem,
-        "LeakSensor" => ServiceType::LeakSensor,
-        "Door" => ServiceType::Door,
-        "Doorbell" => ServiceType::Doorbell,
-        "GarageDoorOpener" => ServiceType::GarageDoorOpener,
-        "LockManagement" => ServiceType::LockManagement,
-        "LockMechanism" => ServiceType::LockMechanism,
-        "MotionSensor" => ServiceType::MotionSensor,
-        "OccupancySensor" => ServiceType::OccupancySensor,
-        "SecuritySystem" => ServiceType::SecuritySystem,
-        "ContactSensor" => ServiceType::ContactSensor,
-        "CameraControl" => ServiceType::CameraControl,
-        "CameraRtpStreamManagement" => ServiceType::CameraRtpStreamManagement,
-        "Microphone" => ServiceType::Microphone,
-        "Speaker" => ServiceType::Speaker,
-        "Label" => ServiceType::Label,
-        "AccessoryInformation " => ServiceType::AccessoryInformation,
-        _ => ServiceType::InvalidServiceType,
-    }
+/// Parses a service type, ignoring case and underscores so that
+/// `LightBulb`, `light_bulb` and `LIGHT_BULB` are all accepted.
+pub fn servicetype_from_str(s: &str) -> ServiceType {
+    let normalized = s.replace("_", "").to_lowercase();
+    (0..64)
+        .filter_map(ServiceType::from_i32)
+        .find(|t| format!("{:?}", t).to_lowercase() == normalized)
+        .unwrap_or(ServiceType::InvalidServiceType)
+}
+
+/// Parses the service types given with `name`, failing on unknown types
+/// rather than silently matching nothing.
+pub fn servicetypes_from_matches(matches: &ArgMatches, name: &str) -> SimpleResult<Vec<i32>> {
+    matches.values_of(name).map_or(Ok(vec![]), |values| values
+        .map(|s| match servicetype_from_str(s) {
+            ServiceType::InvalidServiceType => Err(SimpleError::new(format!("Unknown service type '{}'", s))),
+            service_type => Ok(service_type as i32),
+        })
+        .collect())
 }
 
-pub fn print_characteristic(c: &CharacteristicInformation, indent: usize) {
+pub fn print_characteristic(c: &CharacteristicInformation, indent: usize, decode_tlv: bool) {
     let prefix = " ".repeat(indent);
     println!("{}Characteristic: {}", prefix, c.uuid);
     println!("{}  Description: {}", prefix, c.description);
@@ -83,11 +60,11 @@ pub fn print_characteristic(c: &CharacteristicInformation, indent: usize) {
         println!("{}    Units: {}", prefix, metadata.units());
     }
     if let Some(ref value) = c.value {
-        println!("{}  Last Value: {}", prefix, value);
+        println!("{}  Last Value:{}", prefix, tlv::format_value(c, value, decode_tlv, indent + 4));
     }
 }
 
-pub fn print_service(service: &ServiceInformation, indent: usize) {
+pub fn print_service(service: &ServiceInformation, indent: usize, decode_tlv: bool) {
     let prefix = " ".repeat(indent);
     println!("{}Service: {}", prefix, service.name);
     println!("{}  UUID: {}", prefix, service.uuid);
@@ -97,33 +74,53 @@ pub fn print_service(service: &ServiceInformation, indent: usize) {
     println!("{}  Associated Service Type: {}", prefix, service.associated_service_type);
     println!("{}  Characteristics: ({})", prefix, service.characteristics.len());
     service.characteristics.iter().for_each(|c| {
-        print_characteristic(c, indent + 4);
+        print_characteristic(c, indent + 4, decode_tlv);
     });
 }
 
-fn print_response(response: &EnumerateServicesResponse) {
+fn print_response(response: &EnumerateServicesResponse, decode_tlv: bool) {
     if let Some(ref home) = response.home {
         println!("Home: {}", home.name);
     }
     println!("Services: ({})", response.services.len());
     response.services.iter().for_each(|service| {
-        print_service(service, 2);
+        print_service(service, 2, decode_tlv);
     });
 }
 
+impl Table for EnumerateServicesResponse {
+    const COLUMNS: &'static [Column] = &[
+        Column { name: "name", default: true },
+        Column { name: "uuid", default: false },
+        Column { name: "accessory", default: true },
+        Column { name: "type", default: true },
+        Column { name: "primary", default: false },
+        Column { name: "characteristics", default: true },
+    ];
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        self.services.iter().map(|service| vec![
+            service.name.clone(),
+            service.uuid.clone(),
+            service.accessory.as_ref().map_or(String::new(), |accessory| accessory.name.clone()),
+            service.service_type().to_string(),
+            service.is_primary.to_string(),
+            service.characteristics.iter()
+                .map(|c| c.characteristic_type().to_string())
+                .collect::<Vec<String>>()
+                .join(", "),
+        ]).collect()
+    }
+}
+
 async fn _run(matches: ArgMatches, mut client: HomeKitServiceClient<Channel>) -> Result<(), Box<dyn std::error::Error>> {
     let response = client.enumerate_services(
         EnumerateServicesRequest {
             home: matches.value_of("home").unwrap_or("").to_string(),
-            types: matches.values_of("type").map(
-                |v| v
-                    .map(|s| servicetype_from_str(s) as i32)
-                    .collect()
-            ).unwrap_or(vec![]),
+            types: servicetypes_from_matches(&matches, "type")?,
             name_filter: matches.value_of("name").unwrap_or("").to_string(),
         }).await?.into_inner();
-    print_response(&response);
-    Ok(())
+    output::print_listing(&matches, &response, |r| print_response(r, matches.is_present("decode_tlv")))
 }
 
 pub fn run(matches: ArgMatches, client: HomeKitServiceClient<Channel>) -> Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error>>>>> {
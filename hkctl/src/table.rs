@@ -0,0 +1,109 @@
+use clap::ArgMatches;
+use simple_error::{SimpleError, SimpleResult};
+use terminal_size::{terminal_size, Width};
+use crate::hkservice::NameUuidPair;
+
+/// A column of a table. Columns that are not shown by default appear with
+/// `--wide`, or when selected with `--columns`.
+pub struct Column {
+    pub name: &'static str,
+    pub default: bool,
+}
+
+/// Responses that can be printed with `--output table`, one row per object.
+pub trait Table {
+    const COLUMNS: &'static [Column];
+
+    /// The cells of every row, one per entry in `COLUMNS`.
+    fn rows(&self) -> Vec<Vec<String>>;
+}
+
+/// Joins the names of related objects into a single cell.
+pub fn names(pairs: &[NameUuidPair]) -> String {
+    pairs.iter().map(|pair| pair.name.as_str()).collect::<Vec<&str>>().join(", ")
+}
+
+const SEPARATOR: &str = "  ";
+// Columns are never truncated below this width
+const MIN_WIDTH: usize = 6;
+
+fn normalize(name: &str) -> String {
+    name.trim().to_lowercase().replace("-", "_").replace(" ", "_")
+}
+
+fn header(name: &str) -> String {
+    name.to_uppercase().replace("_", " ")
+}
+
+fn selected_columns(matches: &ArgMatches, columns: &[Column]) -> SimpleResult<Vec<usize>> {
+    if let Some(selection) = matches.value_of("columns") {
+        return selection.split(',')
+            .map(|name| {
+                let name = normalize(name);
+                columns.iter().position(|c| c.name == name).ok_or_else(|| {
+                    let available = columns.iter().map(|c| c.name).collect::<Vec<&str>>().join(", ");
+                    SimpleError::new(format!("Unknown column '{}'. Available columns: {}", name, available))
+                })
+            })
+            .collect();
+    }
+    let wide = matches.is_present("wide");
+    Ok((0..columns.len()).filter(|&i| wide || columns[i].default).collect())
+}
+
+fn truncate(cell: &str, width: usize) -> String {
+    if cell.chars().count() <= width {
+        return cell.to_string();
+    }
+    let mut truncated = cell.chars().take(width.saturating_sub(1)).collect::<String>();
+    truncated.push('…');
+    truncated
+}
+
+/// Shrinks the widest columns until the table fits in `max_width`, or every
+/// column is down to `MIN_WIDTH`.
+fn fit(widths: &mut Vec<usize>, max_width: usize) {
+    let total = |widths: &Vec<usize>| widths.iter().sum::<usize>() + SEPARATOR.len() * widths.len().saturating_sub(1);
+    while total(widths) > max_width {
+        let (widest, width) = match widths.iter().cloned().enumerate().max_by_key(|&(_, w)| w) {
+            Some(widest) => widest,
+            None => return,
+        };
+        if width <= MIN_WIDTH {
+            return;
+        }
+        widths[widest] -= 1;
+    }
+}
+
+pub fn print_table<T: Table>(matches: &ArgMatches, response: &T) -> SimpleResult<()> {
+    let columns = selected_columns(matches, T::COLUMNS)?;
+    let headers = columns.iter()
+        .map(|&i| header(T::COLUMNS[i].name))
+        .collect::<Vec<String>>();
+    let rows = response.rows().into_iter()
+        .map(|row| columns.iter().map(|&i| row.get(i).cloned().unwrap_or_default()).collect())
+        .collect::<Vec<Vec<String>>>();
+
+    let mut widths = headers.iter().map(|h| h.chars().count()).collect::<Vec<usize>>();
+    for row in rows.iter() {
+        for (width, cell) in widths.iter_mut().zip(row.iter()) {
+            *width = std::cmp::max(*width, cell.chars().count());
+        }
+    }
+    // Only truncate when printing to a terminal, so that piped output is
+    // complete
+    if !matches.is_present("wide") {
+        if let Some((Width(terminal_width), _)) = terminal_size() {
+            fit(&mut widths, terminal_width as usize);
+        }
+    }
+
+    for row in std::iter::once(&headers).chain(rows.iter()) {
+        let cells = row.iter().zip(widths.iter())
+            .map(|(cell, &width)| format!("{:<width$}", truncate(cell, width), width = width))
+            .collect::<Vec<String>>();
+        println!("{}", cells.join(SEPARATOR).trim_end());
+    }
+    Ok(())
+}
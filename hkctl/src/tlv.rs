@@ -0,0 +1,47 @@
+use hktlv::{schemas, Schema};
+use simple_error::{SimpleError, SimpleResult};
+use crate::hkservice::{CharacteristicInformation, Value};
+use crate::hkservice::characteristic_information::{CharacteristicType, Format};
+use crate::hkservice::value::Value as ValueEnum;
+
+/// The schema of a TLV8 characteristic. Characteristics without a known
+/// schema get an empty one, which decodes every item as bytes.
+pub fn schema(characteristic_type: CharacteristicType) -> Schema {
+    match characteristic_type {
+        CharacteristicType::SupportedVideoStreamConfiguration => schemas::SUPPORTED_VIDEO_STREAM_CONFIGURATION,
+        CharacteristicType::SupportedAudioStreamConfiguration => schemas::SUPPORTED_AUDIO_STREAM_CONFIGURATION,
+        CharacteristicType::SupportedRtPconfiguration => schemas::SUPPORTED_RTP_CONFIGURATION,
+        CharacteristicType::SelectedStreamConfiguration => schemas::SELECTED_RTP_STREAM_CONFIGURATION,
+        CharacteristicType::SetupStreamEndpoint => schemas::SETUP_ENDPOINTS,
+        CharacteristicType::StreamingStatus => schemas::STREAMING_STATUS,
+        CharacteristicType::LockManagementControlPoint => schemas::LOCK_CONTROL_POINT,
+        CharacteristicType::Logs => schemas::LOGS,
+        _ => Schema::EMPTY,
+    }
+}
+
+fn is_tlv8(characteristic: &CharacteristicInformation) -> bool {
+    characteristic.metadata.as_ref().map_or(false, |m| m.format() == Format::Tlv8)
+}
+
+/// Formats a value to follow its label, starting with a space. With
+/// `decode`, the value of a TLV8 characteristic is decoded into a tree of its
+/// fields instead, starting on the next line and indented by `indent`.
+pub fn format_value(characteristic: &CharacteristicInformation, value: &Value, decode: bool, indent: usize) -> String {
+    let data = match value.value {
+        Some(ValueEnum::DataValue(ref data)) if decode && is_tlv8(characteristic) => data,
+        _ => return format!(" {}", value),
+    };
+    match schema(characteristic.characteristic_type()).decode(data) {
+        Ok(tree) => tree.to_string().lines().map(|line| format!("\n{}{}", " ".repeat(indent), line)).collect(),
+        Err(e) => format!(" {} ({})", value, e),
+    }
+}
+
+/// Encodes a TLV8 value written as a JSON object of field names and values,
+/// such as `{"status": "AVAILABLE"}`.
+pub fn parse_value(characteristic_type: CharacteristicType, s: &str) -> SimpleResult<Value> {
+    let json = serde_json::from_str(s).map_err(|e| SimpleError::new(format!("'{}' is not valid JSON: {}", s, e)))?;
+    let data = schema(characteristic_type).encode(&json).map_err(|e| SimpleError::new(e.to_string()))?;
+    Ok(Value { value: Some(ValueEnum::DataValue(data)) })
+}
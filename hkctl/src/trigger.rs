@@ -0,0 +1,655 @@
+use chrono::{DateTime, NaiveDateTime};
+use clap::{ArgMatches};
+use simple_error::{SimpleError, SimpleResult};
+use std::boxed::Box;
+use std::future::Future;
+use std::pin::Pin;
+use std::str::FromStr;
+use tonic::transport::Channel;
+use crate::characteristic::{parse_value, Address};
+use crate::hkservice::home_kit_service_client::HomeKitServiceClient;
+use crate::hkservice::{
+    AddRemoveTriggersRequest,
+    AddRemoveTriggersResponse,
+    ChangeTriggerMembershipRequest,
+    ChangeTriggerMembershipResponse,
+    CalendarEventInformation,
+    CharacteristicEventInformation,
+    CharacteristicInformation,
+    CharacteristicPredicate,
+    CharacteristicThresholdRangeEventInformation,
+    CircularRegion,
+    ComparisonOperator,
+    CompoundPredicate,
+    Coordinate2D,
+    DurationEventInformation,
+    EnableDisableTriggerRequest,
+    EnableDisableTriggerResponse,
+    EnumerateTriggersRequest,
+    EventInformation,
+    EventTriggerDefinition,
+    LocationEventInformation,
+    NameUuidPair,
+    Number,
+    NumberRange,
+    Operation,
+    PredicateInformation,
+    PresenceEventInformation,
+    PresencePredicate,
+    PresenceEventType,
+    PresenceEventUserType,
+    SignificantEvent,
+    SignificantTimeEventInformation,
+    SignificantTimePredicate,
+    TimeOfDay,
+    TimeOfDayPredicate,
+    TimerRecurrence,
+    TimerTriggerDefinition,
+    TriggerDefinition,
+    UpdateEventTriggerRequest,
+    UpdateEventTriggerResponse,
+    Weekday,
+};
+use crate::hkservice::compound_predicate::Kind;
+use crate::hkservice::event_information::Event;
+use crate::hkservice::predicate_information::Predicate;
+use crate::hkservice::significant_time_predicate::Relation;
+use crate::hkservice::timer_recurrence::Unit;
+use crate::hkservice::trigger_definition::TriggerDefinition as TriggerDefinitionEnum;
+use crate::hkservice::trigger_information::Trigger;
+use crate::hkservice::value::Value as ValueEnum;
+use crate::output;
+use crate::resolve;
+
+fn print_response(response: &AddRemoveTriggersResponse) {
+    println!("Home: {}, Trigger {}", response.home.as_ref().unwrap().name, response.trigger.as_ref().unwrap().name);
+}
+
+fn print_enable_response(response: &EnableDisableTriggerResponse, enable: bool) {
+    println!("Home: {}, Trigger {} {}", response.home.as_ref().unwrap().name, response.trigger.as_ref().unwrap().name,
+             if enable { "enabled" } else { "disabled" });
+}
+
+fn print_membership_response(response: &ChangeTriggerMembershipResponse) {
+    println!("Home: {}, Trigger {}", response.home.as_ref().unwrap().name, response.trigger.as_ref().unwrap().name);
+}
+
+fn print_update_response(response: &UpdateEventTriggerResponse) {
+    println!("Home: {}, Trigger {}", response.home.as_ref().unwrap().name, response.trigger.as_ref().unwrap().name);
+}
+
+/// Parses a fire date given as RFC 3339, or as "YYYY-MM-DD HH:MM" in UTC like
+/// the dates `hkctl triggers` prints.
+fn parse_fire_date(s: &str) -> SimpleResult<u64> {
+    if let Ok(date) = DateTime::parse_from_rfc3339(s) {
+        return Ok(date.timestamp() as u64);
+    }
+    ["%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M:%S"].iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(s, format).ok())
+        .map(|date| date.timestamp() as u64)
+        .ok_or_else(|| SimpleError::new(format!("Invalid date '{}': expected YYYY-MM-DD HH:MM (UTC) or RFC 3339", s)))
+}
+
+/// Parses "daily", "weekly", "hourly" or a count and unit such as "15m",
+/// "2h", "3d" or "1w".
+fn parse_recurrence(s: &str) -> SimpleResult<TimerRecurrence> {
+    let invalid = || SimpleError::new(format!("Invalid recurrence '{}': expected daily, weekly, hourly or a number followed by m, h, d or w", s));
+    let (interval, unit) = match s {
+        "hourly" => (1, Unit::Hour),
+        "daily" => (1, Unit::Day),
+        "weekly" => (1, Unit::Week),
+        _ => {
+            let split = s.find(|c: char| !c.is_ascii_digit()).ok_or_else(invalid)?;
+            let interval = s[..split].parse::<u32>().map_err(|_| invalid())?;
+            let unit = match &s[split..] {
+                "m" | "min" | "minute" | "minutes" => Unit::Minute,
+                "h" | "hour" | "hours" => Unit::Hour,
+                "d" | "day" | "days" => Unit::Day,
+                "w" | "week" | "weeks" => Unit::Week,
+                _ => return Err(invalid()),
+            };
+            (interval, unit)
+        },
+    };
+    if interval == 0 {
+        return Err(invalid());
+    }
+    Ok(TimerRecurrence { unit: unit as i32, interval: interval })
+}
+
+/// Parses a duration such as "90s", "5m" or "1h30m" into seconds.
+fn parse_duration(s: &str) -> SimpleResult<i64> {
+    let invalid = || SimpleError::new(format!("Invalid duration '{}': expected a number followed by s, m or h, such as 5m or 1h30m", s));
+    let mut seconds = 0;
+    let mut rest = s;
+    while !rest.is_empty() {
+        let split = rest.find(|c: char| !c.is_ascii_digit()).ok_or_else(invalid)?;
+        let count = rest[..split].parse::<i64>().map_err(|_| invalid())?;
+        let unit = rest[split..].chars().next().unwrap();
+        seconds += count * match unit {
+            's' => 1,
+            'm' => 60,
+            'h' => 60 * 60,
+            _ => return Err(invalid()),
+        };
+        rest = &rest[split + 1..];
+    }
+    if seconds == 0 {
+        return Err(invalid());
+    }
+    Ok(seconds)
+}
+
+/// Parses a comma separated list of days such as "mon,wed,fri", or one of
+/// "weekdays", "weekends" and "every-day".
+fn parse_weekdays(s: &str) -> SimpleResult<Vec<Weekday>> {
+    let weekdays = match s {
+        "every-day" => vec![],
+        "weekdays" => vec![Weekday::Monday, Weekday::Tuesday, Weekday::Wednesday, Weekday::Thursday, Weekday::Friday],
+        "weekends" => vec![Weekday::Saturday, Weekday::Sunday],
+        _ => s.split(',').map(|day| match &day.trim().to_lowercase()[..] {
+            "sun" | "sunday" => Ok(Weekday::Sunday),
+            "mon" | "monday" => Ok(Weekday::Monday),
+            "tue" | "tuesday" => Ok(Weekday::Tuesday),
+            "wed" | "wednesday" => Ok(Weekday::Wednesday),
+            "thu" | "thursday" => Ok(Weekday::Thursday),
+            "fri" | "friday" => Ok(Weekday::Friday),
+            "sat" | "saturday" => Ok(Weekday::Saturday),
+            _ => Err(SimpleError::new(format!("Invalid day '{}': expected a day such as mon, or weekdays, weekends or every-day", day))),
+        }).collect::<SimpleResult<Vec<Weekday>>>()?,
+    };
+    Ok(weekdays)
+}
+
+/// Parses `sunrise` or `sunset` with an optional offset such as `-30m`, or
+/// returns None when `s` is neither.
+fn parse_significant_time(s: &str) -> Option<SimpleResult<(SignificantEvent, i64)>> {
+    let (significant_event, offset) = if let Some(offset) = s.strip_prefix("sunrise") {
+        (SignificantEvent::Sunrise, offset)
+    } else if let Some(offset) = s.strip_prefix("sunset") {
+        (SignificantEvent::Sunset, offset)
+    } else {
+        return None;
+    };
+    let offset = match offset.chars().next() {
+        None => Ok(0),
+        Some('+') => parse_duration(&offset[1..]),
+        Some('-') => parse_duration(&offset[1..]).map(|d| -d),
+        Some(_) => Err(SimpleError::new("expected an offset such as +1h or -30m")),
+    };
+    Some(offset.map(|offset| (significant_event, offset)))
+}
+
+/// Parses a time of day such as "22:00" or "6:30".
+fn parse_time_of_day(s: &str) -> SimpleResult<TimeOfDay> {
+    let invalid = || SimpleError::new(format!("Invalid time of day '{}': expected HH:MM", s));
+    let split = s.find(':').ok_or_else(invalid)?;
+    let hour = s[..split].parse::<u32>().map_err(|_| invalid())?;
+    let minute = s[split + 1..].parse::<u32>().map_err(|_| invalid())?;
+    if hour > 23 || minute > 59 {
+        return Err(invalid());
+    }
+    Ok(TimeOfDay { hour: hour, minute: minute })
+}
+
+fn parse_number(characteristic: &CharacteristicInformation, s: &str) -> SimpleResult<Number> {
+    let format = characteristic.metadata.as_ref().map(|m| m.format());
+    match parse_value(format, s)?.value {
+        Some(ValueEnum::NumberValue(number)) => Ok(number),
+        _ => Err(SimpleError::new(format!("'{}' is not a number", s))),
+    }
+}
+
+/// Parses an event given as one of
+///
+/// * `ACCESSORY/SERVICE/CHARACTERISTIC`: any change of the characteristic
+/// * `ACCESSORY/SERVICE/CHARACTERISTIC=VALUE`: the characteristic takes a value
+/// * `ACCESSORY/SERVICE/CHARACTERISTIC>=MIN`, `<=MAX` or `=MIN..MAX`: the
+///   characteristic enters a range
+/// * `sunrise` or `sunset`, with an optional offset such as `sunset-30m` or
+///   `sunset -30m`
+/// * `arrive`, `leave`, `first-arrive` or `last-leave`: presence of the home's
+///   users, or of the current user with `@me`
+/// * `enter LAT,LON,RADIUS` or `exit LAT,LON,RADIUS`: a location event
+/// * `at DATE`: a calendar event
+///
+/// Specs containing a `/` are always characteristics, so accessories can be
+/// named after the keywords.
+async fn parse_event(client: &mut HomeKitServiceClient<Channel>, home: &str, spec: &str) -> Result<EventInformation, Box<dyn std::error::Error>> {
+    let invalid = |e: &dyn std::fmt::Display| SimpleError::new(format!("Invalid event '{}': {}", spec, e));
+    let keyword = !spec.contains('/');
+    let (word, argument) = match spec.find(' ') {
+        Some(split) => (&spec[..split], spec[split + 1..].trim()),
+        None => (spec, ""),
+    };
+    let (presence, user) = match word.find('@') {
+        Some(split) => (&word[..split], &word[split + 1..]),
+        None => (word, ""),
+    };
+    let presence_event = match presence {
+        _ if !keyword => None,
+        "arrive" => Some(PresenceEventType::EveryEntry),
+        "leave" => Some(PresenceEventType::EveryExit),
+        "first-arrive" => Some(PresenceEventType::FirstEntry),
+        "last-leave" => Some(PresenceEventType::LastExit),
+        _ => None,
+    };
+    // The offset of a sun event may be separated by spaces
+    let significant_time = if keyword {
+        parse_significant_time(&spec.split_whitespace().collect::<String>())
+    } else {
+        None
+    };
+    let event = if let Some(presence_event) = presence_event {
+        if !argument.is_empty() {
+            return Err(Box::new(invalid(&format!("unexpected '{}' after {}", argument, word))));
+        }
+        let presence_user = match user {
+            "" => PresenceEventUserType::HomeUsers,
+            "me" => PresenceEventUserType::CurrentUser,
+            _ => return Err(Box::new(invalid(&"the only user that can be given is @me"))),
+        };
+        Event::PresenceEvent(PresenceEventInformation {
+            presence_event: presence_event as i32,
+            presence_user: presence_user as i32,
+            ..Default::default()
+        })
+    } else if let Some(significant_time) = significant_time {
+        let (significant_event, offset) = significant_time.map_err(|e| invalid(&e))?;
+        Event::SignificantTimeEvent(SignificantTimeEventInformation {
+            significant_event: significant_event as i32,
+            offset: offset,
+            ..Default::default()
+        })
+    } else if keyword && (word == "enter" || word == "exit") {
+        let coordinates = argument.split(',')
+            .map(|c| c.trim().parse::<f64>())
+            .collect::<Result<Vec<f64>, _>>()
+            .ok()
+            .filter(|c| c.len() == 3)
+            .ok_or_else(|| invalid(&"expected LATITUDE,LONGITUDE,RADIUS"))?;
+        Event::LocationEvent(LocationEventInformation {
+            notify_on_entry: word == "enter",
+            notify_on_exit: word == "exit",
+            region: Some(CircularRegion {
+                center: Some(Coordinate2D { latitude: coordinates[0], longitude: coordinates[1] }),
+                radius: coordinates[2],
+            }),
+            ..Default::default()
+        })
+    } else if keyword && word == "at" {
+        Event::CalendarEvent(CalendarEventInformation {
+            fire_date: parse_fire_date(argument).map_err(|e| invalid(&e))?,
+            ..Default::default()
+        })
+    } else {
+        let (address, operator, value) = match (spec.find(">="), spec.find("<="), spec.find('=')) {
+            (Some(split), _, _) => (&spec[..split], ">=", &spec[split + 2..]),
+            (_, Some(split), _) => (&spec[..split], "<=", &spec[split + 2..]),
+            (_, _, Some(split)) => (&spec[..split], "=", &spec[split + 1..]),
+            _ => (spec, "", ""),
+        };
+        let (_, _, characteristic) = Address::parse(home, address).map_err(|e| invalid(&e))?
+            .resolve(client).await.map_err(|e| invalid(&e))?;
+        let pair = Some(NameUuidPair { name: String::new(), uuid: characteristic.uuid.clone() });
+        let range = |min: Option<&str>, max: Option<&str>| -> SimpleResult<Event> {
+            Ok(Event::CharacteristicThresholdRangeEvent(CharacteristicThresholdRangeEventInformation {
+                characteristic: pair.clone(),
+                range: Some(NumberRange {
+                    min_value: min.map(|s| parse_number(&characteristic, s)).transpose()?,
+                    max_value: max.map(|s| parse_number(&characteristic, s)).transpose()?,
+                }),
+                ..Default::default()
+            }))
+        };
+        match operator {
+            ">=" => range(Some(value), None).map_err(|e| invalid(&e))?,
+            "<=" => range(None, Some(value)).map_err(|e| invalid(&e))?,
+            "=" if value.contains("..") => {
+                let split = value.find("..").unwrap();
+                range(Some(&value[..split]), Some(&value[split + 2..])).map_err(|e| invalid(&e))?
+            },
+            "=" => {
+                let format = characteristic.metadata.as_ref().map(|m| m.format());
+                Event::CharacteristicEvent(CharacteristicEventInformation {
+                    characteristic: pair.clone(),
+                    trigger_value: Some(parse_value(format, value).map_err(|e| invalid(&e))?),
+                    ..Default::default()
+                })
+            },
+            _ => Event::CharacteristicEvent(CharacteristicEventInformation {
+                characteristic: pair.clone(),
+                ..Default::default()
+            }),
+        }
+    };
+    Ok(EventInformation { event: Some(event) })
+}
+
+/// Parses a condition given as one of
+///
+/// * `ACCESSORY/SERVICE/CHARACTERISTIC` followed by one of `=`, `!=`, `<`,
+///   `<=`, `>` or `>=` and a value
+/// * `before` or `after` followed by `sunrise` or `sunset`, with an optional
+///   offset such as `sunset+30m`
+/// * `before` or `after` followed by a time of day such as `22:00`, or
+///   `between 22:00-06:00`
+/// * `home` or `away`: whether the home's users are at home, or the current
+///   user with `@me`
+///
+/// Conditions can be negated with a leading `not`, and combined with `or`.
+async fn parse_condition(client: &mut HomeKitServiceClient<Channel>, home: &str, spec: &str) -> Result<PredicateInformation, Box<dyn std::error::Error>> {
+    let mut alternatives = vec![];
+    for alternative in spec.split(" or ") {
+        let alternative = alternative.trim();
+        alternatives.push(if let Some(negated) = alternative.strip_prefix("not ") {
+            compound(Kind::Not, vec![parse_simple_condition(client, home, negated.trim()).await?])
+        } else {
+            parse_simple_condition(client, home, alternative).await?
+        });
+    }
+    Ok(match alternatives.len() {
+        1 => alternatives.pop().unwrap(),
+        _ => compound(Kind::Or, alternatives),
+    })
+}
+
+async fn parse_simple_condition(client: &mut HomeKitServiceClient<Channel>, home: &str, spec: &str) -> Result<PredicateInformation, Box<dyn std::error::Error>> {
+    let invalid = |e: &dyn std::fmt::Display| SimpleError::new(format!("Invalid condition '{}': {}", spec, e));
+    let (word, argument) = match spec.find(' ') {
+        Some(split) => (&spec[..split], spec[split + 1..].trim()),
+        None => (spec, ""),
+    };
+    let (presence, user) = match word.find('@') {
+        Some(split) => (&word[..split], &word[split + 1..]),
+        None => (word, ""),
+    };
+    let predicate = if (presence == "home" || presence == "away") && argument.is_empty() {
+        let presence_user = match user {
+            "" => PresenceEventUserType::HomeUsers,
+            "me" => PresenceEventUserType::CurrentUser,
+            _ => return Err(Box::new(invalid(&"the only user that can be given is @me"))),
+        };
+        // HomeKit evaluates presence predicates with these event types
+        let presence_event = if presence == "home" { PresenceEventType::FirstEntry } else { PresenceEventType::LastExit };
+        Predicate::Presence(PresencePredicate {
+            presence_event: presence_event as i32,
+            presence_user: presence_user as i32,
+        })
+    } else if word == "before" || word == "after" {
+        let relation = if word == "before" { Relation::Before } else { Relation::After };
+        if let Some(significant_time) = parse_significant_time(argument) {
+            let (significant_event, offset) = significant_time.map_err(|e| invalid(&e))?;
+            Predicate::SignificantTime(SignificantTimePredicate {
+                relation: relation as i32,
+                significant_event: significant_event as i32,
+                offset: offset,
+            })
+        } else {
+            let time = Some(parse_time_of_day(argument).map_err(|e| invalid(&e))?);
+            Predicate::TimeOfDay(match relation {
+                Relation::Before => TimeOfDayPredicate { before: time, after: None },
+                _ => TimeOfDayPredicate { after: time, before: None },
+            })
+        }
+    } else if word == "between" {
+        let split = argument.find('-').ok_or_else(|| invalid(&"expected a window such as 22:00-06:00"))?;
+        Predicate::TimeOfDay(TimeOfDayPredicate {
+            after: Some(parse_time_of_day(argument[..split].trim()).map_err(|e| invalid(&e))?),
+            before: Some(parse_time_of_day(argument[split + 1..].trim()).map_err(|e| invalid(&e))?),
+        })
+    } else {
+        let (split, operator, comparison) = ["!=", "<=", ">=", "=", "<", ">"].iter()
+            .find_map(|operator| spec.find(operator).map(|split| (split, *operator)))
+            .map(|(split, operator)| (split, operator, match operator {
+                "!=" => ComparisonOperator::NotEqual,
+                "<=" => ComparisonOperator::LessThanOrEqual,
+                ">=" => ComparisonOperator::GreaterThanOrEqual,
+                "<" => ComparisonOperator::LessThan,
+                ">" => ComparisonOperator::GreaterThan,
+                _ => ComparisonOperator::Equal,
+            }))
+            .ok_or_else(|| invalid(&"expected a comparison such as ACCESSORY/SERVICE/CHARACTERISTIC=VALUE, before, after, between, home or away"))?;
+        let (_, _, characteristic) = Address::parse(home, spec[..split].trim()).map_err(|e| invalid(&e))?
+            .resolve(client).await.map_err(|e| invalid(&e))?;
+        let format = characteristic.metadata.as_ref().map(|m| m.format());
+        Predicate::Characteristic(CharacteristicPredicate {
+            characteristic: Some(NameUuidPair { name: String::new(), uuid: characteristic.uuid.clone() }),
+            comparison: comparison as i32,
+            value: Some(parse_value(format, spec[split + operator.len()..].trim()).map_err(|e| invalid(&e))?),
+        })
+    };
+    Ok(PredicateInformation { predicate: Some(predicate) })
+}
+
+fn compound(kind: Kind, predicates: Vec<PredicateInformation>) -> PredicateInformation {
+    PredicateInformation {
+        predicate: Some(Predicate::Compound(CompoundPredicate { kind: kind as i32, predicates: predicates })),
+    }
+}
+
+/// The predicate for the conditions given with --if, which must all hold.
+async fn parse_conditions(client: &mut HomeKitServiceClient<Channel>, home: &str, specs: Vec<&str>) -> Result<Option<PredicateInformation>, Box<dyn std::error::Error>> {
+    let mut predicates = vec![];
+    for spec in specs {
+        predicates.push(parse_condition(client, home, spec).await?);
+    }
+    Ok(match predicates.len() {
+        0 => None,
+        1 => predicates.pop(),
+        _ => Some(compound(Kind::And, predicates)),
+    })
+}
+
+async fn parse_events(client: &mut HomeKitServiceClient<Channel>, home: &str, specs: Vec<&str>) -> Result<Vec<EventInformation>, Box<dyn std::error::Error>> {
+    let mut events = vec![];
+    for spec in specs {
+        events.push(parse_event(client, home, spec).await?);
+    }
+    Ok(events)
+}
+
+/// The end events given with --end-when and --end-after, if any were.
+async fn parse_end_events(client: &mut HomeKitServiceClient<Channel>, home: &str, matches: &ArgMatches) -> Result<Option<Vec<EventInformation>>, Box<dyn std::error::Error>> {
+    if !matches.is_present("end_when") && !matches.is_present("end_after") {
+        return Ok(None);
+    }
+    let mut end_events = parse_events(client, home, matches.values_of("end_when").map_or(vec![], |values| values.collect())).await?;
+    if let Some(duration) = matches.value_of("end_after") {
+        end_events.push(EventInformation {
+            event: Some(Event::DurationEvent(DurationEventInformation {
+                duration: parse_duration(duration)? as f64,
+                ..Default::default()
+            })),
+        });
+    }
+    Ok(Some(end_events))
+}
+
+async fn _create_event(matches: &ArgMatches, mut client: HomeKitServiceClient<Channel>) -> Result<(), Box<dyn std::error::Error>> {
+    let home = matches.value_of("home").unwrap_or("");
+    let event = EventTriggerDefinition {
+        events: parse_events(&mut client, home, matches.values_of("when").unwrap().collect()).await?,
+        end_events: parse_end_events(&mut client, home, matches).await?.unwrap_or_default(),
+        recurrences: matches.value_of("on").map_or(Ok(vec![]), parse_weekdays)?.into_iter().map(|w| w as i32).collect(),
+        executes_once: matches.is_present("once"),
+        predicate: parse_conditions(&mut client, home, matches.values_of("if").map_or(vec![], |values| values.collect())).await?,
+    };
+    let mut action_sets = vec![];
+    for action_set in matches.values_of("action_set").map_or(vec![], |values| values.collect()) {
+        action_sets.push(resolve::action_set(&mut client, home, action_set).await?.uuid);
+    }
+    let response = client.add_remove_triggers(AddRemoveTriggersRequest {
+        home: home.to_string(),
+        name: matches.value_of("name").unwrap().to_string(),
+        operation: Operation::Add as i32,
+        action_sets: action_sets,
+        definition: Some(TriggerDefinition {
+            trigger_definition: Some(TriggerDefinitionEnum::Event(event)),
+        }),
+    }).await?.into_inner();
+    output::print(matches, &response, print_response)
+}
+
+/// Changes the parts of an event trigger given on the command line, keeping
+/// the others.
+async fn _edit(matches: &ArgMatches, mut client: HomeKitServiceClient<Channel>) -> Result<(), Box<dyn std::error::Error>> {
+    let home = matches.value_of("home").unwrap_or("");
+    let trigger = resolve::trigger(&mut client, home, matches.value_of("name").unwrap()).await?;
+    let current = client.enumerate_triggers(EnumerateTriggersRequest {
+        home: home.to_string(),
+        name_filter: resolve::exactly(&trigger.uuid),
+        ..Default::default()
+    }).await?.into_inner().triggers.into_iter()
+        .filter_map(|t| match t.trigger {
+            Some(Trigger::Event(event)) => Some(event),
+            _ => None,
+        })
+        .next()
+        .ok_or_else(|| SimpleError::new(format!("Trigger {} is not an event trigger", trigger.name)))?;
+    let mut definition = EventTriggerDefinition {
+        events: current.events,
+        end_events: current.end_events,
+        recurrences: current.recurrences,
+        executes_once: current.executes_once,
+        predicate: current.predicate,
+    };
+    if let Some(specs) = matches.values_of("when") {
+        definition.events = parse_events(&mut client, home, specs.collect()).await?;
+    }
+    if matches.is_present("no_end") {
+        definition.end_events = vec![];
+    } else if let Some(end_events) = parse_end_events(&mut client, home, matches).await? {
+        definition.end_events = end_events;
+    }
+    if let Some(days) = matches.value_of("on") {
+        definition.recurrences = parse_weekdays(days)?.into_iter().map(|w| w as i32).collect();
+    }
+    if matches.is_present("no_condition") {
+        definition.predicate = None;
+    } else if let Some(specs) = matches.values_of("if") {
+        definition.predicate = parse_conditions(&mut client, home, specs.collect()).await?;
+    }
+    if matches.is_present("once") {
+        definition.executes_once = true;
+    } else if matches.is_present("repeat") {
+        definition.executes_once = false;
+    }
+    let response = client.update_event_trigger(UpdateEventTriggerRequest {
+        home: home.to_string(),
+        name: trigger.uuid,
+        definition: Some(definition),
+    }).await?.into_inner();
+    output::print(matches, &response, print_update_response)
+}
+
+async fn _create_timer(matches: &ArgMatches, mut client: HomeKitServiceClient<Channel>) -> Result<(), Box<dyn std::error::Error>> {
+    let home = matches.value_of("home").unwrap_or("");
+    let timer = TimerTriggerDefinition {
+        fire_date: parse_fire_date(matches.value_of("at").unwrap())?,
+        time_zone: matches.value_of("time_zone").unwrap_or("").to_string(),
+        recurrence: matches.value_of("every").map(parse_recurrence).transpose()?,
+    };
+    let mut action_sets = vec![];
+    for action_set in matches.values_of("action_set").map_or(vec![], |values| values.collect()) {
+        action_sets.push(resolve::action_set(&mut client, home, action_set).await?.uuid);
+    }
+    let response = client.add_remove_triggers(AddRemoveTriggersRequest {
+        home: home.to_string(),
+        name: matches.value_of("name").unwrap().to_string(),
+        operation: Operation::Add as i32,
+        action_sets: action_sets,
+        definition: Some(TriggerDefinition {
+            trigger_definition: Some(TriggerDefinitionEnum::Timer(timer)),
+        }),
+    }).await?.into_inner();
+    output::print(matches, &response, print_response)
+}
+
+async fn _remove(matches: &ArgMatches, mut client: HomeKitServiceClient<Channel>) -> Result<(), Box<dyn std::error::Error>> {
+    let home = matches.value_of("home").unwrap_or("");
+    // The server removes the first trigger matching the pattern, so make sure
+    // it is the only one
+    let name = matches.value_of("name").unwrap();
+    let trigger = resolve::trigger(&mut client, home, name).await?;
+    if !resolve::confirm_removal(matches, "trigger", name, &trigger)? {
+        return Ok(());
+    }
+    let response = client.add_remove_triggers(AddRemoveTriggersRequest {
+        home: home.to_string(),
+        name: resolve::exactly(&trigger.uuid),
+        operation: Operation::Remove as i32,
+        ..Default::default()
+    }).await?.into_inner();
+    output::print(matches, &response, print_response)
+}
+
+/// Enables or disables every trigger matching the pattern, or the one with
+/// that UUID. Changing several triggers has to be confirmed.
+async fn _enable(matches: &ArgMatches, mut client: HomeKitServiceClient<Channel>, enable: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let home = matches.value_of("home").unwrap_or("");
+    let pattern = matches.value_of("name").unwrap();
+    let mut triggers = resolve::triggers(&mut client, home, pattern).await?;
+    if let Some(index) = triggers.iter().position(|t| t.uuid.eq_ignore_ascii_case(pattern)) {
+        triggers = vec![triggers.remove(index)];
+    }
+    if triggers.is_empty() {
+        return Err(Box::new(SimpleError::new(format!("No trigger matches '{}'", pattern))));
+    }
+    if triggers.len() > 1 {
+        // Standard output is kept for the responses, which may be JSON or YAML
+        eprintln!("'{}' matches {} triggers:", pattern, triggers.len());
+        triggers.iter().for_each(|trigger| eprintln!("  {} ({})", trigger.name, trigger.uuid));
+        let prompt = format!("{} all of them?", if enable { "Enable" } else { "Disable" });
+        if !matches.is_present("yes") && !resolve::confirm(&prompt)? {
+            eprintln!("Nothing was changed");
+            return Ok(());
+        }
+    }
+    for trigger in triggers {
+        let response = client.enable_disable_trigger(EnableDisableTriggerRequest {
+            home: home.to_string(),
+            name: trigger.uuid,
+            enable: enable,
+        }).await?.into_inner();
+        output::print(matches, &response, |response| print_enable_response(response, enable))?;
+    }
+    Ok(())
+}
+
+async fn _action_sets(matches: &ArgMatches, mut client: HomeKitServiceClient<Channel>) -> Result<(), Box<dyn std::error::Error>> {
+    let home = matches.value_of("home").unwrap_or("");
+    let trigger = resolve::trigger(&mut client, home, matches.value_of("trigger").unwrap()).await?;
+    let operation = Operation::from_str(matches.value_of("operation").unwrap())?;
+    let mut action_sets = vec![];
+    for action_set in matches.values_of("action_sets").unwrap() {
+        action_sets.push(resolve::action_set(&mut client, home, action_set).await?.uuid);
+    }
+    let response = client.change_trigger_membership(ChangeTriggerMembershipRequest {
+        home: home.to_string(),
+        name: trigger.uuid,
+        operation: operation as i32,
+        action_sets: action_sets,
+    }).await?.into_inner();
+    output::print(matches, &response, print_membership_response)
+}
+
+async fn _run(matches: ArgMatches, client: HomeKitServiceClient<Channel>) -> Result<(), Box<dyn std::error::Error>> {
+    match matches.subcommand() {
+        Some(("create", create_matches)) => match create_matches.subcommand() {
+            Some(("timer", timer_matches)) => _create_timer(timer_matches, client).await,
+            Some(("event", event_matches)) => _create_event(event_matches, client).await,
+            _ => Err(Box::new(SimpleError::new("Expected one of timer or event"))),
+        },
+        Some(("edit", edit_matches)) => _edit(edit_matches, client).await,
+        Some(("remove", remove_matches)) => _remove(remove_matches, client).await,
+        Some(("enable", enable_matches)) => _enable(enable_matches, client, true).await,
+        Some(("disable", disable_matches)) => _enable(disable_matches, client, false).await,
+        Some(("actionsets", action_sets_matches)) => _action_sets(action_sets_matches, client).await,
+        _ => Err(Box::new(SimpleError::new("Expected one of create, edit, remove, enable, disable or actionsets"))),
+    }
+}
+
+pub fn run(matches: ArgMatches, client: HomeKitServiceClient<Channel>) -> Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error>>>>> {
+    Box::pin(_run(matches, client))
+}
@@ -8,7 +8,8 @@ use crate::hkservice::home_kit_service_client::HomeKitServiceClient;
 use crate::hkservice::{EnumerateTriggersRequest, EnumerateTriggersResponse};
 use crate::hkservice::enumerate_triggers_request::EnabledFilter;
 use crate::hkservice::trigger_information::Trigger;
-use crate::hkservice::{EventTriggerInformation, TimerTriggerInformation};
+use crate::hkservice::{EventTriggerInformation, TimerRecurrence, TimerTriggerInformation};
+use crate::hkservice::timer_recurrence::Unit;
 use crate::hkservice::{
     event_information::Event,
     EventInformation,
@@ -18,11 +19,21 @@ use crate::hkservice::{
     DurationEventInformation,
     CharacteristicEventInformation,
     CharacteristicThresholdRangeEventInformation,
+    ComparisonOperator,
+    NameUuidPair,
+    PredicateInformation,
     PresenceEventInformation,
     PresenceEventType,
     PresenceEventUserType,
+    SignificantEvent,
+    Weekday,
 };
+use crate::hkservice::compound_predicate::Kind;
 use crate::hkservice::event_trigger_information::ActivationState;
+use crate::hkservice::predicate_information::Predicate;
+use crate::hkservice::significant_time_predicate::Relation;
+use crate::output;
+use crate::table::{names, Column, Table};
 
 
 impl std::fmt::Display for ActivationState {
@@ -31,6 +42,18 @@ impl std::fmt::Display for ActivationState {
     }
 }
 
+impl std::fmt::Display for SignificantEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::fmt::Display for Weekday {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
 impl std::fmt::Display for PresenceEventType {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(f, "{:?}", self)
@@ -43,6 +66,22 @@ impl std::fmt::Display for PresenceEventUserType {
     }
 }
 
+impl std::fmt::Display for TimerRecurrence {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let unit = match self.unit() {
+            Unit::Minute => "minute",
+            Unit::Hour => "hour",
+            Unit::Day => "day",
+            Unit::Week => "week",
+            Unit::InvalidUnit => "?",
+        };
+        match self.interval {
+            1 => write!(f, "every {}", unit),
+            n => write!(f, "every {} {}s", n, unit),
+        }
+    }
+}
+
 fn print_timer_trigger(timer_trigger: &TimerTriggerInformation) {
     let trigger = timer_trigger.trigger.as_ref().unwrap();
     println!("  Trigger: {}", trigger.name);
@@ -51,7 +90,13 @@ fn print_timer_trigger(timer_trigger: &TimerTriggerInformation) {
     println!("    Is Enabled: {}", trigger.is_enabled);
     println!("    Last Fire Date (UTC): {}", NaiveDateTime::from_timestamp(trigger.last_fire_date as i64, 0));
     println!("    Next Fire Date (UTC): {}", NaiveDateTime::from_timestamp(timer_trigger.fire_date as i64, 0));
-    println!("    Recurrence: {}", Duration::seconds(timer_trigger.recurrence as i64));
+    match timer_trigger.recurrence_components {
+        Some(ref recurrence) => println!("    Recurrence: {}", recurrence),
+        None => println!("    Recurrence: {}", Duration::seconds(timer_trigger.recurrence as i64)),
+    };
+    if !timer_trigger.time_zone.is_empty() {
+        println!("    Time Zone: {}", timer_trigger.time_zone);
+    }
     println!("    Action Sets: ({})", trigger.action_sets.len());
     trigger.action_sets.iter().for_each(|action_set| {
         println!("      Action Set: {} ({})", action_set.name, action_set.uuid);
@@ -79,10 +124,10 @@ fn print_calendar_event(calendar_event: &CalendarEventInformation) {
 fn print_significant_time_event(significant_time_event: &SignificantTimeEventInformation) {
     println!("      Event: {}", significant_time_event.uuid);
     println!("        Type: Significant Time");
-    println!("        Significant Event: {}", significant_time_event.significant_event);
+    println!("        Significant Event: {}", significant_time_event.significant_event());
     match significant_time_event.offset {
         0 => println!("        Offset: None"),
-        o => println!("        Offset: {}", Duration::seconds(o as i64)),
+        o => println!("        Offset: {}", Duration::seconds(o)),
     };
 }
 
@@ -92,11 +137,20 @@ fn print_duration_event(duration_event: &DurationEventInformation) {
     println!("        Duration: {}", Duration::seconds(duration_event.duration as i64));
 }
 
+/// Shows a characteristic as ACCESSORY/SERVICE/TYPE when the server names
+/// it, and by UUID otherwise.
+fn describe_characteristic(characteristic: &NameUuidPair) -> String {
+    match characteristic.name.as_str() {
+        "" => characteristic.uuid.clone(),
+        name => format!("{} ({})", name, characteristic.uuid),
+    }
+}
+
 fn print_characteristic_event(characteristic_event: &CharacteristicEventInformation) {
     println!("      Event: {}", characteristic_event.uuid);
     println!("        Type: Characteristic Event");
     let characteristic = characteristic_event.characteristic.as_ref().unwrap();
-    println!("        Characteristic: {} ({})", characteristic.name, characteristic.uuid);
+    println!("        Characteristic: {}", describe_characteristic(characteristic));
     match characteristic_event.trigger_value.as_ref() {
         Some(value) => println!("        Trigger Value: {}", value),
         None => println!("        Trigger Value: Any Change"),
@@ -107,7 +161,7 @@ fn print_characteristic_threshold_range_event(characteristic_threshold_range_eve
     println!("      Event: {}", characteristic_threshold_range_event.uuid);
     println!("        Type: Characteristic Threshold Range");
     let characteristic = characteristic_threshold_range_event.characteristic.as_ref().unwrap();
-    println!("        Characteristic: {} ({})", characteristic.name, characteristic.uuid);
+    println!("        Characteristic: {}", describe_characteristic(characteristic));
     let range = characteristic_threshold_range_event.range.as_ref().unwrap();
     match &range.min_value {
         Some(ref min_value) => println!("        Min: {}", min_value),
@@ -122,8 +176,8 @@ fn print_characteristic_threshold_range_event(characteristic_threshold_range_eve
 fn print_presence_event(presence_event: &PresenceEventInformation) {
     println!("      Event: {}", presence_event.uuid);
     println!("        Type: Presence");
-    println!("        Event Type: {}", presence_event.presence_event);
-    println!("        Users: {}", presence_event.presence_user);
+    println!("        Event Type: {}", presence_event.presence_event());
+    println!("        Users: {}", presence_event.presence_user());
 }
 
 fn print_event(event: &EventInformation) {
@@ -138,6 +192,92 @@ fn print_event(event: &EventInformation) {
     };
 }
 
+/// Formats an offset the way `--when` and `--if` take it, such as "+1h30m".
+fn format_offset(offset: i64) -> String {
+    if offset == 0 {
+        return String::new();
+    }
+    let sign = if offset < 0 { "-" } else { "+" };
+    let seconds = offset.abs();
+    let mut parts = String::new();
+    if seconds >= 3600 {
+        parts.push_str(&format!("{}h", seconds / 3600));
+    }
+    if seconds % 3600 >= 60 {
+        parts.push_str(&format!("{}m", seconds % 3600 / 60));
+    }
+    if seconds % 60 != 0 {
+        parts.push_str(&format!("{}s", seconds % 60));
+    }
+    format!("{}{}", sign, parts)
+}
+
+/// Formats a predicate as a single condition, in the syntax of `--if`.
+/// Nested and and or predicates are parenthesized.
+fn format_predicate(predicate: &PredicateInformation) -> String {
+    let predicate = match predicate.predicate.as_ref() {
+        Some(predicate) => predicate,
+        None => return "?".to_string(),
+    };
+    match predicate {
+        Predicate::Characteristic(p) => {
+            let characteristic = p.characteristic.as_ref().map_or("?".to_string(), |c| match c.name.as_str() {
+                "" => c.uuid.clone(),
+                name => name.to_string(),
+            });
+            let operator = match p.comparison() {
+                ComparisonOperator::Equal => "=",
+                ComparisonOperator::NotEqual => "!=",
+                ComparisonOperator::LessThan => "<",
+                ComparisonOperator::LessThanOrEqual => "<=",
+                ComparisonOperator::GreaterThan => ">",
+                ComparisonOperator::GreaterThanOrEqual => ">=",
+                ComparisonOperator::InvalidComparisonOperator => "?",
+            };
+            let value = p.value.as_ref().map_or("?".to_string(), |v| v.to_string());
+            format!("{} {} {}", characteristic, operator, value)
+        },
+        Predicate::SignificantTime(p) => {
+            let relation = match p.relation() {
+                Relation::Before => "before",
+                Relation::After => "after",
+                Relation::InvalidRelation => "?",
+            };
+            format!("{} {}{}", relation, p.significant_event().to_string().to_lowercase(), format_offset(p.offset))
+        },
+        Predicate::TimeOfDay(p) => match (&p.after, &p.before) {
+            (Some(after), Some(before)) => format!("between {:02}:{:02}-{:02}:{:02}", after.hour, after.minute, before.hour, before.minute),
+            (Some(after), None) => format!("after {:02}:{:02}", after.hour, after.minute),
+            (None, Some(before)) => format!("before {:02}:{:02}", before.hour, before.minute),
+            (None, None) => "?".to_string(),
+        },
+        Predicate::Presence(p) => {
+            let presence = match p.presence_event() {
+                PresenceEventType::FirstEntry => "home",
+                PresenceEventType::LastExit => "away",
+                _ => "?",
+            };
+            match p.presence_user() {
+                PresenceEventUserType::CurrentUser => format!("{}@me", presence),
+                _ => presence.to_string(),
+            }
+        },
+        Predicate::Compound(p) => {
+            let operands = p.predicates.iter().map(|operand| match operand.predicate {
+                Some(Predicate::Compound(ref c)) if c.kind() != Kind::Not => format!("({})", format_predicate(operand)),
+                _ => format_predicate(operand),
+            }).collect::<Vec<String>>();
+            match p.kind() {
+                Kind::And => operands.join(" and "),
+                Kind::Or => operands.join(" or "),
+                Kind::Not => format!("not {}", operands.join(" ")),
+                Kind::InvalidKind => "?".to_string(),
+            }
+        },
+        Predicate::Format(format) => format.clone(),
+    }
+}
+
 fn print_event_trigger(event_trigger: &EventTriggerInformation) {
     let trigger = event_trigger.trigger.as_ref().unwrap();
     println!("  Trigger: {}", trigger.name);
@@ -147,6 +287,14 @@ fn print_event_trigger(event_trigger: &EventTriggerInformation) {
     println!("    Last Fire Date (UTC): {}", NaiveDateTime::from_timestamp(trigger.last_fire_date as i64, 0));
     println!("    Activation State: {}", event_trigger.activation_state());
     println!("    Executes Once: {}", event_trigger.executes_once);
+    match event_trigger.recurrences.len() {
+        0 => println!("    Recurrences: Every Day"),
+        _ => println!("    Recurrences: {}", event_trigger.recurrences().map(|w| w.to_string()).collect::<Vec<String>>().join(", ")),
+    };
+    match event_trigger.predicate {
+        Some(ref predicate) => println!("    Condition: {}", format_predicate(predicate)),
+        None => println!("    Condition: None"),
+    };
     println!("    Events: ({})", event_trigger.events.len());
     event_trigger.events.iter().for_each(|event| {
         print_event(event);
@@ -184,6 +332,45 @@ fn parse_timestamp(s: Option<&str>) -> u64 {
     }
 }
 
+fn format_date(timestamp: u64) -> String {
+    if timestamp == 0 {
+        return "-".to_string();
+    }
+    NaiveDateTime::from_timestamp(timestamp as i64, 0).to_string()
+}
+
+impl Table for EnumerateTriggersResponse {
+    const COLUMNS: &'static [Column] = &[
+        Column { name: "name", default: true },
+        Column { name: "uuid", default: false },
+        Column { name: "type", default: true },
+        Column { name: "enabled", default: true },
+        Column { name: "last_fired", default: true },
+        Column { name: "next_fire", default: false },
+        Column { name: "events", default: false },
+        Column { name: "action_sets", default: true },
+    ];
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        self.triggers.iter().filter_map(|trigger| {
+            let (kind, common, next_fire, events) = match trigger.trigger.as_ref()? {
+                Trigger::Event(event_trigger) => ("Event", event_trigger.trigger.as_ref()?, "-".to_string(), event_trigger.events.len().to_string()),
+                Trigger::Timer(timer_trigger) => ("Timer", timer_trigger.trigger.as_ref()?, format_date(timer_trigger.fire_date), "-".to_string()),
+            };
+            Some(vec![
+                common.name.clone(),
+                common.uuid.clone(),
+                kind.to_string(),
+                common.is_enabled.to_string(),
+                format_date(common.last_fire_date),
+                next_fire,
+                events,
+                names(&common.action_sets),
+            ])
+        }).collect()
+    }
+}
+
 async fn _run(matches: ArgMatches, mut client: HomeKitServiceClient<Channel>) -> Result<(), Box<dyn std::error::Error>> {
     let before = parse_timestamp(matches.value_of("before"));
     let after = parse_timestamp(matches.value_of("after"));
@@ -201,8 +388,7 @@ async fn _run(matches: ArgMatches, mut client: HomeKitServiceClient<Channel>) ->
             before: before,
             after: after,
         }).await?.into_inner();
-    print_response(&response);
-    Ok(())
+    output::print_listing(&matches, &response, print_response)
 }
 
 pub fn run(matches: ArgMatches, client: HomeKitServiceClient<Channel>) -> Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error>>>>> {
@@ -0,0 +1,122 @@
+use chrono::NaiveDateTime;
+use clap::ArgMatches;
+use std::boxed::Box;
+use std::future::Future;
+use std::pin::Pin;
+use tonic::transport::Channel;
+use crate::hkservice::home_kit_service_client::HomeKitServiceClient;
+use crate::hkservice::{
+    CharacteristicChangeEvent,
+    HomeEvent,
+    NameUuidPair,
+    SubscribeCharacteristicsRequest,
+    WatchHomeRequest,
+};
+use crate::hkservice::home_event::{Event, MembershipChanged};
+use crate::characteristic::characteristictypes_from_matches;
+use crate::services::servicetypes_from_matches;
+use crate::output;
+use crate::tlv;
+
+fn print_event(event: &CharacteristicChangeEvent, decode_tlv: bool) {
+    let accessory = event.accessory.as_ref().map_or("", |a| a.name.as_str());
+    let service = event.service.as_ref().map_or("", |s| s.name.as_str());
+    if let Some(ref characteristic) = event.characteristic {
+        let value = characteristic.value.as_ref().map_or(" <None>".to_string(), |v| tlv::format_value(characteristic, v, decode_tlv, 2));
+        println!("{} {}{}/{}/{} ={}",
+                 NaiveDateTime::from_timestamp(event.timestamp as i64, 0),
+                 if event.is_snapshot { "(snapshot) " } else { "" },
+                 accessory,
+                 service,
+                 characteristic.characteristic_type(),
+                 value);
+    }
+}
+
+fn describe(pair: &NameUuidPair) -> String {
+    format!("{} ({})", pair.name, pair.uuid)
+}
+
+fn pair(pair: &Option<NameUuidPair>) -> String {
+    pair.as_ref().map_or("<unknown>".to_string(), describe)
+}
+
+fn membership(change: &MembershipChanged, preposition: &str) -> String {
+    format!("{} {} {}", pair(&change.member), preposition, pair(&change.container))
+}
+
+fn describe_home_event(event: &Event) -> String {
+    match event {
+        Event::HomeRenamed(p) => format!("Home renamed: {}", describe(p)),
+        Event::RoomAdded(p) => format!("Room added: {}", describe(p)),
+        Event::RoomRemoved(p) => format!("Room removed: {}", describe(p)),
+        Event::RoomRenamed(p) => format!("Room renamed: {}", describe(p)),
+        Event::ZoneAdded(p) => format!("Zone added: {}", describe(p)),
+        Event::ZoneRemoved(p) => format!("Zone removed: {}", describe(p)),
+        Event::ZoneRenamed(p) => format!("Zone renamed: {}", describe(p)),
+        Event::RoomAddedToZone(m) => format!("Room added: {}", membership(m, "to")),
+        Event::RoomRemovedFromZone(m) => format!("Room removed: {}", membership(m, "from")),
+        Event::AccessoryAdded(p) => format!("Accessory added: {}", describe(p)),
+        Event::AccessoryRemoved(p) => format!("Accessory removed: {}", describe(p)),
+        Event::AccessoryRenamed(p) => format!("Accessory renamed: {}", describe(p)),
+        Event::AccessoryMoved(m) => format!("Accessory moved: {} to {}", pair(&m.accessory), pair(&m.room)),
+        Event::ServiceGroupAdded(p) => format!("Service group added: {}", describe(p)),
+        Event::ServiceGroupRemoved(p) => format!("Service group removed: {}", describe(p)),
+        Event::ServiceGroupRenamed(p) => format!("Service group renamed: {}", describe(p)),
+        Event::ServiceAddedToGroup(m) => format!("Service added: {}", membership(m, "to")),
+        Event::ServiceRemovedFromGroup(m) => format!("Service removed: {}", membership(m, "from")),
+        Event::ActionSetAdded(p) => format!("Action set added: {}", describe(p)),
+        Event::ActionSetRemoved(p) => format!("Action set removed: {}", describe(p)),
+        Event::ActionSetRenamed(p) => format!("Action set renamed: {}", describe(p)),
+        Event::ActionSetUpdated(p) => format!("Action set updated: {}", describe(p)),
+        Event::TriggerAdded(p) => format!("Trigger added: {}", describe(p)),
+        Event::TriggerRemoved(p) => format!("Trigger removed: {}", describe(p)),
+        Event::TriggerRenamed(p) => format!("Trigger renamed: {}", describe(p)),
+        Event::TriggerEnabledChanged(e) => format!("Trigger {}: {}", if e.is_enabled { "enabled" } else { "disabled" }, pair(&e.trigger)),
+        Event::ActionSetAddedToTrigger(m) => format!("Action set added: {}", membership(m, "to")),
+        Event::ActionSetRemovedFromTrigger(m) => format!("Action set removed: {}", membership(m, "from")),
+        Event::TriggerUpdated(p) => format!("Trigger updated: {}", describe(p)),
+    }
+}
+
+fn print_home_event(event: &HomeEvent) {
+    if let Some(ref e) = event.event {
+        println!("{} {}", NaiveDateTime::from_timestamp(event.timestamp as i64, 0), describe_home_event(e));
+    }
+}
+
+async fn _run(matches: ArgMatches, mut client: HomeKitServiceClient<Channel>) -> Result<(), Box<dyn std::error::Error>> {
+    let request = SubscribeCharacteristicsRequest {
+        home: matches.value_of("home").unwrap_or("").to_string(),
+        room_filter: matches.value_of("room").unwrap_or("").to_string(),
+        accessory_filter: matches.value_of("accessory").unwrap_or("").to_string(),
+        service_filter: matches.value_of("service").unwrap_or("").to_string(),
+        service_types: servicetypes_from_matches(&matches, "type")?,
+        characteristic_types: characteristictypes_from_matches(&matches, "characteristic")?,
+        initial_snapshot: matches.is_present("snapshot"),
+    };
+    let mut stream = client.subscribe_characteristics(request).await?.into_inner();
+    while let Some(event) = stream.message().await? {
+        output::print(&matches, &event, |e| print_event(e, matches.is_present("decode_tlv")))?;
+    }
+    Ok(())
+}
+
+async fn _run_home(matches: ArgMatches, mut client: HomeKitServiceClient<Channel>) -> Result<(), Box<dyn std::error::Error>> {
+    let request = WatchHomeRequest {
+        home: matches.value_of("home").unwrap_or("").to_string(),
+    };
+    let mut stream = client.watch_home(request).await?.into_inner();
+    while let Some(event) = stream.message().await? {
+        output::print(&matches, &event, print_home_event)?;
+    }
+    Ok(())
+}
+
+pub fn run(matches: ArgMatches, client: HomeKitServiceClient<Channel>) -> Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error>>>>> {
+    Box::pin(_run(matches, client))
+}
+
+pub fn run_home(matches: ArgMatches, client: HomeKitServiceClient<Channel>) -> Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error>>>>> {
+    Box::pin(_run_home(matches, client))
+}
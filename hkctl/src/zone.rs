@@ -0,0 +1,85 @@
+use clap::{ArgMatches};
+use std::boxed::Box;
+use std::future::Future;
+use std::pin::Pin;
+use std::str::FromStr;
+use tonic::transport::Channel;
+use crate::hkservice::home_kit_service_client::HomeKitServiceClient;
+use crate::hkservice::{
+    AddRemoveZoneRequest,
+    AddRemoveZoneResponse,
+    ChangeRoomZoneMembershipRequest,
+    ChangeRoomZoneMembershipResponse,
+    Operation,
+};
+use crate::output;
+use crate::resolve;
+
+fn print_response(response: &AddRemoveZoneResponse) {
+    println!("Home: {}, Zone {}", response.home.as_ref().unwrap().name, response.zone.as_ref().unwrap().name);
+}
+
+fn print_membership_response(response: &ChangeRoomZoneMembershipResponse) {
+    println!("Home: {}, Zone {}, Room {}",
+             response.home.as_ref().unwrap().name,
+             response.zone.as_ref().unwrap().name,
+             response.room.as_ref().unwrap().name);
+}
+
+async fn _run(matches: ArgMatches, mut client: HomeKitServiceClient<Channel>) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(rooms_matches) = matches.subcommand_matches("rooms") {
+        return change_membership(rooms_matches, client).await;
+    }
+    let home = matches.value_of("home").unwrap_or("");
+    let name = matches.value_of("name").unwrap();
+    let operation = Operation::from_str(matches.value_of("operation").unwrap())?;
+    let request = match operation {
+        Operation::Add => {
+            // Resolve rooms here, since the server skips names it can't find
+            let mut rooms = vec![];
+            for room in matches.values_of("rooms").map_or(vec![], |values| values.collect()) {
+                rooms.push(resolve::room(&mut client, home, room).await?.uuid);
+            }
+            AddRemoveZoneRequest {
+                home: home.to_string(),
+                name: name.to_string(),
+                rooms: rooms,
+                operation: operation as i32,
+            }
+        },
+        Operation::Remove => {
+            // The server removes the first zone matching the pattern, so make
+            // sure it is the only one
+            let zone = resolve::zone(&mut client, home, name).await?;
+            if !resolve::confirm_removal(&matches, "zone", name, &zone)? {
+                return Ok(());
+            }
+            AddRemoveZoneRequest {
+                home: home.to_string(),
+                name: resolve::exactly(&zone.uuid),
+                rooms: vec![],
+                operation: operation as i32,
+            }
+        },
+    };
+    let response = client.add_remove_zone(request).await?.into_inner();
+    output::print(&matches, &response, print_response)
+}
+
+async fn change_membership(matches: &ArgMatches, mut client: HomeKitServiceClient<Channel>) -> Result<(), Box<dyn std::error::Error>> {
+    let home = matches.value_of("home").unwrap_or("");
+    let operation = Operation::from_str(matches.value_of("operation").unwrap())?;
+    let zone = resolve::zone(&mut client, home, matches.value_of("zone").unwrap()).await?;
+    let room = resolve::room(&mut client, home, matches.value_of("room").unwrap()).await?;
+    let response = client.change_room_zone_membership(ChangeRoomZoneMembershipRequest {
+        home: home.to_string(),
+        name: room.uuid,
+        zone: zone.uuid,
+        operation: operation as i32,
+    }).await?.into_inner();
+    output::print(matches, &response, print_membership_response)
+}
+
+pub fn run(matches: ArgMatches, client: HomeKitServiceClient<Channel>) -> Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error>>>>> {
+    Box::pin(_run(matches, client))
+}
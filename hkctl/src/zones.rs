@@ -5,6 +5,8 @@ use std::pin::Pin;
 use tonic::transport::Channel;
 use crate::hkservice::home_kit_service_client::HomeKitServiceClient;
 use crate::hkservice::{EnumerateZonesRequest, EnumerateZonesResponse};
+use crate::output;
+use crate::table::{names, Column, Table};
 
 fn print_response(response: &EnumerateZonesResponse) {
     println!("Zones ({}):", response.zones.len());
@@ -18,6 +20,22 @@ fn print_response(response: &EnumerateZonesResponse) {
     });
 }
 
+impl Table for EnumerateZonesResponse {
+    const COLUMNS: &'static [Column] = &[
+        Column { name: "name", default: true },
+        Column { name: "uuid", default: false },
+        Column { name: "rooms", default: true },
+    ];
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        self.zones.iter().map(|zone| vec![
+            zone.name.clone(),
+            zone.uuid.clone(),
+            names(&zone.rooms),
+        ]).collect()
+    }
+}
+
 async fn _run(matches: ArgMatches, mut client: HomeKitServiceClient<Channel>) -> Result<(), Box<dyn std::error::Error>> {
     let response = client.enumerate_zones(
         EnumerateZonesRequest {
@@ -25,8 +43,7 @@ async fn _run(matches: ArgMatches, mut client: HomeKitServiceClient<Channel>) ->
             room_filter: matches.value_of("room").unwrap_or("").to_string(),
             name_filter: matches.value_of("name").unwrap_or("").to_string(),
     }).await?.into_inner();
-    print_response(&response);
-    Ok(())
+    output::print_listing(&matches, &response, print_response)
 }
 
 pub fn run(matches: ArgMatches, client: HomeKitServiceClient<Channel>) -> Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error>>>>> {
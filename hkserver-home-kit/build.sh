@@ -10,7 +10,7 @@ set -x
 # When building with a local toolchain, it's not necessary since the local
 # toolchain build can include std (and only nightly cargo supports -Z
 # build-std).
-cargo build $*
+cargo build --target x86_64-apple-ios-macabi $*
 
 # Use cargo-bundle to build and package the .app for the server. It requires
 # using a --target argument regardless of whether .cargo/config.toml exists.
@@ -0,0 +1,191 @@
+use tokio::sync::{broadcast, watch};
+use hkrpc::backend::{ActionOutcome, BackendError, BackendResult, CharacteristicChange, HomeBackend, HomeChange};
+use hkrpc::hkservice::{
+    AccessoryInformation,
+    ActionSetInformation,
+    EventTriggerDefinition,
+    HomeInformation,
+    NameUuidPair,
+    RoomInformation,
+    ServiceGroupInformation,
+    TriggerDefinition,
+    TriggerInformation,
+    Value,
+    ZoneInformation,
+};
+use hkrpc::hkservice::set_name_request::ObjectType;
+
+/// Will serve the HomeKit database of the logged in user through
+/// `HMHomeManager`.
+///
+/// The fruity bindings do not expose the HomeKit object graph beyond the
+/// home manager yet, so every operation currently reports `Unimplemented`.
+/// The home manager itself stays on the main thread, in `main`.
+pub struct HomeKitBackend {
+    // Nothing is published until the bindings expose HMAccessoryDelegate
+    // and HMHomeDelegate
+    characteristic_changes: broadcast::Sender<CharacteristicChange>,
+    home_changes: broadcast::Sender<HomeChange>,
+    // Never ready until the bindings expose HMHomeManagerDelegate, whose
+    // homeManagerDidUpdateHomes should send true
+    readiness: (watch::Sender<bool>, watch::Receiver<bool>),
+}
+
+impl HomeKitBackend {
+    pub fn new() -> HomeKitBackend {
+        let (characteristic_changes, _) = broadcast::channel(256);
+        let (home_changes, _) = broadcast::channel(256);
+        HomeKitBackend {
+            characteristic_changes: characteristic_changes,
+            home_changes: home_changes,
+            readiness: watch::channel(false),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl HomeBackend for HomeKitBackend {
+    async fn homes(&self) -> BackendResult<Vec<HomeInformation>> {
+        Err(BackendError::Unimplemented)
+    }
+
+    async fn rooms(&self, _home: &str) -> BackendResult<Vec<RoomInformation>> {
+        Err(BackendError::Unimplemented)
+    }
+
+    async fn room_for_entire_home(&self, _home: &str) -> BackendResult<RoomInformation> {
+        Err(BackendError::Unimplemented)
+    }
+
+    async fn zones(&self, _home: &str) -> BackendResult<Vec<ZoneInformation>> {
+        Err(BackendError::Unimplemented)
+    }
+
+    async fn accessories(&self, _home: &str) -> BackendResult<Vec<AccessoryInformation>> {
+        Err(BackendError::Unimplemented)
+    }
+
+    async fn service_groups(&self, _home: &str) -> BackendResult<Vec<ServiceGroupInformation>> {
+        Err(BackendError::Unimplemented)
+    }
+
+    async fn action_sets(&self, _home: &str) -> BackendResult<Vec<ActionSetInformation>> {
+        Err(BackendError::Unimplemented)
+    }
+
+    async fn triggers(&self, _home: &str) -> BackendResult<Vec<TriggerInformation>> {
+        Err(BackendError::Unimplemented)
+    }
+
+    async fn read_characteristic(&self, _home: &str, _characteristic: &str) -> BackendResult<Value> {
+        Err(BackendError::Unimplemented)
+    }
+
+    async fn write_characteristic(&self, _home: &str, _characteristic: &str, _value: &Value) -> BackendResult<()> {
+        Err(BackendError::Unimplemented)
+    }
+
+    fn subscribe_characteristics(&self) -> broadcast::Receiver<CharacteristicChange> {
+        self.characteristic_changes.subscribe()
+    }
+
+    fn watch_homes(&self) -> broadcast::Receiver<HomeChange> {
+        self.home_changes.subscribe()
+    }
+
+    fn readiness(&self) -> watch::Receiver<bool> {
+        self.readiness.1.clone()
+    }
+
+    async fn add_room(&self, _home: &str, _name: &str) -> BackendResult<NameUuidPair> {
+        Err(BackendError::Unimplemented)
+    }
+
+    async fn remove_room(&self, _home: &str, _room: &str) -> BackendResult<()> {
+        Err(BackendError::Unimplemented)
+    }
+
+    async fn assign_accessory(&self, _home: &str, _accessory: &str, _room: &str) -> BackendResult<()> {
+        Err(BackendError::Unimplemented)
+    }
+
+    async fn add_zone(&self, _home: &str, _name: &str) -> BackendResult<NameUuidPair> {
+        Err(BackendError::Unimplemented)
+    }
+
+    async fn remove_zone(&self, _home: &str, _zone: &str) -> BackendResult<()> {
+        Err(BackendError::Unimplemented)
+    }
+
+    async fn add_room_to_zone(&self, _home: &str, _zone: &str, _room: &str) -> BackendResult<()> {
+        Err(BackendError::Unimplemented)
+    }
+
+    async fn remove_room_from_zone(&self, _home: &str, _zone: &str, _room: &str) -> BackendResult<()> {
+        Err(BackendError::Unimplemented)
+    }
+
+    async fn add_service_group(&self, _home: &str, _name: &str) -> BackendResult<NameUuidPair> {
+        Err(BackendError::Unimplemented)
+    }
+
+    async fn remove_service_group(&self, _home: &str, _service_group: &str) -> BackendResult<()> {
+        Err(BackendError::Unimplemented)
+    }
+
+    async fn add_service_to_group(&self, _home: &str, _service_group: &str, _service: &str) -> BackendResult<()> {
+        Err(BackendError::Unimplemented)
+    }
+
+    async fn remove_service_from_group(&self, _home: &str, _service_group: &str, _service: &str) -> BackendResult<()> {
+        Err(BackendError::Unimplemented)
+    }
+
+    async fn add_action_set(&self, _home: &str, _name: &str) -> BackendResult<NameUuidPair> {
+        Err(BackendError::Unimplemented)
+    }
+
+    async fn remove_action_set(&self, _home: &str, _action_set: &str) -> BackendResult<()> {
+        Err(BackendError::Unimplemented)
+    }
+
+    async fn add_action(&self, _home: &str, _action_set: &str, _characteristic: &str, _target_value: &Value) -> BackendResult<String> {
+        Err(BackendError::Unimplemented)
+    }
+
+    async fn remove_action(&self, _home: &str, _action_set: &str, _action: &str) -> BackendResult<()> {
+        Err(BackendError::Unimplemented)
+    }
+
+    async fn add_trigger(&self, _home: &str, _name: &str, _definition: &TriggerDefinition) -> BackendResult<NameUuidPair> {
+        Err(BackendError::Unimplemented)
+    }
+
+    async fn remove_trigger(&self, _home: &str, _trigger: &str) -> BackendResult<()> {
+        Err(BackendError::Unimplemented)
+    }
+
+    async fn update_event_trigger(&self, _home: &str, _trigger: &str, _definition: &EventTriggerDefinition) -> BackendResult<()> {
+        Err(BackendError::Unimplemented)
+    }
+
+    async fn enable_trigger(&self, _home: &str, _trigger: &str, _enable: bool) -> BackendResult<()> {
+        Err(BackendError::Unimplemented)
+    }
+
+    async fn add_action_set_to_trigger(&self, _home: &str, _trigger: &str, _action_set: &str) -> BackendResult<()> {
+        Err(BackendError::Unimplemented)
+    }
+
+    async fn remove_action_set_from_trigger(&self, _home: &str, _trigger: &str, _action_set: &str) -> BackendResult<()> {
+        Err(BackendError::Unimplemented)
+    }
+
+    async fn execute_action_set(&self, _home: &str, _action_set: &str) -> BackendResult<Vec<ActionOutcome>> {
+        Err(BackendError::Unimplemented)
+    }
+
+    async fn set_name(&self, _home: &str, _object_type: ObjectType, _object: &str, _name: &str) -> BackendResult<()> {
+        Err(BackendError::Unimplemented)
+    }
+}
@@ -0,0 +1,18 @@
+use fruity::home_kit::HMHomeManager;
+use tokio;
+
+mod home_kit;
+
+use home_kit::HomeKitBackend;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Creating the home manager asks for access to HomeKit the first time.
+    // It must stay on the main thread, which runs the server until it exits,
+    // so it is held here rather than by the backend.
+    let mut _home_manager = None;
+    hkrpc::run(|| {
+        _home_manager = Some(HMHomeManager::new());
+        Ok(HomeKitBackend::new())
+    }).await
+}
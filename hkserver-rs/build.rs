@@ -1,9 +1,73 @@
+use prost::Message;
+use prost_types::{DescriptorProto, EnumDescriptorProto, FileDescriptorSet};
 use std::process::Command;
 use std::path::{Path, PathBuf};
 use tonic_build;
 
+// Enums that can be spelled by name in simulated home fixtures
+const DESERIALIZABLE_ENUMS: &[&str] = &[
+    ".org.hkserver.ServiceType",
+    ".org.hkserver.CharacteristicInformation.Property",
+    ".org.hkserver.CharacteristicInformation.CharacteristicType",
+    ".org.hkserver.CharacteristicInformation.Format",
+    ".org.hkserver.CharacteristicInformation.Units",
+    ".org.hkserver.AccessoryInformation.Category",
+    ".org.hkserver.ActionSetInformation.ActionSetType",
+    ".org.hkserver.HomeInformation.HomeHubState",
+    ".org.hkserver.Weekday",
+    ".org.hkserver.SignificantEvent",
+    ".org.hkserver.PresenceEventType",
+    ".org.hkserver.PresenceEventUserType",
+    ".org.hkserver.ComparisonOperator",
+    ".org.hkserver.SignificantTimePredicate.Relation",
+    ".org.hkserver.CompoundPredicate.Kind",
+];
+
+const PROTOS: &[&str] = &[
+    "../protos/hkserver.proto",
+    "../protos/grpc/health/v1/health.proto",
+    "../protos/grpc/reflection/v1alpha/reflection.proto",
+];
+
+/// Finds the value names of the enum with the fully qualified name `path`
+/// among `enums` and the enums nested in `messages`.
+fn enum_values(scope: &str, messages: &[DescriptorProto], enums: &[EnumDescriptorProto], path: &str) -> Option<Vec<String>> {
+    let found = enums.iter()
+        .find(|e| format!("{}.{}", scope, e.name()) == path)
+        .map(|e| e.value.iter().map(|v| v.name().to_string()).collect());
+    found.or_else(|| messages.iter().find_map(|m| {
+        let scope = format!("{}.{}", scope, m.name());
+        enum_values(&scope, &m.nested_type, &m.enum_type, path)
+    }))
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    tonic_build::compile_protos("../protos/hkserver.proto")?;
+    // Descriptors of every served file and its imports, for server reflection
+    let descriptors = Path::new(&std::env::var("OUT_DIR")?).join("descriptors.bin");
+    let status = Command::new(prost_build::protoc())
+        .arg("--include_imports")
+        .arg(format!("--descriptor_set_out={}", descriptors.display()))
+        .arg("-I../protos")
+        .args(PROTOS)
+        .status()?;
+    if !status.success() {
+        return Err(format!("protoc failed to write descriptors: {}", status).into());
+    }
+    let set = FileDescriptorSet::decode(std::fs::read(&descriptors)?.as_slice())?;
+
+    let mut builder = tonic_build::configure();
+    for path in DESERIALIZABLE_ENUMS {
+        builder = builder.type_attribute(path, "#[derive(serde::Deserialize)]");
+        // prost renames values to CamelCase, which does not convert back to
+        // names such as PM2_5_DENSITY, so every value is renamed explicitly
+        let values = set.file.iter()
+            .find_map(|f| enum_values(&format!(".{}", f.package()), &f.message_type, &f.enum_type, path))
+            .ok_or_else(|| format!("No enum {} in the protos", path))?;
+        for value in values {
+            builder = builder.field_attribute(format!("{}.{}", path, value), format!("#[serde(rename = \"{}\")]", value));
+        }
+    }
+    builder.compile(PROTOS, &["../protos"])?;
 
     // Inject build project as cfg "profile" key
     println!("cargo:rustc-cfg=profile=\"{}\"", std::env::var("PROFILE").unwrap());
@@ -0,0 +1,72 @@
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use tonic::{Request, Status};
+use tonic::metadata::MetadataValue;
+use crate::token_file::{hash, Scope, TokenFile};
+
+/// Metadata the interceptor sets to the scope of the caller. Interceptors
+/// do not know which RPC is being called, so the service checks the scope.
+const SCOPE_KEY: &str = "x-hkserver-scope";
+
+/// The token file, reloaded when it changes so tokens can be added and
+/// revoked while the server runs.
+struct Tokens {
+    path: PathBuf,
+    loaded: Mutex<Option<(SystemTime, TokenFile)>>,
+}
+
+impl Tokens {
+    fn scope(&self, token: &str) -> Result<Scope, Status> {
+        let internal = |e: &dyn std::fmt::Display| Status::internal(format!("Could not load API tokens: {}", e));
+        let modified = std::fs::metadata(&self.path).and_then(|m| m.modified()).map_err(|e| internal(&e))?;
+        let mut loaded = self.loaded.lock().unwrap();
+        if loaded.as_ref().map(|(time, _)| *time) != Some(modified) {
+            *loaded = Some((modified, TokenFile::load(&self.path).map_err(|e| internal(&e))?));
+        }
+        let sha256 = hash(token);
+        loaded.as_ref().unwrap().1.tokens.iter()
+            .find(|t| t.sha256 == sha256)
+            .map(|t| t.scope)
+            .ok_or_else(|| Status::unauthenticated("Invalid API token"))
+    }
+}
+
+/// Returns an interceptor that checks the bearer token in the authorization
+/// metadata against the token file and passes its scope on to the service.
+/// Without a token file every caller has the admin scope.
+pub fn interceptor(tokens: Option<PathBuf>) -> impl Fn(Request<()>) -> Result<Request<()>, Status> + Send + Sync + 'static {
+    let tokens = tokens.map(|path| Arc::new(Tokens { path, loaded: Mutex::new(None) }));
+    move |mut request: Request<()>| {
+        let scope = match tokens {
+            Some(ref tokens) => {
+                let scope = request.metadata().get("authorization")
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(|value| value.strip_prefix("Bearer "))
+                    .ok_or_else(|| Status::unauthenticated("An API token is required"))
+                    .and_then(|token| tokens.scope(token));
+                if let Err(ref status) = scope {
+                    let peer = request.remote_addr().map_or("the unix socket".to_string(), |addr| addr.to_string());
+                    log::warn!("Rejected a request from {}: {}", peer, status.message());
+                }
+                scope?
+            },
+            None => Scope::Admin,
+        };
+        let metadata = request.metadata_mut();
+        metadata.remove("authorization");
+        metadata.insert(SCOPE_KEY, MetadataValue::from_str(&scope.to_string()).unwrap());
+        Ok(request)
+    }
+}
+
+/// Fails unless the caller's token has at least `scope`.
+pub fn require<T>(request: &Request<T>, scope: Scope) -> Result<(), Status> {
+    let granted = request.metadata().get(SCOPE_KEY)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<Scope>().ok());
+    match granted {
+        Some(granted) if granted >= scope => Ok(()),
+        _ => Err(Status::permission_denied(format!("This API token does not have the {} scope", scope))),
+    }
+}
@@ -0,0 +1,160 @@
+use crate::hkservice::{
+    AccessoryInformation,
+    ActionSetInformation,
+    EventTriggerDefinition,
+    HomeInformation,
+    NameUuidPair,
+    RoomInformation,
+    ServiceGroupInformation,
+    ServiceInformation,
+    TriggerDefinition,
+    TriggerInformation,
+    Value,
+    ZoneInformation,
+};
+use crate::hkservice::home_event::Event as HomeEventEnum;
+use crate::hkservice::set_name_request::ObjectType;
+use tokio::sync::{broadcast, watch};
+use tonic::Status;
+
+pub mod simulated;
+
+/// Errors reported by a `HomeBackend`. These map directly onto gRPC status
+/// codes, the same way `HomeKitServiceError` does in the Swift server.
+#[derive(Debug)]
+pub enum BackendError {
+    NotFound(String),
+    AlreadyExists(String),
+    InvalidArgument(String),
+    Unimplemented,
+    Failed(String),
+}
+
+impl BackendError {
+    pub fn not_found(object_type: &str, pattern: &str) -> BackendError {
+        BackendError::NotFound(format!("Could not find {} matching '{}'", object_type, pattern))
+    }
+}
+
+impl std::fmt::Display for BackendError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            BackendError::NotFound(message) => write!(f, "{}", message),
+            BackendError::AlreadyExists(message) => write!(f, "{}", message),
+            BackendError::InvalidArgument(message) => write!(f, "{}", message),
+            BackendError::Unimplemented => write!(f, "NYI"),
+            BackendError::Failed(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for BackendError {}
+
+impl From<BackendError> for Status {
+    fn from(error: BackendError) -> Status {
+        match error {
+            BackendError::NotFound(message) => Status::not_found(message),
+            BackendError::AlreadyExists(message) => Status::already_exists(message),
+            BackendError::InvalidArgument(message) => Status::invalid_argument(message),
+            BackendError::Unimplemented => Status::unimplemented("NYI"),
+            BackendError::Failed(message) => Status::internal(message),
+        }
+    }
+}
+
+pub type BackendResult<T> = Result<T, BackendError>;
+
+/// A new value reported for a characteristic, either because it was written
+/// or because the accessory changed it.
+#[derive(Clone, Debug)]
+pub struct CharacteristicChange {
+    pub home: String,
+    pub characteristic: String,
+    pub value: Value,
+}
+
+/// A change to the configuration of a home, such as a room being added or
+/// an accessory being moved.
+#[derive(Clone, Debug)]
+pub struct HomeChange {
+    pub home: NameUuidPair,
+    pub event: HomeEventEnum,
+}
+
+/// The outcome of one action when an action set is executed. Executing an
+/// action set only fails as a whole when it cannot be started; actions that
+/// fail are reported here.
+#[derive(Debug)]
+pub struct ActionOutcome {
+    pub action: String,
+    pub result: BackendResult<()>,
+}
+
+/// The home object graph served by `HKServer`.
+///
+/// Objects are always addressed by UUID; resolving user supplied names and
+/// patterns to UUIDs is the job of the service layer. Every method that
+/// takes a `home` argument expects the UUID of one of the homes returned by
+/// `homes()`.
+#[tonic::async_trait]
+pub trait HomeBackend: Send + Sync + 'static {
+    // Enumerate stuff
+    async fn homes(&self) -> BackendResult<Vec<HomeInformation>>;
+    async fn rooms(&self, home: &str) -> BackendResult<Vec<RoomInformation>>;
+    async fn room_for_entire_home(&self, home: &str) -> BackendResult<RoomInformation>;
+    async fn zones(&self, home: &str) -> BackendResult<Vec<ZoneInformation>>;
+    async fn accessories(&self, home: &str) -> BackendResult<Vec<AccessoryInformation>>;
+    async fn service_groups(&self, home: &str) -> BackendResult<Vec<ServiceGroupInformation>>;
+    async fn action_sets(&self, home: &str) -> BackendResult<Vec<ActionSetInformation>>;
+    async fn triggers(&self, home: &str) -> BackendResult<Vec<TriggerInformation>>;
+
+    async fn services(&self, home: &str) -> BackendResult<Vec<ServiceInformation>> {
+        Ok(self.accessories(home).await?
+           .into_iter()
+           .flat_map(|accessory| accessory.services)
+           .collect())
+    }
+
+    // Characteristics
+    async fn read_characteristic(&self, home: &str, characteristic: &str) -> BackendResult<Value>;
+    async fn write_characteristic(&self, home: &str, characteristic: &str, value: &Value) -> BackendResult<()>;
+    fn subscribe_characteristics(&self) -> broadcast::Receiver<CharacteristicChange>;
+
+    // Watch for configuration changes
+    fn watch_homes(&self) -> broadcast::Receiver<HomeChange>;
+
+    // Whether the home database has loaded, like HomeControllerDelegate's
+    // isReady in the Swift server. Health checks report it.
+    fn readiness(&self) -> watch::Receiver<bool>;
+
+    // Organize a home
+    async fn add_room(&self, home: &str, name: &str) -> BackendResult<NameUuidPair>;
+    async fn remove_room(&self, home: &str, room: &str) -> BackendResult<()>;
+    async fn assign_accessory(&self, home: &str, accessory: &str, room: &str) -> BackendResult<()>;
+    async fn add_zone(&self, home: &str, name: &str) -> BackendResult<NameUuidPair>;
+    async fn remove_zone(&self, home: &str, zone: &str) -> BackendResult<()>;
+    async fn add_room_to_zone(&self, home: &str, zone: &str, room: &str) -> BackendResult<()>;
+    async fn remove_room_from_zone(&self, home: &str, zone: &str, room: &str) -> BackendResult<()>;
+    async fn add_service_group(&self, home: &str, name: &str) -> BackendResult<NameUuidPair>;
+    async fn remove_service_group(&self, home: &str, service_group: &str) -> BackendResult<()>;
+    async fn add_service_to_group(&self, home: &str, service_group: &str, service: &str) -> BackendResult<()>;
+    async fn remove_service_from_group(&self, home: &str, service_group: &str, service: &str) -> BackendResult<()>;
+
+    // Manage action sets and triggers
+    async fn add_action_set(&self, home: &str, name: &str) -> BackendResult<NameUuidPair>;
+    async fn remove_action_set(&self, home: &str, action_set: &str) -> BackendResult<()>;
+    async fn add_action(&self, home: &str, action_set: &str, characteristic: &str, target_value: &Value) -> BackendResult<String>;
+    async fn remove_action(&self, home: &str, action_set: &str, action: &str) -> BackendResult<()>;
+    async fn add_trigger(&self, home: &str, name: &str, definition: &TriggerDefinition) -> BackendResult<NameUuidPair>;
+    async fn remove_trigger(&self, home: &str, trigger: &str) -> BackendResult<()>;
+    async fn update_event_trigger(&self, home: &str, trigger: &str, definition: &EventTriggerDefinition) -> BackendResult<()>;
+    async fn enable_trigger(&self, home: &str, trigger: &str, enable: bool) -> BackendResult<()>;
+    async fn add_action_set_to_trigger(&self, home: &str, trigger: &str, action_set: &str) -> BackendResult<()>;
+    async fn remove_action_set_from_trigger(&self, home: &str, trigger: &str, action_set: &str) -> BackendResult<()>;
+
+    // Run actions. Returns once every action has completed or failed.
+    async fn execute_action_set(&self, home: &str, action_set: &str) -> BackendResult<Vec<ActionOutcome>>;
+
+    // Rename things
+    async fn set_name(&self, home: &str, object_type: ObjectType, object: &str, name: &str) -> BackendResult<()>;
+}
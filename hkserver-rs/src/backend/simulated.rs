@@ -0,0 +1,1889 @@
+use serde::Deserialize;
+use serde::de::IntoDeserializer;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::RwLock;
+use tokio::sync::{broadcast, watch};
+use uuid::Uuid;
+use crate::backend::{ActionOutcome, BackendError, BackendResult, CharacteristicChange, HomeBackend, HomeChange};
+use crate::hkservice::{
+    AccessoryInformation,
+    ActionSetInformation,
+    CalendarEventInformation,
+    CharacteristicEventInformation,
+    CharacteristicInformation,
+    CharacteristicPredicate,
+    CharacteristicThresholdRangeEventInformation,
+    CircularRegion,
+    CommonTriggerInformation,
+    ComparisonOperator,
+    CompoundPredicate,
+    Coordinate2D,
+    DurationEventInformation,
+    EventInformation,
+    EventTriggerDefinition,
+    EventTriggerInformation,
+    HomeInformation,
+    ListValue,
+    LocationEventInformation,
+    MapValue,
+    NameUuidPair,
+    Number,
+    NumberRange,
+    PredicateInformation,
+    PresenceEventInformation,
+    PresenceEventType,
+    PresenceEventUserType,
+    PresencePredicate,
+    RoomInformation,
+    ServiceGroupInformation,
+    ServiceInformation,
+    ServiceType,
+    SignificantEvent,
+    SignificantTimeEventInformation,
+    SignificantTimePredicate,
+    TimeOfDay,
+    TimeOfDayPredicate,
+    TimerRecurrence,
+    TimerTriggerInformation,
+    TriggerDefinition,
+    TriggerInformation,
+    Value,
+    Weekday,
+    ZoneInformation,
+};
+use crate::hkservice::home_event::{AccessoryMoved, Event as HomeEventEnum, MembershipChanged, TriggerEnabledChanged};
+use crate::hkservice::accessory_information::Category;
+use crate::hkservice::action_set_information::{ActionSetType, Action as ActionInformation, CharacteristicAction};
+use crate::hkservice::characteristic_information::{CharacteristicType, Format, Metadata, Property, Units};
+use crate::hkservice::compound_predicate::Kind;
+use crate::hkservice::event_information::Event;
+use crate::hkservice::event_trigger_information::ActivationState;
+use crate::hkservice::home_information::HomeHubState;
+use crate::hkservice::predicate_information::Predicate;
+use crate::hkservice::set_name_request::ObjectType;
+use crate::hkservice::significant_time_predicate::Relation;
+use crate::hkservice::timer_recurrence::Unit;
+use crate::hkservice::trigger_definition::TriggerDefinition as TriggerDefinitionEnum;
+use crate::hkservice::trigger_information::Trigger as TriggerEnum;
+
+// ============== Fixture ============
+//
+// A fixture describes one or more homes. Objects refer to each other by name:
+// accessories name their room, zones list room names, services are addressed
+// as "<accessory>/<service>" and characteristics as
+// "<accessory>/<service>/<CHARACTERISTIC_TYPE>". UUIDs are generated for any
+// object that does not specify one.
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FixtureFile {
+    homes: Vec<FixtureHome>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FixtureHome {
+    name: String,
+    uuid: Option<String>,
+    #[serde(default)]
+    primary: bool,
+    hub_state: Option<HomeHubState>,
+    #[serde(default)]
+    rooms: Vec<FixtureRoom>,
+    #[serde(default)]
+    zones: Vec<FixtureZone>,
+    #[serde(default)]
+    accessories: Vec<FixtureAccessory>,
+    #[serde(default)]
+    service_groups: Vec<FixtureServiceGroup>,
+    #[serde(default)]
+    action_sets: Vec<FixtureActionSet>,
+    #[serde(default)]
+    triggers: Vec<FixtureTrigger>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FixtureRoom {
+    name: String,
+    uuid: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FixtureZone {
+    name: String,
+    uuid: Option<String>,
+    #[serde(default)]
+    rooms: Vec<String>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FixtureAccessory {
+    name: String,
+    uuid: Option<String>,
+    room: Option<String>,
+    category: Option<Category>,
+    #[serde(default)]
+    manufacturer: String,
+    #[serde(default)]
+    model: String,
+    #[serde(default)]
+    firmware_version: String,
+    #[serde(default = "default_true")]
+    reachable: bool,
+    #[serde(default)]
+    blocked: bool,
+    #[serde(default)]
+    supports_identify: bool,
+    #[serde(default)]
+    bridged: bool,
+    #[serde(default)]
+    bridged_accessories: Vec<String>,
+    #[serde(default)]
+    services: Vec<FixtureService>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FixtureService {
+    name: String,
+    uuid: Option<String>,
+    #[serde(rename = "type")]
+    service_type: ServiceType,
+    #[serde(default)]
+    primary: bool,
+    #[serde(default = "default_true")]
+    interactive: bool,
+    #[serde(default)]
+    characteristics: Vec<FixtureCharacteristic>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum FixtureValue {
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+    List(Vec<FixtureValue>),
+    Map(HashMap<String, FixtureValue>),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FixtureCharacteristic {
+    #[serde(rename = "type")]
+    characteristic_type: CharacteristicType,
+    uuid: Option<String>,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    properties: Vec<Property>,
+    format: Option<Format>,
+    units: Option<Units>,
+    min: Option<FixtureValue>,
+    max: Option<FixtureValue>,
+    step: Option<FixtureValue>,
+    #[serde(default)]
+    valid_values: Vec<FixtureValue>,
+    value: Option<FixtureValue>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FixtureServiceGroup {
+    name: String,
+    uuid: Option<String>,
+    #[serde(default)]
+    services: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FixtureAction {
+    characteristic: String,
+    value: FixtureValue,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FixtureActionSet {
+    name: String,
+    uuid: Option<String>,
+    #[serde(rename = "type")]
+    action_set_type: Option<ActionSetType>,
+    #[serde(default)]
+    actions: Vec<FixtureAction>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FixtureTimer {
+    fire_date: u64,
+    #[serde(default)]
+    time_zone: String,
+    // Seconds, in whole minutes
+    #[serde(default)]
+    recurrence: u64,
+}
+
+fn default_presence_user() -> PresenceEventUserType {
+    PresenceEventUserType::HomeUsers
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+enum FixtureEvent {
+    Characteristic {
+        characteristic: String,
+        value: Option<FixtureValue>,
+    },
+    CharacteristicThresholdRange {
+        characteristic: String,
+        min: Option<FixtureValue>,
+        max: Option<FixtureValue>,
+    },
+    SignificantTime {
+        event: SignificantEvent,
+        #[serde(default)]
+        offset: i64,
+    },
+    Duration {
+        seconds: f64,
+    },
+    Presence {
+        event: PresenceEventType,
+        #[serde(default = "default_presence_user")]
+        user: PresenceEventUserType,
+    },
+    Location {
+        latitude: f64,
+        longitude: f64,
+        // Meters
+        radius: f64,
+        #[serde(default)]
+        notify_on_entry: bool,
+        #[serde(default)]
+        notify_on_exit: bool,
+    },
+    Calendar {
+        fire_date: u64,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FixtureTimeOfDay {
+    hour: u32,
+    #[serde(default)]
+    minute: u32,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+enum FixturePredicate {
+    Characteristic {
+        characteristic: String,
+        comparison: ComparisonOperator,
+        value: FixtureValue,
+    },
+    SignificantTime {
+        relation: Relation,
+        event: SignificantEvent,
+        #[serde(default)]
+        offset: i64,
+    },
+    TimeOfDay {
+        after: Option<FixtureTimeOfDay>,
+        before: Option<FixtureTimeOfDay>,
+    },
+    Presence {
+        event: PresenceEventType,
+        #[serde(default = "default_presence_user")]
+        user: PresenceEventUserType,
+    },
+    Compound {
+        kind: Kind,
+        predicates: Vec<FixturePredicate>,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FixtureEventTrigger {
+    events: Vec<FixtureEvent>,
+    #[serde(default)]
+    end_events: Vec<FixtureEvent>,
+    #[serde(default)]
+    recurrences: Vec<Weekday>,
+    #[serde(default)]
+    executes_once: bool,
+    predicate: Option<FixturePredicate>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FixtureTrigger {
+    name: String,
+    uuid: Option<String>,
+    #[serde(default = "default_true")]
+    enabled: bool,
+    #[serde(default)]
+    last_fire_date: u64,
+    #[serde(default)]
+    action_sets: Vec<String>,
+    timer: Option<FixtureTimer>,
+    event: Option<FixtureEventTrigger>,
+}
+
+// ============== Model ============
+
+struct Room {
+    uuid: String,
+    name: String,
+}
+
+struct Zone {
+    uuid: String,
+    name: String,
+    rooms: Vec<String>,
+}
+
+struct Characteristic {
+    uuid: String,
+    description: String,
+    characteristic_type: CharacteristicType,
+    properties: Vec<Property>,
+    metadata: Metadata,
+    value: Option<Value>,
+}
+
+struct Service {
+    uuid: String,
+    name: String,
+    service_type: ServiceType,
+    is_primary: bool,
+    is_interactive: bool,
+    characteristics: Vec<Characteristic>,
+}
+
+struct Accessory {
+    uuid: String,
+    name: String,
+    room: String,
+    category: Category,
+    manufacturer: String,
+    model: String,
+    firmware_version: String,
+    is_reachable: bool,
+    is_blocked: bool,
+    supports_identify: bool,
+    is_bridged: bool,
+    bridged_accessories: Vec<String>,
+    services: Vec<Service>,
+}
+
+struct ServiceGroup {
+    uuid: String,
+    name: String,
+    services: Vec<String>,
+}
+
+struct Action {
+    uuid: String,
+    characteristic: String,
+    target_value: Value,
+}
+
+struct ActionSet {
+    uuid: String,
+    name: String,
+    action_set_type: ActionSetType,
+    actions: Vec<Action>,
+}
+
+enum TriggerKind {
+    Timer {
+        fire_date: u64,
+        time_zone: String,
+        recurrence: Option<TimerRecurrence>,
+    },
+    Event {
+        events: Vec<EventInformation>,
+        end_events: Vec<EventInformation>,
+        recurrences: Vec<Weekday>,
+        executes_once: bool,
+        predicate: Option<PredicateInformation>,
+    },
+}
+
+struct Trigger {
+    uuid: String,
+    name: String,
+    is_enabled: bool,
+    last_fire_date: u64,
+    action_sets: Vec<String>,
+    kind: TriggerKind,
+}
+
+struct Home {
+    uuid: String,
+    name: String,
+    is_primary: bool,
+    hub_state: HomeHubState,
+    room_for_entire_home: Room,
+    rooms: Vec<Room>,
+    zones: Vec<Zone>,
+    accessories: Vec<Accessory>,
+    service_groups: Vec<ServiceGroup>,
+    action_sets: Vec<ActionSet>,
+    triggers: Vec<Trigger>,
+}
+
+fn unit_seconds(unit: Unit) -> u64 {
+    match unit {
+        Unit::InvalidUnit => 0,
+        Unit::Minute => 60,
+        Unit::Hour => 60 * 60,
+        Unit::Day => 24 * 60 * 60,
+        Unit::Week => 7 * 24 * 60 * 60,
+    }
+}
+
+/// Expresses a recurrence in seconds as the largest calendar unit that
+/// divides it.
+fn recurrence_from_seconds(seconds: u64) -> BackendResult<Option<TimerRecurrence>> {
+    if seconds == 0 {
+        return Ok(None);
+    }
+    [Unit::Week, Unit::Day, Unit::Hour, Unit::Minute].iter()
+        .find(|unit| seconds % unit_seconds(**unit) == 0)
+        .map(|unit| Some(TimerRecurrence {
+            unit: *unit as i32,
+            interval: (seconds / unit_seconds(*unit)) as u32,
+        }))
+        .ok_or_else(|| BackendError::InvalidArgument(format!("Recurrence of {} seconds is not in whole minutes", seconds)))
+}
+
+/// Copies the events of a trigger definition, giving them new UUIDs.
+/// Characteristics are referred to by UUID only, like fixture events, and
+/// named when the trigger is read so that renames show up.
+fn events_from_definition(events: &[EventInformation]) -> Vec<EventInformation> {
+    events.iter().map(|event| {
+        let event = match event.event.clone() {
+            Some(Event::CharacteristicEvent(e)) => Event::CharacteristicEvent(CharacteristicEventInformation {
+                uuid: new_uuid(),
+                characteristic: e.characteristic.map(|c| name_uuid_pair("", &c.uuid)),
+                ..e
+            }),
+            Some(Event::CharacteristicThresholdRangeEvent(e)) => Event::CharacteristicThresholdRangeEvent(CharacteristicThresholdRangeEventInformation {
+                uuid: new_uuid(),
+                characteristic: e.characteristic.map(|c| name_uuid_pair("", &c.uuid)),
+                ..e
+            }),
+            Some(Event::SignificantTimeEvent(e)) => Event::SignificantTimeEvent(SignificantTimeEventInformation { uuid: new_uuid(), ..e }),
+            Some(Event::DurationEvent(e)) => Event::DurationEvent(DurationEventInformation { uuid: new_uuid(), ..e }),
+            Some(Event::PresenceEvent(e)) => Event::PresenceEvent(PresenceEventInformation { uuid: new_uuid(), ..e }),
+            Some(Event::LocationEvent(e)) => Event::LocationEvent(LocationEventInformation { uuid: new_uuid(), ..e }),
+            Some(Event::CalendarEvent(e)) => Event::CalendarEvent(CalendarEventInformation { uuid: new_uuid(), ..e }),
+            None => return EventInformation { event: None },
+        };
+        EventInformation { event: Some(event) }
+    }).collect()
+}
+
+/// Copies the predicate of a trigger definition, referring to
+/// characteristics by UUID only like events_from_definition.
+fn predicate_from_definition(predicate: &PredicateInformation) -> PredicateInformation {
+    let predicate = match predicate.predicate.clone() {
+        Some(Predicate::Characteristic(p)) => Predicate::Characteristic(CharacteristicPredicate {
+            characteristic: p.characteristic.map(|c| name_uuid_pair("", &c.uuid)),
+            ..p
+        }),
+        Some(Predicate::Compound(p)) => Predicate::Compound(CompoundPredicate {
+            kind: p.kind,
+            predicates: p.predicates.iter().map(predicate_from_definition).collect(),
+        }),
+        Some(p) => p,
+        None => return PredicateInformation { predicate: None },
+    };
+    PredicateInformation { predicate: Some(predicate) }
+}
+
+fn weekdays_from_definition(recurrences: &[i32]) -> Vec<Weekday> {
+    recurrences.iter().filter_map(|w| Weekday::from_i32(*w)).collect()
+}
+
+fn new_uuid() -> String {
+    Uuid::new_v4().to_hyphenated().to_string().to_uppercase()
+}
+
+fn name_uuid_pair(name: &str, uuid: &str) -> NameUuidPair {
+    NameUuidPair {
+        name: name.to_string(),
+        uuid: uuid.to_string(),
+    }
+}
+
+fn number_from_fixture_value(format: Format, value: &FixtureValue) -> BackendResult<Number> {
+    use crate::hkservice::number::Value as NumberValue;
+    let number = match (format, value) {
+        (Format::Bool, FixtureValue::Bool(b)) => NumberValue::SignedIntegerValue(*b as i64),
+        (Format::Int, FixtureValue::Integer(i)) => NumberValue::SignedIntegerValue(*i),
+        (Format::Uint8, FixtureValue::Integer(i)) |
+        (Format::Uint16, FixtureValue::Integer(i)) |
+        (Format::Uint32, FixtureValue::Integer(i)) |
+        (Format::Uint64, FixtureValue::Integer(i)) if *i >= 0 => NumberValue::UnsignedIntegerValue(*i as u64),
+        (Format::Float, FixtureValue::Integer(i)) => NumberValue::FloatValue(*i as f32),
+        (Format::Float, FixtureValue::Float(f)) => NumberValue::FloatValue(*f as f32),
+        _ => return Err(BackendError::InvalidArgument(format!("Value is not a valid {:?} number", format))),
+    };
+    Ok(Number { value: Some(number) })
+}
+
+fn value_from_fixture_value(format: Format, value: &FixtureValue) -> BackendResult<Value> {
+    use crate::hkservice::value::Value as ValueEnum;
+    let value = match (format, value) {
+        (Format::Bool, FixtureValue::Bool(b)) => ValueEnum::BoolValue(*b),
+        (Format::String, FixtureValue::String(s)) => ValueEnum::StringValue(s.clone()),
+        (Format::Data, FixtureValue::String(s)) |
+        (Format::Tlv8, FixtureValue::String(s)) => ValueEnum::DataValue(
+            hex::decode(s).map_err(|e| BackendError::InvalidArgument(format!("Invalid hex data '{}': {}", s, e)))?),
+        // Elements take the format their YAML type suggests
+        (Format::Array, FixtureValue::List(values)) => ValueEnum::ListValue(ListValue {
+            values: values.iter()
+                .map(|v| value_from_fixture_value(format_from_fixture_value(Some(v)), v))
+                .collect::<BackendResult<_>>()?,
+        }),
+        (Format::Dictionary, FixtureValue::Map(entries)) => ValueEnum::MapValue(MapValue {
+            entries: entries.iter()
+                .map(|(k, v)| Ok((k.clone(), value_from_fixture_value(format_from_fixture_value(Some(v)), v)?)))
+                .collect::<BackendResult<_>>()?,
+        }),
+        (_, value) => ValueEnum::NumberValue(number_from_fixture_value(format, value)?),
+    };
+    Ok(Value { value: Some(value) })
+}
+
+fn format_from_fixture_value(value: Option<&FixtureValue>) -> Format {
+    match value {
+        Some(FixtureValue::Bool(_)) => Format::Bool,
+        Some(FixtureValue::Integer(_)) => Format::Int,
+        Some(FixtureValue::Float(_)) => Format::Float,
+        Some(FixtureValue::String(_)) => Format::String,
+        Some(FixtureValue::List(_)) => Format::Array,
+        Some(FixtureValue::Map(_)) => Format::Dictionary,
+        None => Format::InvalidFormat,
+    }
+}
+
+impl Characteristic {
+    fn format(&self) -> Format {
+        Format::from_i32(self.metadata.format).unwrap_or(Format::InvalidFormat)
+    }
+
+    fn from_fixture(fixture: &FixtureCharacteristic) -> BackendResult<Characteristic> {
+        let format = fixture.format.unwrap_or_else(|| format_from_fixture_value(fixture.value.as_ref()));
+        let number = |value: &Option<FixtureValue>| -> BackendResult<Option<Number>> {
+            value.as_ref().map(|v| number_from_fixture_value(format, v)).transpose()
+        };
+        let metadata = Metadata {
+            manufacturer_description: String::new(),
+            valid_values: fixture.valid_values.iter()
+                .map(|v| number_from_fixture_value(format, v))
+                .collect::<BackendResult<Vec<Number>>>()?,
+            minimum_value: number(&fixture.min)?,
+            maximum_value: number(&fixture.max)?,
+            step_value: number(&fixture.step)?,
+            max_length: None,
+            format: format as i32,
+            units: fixture.units.unwrap_or(Units::InvalidUnits) as i32,
+        };
+        let value = fixture.value.as_ref().map(|v| value_from_fixture_value(format, v)).transpose()?;
+        Ok(Characteristic {
+            uuid: fixture.uuid.clone().unwrap_or_else(new_uuid),
+            description: fixture.description.clone(),
+            characteristic_type: fixture.characteristic_type,
+            properties: fixture.properties.clone(),
+            metadata: metadata,
+            value: value,
+        })
+    }
+
+    fn information(&self) -> CharacteristicInformation {
+        CharacteristicInformation {
+            uuid: self.uuid.clone(),
+            description: self.description.clone(),
+            properties: self.properties.iter().map(|p| *p as i32).collect(),
+            characteristic_type: self.characteristic_type as i32,
+            metadata: Some(self.metadata.clone()),
+            value: self.value.clone(),
+        }
+    }
+}
+
+impl Accessory {
+    fn information(&self, home: &Home) -> AccessoryInformation {
+        let accessory = name_uuid_pair(&self.name, &self.uuid);
+        AccessoryInformation {
+            name: self.name.clone(),
+            uuid: self.uuid.clone(),
+            category: self.category as i32,
+            room: home.find_any_room(&self.room).map(|room| name_uuid_pair(&room.name, &room.uuid)),
+            profiles: vec![],
+            is_reachable: self.is_reachable,
+            is_blocked: self.is_blocked,
+            supports_identify: self.supports_identify,
+            services: self.services.iter().map(|service| service.information(&accessory)).collect(),
+            is_bridged: self.is_bridged,
+            bridged_accessory_uuids: self.bridged_accessories.clone(),
+            firmware_version: self.firmware_version.clone(),
+            manufacturer: self.manufacturer.clone(),
+            model: self.model.clone(),
+        }
+    }
+}
+
+impl Service {
+    fn information(&self, accessory: &NameUuidPair) -> ServiceInformation {
+        ServiceInformation {
+            name: self.name.clone(),
+            uuid: self.uuid.clone(),
+            service_type: self.service_type as i32,
+            characteristics: self.characteristics.iter().map(|c| c.information()).collect(),
+            is_primary: self.is_primary,
+            is_interactive: self.is_interactive,
+            associated_service_type: ServiceType::InvalidServiceType as i32,
+            linked_services: vec![],
+            accessory: Some(accessory.clone()),
+        }
+    }
+}
+
+impl ActionSet {
+    fn ensure_no_action_for(&self, characteristic: &str) -> BackendResult<()> {
+        if self.actions.iter().any(|action| action.characteristic == characteristic) {
+            return Err(BackendError::InvalidArgument(format!(
+                "Action set '{}' already has an action for characteristic {}", self.name, characteristic)));
+        }
+        Ok(())
+    }
+}
+
+impl Home {
+    fn from_fixture(fixture: &FixtureHome) -> BackendResult<Home> {
+        let mut home = Home {
+            uuid: fixture.uuid.clone().unwrap_or_else(new_uuid),
+            name: fixture.name.clone(),
+            is_primary: fixture.primary,
+            hub_state: fixture.hub_state.unwrap_or(HomeHubState::Connected),
+            room_for_entire_home: Room {
+                uuid: new_uuid(),
+                name: format!("{} Default Room", fixture.name),
+            },
+            rooms: vec![],
+            zones: vec![],
+            accessories: vec![],
+            service_groups: vec![],
+            action_sets: vec![],
+            triggers: vec![],
+        };
+
+        for room in fixture.rooms.iter() {
+            home.rooms.push(Room {
+                uuid: room.uuid.clone().unwrap_or_else(new_uuid),
+                name: room.name.clone(),
+            });
+        }
+
+        for zone in fixture.zones.iter() {
+            let rooms = zone.rooms.iter()
+                .map(|name| home.room_by_name(name).map(|room| room.uuid.clone()))
+                .collect::<BackendResult<Vec<String>>>()?;
+            home.zones.push(Zone {
+                uuid: zone.uuid.clone().unwrap_or_else(new_uuid),
+                name: zone.name.clone(),
+                rooms: rooms,
+            });
+        }
+
+        for accessory in fixture.accessories.iter() {
+            let room = match accessory.room {
+                Some(ref name) => home.room_by_name(name)?.uuid.clone(),
+                None => home.room_for_entire_home.uuid.clone(),
+            };
+            let services = accessory.services.iter()
+                .map(|service| -> BackendResult<Service> {
+                    Ok(Service {
+                        uuid: service.uuid.clone().unwrap_or_else(new_uuid),
+                        name: service.name.clone(),
+                        service_type: service.service_type,
+                        is_primary: service.primary,
+                        is_interactive: service.interactive,
+                        characteristics: service.characteristics.iter()
+                            .map(Characteristic::from_fixture)
+                            .collect::<BackendResult<Vec<Characteristic>>>()?,
+                    })
+                })
+                .collect::<BackendResult<Vec<Service>>>()?;
+            home.accessories.push(Accessory {
+                uuid: accessory.uuid.clone().unwrap_or_else(new_uuid),
+                name: accessory.name.clone(),
+                room: room,
+                category: accessory.category.unwrap_or(Category::Other),
+                manufacturer: accessory.manufacturer.clone(),
+                model: accessory.model.clone(),
+                firmware_version: accessory.firmware_version.clone(),
+                is_reachable: accessory.reachable,
+                is_blocked: accessory.blocked,
+                supports_identify: accessory.supports_identify,
+                is_bridged: accessory.bridged,
+                bridged_accessories: vec![],
+                services: services,
+            });
+        }
+
+        // Bridged accessories can only be resolved once every accessory exists
+        for (index, accessory) in fixture.accessories.iter().enumerate() {
+            let bridged = accessory.bridged_accessories.iter()
+                .map(|name| home.accessory_by_name(name).map(|a| a.uuid.clone()))
+                .collect::<BackendResult<Vec<String>>>()?;
+            home.accessories[index].bridged_accessories = bridged;
+        }
+
+        for service_group in fixture.service_groups.iter() {
+            let services = service_group.services.iter()
+                .map(|path| home.service_by_path(path).map(|s| s.uuid.clone()))
+                .collect::<BackendResult<Vec<String>>>()?;
+            home.service_groups.push(ServiceGroup {
+                uuid: service_group.uuid.clone().unwrap_or_else(new_uuid),
+                name: service_group.name.clone(),
+                services: services,
+            });
+        }
+
+        for fixture_action_set in fixture.action_sets.iter() {
+            let mut action_set = ActionSet {
+                uuid: fixture_action_set.uuid.clone().unwrap_or_else(new_uuid),
+                name: fixture_action_set.name.clone(),
+                action_set_type: fixture_action_set.action_set_type.unwrap_or(ActionSetType::UserDefined),
+                actions: vec![],
+            };
+            for action in fixture_action_set.actions.iter() {
+                let characteristic = home.characteristic_by_path(&action.characteristic)?;
+                action_set.ensure_no_action_for(&characteristic.uuid)?;
+                action_set.actions.push(Action {
+                    uuid: new_uuid(),
+                    characteristic: characteristic.uuid.clone(),
+                    target_value: value_from_fixture_value(characteristic.format(), &action.value)?,
+                });
+            }
+            home.action_sets.push(action_set);
+        }
+
+        for trigger in fixture.triggers.iter() {
+            let action_sets = trigger.action_sets.iter()
+                .map(|name| home.action_set_by_name(name).map(|a| a.uuid.clone()))
+                .collect::<BackendResult<Vec<String>>>()?;
+            let kind = match (&trigger.timer, &trigger.event) {
+                (Some(timer), None) => TriggerKind::Timer {
+                    fire_date: timer.fire_date,
+                    time_zone: timer.time_zone.clone(),
+                    recurrence: recurrence_from_seconds(timer.recurrence)?,
+                },
+                (None, Some(event)) => TriggerKind::Event {
+                    events: event.events.iter()
+                        .map(|e| home.event_from_fixture(e))
+                        .collect::<BackendResult<Vec<EventInformation>>>()?,
+                    end_events: event.end_events.iter()
+                        .map(|e| home.event_from_fixture(e))
+                        .collect::<BackendResult<Vec<EventInformation>>>()?,
+                    recurrences: event.recurrences.clone(),
+                    executes_once: event.executes_once,
+                    predicate: event.predicate.as_ref()
+                        .map(|p| home.predicate_from_fixture(p))
+                        .transpose()?,
+                },
+                _ => return Err(BackendError::InvalidArgument(
+                    format!("Trigger '{}' must specify exactly one of timer or event", trigger.name))),
+            };
+            home.triggers.push(Trigger {
+                uuid: trigger.uuid.clone().unwrap_or_else(new_uuid),
+                name: trigger.name.clone(),
+                is_enabled: trigger.enabled,
+                last_fire_date: trigger.last_fire_date,
+                action_sets: action_sets,
+                kind: kind,
+            });
+        }
+
+        Ok(home)
+    }
+
+    fn event_from_fixture(&self, fixture: &FixtureEvent) -> BackendResult<EventInformation> {
+        let event = match fixture {
+            FixtureEvent::Characteristic { characteristic, value } => {
+                let characteristic = self.characteristic_by_path(characteristic)?;
+                Event::CharacteristicEvent(CharacteristicEventInformation {
+                    uuid: new_uuid(),
+                    characteristic: Some(name_uuid_pair("", &characteristic.uuid)),
+                    trigger_value: value.as_ref()
+                        .map(|v| value_from_fixture_value(characteristic.format(), v))
+                        .transpose()?,
+                })
+            },
+            FixtureEvent::SignificantTime { event, offset } => Event::SignificantTimeEvent(SignificantTimeEventInformation {
+                uuid: new_uuid(),
+                significant_event: *event as i32,
+                offset: *offset,
+            }),
+            FixtureEvent::CharacteristicThresholdRange { characteristic, min, max } => {
+                let characteristic = self.characteristic_by_path(characteristic)?;
+                let number = |value: &Option<FixtureValue>| value.as_ref()
+                    .map(|v| number_from_fixture_value(characteristic.format(), v))
+                    .transpose();
+                Event::CharacteristicThresholdRangeEvent(CharacteristicThresholdRangeEventInformation {
+                    uuid: new_uuid(),
+                    characteristic: Some(name_uuid_pair("", &characteristic.uuid)),
+                    range: Some(NumberRange {
+                        min_value: number(min)?,
+                        max_value: number(max)?,
+                    }),
+                })
+            },
+            FixtureEvent::Duration { seconds } => Event::DurationEvent(DurationEventInformation {
+                uuid: new_uuid(),
+                duration: *seconds,
+            }),
+            FixtureEvent::Presence { event, user } => Event::PresenceEvent(PresenceEventInformation {
+                uuid: new_uuid(),
+                presence_event: *event as i32,
+                presence_user: *user as i32,
+            }),
+            FixtureEvent::Location { latitude, longitude, radius, notify_on_entry, notify_on_exit } => Event::LocationEvent(LocationEventInformation {
+                uuid: new_uuid(),
+                notify_on_entry: *notify_on_entry,
+                notify_on_exit: *notify_on_exit,
+                region: Some(CircularRegion {
+                    center: Some(Coordinate2D { latitude: *latitude, longitude: *longitude }),
+                    radius: *radius,
+                }),
+            }),
+            FixtureEvent::Calendar { fire_date } => Event::CalendarEvent(CalendarEventInformation {
+                uuid: new_uuid(),
+                fire_date: *fire_date,
+            }),
+        };
+        Ok(EventInformation { event: Some(event) })
+    }
+
+    fn predicate_from_fixture(&self, fixture: &FixturePredicate) -> BackendResult<PredicateInformation> {
+        let time_of_day = |t: &Option<FixtureTimeOfDay>| t.as_ref().map(|t| TimeOfDay { hour: t.hour, minute: t.minute });
+        let predicate = match fixture {
+            FixturePredicate::Characteristic { characteristic, comparison, value } => {
+                let characteristic = self.characteristic_by_path(characteristic)?;
+                Predicate::Characteristic(CharacteristicPredicate {
+                    characteristic: Some(name_uuid_pair("", &characteristic.uuid)),
+                    comparison: *comparison as i32,
+                    value: Some(value_from_fixture_value(characteristic.format(), value)?),
+                })
+            },
+            FixturePredicate::SignificantTime { relation, event, offset } => Predicate::SignificantTime(SignificantTimePredicate {
+                relation: *relation as i32,
+                significant_event: *event as i32,
+                offset: *offset,
+            }),
+            FixturePredicate::TimeOfDay { after, before } => Predicate::TimeOfDay(TimeOfDayPredicate {
+                after: time_of_day(after),
+                before: time_of_day(before),
+            }),
+            FixturePredicate::Presence { event, user } => Predicate::Presence(PresencePredicate {
+                presence_event: *event as i32,
+                presence_user: *user as i32,
+            }),
+            FixturePredicate::Compound { kind, predicates } => Predicate::Compound(CompoundPredicate {
+                kind: *kind as i32,
+                predicates: predicates.iter()
+                    .map(|p| self.predicate_from_fixture(p))
+                    .collect::<BackendResult<Vec<PredicateInformation>>>()?,
+            }),
+        };
+        Ok(PredicateInformation { predicate: Some(predicate) })
+    }
+
+    fn room_by_name(&self, name: &str) -> BackendResult<&Room> {
+        self.rooms.iter()
+            .find(|room| room.name == name)
+            .ok_or_else(|| BackendError::not_found("room", name))
+    }
+
+    fn accessory_by_name(&self, name: &str) -> BackendResult<&Accessory> {
+        self.accessories.iter()
+            .find(|accessory| accessory.name == name)
+            .ok_or_else(|| BackendError::not_found("accessory", name))
+    }
+
+    fn action_set_by_name(&self, name: &str) -> BackendResult<&ActionSet> {
+        self.action_sets.iter()
+            .find(|action_set| action_set.name == name)
+            .ok_or_else(|| BackendError::not_found("action set", name))
+    }
+
+    fn service_by_path(&self, path: &str) -> BackendResult<&Service> {
+        let mut parts = path.splitn(2, '/');
+        let accessory = self.accessory_by_name(parts.next().unwrap_or(""))?;
+        let name = parts.next().unwrap_or("");
+        accessory.services.iter()
+            .find(|service| service.name == name)
+            .ok_or_else(|| BackendError::not_found("service", path))
+    }
+
+    /// Finds a characteristic by a path such as `Lamp/Light/POWER_STATE`,
+    /// whose type is spelled like the type of a fixture characteristic.
+    fn characteristic_by_path(&self, path: &str) -> BackendResult<&Characteristic> {
+        let split = path.rfind('/').ok_or_else(|| BackendError::not_found("characteristic", path))?;
+        let service = self.service_by_path(&path[..split])?;
+        let characteristic_type = &path[split + 1..];
+        let characteristic_type = CharacteristicType::deserialize(characteristic_type.into_deserializer())
+            .map_err(|_: serde::de::value::Error| BackendError::InvalidArgument(
+                format!("Invalid characteristic '{}': '{}' is not a characteristic type such as POWER_STATE", path, characteristic_type)))?;
+        service.characteristics.iter()
+            .find(|c| c.characteristic_type == characteristic_type)
+            .ok_or_else(|| BackendError::not_found("characteristic", path))
+    }
+
+    /// Names a characteristic by its accessory, service and type, the way
+    /// hkctl addresses it.
+    fn characteristic_pair(&self, uuid: &str) -> NameUuidPair {
+        for accessory in self.accessories.iter() {
+            for service in accessory.services.iter() {
+                if let Some(c) = service.characteristics.iter().find(|c| c.uuid == uuid) {
+                    let name = format!("{}/{}/{:?}", accessory.name, service.name, c.characteristic_type);
+                    return name_uuid_pair(&name, uuid);
+                }
+            }
+        }
+        name_uuid_pair("", uuid)
+    }
+
+    fn named_event(&self, event: &EventInformation) -> EventInformation {
+        let event = match event.event.clone() {
+            Some(Event::CharacteristicEvent(e)) => Event::CharacteristicEvent(CharacteristicEventInformation {
+                characteristic: e.characteristic.map(|c| self.characteristic_pair(&c.uuid)),
+                ..e
+            }),
+            Some(Event::CharacteristicThresholdRangeEvent(e)) => Event::CharacteristicThresholdRangeEvent(CharacteristicThresholdRangeEventInformation {
+                characteristic: e.characteristic.map(|c| self.characteristic_pair(&c.uuid)),
+                ..e
+            }),
+            event => return EventInformation { event },
+        };
+        EventInformation { event: Some(event) }
+    }
+
+    fn named_predicate(&self, predicate: &PredicateInformation) -> PredicateInformation {
+        let predicate = match predicate.predicate.clone() {
+            Some(Predicate::Characteristic(p)) => Predicate::Characteristic(CharacteristicPredicate {
+                characteristic: p.characteristic.map(|c| self.characteristic_pair(&c.uuid)),
+                ..p
+            }),
+            Some(Predicate::Compound(p)) => Predicate::Compound(CompoundPredicate {
+                kind: p.kind,
+                predicates: p.predicates.iter().map(|p| self.named_predicate(p)).collect(),
+            }),
+            predicate => return PredicateInformation { predicate },
+        };
+        PredicateInformation { predicate: Some(predicate) }
+    }
+
+    fn find_any_room(&self, uuid: &str) -> Option<&Room> {
+        if self.room_for_entire_home.uuid == uuid {
+            return Some(&self.room_for_entire_home);
+        }
+        self.rooms.iter().find(|room| room.uuid == uuid)
+    }
+
+    fn room_index(&self, uuid: &str) -> BackendResult<usize> {
+        self.rooms.iter()
+            .position(|room| room.uuid == uuid)
+            .ok_or_else(|| BackendError::not_found("room", uuid))
+    }
+
+    fn zone_mut(&mut self, uuid: &str) -> BackendResult<&mut Zone> {
+        self.zones.iter_mut()
+            .find(|zone| zone.uuid == uuid)
+            .ok_or_else(|| BackendError::not_found("zone", uuid))
+    }
+
+    fn accessory_mut(&mut self, uuid: &str) -> BackendResult<&mut Accessory> {
+        self.accessories.iter_mut()
+            .find(|accessory| accessory.uuid == uuid)
+            .ok_or_else(|| BackendError::not_found("accessory", uuid))
+    }
+
+    fn service_group_mut(&mut self, uuid: &str) -> BackendResult<&mut ServiceGroup> {
+        self.service_groups.iter_mut()
+            .find(|service_group| service_group.uuid == uuid)
+            .ok_or_else(|| BackendError::not_found("service group", uuid))
+    }
+
+    fn action_set(&self, uuid: &str) -> BackendResult<&ActionSet> {
+        self.action_sets.iter()
+            .find(|action_set| action_set.uuid == uuid)
+            .ok_or_else(|| BackendError::not_found("action set", uuid))
+    }
+
+    fn action_set_mut(&mut self, uuid: &str) -> BackendResult<&mut ActionSet> {
+        self.action_sets.iter_mut()
+            .find(|action_set| action_set.uuid == uuid)
+            .ok_or_else(|| BackendError::not_found("action set", uuid))
+    }
+
+    fn trigger_mut(&mut self, uuid: &str) -> BackendResult<&mut Trigger> {
+        self.triggers.iter_mut()
+            .find(|trigger| trigger.uuid == uuid)
+            .ok_or_else(|| BackendError::not_found("trigger", uuid))
+    }
+
+    fn service(&self, uuid: &str) -> BackendResult<&Service> {
+        self.accessories.iter()
+            .flat_map(|accessory| accessory.services.iter())
+            .find(|service| service.uuid == uuid)
+            .ok_or_else(|| BackendError::not_found("service", uuid))
+    }
+
+    fn characteristic(&self, uuid: &str) -> BackendResult<&Characteristic> {
+        self.accessories.iter()
+            .flat_map(|accessory| accessory.services.iter())
+            .flat_map(|service| service.characteristics.iter())
+            .find(|c| c.uuid == uuid)
+            .ok_or_else(|| BackendError::not_found("characteristic", uuid))
+    }
+
+    fn characteristic_mut(&mut self, uuid: &str) -> BackendResult<&mut Characteristic> {
+        self.accessories.iter_mut()
+            .flat_map(|accessory| accessory.services.iter_mut())
+            .flat_map(|service| service.characteristics.iter_mut())
+            .find(|c| c.uuid == uuid)
+            .ok_or_else(|| BackendError::not_found("characteristic", uuid))
+    }
+
+    fn ensure_unique_name<'a, I: Iterator<Item = &'a str>>(object_type: &str, mut names: I, name: &str) -> BackendResult<()> {
+        if names.any(|existing| existing == name) {
+            return Err(BackendError::AlreadyExists(format!("A {} named '{}' already exists", object_type, name)));
+        }
+        Ok(())
+    }
+
+    fn room_information(&self, room: &Room) -> RoomInformation {
+        RoomInformation {
+            name: room.name.clone(),
+            uuid: room.uuid.clone(),
+            home: self.name.clone(),
+            accessories: self.accessories.iter()
+                .filter(|accessory| accessory.room == room.uuid)
+                .map(|accessory| name_uuid_pair(&accessory.name, &accessory.uuid))
+                .collect(),
+        }
+    }
+
+    fn action_set_information(&self, action_set: &ActionSet) -> ActionSetInformation {
+        ActionSetInformation {
+            name: action_set.name.clone(),
+            uuid: action_set.uuid.clone(),
+            action_set_type: action_set.action_set_type as i32,
+            actions: action_set.actions.iter()
+                .map(|action| {
+                    use crate::hkservice::action_set_information::action::Action as ActionEnum;
+                    ActionInformation {
+                        action: Some(ActionEnum::CharacteristicAction(CharacteristicAction {
+                            uuid: action.uuid.clone(),
+                            characteristic: self.characteristic(&action.characteristic).ok().map(|c| c.information()),
+                            target_value: Some(action.target_value.clone()),
+                        })),
+                    }
+                })
+                .collect(),
+            is_executing: false,
+        }
+    }
+
+    fn trigger_information(&self, trigger: &Trigger) -> TriggerInformation {
+        let common = CommonTriggerInformation {
+            name: trigger.name.clone(),
+            uuid: trigger.uuid.clone(),
+            is_enabled: trigger.is_enabled,
+            last_fire_date: trigger.last_fire_date,
+            action_sets: trigger.action_sets.iter()
+                .filter_map(|uuid| self.action_set(uuid).ok())
+                .map(|action_set| name_uuid_pair(&action_set.name, &action_set.uuid))
+                .collect(),
+        };
+        let trigger = match &trigger.kind {
+            TriggerKind::Timer { fire_date, time_zone, recurrence } => TriggerEnum::Timer(TimerTriggerInformation {
+                trigger: Some(common),
+                fire_date: *fire_date,
+                recurrence: recurrence.as_ref()
+                    .map_or(0, |r| unit_seconds(Unit::from_i32(r.unit).unwrap_or(Unit::InvalidUnit)) * r.interval as u64),
+                time_zone: time_zone.clone(),
+                recurrence_components: recurrence.clone(),
+            }),
+            TriggerKind::Event { events, end_events, recurrences, executes_once, predicate } => {
+                let activation_state = if trigger.is_enabled {
+                    ActivationState::EventTriggerEnabled
+                } else {
+                    ActivationState::EventTriggerDisabled
+                };
+                TriggerEnum::Event(EventTriggerInformation {
+                    trigger: Some(common),
+                    activation_state: activation_state as i32,
+                    events: events.iter().map(|e| self.named_event(e)).collect(),
+                    end_events: end_events.iter().map(|e| self.named_event(e)).collect(),
+                    recurrences: recurrences.iter().map(|w| *w as i32).collect(),
+                    executes_once: *executes_once,
+                    predicate: predicate.as_ref().map(|p| self.named_predicate(p)),
+                })
+            },
+        };
+        TriggerInformation { trigger: Some(trigger) }
+    }
+
+    fn information(&self) -> HomeInformation {
+        HomeInformation {
+            name: self.name.clone(),
+            uuid: self.uuid.clone(),
+            is_primary: self.is_primary,
+            hub_state: self.hub_state as i32,
+            accessories: self.accessories.iter().map(|o| name_uuid_pair(&o.name, &o.uuid)).collect(),
+            rooms: self.rooms.iter().map(|o| name_uuid_pair(&o.name, &o.uuid)).collect(),
+            zones: self.zones.iter().map(|o| name_uuid_pair(&o.name, &o.uuid)).collect(),
+            service_groups: self.service_groups.iter().map(|o| name_uuid_pair(&o.name, &o.uuid)).collect(),
+            action_sets: self.action_sets.iter().map(|o| name_uuid_pair(&o.name, &o.uuid)).collect(),
+            triggers: self.triggers.iter().map(|o| name_uuid_pair(&o.name, &o.uuid)).collect(),
+        }
+    }
+}
+
+/// An in-memory home seeded from a fixture file. Mutations update the
+/// in-memory state only; nothing is written back to the fixture.
+pub struct SimulatedBackend {
+    homes: RwLock<Vec<Home>>,
+    characteristic_changes: broadcast::Sender<CharacteristicChange>,
+    home_changes: broadcast::Sender<HomeChange>,
+    // A fixture is ready as soon as it is loaded. The sender is kept so that
+    // watchers wait for changes rather than seeing the channel close.
+    readiness: (watch::Sender<bool>, watch::Receiver<bool>),
+}
+
+impl SimulatedBackend {
+    pub fn from_file(path: &Path) -> Result<SimulatedBackend, Box<dyn std::error::Error>> {
+        let contents = std::fs::read_to_string(path)?;
+        let fixture: FixtureFile = match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => serde_json::from_str(&contents)?,
+            _ => serde_yaml::from_str(&contents)?,
+        };
+        Ok(SimulatedBackend::from_fixture(&fixture)?)
+    }
+
+    fn from_fixture(fixture: &FixtureFile) -> BackendResult<SimulatedBackend> {
+        let mut homes = fixture.homes.iter()
+            .map(Home::from_fixture)
+            .collect::<BackendResult<Vec<Home>>>()?;
+        if homes.len() != 0 && !homes.iter().any(|home| home.is_primary) {
+            homes[0].is_primary = true;
+        }
+        let (characteristic_changes, _) = broadcast::channel(256);
+        let (home_changes, _) = broadcast::channel(256);
+        Ok(SimulatedBackend {
+            homes: RwLock::new(homes),
+            characteristic_changes: characteristic_changes,
+            home_changes: home_changes,
+            readiness: watch::channel(true),
+        })
+    }
+
+    fn notify(&self, home: &str, changes: Vec<(String, Value)>) {
+        for (characteristic, value) in changes {
+            // Sending only fails when nobody is subscribed
+            let _ = self.characteristic_changes.send(CharacteristicChange {
+                home: home.to_string(),
+                characteristic: characteristic,
+                value: value,
+            });
+        }
+    }
+
+    fn read<T, F: FnOnce(&Home) -> BackendResult<T>>(&self, home: &str, f: F) -> BackendResult<T> {
+        let homes = self.homes.read().unwrap();
+        let home = homes.iter()
+            .find(|h| h.uuid == home)
+            .ok_or_else(|| BackendError::not_found("home", home))?;
+        f(home)
+    }
+
+    fn write<T, F: FnOnce(&mut Home) -> BackendResult<T>>(&self, home: &str, f: F) -> BackendResult<T> {
+        let mut homes = self.homes.write().unwrap();
+        let home = homes.iter_mut()
+            .find(|h| h.uuid == home)
+            .ok_or_else(|| BackendError::not_found("home", home))?;
+        f(home)
+    }
+
+    /// Like `write`, but publishes the configuration changes that `f`
+    /// records once the home is unlocked again.
+    fn change<T, F: FnOnce(&mut Home, &mut Vec<HomeEventEnum>) -> BackendResult<T>>(&self, home: &str, f: F) -> BackendResult<T> {
+        let mut events = vec![];
+        let (home, result) = self.write(home, |home| {
+            let result = f(home, &mut events)?;
+            Ok((name_uuid_pair(&home.name, &home.uuid), result))
+        })?;
+        for event in events {
+            // Sending only fails when nobody is watching
+            let _ = self.home_changes.send(HomeChange {
+                home: home.clone(),
+                event: event,
+            });
+        }
+        Ok(result)
+    }
+}
+
+#[tonic::async_trait]
+impl HomeBackend for SimulatedBackend {
+    async fn homes(&self) -> BackendResult<Vec<HomeInformation>> {
+        Ok(self.homes.read().unwrap().iter().map(|home| home.information()).collect())
+    }
+
+    async fn rooms(&self, home: &str) -> BackendResult<Vec<RoomInformation>> {
+        self.read(home, |home| {
+            Ok(home.rooms.iter().map(|room| home.room_information(room)).collect())
+        })
+    }
+
+    async fn room_for_entire_home(&self, home: &str) -> BackendResult<RoomInformation> {
+        self.read(home, |home| Ok(home.room_information(&home.room_for_entire_home)))
+    }
+
+    async fn zones(&self, home: &str) -> BackendResult<Vec<ZoneInformation>> {
+        self.read(home, |home| {
+            Ok(home.zones.iter()
+               .map(|zone| ZoneInformation {
+                   name: zone.name.clone(),
+                   uuid: zone.uuid.clone(),
+                   rooms: zone.rooms.iter()
+                       .filter_map(|uuid| home.find_any_room(uuid))
+                       .map(|room| name_uuid_pair(&room.name, &room.uuid))
+                       .collect(),
+               })
+               .collect())
+        })
+    }
+
+    async fn accessories(&self, home: &str) -> BackendResult<Vec<AccessoryInformation>> {
+        self.read(home, |home| {
+            Ok(home.accessories.iter().map(|accessory| accessory.information(home)).collect())
+        })
+    }
+
+    async fn service_groups(&self, home: &str) -> BackendResult<Vec<ServiceGroupInformation>> {
+        self.read(home, |home| {
+            let services = home.accessories.iter()
+                .flat_map(|accessory| accessory.services.iter())
+                .collect::<Vec<&Service>>();
+            Ok(home.service_groups.iter()
+               .map(|service_group| ServiceGroupInformation {
+                   name: service_group.name.clone(),
+                   uuid: service_group.uuid.clone(),
+                   services: service_group.services.iter()
+                       .filter_map(|uuid| services.iter().find(|service| &service.uuid == uuid))
+                       .map(|service| name_uuid_pair(&service.name, &service.uuid))
+                       .collect(),
+               })
+               .collect())
+        })
+    }
+
+    async fn action_sets(&self, home: &str) -> BackendResult<Vec<ActionSetInformation>> {
+        self.read(home, |home| {
+            Ok(home.action_sets.iter().map(|action_set| home.action_set_information(action_set)).collect())
+        })
+    }
+
+    async fn triggers(&self, home: &str) -> BackendResult<Vec<TriggerInformation>> {
+        self.read(home, |home| {
+            Ok(home.triggers.iter().map(|trigger| home.trigger_information(trigger)).collect())
+        })
+    }
+
+    async fn read_characteristic(&self, home: &str, characteristic: &str) -> BackendResult<Value> {
+        self.read(home, |home| {
+            let characteristic = home.characteristic(characteristic)?;
+            characteristic.value.clone()
+                .ok_or_else(|| BackendError::Failed(format!("Characteristic {} has no value", characteristic.uuid)))
+        })
+    }
+
+    async fn write_characteristic(&self, home: &str, characteristic: &str, value: &Value) -> BackendResult<()> {
+        self.write(home, |home| {
+            home.characteristic_mut(characteristic)?.value = Some(value.clone());
+            Ok(())
+        })?;
+        self.notify(home, vec![(characteristic.to_string(), value.clone())]);
+        Ok(())
+    }
+
+    fn subscribe_characteristics(&self) -> broadcast::Receiver<CharacteristicChange> {
+        self.characteristic_changes.subscribe()
+    }
+
+    fn watch_homes(&self) -> broadcast::Receiver<HomeChange> {
+        self.home_changes.subscribe()
+    }
+
+    fn readiness(&self) -> watch::Receiver<bool> {
+        self.readiness.1.clone()
+    }
+
+    async fn add_room(&self, home: &str, name: &str) -> BackendResult<NameUuidPair> {
+        self.change(home, |home, events| {
+            Home::ensure_unique_name("room", home.rooms.iter().map(|r| r.name.as_str()), name)?;
+            let room = Room { uuid: new_uuid(), name: name.to_string() };
+            let pair = name_uuid_pair(&room.name, &room.uuid);
+            home.rooms.push(room);
+            events.push(HomeEventEnum::RoomAdded(pair.clone()));
+            Ok(pair)
+        })
+    }
+
+    async fn remove_room(&self, home: &str, room: &str) -> BackendResult<()> {
+        self.change(home, |home, events| {
+            let index = home.room_index(room)?;
+            let room = home.rooms.remove(index);
+            let room_pair = name_uuid_pair(&room.name, &room.uuid);
+            // Like HomeKit, accessories in a deleted room fall back to the
+            // room for the entire home.
+            let default_room = name_uuid_pair(&home.room_for_entire_home.name, &home.room_for_entire_home.uuid);
+            for accessory in home.accessories.iter_mut().filter(|accessory| accessory.room == room.uuid) {
+                accessory.room = default_room.uuid.clone();
+                events.push(HomeEventEnum::AccessoryMoved(AccessoryMoved {
+                    accessory: Some(name_uuid_pair(&accessory.name, &accessory.uuid)),
+                    room: Some(default_room.clone()),
+                }));
+            }
+            for zone in home.zones.iter_mut().filter(|zone| zone.rooms.contains(&room.uuid)) {
+                zone.rooms.retain(|uuid| uuid != &room.uuid);
+                events.push(HomeEventEnum::RoomRemovedFromZone(MembershipChanged {
+                    container: Some(name_uuid_pair(&zone.name, &zone.uuid)),
+                    member: Some(room_pair.clone()),
+                }));
+            }
+            events.push(HomeEventEnum::RoomRemoved(room_pair));
+            Ok(())
+        })
+    }
+
+    async fn assign_accessory(&self, home: &str, accessory: &str, room: &str) -> BackendResult<()> {
+        self.change(home, |home, events| {
+            let room = match home.find_any_room(room) {
+                Some(room) => name_uuid_pair(&room.name, &room.uuid),
+                None => return Err(BackendError::not_found("room", room)),
+            };
+            let accessory = home.accessory_mut(accessory)?;
+            if accessory.room != room.uuid {
+                accessory.room = room.uuid.clone();
+                events.push(HomeEventEnum::AccessoryMoved(AccessoryMoved {
+                    accessory: Some(name_uuid_pair(&accessory.name, &accessory.uuid)),
+                    room: Some(room),
+                }));
+            }
+            Ok(())
+        })
+    }
+
+    async fn add_zone(&self, home: &str, name: &str) -> BackendResult<NameUuidPair> {
+        self.change(home, |home, events| {
+            Home::ensure_unique_name("zone", home.zones.iter().map(|z| z.name.as_str()), name)?;
+            let zone = Zone { uuid: new_uuid(), name: name.to_string(), rooms: vec![] };
+            let pair = name_uuid_pair(&zone.name, &zone.uuid);
+            home.zones.push(zone);
+            events.push(HomeEventEnum::ZoneAdded(pair.clone()));
+            Ok(pair)
+        })
+    }
+
+    async fn remove_zone(&self, home: &str, zone: &str) -> BackendResult<()> {
+        self.change(home, |home, events| {
+            let index = home.zones.iter()
+                .position(|z| z.uuid == zone)
+                .ok_or_else(|| BackendError::not_found("zone", zone))?;
+            let zone = home.zones.remove(index);
+            events.push(HomeEventEnum::ZoneRemoved(name_uuid_pair(&zone.name, &zone.uuid)));
+            Ok(())
+        })
+    }
+
+    async fn add_room_to_zone(&self, home: &str, zone: &str, room: &str) -> BackendResult<()> {
+        self.change(home, |home, events| {
+            let index = home.room_index(room)?;
+            let room_pair = name_uuid_pair(&home.rooms[index].name, &home.rooms[index].uuid);
+            let zone = home.zone_mut(zone)?;
+            if !zone.rooms.iter().any(|uuid| uuid == room) {
+                zone.rooms.push(room.to_string());
+                events.push(HomeEventEnum::RoomAddedToZone(MembershipChanged {
+                    container: Some(name_uuid_pair(&zone.name, &zone.uuid)),
+                    member: Some(room_pair),
+                }));
+            }
+            Ok(())
+        })
+    }
+
+    async fn remove_room_from_zone(&self, home: &str, zone: &str, room: &str) -> BackendResult<()> {
+        self.change(home, |home, events| {
+            let room_pair = home.find_any_room(room).map(|r| name_uuid_pair(&r.name, &r.uuid));
+            let zone = home.zone_mut(zone)?;
+            if zone.rooms.iter().any(|uuid| uuid == room) {
+                zone.rooms.retain(|uuid| uuid != room);
+                events.push(HomeEventEnum::RoomRemovedFromZone(MembershipChanged {
+                    container: Some(name_uuid_pair(&zone.name, &zone.uuid)),
+                    member: room_pair,
+                }));
+            }
+            Ok(())
+        })
+    }
+
+    async fn add_service_group(&self, home: &str, name: &str) -> BackendResult<NameUuidPair> {
+        self.change(home, |home, events| {
+            Home::ensure_unique_name("service group", home.service_groups.iter().map(|g| g.name.as_str()), name)?;
+            let service_group = ServiceGroup { uuid: new_uuid(), name: name.to_string(), services: vec![] };
+            let pair = name_uuid_pair(&service_group.name, &service_group.uuid);
+            home.service_groups.push(service_group);
+            events.push(HomeEventEnum::ServiceGroupAdded(pair.clone()));
+            Ok(pair)
+        })
+    }
+
+    async fn remove_service_group(&self, home: &str, service_group: &str) -> BackendResult<()> {
+        self.change(home, |home, events| {
+            let index = home.service_groups.iter()
+                .position(|g| g.uuid == service_group)
+                .ok_or_else(|| BackendError::not_found("service group", service_group))?;
+            let service_group = home.service_groups.remove(index);
+            events.push(HomeEventEnum::ServiceGroupRemoved(name_uuid_pair(&service_group.name, &service_group.uuid)));
+            Ok(())
+        })
+    }
+
+    async fn add_service_to_group(&self, home: &str, service_group: &str, service: &str) -> BackendResult<()> {
+        self.change(home, |home, events| {
+            let service_pair = home.service(service).map(|s| name_uuid_pair(&s.name, &s.uuid))?;
+            let service_group = home.service_group_mut(service_group)?;
+            if !service_group.services.iter().any(|uuid| uuid == service) {
+                service_group.services.push(service.to_string());
+                events.push(HomeEventEnum::ServiceAddedToGroup(MembershipChanged {
+                    container: Some(name_uuid_pair(&service_group.name, &service_group.uuid)),
+                    member: Some(service_pair),
+                }));
+            }
+            Ok(())
+        })
+    }
+
+    async fn remove_service_from_group(&self, home: &str, service_group: &str, service: &str) -> BackendResult<()> {
+        self.change(home, |home, events| {
+            let service_pair = home.service(service).map(|s| name_uuid_pair(&s.name, &s.uuid)).ok();
+            let service_group = home.service_group_mut(service_group)?;
+            if service_group.services.iter().any(|uuid| uuid == service) {
+                service_group.services.retain(|uuid| uuid != service);
+                events.push(HomeEventEnum::ServiceRemovedFromGroup(MembershipChanged {
+                    container: Some(name_uuid_pair(&service_group.name, &service_group.uuid)),
+                    member: service_pair,
+                }));
+            }
+            Ok(())
+        })
+    }
+
+    async fn add_action_set(&self, home: &str, name: &str) -> BackendResult<NameUuidPair> {
+        self.change(home, |home, events| {
+            Home::ensure_unique_name("action set", home.action_sets.iter().map(|a| a.name.as_str()), name)?;
+            let action_set = ActionSet {
+                uuid: new_uuid(),
+                name: name.to_string(),
+                action_set_type: ActionSetType::UserDefined,
+                actions: vec![],
+            };
+            let pair = name_uuid_pair(&action_set.name, &action_set.uuid);
+            home.action_sets.push(action_set);
+            events.push(HomeEventEnum::ActionSetAdded(pair.clone()));
+            Ok(pair)
+        })
+    }
+
+    async fn remove_action_set(&self, home: &str, action_set: &str) -> BackendResult<()> {
+        self.change(home, |home, events| {
+            let index = home.action_sets.iter()
+                .position(|a| a.uuid == action_set)
+                .ok_or_else(|| BackendError::not_found("action set", action_set))?;
+            if home.action_sets[index].action_set_type != ActionSetType::UserDefined {
+                return Err(BackendError::InvalidArgument(
+                    format!("Built-in action set '{}' cannot be removed", home.action_sets[index].name)));
+            }
+            let removed = home.action_sets.remove(index);
+            let action_set_pair = name_uuid_pair(&removed.name, &removed.uuid);
+            for trigger in home.triggers.iter_mut().filter(|trigger| trigger.action_sets.iter().any(|uuid| uuid == action_set)) {
+                trigger.action_sets.retain(|uuid| uuid != action_set);
+                events.push(HomeEventEnum::ActionSetRemovedFromTrigger(MembershipChanged {
+                    container: Some(name_uuid_pair(&trigger.name, &trigger.uuid)),
+                    member: Some(action_set_pair.clone()),
+                }));
+            }
+            events.push(HomeEventEnum::ActionSetRemoved(action_set_pair));
+            Ok(())
+        })
+    }
+
+    async fn add_action(&self, home: &str, action_set: &str, characteristic: &str, target_value: &Value) -> BackendResult<String> {
+        self.change(home, |home, events| {
+            home.characteristic(characteristic)?;
+            let action = Action {
+                uuid: new_uuid(),
+                characteristic: characteristic.to_string(),
+                target_value: target_value.clone(),
+            };
+            let uuid = action.uuid.clone();
+            let action_set = home.action_set_mut(action_set)?;
+            // HomeKit allows a single action per characteristic in a set
+            action_set.ensure_no_action_for(characteristic)?;
+            action_set.actions.push(action);
+            events.push(HomeEventEnum::ActionSetUpdated(name_uuid_pair(&action_set.name, &action_set.uuid)));
+            Ok(uuid)
+        })
+    }
+
+    async fn remove_action(&self, home: &str, action_set: &str, action: &str) -> BackendResult<()> {
+        self.change(home, |home, events| {
+            let action_set = home.action_set_mut(action_set)?;
+            let index = action_set.actions.iter()
+                .position(|a| a.uuid == action)
+                .ok_or_else(|| BackendError::not_found("action", action))?;
+            action_set.actions.remove(index);
+            events.push(HomeEventEnum::ActionSetUpdated(name_uuid_pair(&action_set.name, &action_set.uuid)));
+            Ok(())
+        })
+    }
+
+    async fn add_trigger(&self, home: &str, name: &str, definition: &TriggerDefinition) -> BackendResult<NameUuidPair> {
+        self.change(home, |home, events| {
+            Home::ensure_unique_name("trigger", home.triggers.iter().map(|t| t.name.as_str()), name)?;
+            let kind = match definition.trigger_definition {
+                Some(TriggerDefinitionEnum::Timer(ref timer)) => TriggerKind::Timer {
+                    fire_date: timer.fire_date,
+                    time_zone: timer.time_zone.clone(),
+                    recurrence: timer.recurrence.clone(),
+                },
+                Some(TriggerDefinitionEnum::Event(ref event)) => TriggerKind::Event {
+                    events: events_from_definition(&event.events),
+                    end_events: events_from_definition(&event.end_events),
+                    recurrences: weekdays_from_definition(&event.recurrences),
+                    executes_once: event.executes_once,
+                    predicate: event.predicate.as_ref().map(predicate_from_definition),
+                },
+                None => return Err(BackendError::InvalidArgument("Missing trigger definition".to_string())),
+            };
+            let trigger = Trigger {
+                uuid: new_uuid(),
+                name: name.to_string(),
+                is_enabled: true,
+                last_fire_date: 0,
+                action_sets: vec![],
+                kind: kind,
+            };
+            let pair = name_uuid_pair(&trigger.name, &trigger.uuid);
+            home.triggers.push(trigger);
+            events.push(HomeEventEnum::TriggerAdded(pair.clone()));
+            Ok(pair)
+        })
+    }
+
+    async fn remove_trigger(&self, home: &str, trigger: &str) -> BackendResult<()> {
+        self.change(home, |home, events| {
+            let index = home.triggers.iter()
+                .position(|t| t.uuid == trigger)
+                .ok_or_else(|| BackendError::not_found("trigger", trigger))?;
+            let trigger = home.triggers.remove(index);
+            events.push(HomeEventEnum::TriggerRemoved(name_uuid_pair(&trigger.name, &trigger.uuid)));
+            Ok(())
+        })
+    }
+
+    async fn update_event_trigger(&self, home: &str, trigger: &str, definition: &EventTriggerDefinition) -> BackendResult<()> {
+        self.change(home, |home, events| {
+            let trigger = home.trigger_mut(trigger)?;
+            match trigger.kind {
+                TriggerKind::Event { .. } => (),
+                TriggerKind::Timer { .. } => return Err(BackendError::InvalidArgument(format!("Trigger {} is not an event trigger", trigger.name))),
+            };
+            trigger.kind = TriggerKind::Event {
+                events: events_from_definition(&definition.events),
+                end_events: events_from_definition(&definition.end_events),
+                recurrences: weekdays_from_definition(&definition.recurrences),
+                executes_once: definition.executes_once,
+                predicate: definition.predicate.as_ref().map(predicate_from_definition),
+            };
+            events.push(HomeEventEnum::TriggerUpdated(name_uuid_pair(&trigger.name, &trigger.uuid)));
+            Ok(())
+        })
+    }
+
+    async fn enable_trigger(&self, home: &str, trigger: &str, enable: bool) -> BackendResult<()> {
+        self.change(home, |home, events| {
+            let trigger = home.trigger_mut(trigger)?;
+            if trigger.is_enabled != enable {
+                trigger.is_enabled = enable;
+                events.push(HomeEventEnum::TriggerEnabledChanged(TriggerEnabledChanged {
+                    trigger: Some(name_uuid_pair(&trigger.name, &trigger.uuid)),
+                    is_enabled: enable,
+                }));
+            }
+            Ok(())
+        })
+    }
+
+    async fn add_action_set_to_trigger(&self, home: &str, trigger: &str, action_set: &str) -> BackendResult<()> {
+        self.change(home, |home, events| {
+            let action_set_pair = home.action_set(action_set).map(|a| name_uuid_pair(&a.name, &a.uuid))?;
+            let trigger = home.trigger_mut(trigger)?;
+            if !trigger.action_sets.iter().any(|uuid| uuid == action_set) {
+                trigger.action_sets.push(action_set.to_string());
+                events.push(HomeEventEnum::ActionSetAddedToTrigger(MembershipChanged {
+                    container: Some(name_uuid_pair(&trigger.name, &trigger.uuid)),
+                    member: Some(action_set_pair),
+                }));
+            }
+            Ok(())
+        })
+    }
+
+    async fn remove_action_set_from_trigger(&self, home: &str, trigger: &str, action_set: &str) -> BackendResult<()> {
+        self.change(home, |home, events| {
+            let action_set_pair = home.action_set(action_set).map(|a| name_uuid_pair(&a.name, &a.uuid)).ok();
+            let trigger = home.trigger_mut(trigger)?;
+            if trigger.action_sets.iter().any(|uuid| uuid == action_set) {
+                trigger.action_sets.retain(|uuid| uuid != action_set);
+                events.push(HomeEventEnum::ActionSetRemovedFromTrigger(MembershipChanged {
+                    container: Some(name_uuid_pair(&trigger.name, &trigger.uuid)),
+                    member: action_set_pair,
+                }));
+            }
+            Ok(())
+        })
+    }
+
+    async fn execute_action_set(&self, home: &str, action_set: &str) -> BackendResult<Vec<ActionOutcome>> {
+        let (outcomes, writes) = self.write(home, |home| {
+            let actions = home.action_set(action_set)?.actions.iter()
+                .map(|action| (action.uuid.clone(), action.characteristic.clone(), action.target_value.clone()))
+                .collect::<Vec<(String, String, Value)>>();
+            let mut outcomes = vec![];
+            let mut writes = vec![];
+            for (action, characteristic, value) in actions {
+                let result = home.accessories.iter()
+                    .find(|a| a.services.iter().any(|s| s.characteristics.iter().any(|c| c.uuid == characteristic)))
+                    .ok_or_else(|| BackendError::not_found("characteristic", &characteristic))
+                    .and_then(|accessory| match accessory.is_reachable {
+                        true => Ok(()),
+                        false => Err(BackendError::Failed(format!("Accessory {} is not reachable", accessory.name))),
+                    })
+                    .and_then(|_| {
+                        home.characteristic_mut(&characteristic)?.value = Some(value.clone());
+                        writes.push((characteristic, value));
+                        Ok(())
+                    });
+                outcomes.push(ActionOutcome { action: action, result: result });
+            }
+            Ok((outcomes, writes))
+        })?;
+        self.notify(home, writes);
+        Ok(outcomes)
+    }
+
+    async fn set_name(&self, home: &str, object_type: ObjectType, object: &str, name: &str) -> BackendResult<()> {
+        self.change(home, |home, events| {
+            let pair = name_uuid_pair(name, object);
+            let event = match object_type {
+                ObjectType::Home => {
+                    home.name = name.to_string();
+                    HomeEventEnum::HomeRenamed(pair)
+                },
+                ObjectType::Room => {
+                    Home::ensure_unique_name("room", home.rooms.iter().filter(|r| r.uuid != object).map(|r| r.name.as_str()), name)?;
+                    let index = home.room_index(object)?;
+                    home.rooms[index].name = name.to_string();
+                    HomeEventEnum::RoomRenamed(pair)
+                },
+                ObjectType::Zone => {
+                    Home::ensure_unique_name("zone", home.zones.iter().filter(|z| z.uuid != object).map(|z| z.name.as_str()), name)?;
+                    home.zone_mut(object)?.name = name.to_string();
+                    HomeEventEnum::ZoneRenamed(pair)
+                },
+                ObjectType::Accessory => {
+                    home.accessory_mut(object)?.name = name.to_string();
+                    HomeEventEnum::AccessoryRenamed(pair)
+                },
+                ObjectType::ServiceGroup => {
+                    Home::ensure_unique_name("service group", home.service_groups.iter().filter(|g| g.uuid != object).map(|g| g.name.as_str()), name)?;
+                    home.service_group_mut(object)?.name = name.to_string();
+                    HomeEventEnum::ServiceGroupRenamed(pair)
+                },
+                ObjectType::ActionSet => {
+                    Home::ensure_unique_name("action set", home.action_sets.iter().filter(|a| a.uuid != object).map(|a| a.name.as_str()), name)?;
+                    home.action_set_mut(object)?.name = name.to_string();
+                    HomeEventEnum::ActionSetRenamed(pair)
+                },
+                ObjectType::Trigger => {
+                    Home::ensure_unique_name("trigger", home.triggers.iter().filter(|t| t.uuid != object).map(|t| t.name.as_str()), name)?;
+                    home.trigger_mut(object)?.name = name.to_string();
+                    HomeEventEnum::TriggerRenamed(pair)
+                },
+                ObjectType::Unknown => return Err(BackendError::InvalidArgument("Unknown object type".to_string())),
+            };
+            events.push(event);
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hkservice::number::Value as NumberValue;
+    use crate::hkservice::value::Value as ValueEnum;
+
+    const CABIN: &str = r#"
+homes:
+  - name: Cabin
+    rooms:
+      - name: Kitchen
+    accessories:
+      - name: Lamp
+        room: Kitchen
+        services:
+          - name: Light
+            type: LIGHT_BULB
+            characteristics:
+              - type: POWER_STATE
+                properties: [READABLE, WRITABLE]
+                value: false
+              - type: BRIGHTNESS
+                properties: [READABLE, WRITABLE]
+                format: INT
+                min: 0
+                max: 100
+                value: 40
+"#;
+
+    /// The Cabin fixture with `extra` appended to its home.
+    fn load(extra: &str) -> BackendResult<SimulatedBackend> {
+        let fixture: FixtureFile = serde_yaml::from_str(&format!("{}{}", CABIN, extra)).unwrap();
+        SimulatedBackend::from_fixture(&fixture)
+    }
+
+    fn load_error(extra: &str) -> String {
+        load(extra).err().expect("the fixture should not load").to_string()
+    }
+
+    #[test]
+    fn loads_a_fixture() {
+        let backend = load("").unwrap();
+        let homes = backend.homes.read().unwrap();
+        assert_eq!(homes.len(), 1);
+        assert!(homes[0].is_primary);
+        let lamp = &homes[0].accessories[0];
+        assert_eq!(lamp.room, homes[0].rooms[0].uuid);
+        assert_eq!(lamp.services[0].characteristics[1].format(), Format::Int);
+    }
+
+    #[test]
+    fn rejects_unknown_fields() {
+        assert!(serde_yaml::from_str::<FixtureFile>("homes:\n  - name: Cabin\n    colour: red\n").is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_rooms() {
+        assert_eq!(load_error("    zones:\n      - name: Upstairs\n        rooms: [Attic]\n"),
+                   "Could not find room matching 'Attic'");
+    }
+
+    #[test]
+    fn rejects_unknown_characteristic_types() {
+        assert_eq!(load_error("    action_sets:\n      - name: On\n        actions:\n          - characteristic: Lamp/Light/COLOUR\n            value: 1\n"),
+                   "Invalid characteristic 'Lamp/Light/COLOUR': 'COLOUR' is not a characteristic type such as POWER_STATE");
+    }
+
+    #[test]
+    fn rejects_values_of_the_wrong_format() {
+        assert_eq!(load_error("    action_sets:\n      - name: On\n        actions:\n          - characteristic: Lamp/Light/BRIGHTNESS\n            value: bright\n"),
+                   "Value is not a valid Int number");
+    }
+
+    #[test]
+    fn rejects_several_actions_for_a_characteristic() {
+        let error = load_error(r#"
+    action_sets:
+      - name: Dim
+        actions:
+          - characteristic: Lamp/Light/BRIGHTNESS
+            value: 10
+          - characteristic: Lamp/Light/BRIGHTNESS
+            value: 20
+"#);
+        assert!(error.starts_with("Action set 'Dim' already has an action for characteristic"), "{}", error);
+    }
+
+    #[test]
+    fn rejects_triggers_that_are_neither_timers_nor_events() {
+        assert_eq!(load_error("    triggers:\n      - name: Nothing\n"),
+                   "Trigger 'Nothing' must specify exactly one of timer or event");
+    }
+
+    #[test]
+    fn rejects_recurrences_in_seconds() {
+        assert_eq!(load_error("    triggers:\n      - name: Often\n        timer:\n          fire_date: 1600000020\n          recurrence: 90\n"),
+                   "Recurrence of 90 seconds is not in whole minutes");
+    }
+
+    #[test]
+    fn expresses_recurrences_in_the_largest_unit() {
+        let recurrence = |seconds| recurrence_from_seconds(seconds).unwrap().map(|r| (r.unit(), r.interval));
+        assert_eq!(recurrence(0), None);
+        assert_eq!(recurrence(15 * 60), Some((Unit::Minute, 15)));
+        assert_eq!(recurrence(2 * 60 * 60), Some((Unit::Hour, 2)));
+        assert_eq!(recurrence(24 * 60 * 60), Some((Unit::Day, 1)));
+        assert_eq!(recurrence(14 * 24 * 60 * 60), Some((Unit::Week, 2)));
+    }
+
+    #[tokio::test]
+    async fn adds_one_action_per_characteristic() {
+        let backend = load("    action_sets:\n      - name: Dim\n").unwrap();
+        let (home, action_set, brightness) = {
+            let homes = backend.homes.read().unwrap();
+            let home = &homes[0];
+            (home.uuid.clone(), home.action_sets[0].uuid.clone(), home.characteristic_by_path("Lamp/Light/BRIGHTNESS").unwrap().uuid.clone())
+        };
+        let value = |n| Value { value: Some(ValueEnum::NumberValue(Number { value: Some(NumberValue::SignedIntegerValue(n)) })) };
+        backend.add_action(&home, &action_set, &brightness, &value(10)).await.unwrap();
+        match backend.add_action(&home, &action_set, &brightness, &value(20)).await {
+            Err(BackendError::InvalidArgument(_)) => (),
+            other => panic!("expected InvalidArgument, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use clap::{Arg, App, crate_version};
+use rcgen::{
+    BasicConstraints,
+    Certificate,
+    CertificateParams,
+    DnType,
+    ExtendedKeyUsagePurpose,
+    IsCa,
+    KeyUsagePurpose,
+    SanType,
+};
+use std::io::Write;
+use std::path::Path;
+
+/// Writes a new file, refusing to replace an existing one. Private keys are
+/// only readable by their owner.
+fn write(dir: &Path, name: &str, contents: &str, private: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let path = dir.join(name);
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(if private { 0o600 } else { 0o644 });
+    }
+    let mut file = options.open(&path).map_err(|e| format!("Could not create {}: {}", path.display(), e))?;
+    file.write_all(contents.as_bytes())?;
+    println!("Wrote {}", path.display());
+    Ok(())
+}
+
+fn issue(common_name: &str, names: Vec<SanType>, usage: ExtendedKeyUsagePurpose) -> Result<Certificate, Box<dyn std::error::Error>> {
+    let mut params = CertificateParams::default();
+    params.distinguished_name.push(DnType::CommonName, common_name);
+    params.subject_alt_names = names;
+    params.key_usages = vec![KeyUsagePurpose::DigitalSignature];
+    params.extended_key_usages = vec![usage];
+    params.use_authority_key_identifier_extension = true;
+    Ok(Certificate::from_params(params)?)
+}
+
+/// Generates a CA, a server certificate and client certificates for a home,
+/// for use with the server's --tls-cert, --tls-key and --client-ca options
+/// and hkctl's --ca-cert, --client-cert and --client-key.
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let matches = App::new("certs")
+        .version(crate_version!())
+        .about("Generates a self-signed CA, and server and client certificates signed by it")
+        .arg(Arg::with_name("dir")
+             .long("dir")
+             .value_name("DIR")
+             .help("Directory to write the certificates and keys to. Existing files are not replaced")
+             .required(true)
+             .takes_value(true))
+        .arg(Arg::with_name("server_name")
+             .long("server-name")
+             .value_name("NAME OR IP")
+             .help("Host name or address clients use to reach the server. Defaults to localhost, 127.0.0.1 and ::1")
+             .multiple(true)
+             .number_of_values(1)
+             .takes_value(true))
+        .arg(Arg::with_name("client")
+             .long("client")
+             .value_name("NAME")
+             .help("Name of a client to issue a certificate to. Defaults to hkctl")
+             .multiple(true)
+             .number_of_values(1)
+             .takes_value(true))
+        .get_matches();
+
+    let dir = Path::new(matches.value_of("dir").unwrap());
+    std::fs::create_dir_all(dir)?;
+
+    let mut params = CertificateParams::default();
+    params.distinguished_name.push(DnType::CommonName, "hkserver CA");
+    params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    params.key_usages = vec![KeyUsagePurpose::KeyCertSign, KeyUsagePurpose::CrlSign];
+    let ca = Certificate::from_params(params)?;
+
+    let server_names = matches.values_of("server_name").map_or(vec!["localhost", "127.0.0.1", "::1"], |v| v.collect());
+    let sans = server_names.iter()
+        .map(|name| name.parse().map_or_else(|_| SanType::DnsName(name.to_string()), SanType::IpAddress))
+        .collect();
+    let server = issue(server_names[0], sans, ExtendedKeyUsagePurpose::ServerAuth)?;
+    let clients = matches.values_of("client").map_or(vec!["hkctl"], |v| v.collect())
+        .into_iter()
+        .map(|name| Ok((name, issue(name, vec![], ExtendedKeyUsagePurpose::ClientAuth)?)))
+        .collect::<Result<Vec<_>, Box<dyn std::error::Error>>>()?;
+
+    // The CA key is not written: certificates for other clients are issued by
+    // generating a new set
+    let mut files = vec![
+        ("ca.pem".to_string(), ca.serialize_pem()?, false),
+        ("server.pem".to_string(), server.serialize_pem_with_signer(&ca)?, false),
+        ("server-key.pem".to_string(), server.serialize_private_key_pem(), true),
+    ];
+    for (name, client) in clients {
+        files.push((format!("{}.pem", name), client.serialize_pem_with_signer(&ca)?, false));
+        files.push((format!("{}-key.pem", name), client.serialize_private_key_pem(), true));
+    }
+    if let Some((name, _, _)) = files.iter().find(|(name, _, _)| dir.join(name).exists()) {
+        return Err(format!("{} already exists", dir.join(name).display()).into());
+    }
+    for (name, contents, private) in files {
+        write(dir, &name, &contents, private)?;
+    }
+    Ok(())
+}
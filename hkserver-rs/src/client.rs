@@ -10,8 +10,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .connect()
         .await?;
     let mut client = HomeKitServiceClient::new(channel);
-    let response = client.enumerate_devices(
-        hkservice::EnumerateDevicesRequest {
+    let response = client.enumerate_homes(
+        hkservice::EnumerateHomesRequest {
             name_filter: String::from("")
         }).await?.into_inner();
     println!("RESPONSE={:?}", response);
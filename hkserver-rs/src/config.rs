@@ -0,0 +1,163 @@
+use clap::ArgMatches;
+use serde::Deserialize;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+
+/// Where the server listens when neither addresses nor a unix socket are
+/// configured.
+pub const DEFAULT_LISTEN: &str = "127.0.0.1:55123";
+
+/// The server's configuration, read from a TOML file and overridden by
+/// command line options. Relative paths in the file are relative to the
+/// file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub listen: Vec<SocketAddr>,
+    pub unix_socket: Option<UnixSocketConfig>,
+    pub tls: Option<TlsConfig>,
+    pub auth: AuthConfig,
+    pub log: LogConfig,
+    pub backend: BackendConfig,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UnixSocketConfig {
+    pub path: PathBuf,
+    /// Permissions of the socket, written in octal such as 0o660.
+    #[serde(default = "default_socket_mode")]
+    pub mode: u32,
+}
+
+/// Only the owner can connect to the socket unless told otherwise.
+fn default_socket_mode() -> u32 {
+    0o600
+}
+
+/// TLS applies to the TCP listeners. The unix socket is protected by its
+/// permissions instead.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TlsConfig {
+    pub cert: PathBuf,
+    pub key: PathBuf,
+    pub client_ca: Option<PathBuf>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthConfig {
+    /// Token file managed by the tokens command. Without one, every client
+    /// may do anything.
+    pub tokens: Option<PathBuf>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    /// A level such as info or debug, or per module levels in the syntax of
+    /// RUST_LOG.
+    pub level: String,
+    /// Log to this file instead of standard error.
+    pub file: Option<PathBuf>,
+}
+
+impl Default for LogConfig {
+    fn default() -> LogConfig {
+        LogConfig {
+            level: "info".to_string(),
+            file: None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BackendConfig {
+    HomeKit,
+    /// A simulated home loaded from a YAML or JSON fixture.
+    Simulated { fixture: PathBuf },
+}
+
+impl Default for BackendConfig {
+    fn default() -> BackendConfig {
+        BackendConfig::HomeKit
+    }
+}
+
+fn parse_mode(mode: &str) -> Result<u32, String> {
+    u32::from_str_radix(mode.trim_start_matches("0o"), 8)
+        .map_err(|_| format!("'{}' is not an octal file mode", mode))
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Config, Box<dyn std::error::Error>> {
+        let contents = std::fs::read_to_string(path).map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+        let mut config: Config = toml::from_str(&contents).map_err(|e| format!("Could not parse {}: {}", path.display(), e))?;
+        if let Some(dir) = path.parent() {
+            config.resolve_paths(dir);
+        }
+        Ok(config)
+    }
+
+    fn resolve_paths(&mut self, dir: &Path) {
+        let mut paths = vec![];
+        if let Some(ref mut socket) = self.unix_socket {
+            paths.push(&mut socket.path);
+        }
+        if let Some(ref mut tls) = self.tls {
+            paths.push(&mut tls.cert);
+            paths.push(&mut tls.key);
+            paths.extend(tls.client_ca.as_mut());
+        }
+        paths.extend(self.auth.tokens.as_mut());
+        paths.extend(self.log.file.as_mut());
+        if let BackendConfig::Simulated { ref mut fixture } = self.backend {
+            paths.push(fixture);
+        }
+        for path in paths {
+            *path = dir.join(&path);
+        }
+    }
+
+    /// Replaces settings given on the command line. TLS options replace the
+    /// whole tls section.
+    pub fn override_with(&mut self, matches: &ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(addrs) = matches.values_of("listen") {
+            self.listen = addrs
+                .map(|addr| addr.parse().map_err(|_| format!("'{}' is not an address and port, such as 127.0.0.1:55123 or [::1]:55123", addr)))
+                .collect::<Result<Vec<SocketAddr>, String>>()?;
+        }
+        if let Some(path) = matches.value_of("unix_socket") {
+            self.unix_socket = Some(UnixSocketConfig { path: PathBuf::from(path), mode: default_socket_mode() });
+        }
+        if let Some(mode) = matches.value_of("unix_socket_mode") {
+            let socket = self.unix_socket.as_mut().ok_or("--unix-socket-mode needs a unix socket, given with --unix-socket or in the config file")?;
+            socket.mode = parse_mode(mode)?;
+        }
+        if let Some(cert) = matches.value_of("tls_cert") {
+            self.tls = Some(TlsConfig {
+                cert: PathBuf::from(cert),
+                key: PathBuf::from(matches.value_of("tls_key").unwrap()),
+                client_ca: matches.value_of("client_ca").map(PathBuf::from),
+            });
+        }
+        if let Some(tokens) = matches.value_of("tokens") {
+            self.auth.tokens = Some(PathBuf::from(tokens));
+        }
+        if let Some(level) = matches.value_of("log_level") {
+            self.log.level = level.to_string();
+        }
+        if let Some(file) = matches.value_of("log_file") {
+            self.log.file = Some(PathBuf::from(file));
+        }
+        if let Some(fixture) = matches.value_of("fixture") {
+            self.backend = BackendConfig::Simulated { fixture: PathBuf::from(fixture) };
+        }
+        if self.listen.is_empty() && self.unix_socket.is_none() {
+            self.listen = vec![DEFAULT_LISTEN.parse().unwrap()];
+        }
+        Ok(())
+    }
+}
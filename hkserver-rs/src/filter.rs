@@ -0,0 +1,136 @@
+use regex::{Regex, RegexBuilder};
+use crate::hkservice::{
+    AccessoryInformation,
+    ActionSetInformation,
+    CommonTriggerInformation,
+    HomeInformation,
+    NameUuidPair,
+    RoomInformation,
+    ServiceGroupInformation,
+    ServiceInformation,
+    TriggerInformation,
+    ZoneInformation,
+};
+use crate::hkservice::trigger_information::Trigger;
+
+/// Objects that can be selected by name or UUID, mirroring
+/// `NameOrUuidFilterable` in the Swift server.
+pub trait NameOrUuidFilterable {
+    fn filterable_name(&self) -> &str;
+    fn filterable_uuid(&self) -> &str;
+
+    fn matches(&self, filter: &Option<Regex>) -> bool {
+        match filter {
+            Some(filter) => filter.is_match(self.filterable_name()) || filter.is_match(self.filterable_uuid()),
+            None => true,
+        }
+    }
+
+    fn matches_exactly(&self, name_or_uuid: &str) -> bool {
+        self.filterable_uuid() == name_or_uuid || self.filterable_name() == name_or_uuid
+    }
+
+    fn name_uuid_pair(&self) -> NameUuidPair {
+        NameUuidPair {
+            name: self.filterable_name().to_string(),
+            uuid: self.filterable_uuid().to_string(),
+        }
+    }
+}
+
+/// Compiles a case insensitive filter. Empty and invalid patterns yield
+/// `None`, which matches everything.
+pub fn filter_expression(pattern: &str) -> Option<Regex> {
+    if pattern.len() == 0 {
+        return None;
+    }
+    RegexBuilder::new(pattern)
+        .case_insensitive(true)
+        .build()
+        .ok()
+}
+
+macro_rules! impl_filterable {
+    ($($t:ty),*) => {
+        $(
+            impl NameOrUuidFilterable for $t {
+                fn filterable_name(&self) -> &str {
+                    &self.name
+                }
+
+                fn filterable_uuid(&self) -> &str {
+                    &self.uuid
+                }
+            }
+        )*
+    }
+}
+
+impl_filterable!(
+    AccessoryInformation,
+    ActionSetInformation,
+    CommonTriggerInformation,
+    HomeInformation,
+    NameUuidPair,
+    RoomInformation,
+    ServiceGroupInformation,
+    ServiceInformation,
+    ZoneInformation
+);
+
+/// The fields shared by every kind of trigger.
+pub fn common_trigger_information(trigger: &TriggerInformation) -> Option<&CommonTriggerInformation> {
+    match trigger.trigger.as_ref()? {
+        Trigger::Event(event) => event.trigger.as_ref(),
+        Trigger::Timer(timer) => timer.trigger.as_ref(),
+    }
+}
+
+impl NameOrUuidFilterable for TriggerInformation {
+    fn filterable_name(&self) -> &str {
+        common_trigger_information(self).map(|c| c.name.as_str()).unwrap_or("")
+    }
+
+    fn filterable_uuid(&self) -> &str {
+        common_trigger_information(self).map(|c| c.uuid.as_str()).unwrap_or("")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn room(name: &str, uuid: &str) -> RoomInformation {
+        RoomInformation { name: name.to_string(), uuid: uuid.to_string(), ..Default::default() }
+    }
+
+    #[test]
+    fn empty_patterns_match_everything() {
+        assert!(filter_expression("").is_none());
+        assert!(room("Kitchen", "R1").matches(&filter_expression("")));
+    }
+
+    #[test]
+    fn invalid_patterns_match_everything() {
+        assert!(filter_expression("[").is_none());
+        assert!(room("Kitchen", "R1").matches(&filter_expression("[")));
+    }
+
+    #[test]
+    fn patterns_match_names_and_uuids_ignoring_case() {
+        let kitchen = room("Kitchen", "AF350FDD-1292");
+        assert!(kitchen.matches(&filter_expression("itch")));
+        assert!(kitchen.matches(&filter_expression("^kitchen$")));
+        assert!(kitchen.matches(&filter_expression("af350fdd")));
+        assert!(!kitchen.matches(&filter_expression("Hallway")));
+    }
+
+    #[test]
+    fn exact_matches_compare_whole_names_and_uuids() {
+        let kitchen = room("Kitchen", "R1");
+        assert!(kitchen.matches_exactly("Kitchen"));
+        assert!(kitchen.matches_exactly("R1"));
+        assert!(!kitchen.matches_exactly("kitchen"));
+        assert!(!kitchen.matches_exactly("Kitch"));
+    }
+}
@@ -0,0 +1,76 @@
+use tokio::sync::{mpsc, watch};
+use tonic::{Request, Response, Status};
+
+pub mod proto {
+    tonic::include_proto!("grpc.health.v1");
+}
+
+use proto::{HealthCheckRequest, HealthCheckResponse};
+use proto::health_check_response::ServingStatus;
+use proto::health_server::Health;
+pub use proto::health_server::HealthServer;
+
+/// Services whose health is reported. The empty name stands for the server
+/// as a whole.
+const SERVICES: &[&str] = &["", "org.hkserver.HomeKitService"];
+
+/// The standard gRPC health service. Everything is served once the backend
+/// is ready, and nothing before.
+pub struct HealthService {
+    readiness: watch::Receiver<bool>,
+}
+
+impl HealthService {
+    pub fn new(readiness: watch::Receiver<bool>) -> HealthService {
+        HealthService { readiness }
+    }
+}
+
+fn response(status: ServingStatus) -> HealthCheckResponse {
+    HealthCheckResponse { status: status as i32 }
+}
+
+fn serving_status(ready: bool) -> ServingStatus {
+    if ready { ServingStatus::Serving } else { ServingStatus::NotServing }
+}
+
+#[tonic::async_trait]
+impl Health for HealthService {
+    async fn check(&self, request: Request<HealthCheckRequest>) -> Result<Response<HealthCheckResponse>, Status> {
+        let service = request.into_inner().service;
+        if !SERVICES.contains(&service.as_str()) {
+            return Err(Status::not_found(format!("Unknown service '{}'", service)));
+        }
+        Ok(Response::new(response(serving_status(*self.readiness.borrow()))))
+    }
+
+    type WatchStream = mpsc::Receiver<Result<HealthCheckResponse, Status>>;
+
+    async fn watch(&self, request: Request<HealthCheckRequest>) -> Result<Response<Self::WatchStream>, Status> {
+        let service = request.into_inner().service;
+        let (mut tx, rx) = mpsc::channel(4);
+        if !SERVICES.contains(&service.as_str()) {
+            // Services are fixed, so there is nothing more to watch for
+            tx.send(Ok(response(ServingStatus::ServiceUnknown))).await.ok();
+            return Ok(Response::new(rx));
+        }
+        let mut readiness = self.readiness.clone();
+        tokio::spawn(async move {
+            let mut sent = None;
+            loop {
+                let status = serving_status(*readiness.borrow());
+                if sent != Some(status) {
+                    // The client went away
+                    if tx.send(Ok(response(status))).await.is_err() {
+                        return;
+                    }
+                    sent = Some(status);
+                }
+                if readiness.recv().await.is_none() {
+                    return;
+                }
+            }
+        });
+        Ok(Response::new(rx))
+    }
+}
@@ -0,0 +1,174 @@
+use clap::{Arg, App, crate_version, crate_description};
+use futures::future::{BoxFuture, FutureExt, TryFutureExt, try_join_all};
+use futures::stream::TryStreamExt;
+use std::path::Path;
+use tonic::transport::{Certificate, Identity, Server, ServerTlsConfig};
+
+mod auth;
+pub mod backend;
+mod config;
+mod filter;
+mod health;
+pub mod hkservice;
+mod reflection;
+mod service;
+mod token_file;
+mod unix_socket;
+mod validate;
+
+use backend::HomeBackend;
+use config::{BackendConfig, Config, LogConfig, TlsConfig};
+
+fn read(path: &Path) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    std::fs::read(path).map_err(|e| format!("Could not read {}: {}", path.display(), e).into())
+}
+
+/// With a client CA, clients must present a certificate signed by it.
+fn tls_config(tls: &TlsConfig) -> Result<ServerTlsConfig, Box<dyn std::error::Error>> {
+    let mut config = ServerTlsConfig::new().identity(Identity::from_pem(read(&tls.cert)?, read(&tls.key)?));
+    if let Some(ref client_ca) = tls.client_ca {
+        config = config.client_ca_root(Certificate::from_pem(read(client_ca)?));
+    }
+    Ok(config)
+}
+
+fn init_logging(log: &LogConfig) -> Result<(), Box<dyn std::error::Error>> {
+    let mut builder = env_logger::Builder::new();
+    builder.parse_filters(&log.level);
+    if let Some(ref file) = log.file {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(file)
+            .map_err(|e| format!("Could not open {}: {}", file.display(), e))?;
+        builder.target(env_logger::Target::Pipe(Box::new(file)));
+    }
+    builder.try_init()?;
+    Ok(())
+}
+
+/// Serves the backend on every configured address and the unix socket, until
+/// one of them fails. Health checks and reflection do not need API tokens.
+async fn serve<B: HomeBackend>(backend: B, config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let health = health::HealthServer::new(health::HealthService::new(backend.readiness()));
+    let reflection = reflection::ServerReflectionServer::new(reflection::ReflectionService::new()?);
+    let service = service::HomeKitServiceServer::with_interceptor(
+        service::HKServer::new(backend), auth::interceptor(config.auth.tokens.clone()));
+    let tls = config.tls.as_ref().map(tls_config).transpose()?;
+    let mut servers: Vec<BoxFuture<Result<(), tonic::transport::Error>>> = vec![];
+    for addr in config.listen.iter() {
+        let mut builder = Server::builder();
+        if let Some(ref tls) = tls {
+            builder = builder.tls_config(tls.clone())?;
+        }
+        log::info!("Listening on {}{}", addr, if tls.is_some() { " with TLS" } else { "" });
+        let router = builder.add_service(service.clone()).add_service(health.clone()).add_service(reflection.clone());
+        servers.push(router.serve(*addr).boxed());
+    }
+    if let Some(ref socket) = config.unix_socket {
+        let incoming = unix_socket::bind(socket)?.map_ok(unix_socket::UnixStream);
+        log::info!("Listening on {} with mode {:o}", socket.path.display(), socket.mode);
+        let router = Server::builder().add_service(service.clone()).add_service(health.clone()).add_service(reflection.clone());
+        servers.push(router.serve_with_incoming(incoming).boxed());
+    }
+    try_join_all(servers).map_ok(|_| ()).await?;
+    Ok(())
+}
+
+/// Runs the server with the command line options. `home_kit` creates the
+/// HomeKit backend, and is only called when no fixture is given.
+pub async fn run<B, F>(home_kit: F) -> Result<(), Box<dyn std::error::Error>>
+where B: HomeBackend,
+      F: FnOnce() -> Result<B, Box<dyn std::error::Error>>,
+{
+    let matches = App::new("HKServer")
+        .version(crate_version!())
+        .about(crate_description!())
+        .arg(Arg::with_name("config")
+             .long("config")
+             .value_name("FILE")
+             .help("Read settings from a TOML file. Command line options override it")
+             .takes_value(true))
+        .arg(Arg::with_name("listen")
+             .long("listen")
+             .value_name("ADDRESS:PORT")
+             .help("Listen on an address, such as 0.0.0.0:55123 or [::]:55123. Defaults to 127.0.0.1:55123 unless a unix socket is given")
+             .multiple(true)
+             .number_of_values(1)
+             .takes_value(true))
+        .arg(Arg::with_name("unix_socket")
+             .long("unix-socket")
+             .value_name("PATH")
+             .help("Listen on a unix socket")
+             .takes_value(true))
+        .arg(Arg::with_name("unix_socket_mode")
+             .long("unix-socket-mode")
+             .value_name("MODE")
+             .help("Octal permissions of the unix socket. Defaults to 600")
+             .takes_value(true))
+        .arg(Arg::with_name("fixture")
+             .long("fixture")
+             .value_name("FILE")
+             .help("Serve a simulated home loaded from a YAML or JSON fixture instead of HomeKit")
+             .takes_value(true))
+        .arg(Arg::with_name("tls_cert")
+             .long("tls-cert")
+             .value_name("FILE")
+             .help("Serve TLS with this PEM certificate chain")
+             .requires("tls_key")
+             .takes_value(true))
+        .arg(Arg::with_name("tls_key")
+             .long("tls-key")
+             .value_name("FILE")
+             .help("PEM private key of the certificate given with --tls-cert")
+             .requires("tls_cert")
+             .takes_value(true))
+        .arg(Arg::with_name("client_ca")
+             .long("client-ca")
+             .value_name("FILE")
+             .help("Require clients to present a certificate signed by this PEM CA certificate")
+             .requires("tls_cert")
+             .takes_value(true))
+        .arg(Arg::with_name("tokens")
+             .long("tokens")
+             .value_name("FILE")
+             .help("Require clients to present an API token from this file, as managed by the tokens command")
+             .takes_value(true))
+        .arg(Arg::with_name("log_level")
+             .long("log-level")
+             .value_name("LEVEL")
+             .help("Log at this level, such as warn, info or debug. Defaults to info")
+             .takes_value(true))
+        .arg(Arg::with_name("log_file")
+             .long("log-file")
+             .value_name("FILE")
+             .help("Append the log to a file instead of standard error")
+             .takes_value(true))
+        .get_matches();
+
+    let mut config = match matches.value_of("config") {
+        Some(path) => Config::load(Path::new(path))?,
+        None => Config::default(),
+    };
+    config.override_with(&matches)?;
+    init_logging(&config.log)?;
+
+    if let Some(ref tokens) = config.auth.tokens {
+        // Fail early rather than on the first request
+        let count = token_file::TokenFile::load(tokens)?.tokens.len();
+        log::info!("Accepting {} API tokens from {}", count, tokens.display());
+    }
+
+    match config.backend {
+        BackendConfig::Simulated { ref fixture } => {
+            let backend = backend::simulated::SimulatedBackend::from_file(fixture)?;
+            log::info!("Serving the simulated home in {}", fixture.display());
+            serve(backend, &config).await
+        },
+        BackendConfig::HomeKit => {
+            let backend = home_kit()?;
+            log::info!("Serving HomeKit");
+            serve(backend, &config).await
+        },
+    }
+}
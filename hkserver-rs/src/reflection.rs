@@ -0,0 +1,184 @@
+use prost::Message;
+use prost_types::{DescriptorProto, FileDescriptorProto, FileDescriptorSet};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tonic::{Code, Request, Response, Status, Streaming};
+
+pub mod proto {
+    tonic::include_proto!("grpc.reflection.v1alpha");
+}
+
+use proto::{
+    ErrorResponse,
+    ExtensionNumberResponse,
+    FileDescriptorResponse,
+    ListServiceResponse,
+    ServerReflectionRequest,
+    ServerReflectionResponse,
+    ServiceResponse,
+};
+use proto::server_reflection_request::MessageRequest;
+use proto::server_reflection_response::MessageResponse;
+use proto::server_reflection_server::ServerReflection;
+pub use proto::server_reflection_server::ServerReflectionServer;
+
+/// Descriptors of the served protos and their imports, written by protoc in
+/// build.rs.
+const FILE_DESCRIPTOR_SET: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/descriptors.bin"));
+
+/// The files of the descriptor set, indexed by name and by the fully
+/// qualified names of the services, methods, messages and enums they
+/// declare.
+struct Descriptors {
+    files: HashMap<String, FileDescriptorProto>,
+    symbols: HashMap<String, String>,
+    services: Vec<String>,
+}
+
+fn qualify(scope: &str, name: &str) -> String {
+    if scope.is_empty() { name.to_string() } else { format!("{}.{}", scope, name) }
+}
+
+fn index_message(symbols: &mut HashMap<String, String>, file: &str, scope: &str, message: &DescriptorProto) {
+    let name = qualify(scope, message.name());
+    for nested in message.nested_type.iter() {
+        index_message(symbols, file, &name, nested);
+    }
+    for enumeration in message.enum_type.iter() {
+        symbols.insert(qualify(&name, enumeration.name()), file.to_string());
+    }
+    symbols.insert(name, file.to_string());
+}
+
+impl Descriptors {
+    fn load() -> Result<Descriptors, prost::DecodeError> {
+        let set = FileDescriptorSet::decode(FILE_DESCRIPTOR_SET)?;
+        let mut symbols = HashMap::new();
+        let mut services = vec![];
+        for file in set.file.iter() {
+            let package = file.package();
+            for message in file.message_type.iter() {
+                index_message(&mut symbols, file.name(), package, message);
+            }
+            for enumeration in file.enum_type.iter() {
+                symbols.insert(qualify(package, enumeration.name()), file.name().to_string());
+            }
+            for service in file.service.iter() {
+                let name = qualify(package, service.name());
+                for method in service.method.iter() {
+                    symbols.insert(qualify(&name, method.name()), file.name().to_string());
+                }
+                symbols.insert(name.clone(), file.name().to_string());
+                services.push(name);
+            }
+        }
+        let files = set.file.into_iter().map(|file| (file.name().to_string(), file)).collect();
+        Ok(Descriptors { files, symbols, services })
+    }
+
+    /// Serializes a file followed by everything it imports, as clients need
+    /// the whole tree to make sense of it.
+    fn file_with_imports(&self, name: &str) -> Option<FileDescriptorResponse> {
+        let mut names = vec![name.to_string()];
+        let mut index = 0;
+        while index < names.len() {
+            let file = self.files.get(&names[index])?;
+            for dependency in file.dependency.iter() {
+                if !names.contains(dependency) {
+                    names.push(dependency.clone());
+                }
+            }
+            index += 1;
+        }
+        let file_descriptor_proto = names.iter()
+            .map(|name| {
+                let mut bytes = vec![];
+                self.files[name].encode(&mut bytes).map(|_| bytes)
+            })
+            .collect::<Result<Vec<Vec<u8>>, prost::EncodeError>>()
+            .ok()?;
+        Some(FileDescriptorResponse { file_descriptor_proto })
+    }
+
+    fn respond(&self, request: &MessageRequest) -> MessageResponse {
+        let not_found = |message: String| MessageResponse::ErrorResponse(ErrorResponse {
+            error_code: Code::NotFound as i32,
+            error_message: message,
+        });
+        match request {
+            MessageRequest::FileByFilename(name) => self.file_with_imports(name)
+                .map(MessageResponse::FileDescriptorResponse)
+                .unwrap_or_else(|| not_found(format!("Unknown file {}", name))),
+            MessageRequest::FileContainingSymbol(symbol) => self.symbols.get(symbol)
+                .and_then(|name| self.file_with_imports(name))
+                .map(MessageResponse::FileDescriptorResponse)
+                .unwrap_or_else(|| not_found(format!("Unknown symbol {}", symbol))),
+            // The served protos declare no extensions
+            MessageRequest::FileContainingExtension(extension) =>
+                not_found(format!("No extension {} of {}", extension.extension_number, extension.containing_type)),
+            MessageRequest::AllExtensionNumbersOfType(name) => match self.symbols.get(name) {
+                Some(_) => MessageResponse::AllExtensionNumbersResponse(ExtensionNumberResponse {
+                    base_type_name: name.clone(),
+                    extension_number: vec![],
+                }),
+                None => not_found(format!("Unknown type {}", name)),
+            },
+            MessageRequest::ListServices(_) => MessageResponse::ListServicesResponse(ListServiceResponse {
+                service: self.services.iter().map(|name| ServiceResponse { name: name.clone() }).collect(),
+            }),
+        }
+    }
+}
+
+/// gRPC server reflection, which lets tools such as grpcurl discover the
+/// served services and messages.
+pub struct ReflectionService {
+    descriptors: Arc<Descriptors>,
+}
+
+impl ReflectionService {
+    pub fn new() -> Result<ReflectionService, prost::DecodeError> {
+        Ok(ReflectionService { descriptors: Arc::new(Descriptors::load()?) })
+    }
+}
+
+#[tonic::async_trait]
+impl ServerReflection for ReflectionService {
+    type ServerReflectionInfoStream = mpsc::Receiver<Result<ServerReflectionResponse, Status>>;
+
+    async fn server_reflection_info(&self, request: Request<Streaming<ServerReflectionRequest>>) -> Result<Response<Self::ServerReflectionInfoStream>, Status> {
+        let mut requests = request.into_inner();
+        let descriptors = self.descriptors.clone();
+        let (mut tx, rx) = mpsc::channel(16);
+        tokio::spawn(async move {
+            loop {
+                let request = match requests.message().await {
+                    Ok(Some(request)) => request,
+                    Ok(None) => return,
+                    Err(status) => {
+                        tx.send(Err(status)).await.ok();
+                        return;
+                    },
+                };
+                let message_response = match request.message_request {
+                    Some(ref message_request) => descriptors.respond(message_request),
+                    None => MessageResponse::ErrorResponse(ErrorResponse {
+                        error_code: Code::InvalidArgument as i32,
+                        error_message: "Missing request".to_string(),
+                    }),
+                };
+                let response = ServerReflectionResponse {
+                    valid_host: request.host.clone(),
+                    original_request: Some(request),
+                    message_response: Some(message_response),
+                };
+                // The client went away
+                if tx.send(Ok(response)).await.is_err() {
+                    return;
+                }
+            }
+        });
+        Ok(Response::new(rx))
+    }
+}
@@ -1,21 +1,11 @@
-use clap::{Arg, App, crate_version, crate_description};
-use tonic::transport::Server;
+use hkrpc::backend::simulated::SimulatedBackend;
 use tokio;
 
-mod hkservice;
-
+/// Serves simulated homes only. The HomeKit server is built from
+/// hkserver-home-kit, which needs the Mac Catalyst toolchain.
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let matches = App::new("HKServer")
-        .version(crate_version!())
-        .about(crate_description!())
-        .get_matches();
-
-    let addr: std::net::SocketAddr = "127.0.0.1:55123".parse().unwrap();
-    let service = hkservice::HKServer::new();
-    Server::builder()
-        .add_service(hkservice::HomeKitServiceServer::new(service))
-        .serve(addr)
-        .await?;
-    Ok(())
+    hkrpc::run(|| -> Result<SimulatedBackend, Box<dyn std::error::Error>> {
+        Err("This server was built without HomeKit. Build hkserver-home-kit for Mac Catalyst, or pass --fixture".into())
+    }).await
 }
@@ -0,0 +1,868 @@
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::{broadcast, mpsc};
+use tonic::{Request, Response, Status};
+use crate::auth::require;
+use crate::backend::{BackendError, HomeBackend};
+use crate::filter::{common_trigger_information, filter_expression, NameOrUuidFilterable};
+use crate::hkservice::*;
+use crate::hkservice::characteristic_information::{CharacteristicType, Property};
+use crate::hkservice::action_definition::ActionDefinition as ActionDefinitionEnum;
+use crate::hkservice::action_set_information::action::Action as ActionEnum;
+use crate::hkservice::trigger_definition::TriggerDefinition as TriggerDefinitionEnum;
+use crate::hkservice::change_action_set_membership_request::name_or_action_definition::Action as NameOrActionDefinition;
+use crate::hkservice::enumerate_triggers_request::EnabledFilter;
+use crate::hkservice::home_kit_service_server::HomeKitService;
+use crate::hkservice::set_name_request::ObjectType;
+use crate::token_file::Scope;
+use crate::validate::{validate_event_trigger, validate_timer, validate_write};
+pub use crate::hkservice::home_kit_service_server::HomeKitServiceServer;
+
+pub struct HKServer<B: HomeBackend> {
+    backend: Arc<B>,
+}
+
+impl<B: HomeBackend> HKServer<B> {
+    pub fn new(backend: B) -> HKServer<B> {
+        HKServer {
+            backend: Arc::new(backend),
+        }
+    }
+
+    // ============== Helpers ============
+
+    /// Finds the first home matching `pattern`. An empty pattern selects the
+    /// primary home, and an invalid pattern matches nothing.
+    async fn find_home(&self, pattern: &str) -> Result<HomeInformation, Status> {
+        let homes = self.backend.homes().await?;
+        let home = if pattern.len() == 0 {
+            homes.into_iter().find(|home| home.is_primary)
+        } else {
+            let filter = filter_expression(pattern);
+            homes.into_iter().find(|home| filter.is_some() && home.matches(&filter))
+        };
+        home.ok_or_else(|| BackendError::not_found("home", pattern).into())
+    }
+
+    async fn find_characteristic(&self, home: &str, uuid: &str) -> Result<CharacteristicInformation, Status> {
+        self.backend.services(home).await?
+            .into_iter()
+            .flat_map(|service| service.characteristics)
+            .find(|characteristic| characteristic.uuid == uuid)
+            .ok_or_else(|| BackendError::not_found("characteristic", uuid).into())
+    }
+
+    /// Resolves an action definition to the characteristic it writes and the
+    /// value it writes, checking that the value may be written.
+    async fn resolve_action(&self, home: &str, definition: &ActionDefinition) -> Result<(String, Value), Status> {
+        match definition.action_definition {
+            Some(ActionDefinitionEnum::CharacteristicAction(ref action)) => {
+                let characteristic = self.find_characteristic(home, &action.characteristic).await?;
+                let target_value = action.target_value.as_ref()
+                    .ok_or_else(|| Status::invalid_argument("Missing target value"))?;
+                validate_write(&characteristic, target_value)?;
+                Ok((characteristic.uuid, target_value.clone()))
+            },
+            None => Err(Status::invalid_argument("Missing action definition")),
+        }
+    }
+
+    /// Checks the events of an event trigger against the characteristics of
+    /// the home.
+    async fn check_event_trigger(&self, home: &str, definition: &EventTriggerDefinition) -> Result<(), Status> {
+        let characteristics = self.backend.services(home).await?
+            .into_iter()
+            .flat_map(|service| service.characteristics)
+            .collect::<Vec<CharacteristicInformation>>();
+        Ok(validate_event_trigger(definition, &characteristics)?)
+    }
+
+    /// Resolves a characteristic from an accessory and service name or UUID,
+    /// and either a characteristic UUID or description or a characteristic
+    /// type. Empty accessory and service names match any accessory or service,
+    /// but the result must be unambiguous.
+    async fn find_characteristic_by_address(&self, home: &str, accessory: &str, service: &str, characteristic: &str, characteristic_type: i32)
+                                            -> Result<(NameUuidPair, NameUuidPair, CharacteristicInformation), Status> {
+        let characteristic_type = CharacteristicType::from_i32(characteristic_type).unwrap_or(CharacteristicType::InvalidCharacteristicType);
+        if characteristic.len() == 0 && characteristic_type == CharacteristicType::InvalidCharacteristicType {
+            return Err(Status::invalid_argument("A characteristic or characteristic type is required"));
+        }
+        let mut matches = vec![];
+        for a in self.backend.accessories(home).await? {
+            if accessory.len() != 0 && !a.matches_exactly(accessory) {
+                continue;
+            }
+            let accessory_pair = a.name_uuid_pair();
+            for s in a.services {
+                if service.len() != 0 && !s.matches_exactly(service) {
+                    continue;
+                }
+                let service_pair = s.name_uuid_pair();
+                for c in s.characteristics {
+                    let found = if characteristic.len() != 0 {
+                        c.uuid == characteristic || c.description == characteristic
+                    } else {
+                        c.characteristic_type == characteristic_type as i32
+                    };
+                    if found {
+                        matches.push((accessory_pair.clone(), service_pair.clone(), c));
+                    }
+                }
+            }
+        }
+
+        let address = [accessory, service]
+            .iter()
+            .filter(|part| part.len() != 0)
+            .map(|part| part.to_string())
+            .chain(std::iter::once(if characteristic.len() != 0 {
+                characteristic.to_string()
+            } else {
+                format!("{:?}", characteristic_type)
+            }))
+            .collect::<Vec<String>>()
+            .join("/");
+        match matches.len() {
+            0 => Err(BackendError::not_found("characteristic", &address).into()),
+            1 => Ok(matches.pop().unwrap()),
+            n => Err(Status::invalid_argument(format!("'{}' matches {} characteristics. Specify an accessory or service", address, n))),
+        }
+    }
+
+    /// Collects the characteristics a subscription should report on. Only
+    /// characteristics that support events are included.
+    async fn subscribed_characteristics(&self, home: &str, request: &SubscribeCharacteristicsRequest)
+                                        -> Result<Vec<(NameUuidPair, NameUuidPair, CharacteristicInformation)>, Status> {
+        let room_filter = filter_expression(&request.room_filter);
+        let accessory_filter = filter_expression(&request.accessory_filter);
+        let service_filter = filter_expression(&request.service_filter);
+        let mut characteristics = vec![];
+        for accessory in self.backend.accessories(home).await? {
+            let in_room = match accessory.room {
+                Some(ref room) => room.matches(&room_filter),
+                None => request.room_filter.len() == 0,
+            };
+            if !in_room || !accessory.matches(&accessory_filter) {
+                continue;
+            }
+            let accessory_pair = accessory.name_uuid_pair();
+            for service in accessory.services {
+                if !service.matches(&service_filter) {
+                    continue;
+                }
+                if request.service_types.len() != 0 && !request.service_types.contains(&service.service_type) {
+                    continue;
+                }
+                let service_pair = service.name_uuid_pair();
+                characteristics.extend(service.characteristics
+                    .into_iter()
+                    .filter(|c| c.properties.contains(&(Property::SupportsEvent as i32)))
+                    .filter(|c| request.characteristic_types.len() == 0 || request.characteristic_types.contains(&c.characteristic_type))
+                    .map(|c| (accessory_pair.clone(), service_pair.clone(), c)));
+            }
+        }
+        Ok(characteristics)
+    }
+
+    /// Runs an action set and describes the outcome of each of its actions.
+    async fn run_action_set(&self, home: &str, action_set: &ActionSetInformation) -> Result<Vec<ActionResult>, Status> {
+        let outcomes = self.backend.execute_action_set(home, &action_set.uuid).await?;
+        let services = self.backend.services(home).await?;
+        Ok(outcomes.into_iter().map(|outcome| {
+            let mut result = ActionResult {
+                uuid: outcome.action.clone(),
+                success: outcome.result.is_ok(),
+                error: outcome.result.as_ref().err().map_or(String::new(), |e| e.to_string()),
+                ..Default::default()
+            };
+            let action = action_set.actions.iter()
+                .filter_map(|a| match a.action {
+                    Some(ActionEnum::CharacteristicAction(ref ca)) if ca.uuid == outcome.action => Some(ca),
+                    _ => None,
+                })
+                .next();
+            if let Some(action) = action {
+                result.target_value = action.target_value.clone();
+                if let Some(ref characteristic) = action.characteristic {
+                    result.characteristic = characteristic.uuid.clone();
+                    result.characteristic_type = characteristic.characteristic_type;
+                    if let Some(service) = services.iter().find(|s| s.characteristics.iter().any(|c| c.uuid == characteristic.uuid)) {
+                        result.accessory = service.accessory.clone();
+                        result.service = Some(service.name_uuid_pair());
+                    }
+                }
+            }
+            result
+        }).collect())
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+fn operation(operation: i32) -> Result<Operation, Status> {
+    Operation::from_i32(operation).ok_or_else(|| Status::invalid_argument("Invalid value for operation"))
+}
+
+fn find_exactly<T: NameOrUuidFilterable>(objects: Vec<T>, object_type: &str, name_or_uuid: &str) -> Result<T, Status> {
+    objects.into_iter()
+        .find(|o| o.matches_exactly(name_or_uuid))
+        .ok_or_else(|| BackendError::not_found(object_type, name_or_uuid).into())
+}
+
+fn find_first_match<T: NameOrUuidFilterable>(objects: Vec<T>, object_type: &str, pattern: &str) -> Result<T, Status> {
+    let filter = filter_expression(pattern);
+    objects.into_iter()
+        .find(|o| o.matches(&filter))
+        .ok_or_else(|| BackendError::not_found(object_type, pattern).into())
+}
+
+#[tonic::async_trait]
+impl<B: HomeBackend> HomeKitService for HKServer<B> {
+    async fn enumerate_homes(&self, request: Request<EnumerateHomesRequest>) -> Result<Response<EnumerateHomesResponse>, Status> {
+        require(&request, Scope::Read)?;
+        let request = request.into_inner();
+        let filter = filter_expression(&request.name_filter);
+        let homes = self.backend.homes().await?
+            .into_iter()
+            .filter(|home| home.matches(&filter))
+            .collect();
+        Ok(Response::new(EnumerateHomesResponse {
+            homes: homes,
+        }))
+    }
+
+    async fn enumerate_rooms(&self, request: Request<EnumerateRoomsRequest>) -> Result<Response<EnumerateRoomsResponse>, Status> {
+        require(&request, Scope::Read)?;
+        let request = request.into_inner();
+        let home = self.find_home(&request.home).await?;
+        let filter = filter_expression(&request.name_filter);
+        let mut rooms = vec![self.backend.room_for_entire_home(&home.uuid).await?];
+        rooms.extend(self.backend.rooms(&home.uuid).await?
+                     .into_iter()
+                     .filter(|room| room.matches(&filter)));
+        Ok(Response::new(EnumerateRoomsResponse {
+            home: Some(home.name_uuid_pair()),
+            rooms: rooms,
+        }))
+    }
+
+    async fn enumerate_zones(&self, request: Request<EnumerateZonesRequest>) -> Result<Response<EnumerateZonesResponse>, Status> {
+        require(&request, Scope::Read)?;
+        let request = request.into_inner();
+        let home = self.find_home(&request.home).await?;
+        let filter = filter_expression(&request.name_filter);
+        let room_filter = filter_expression(&request.room_filter);
+        let zones = self.backend.zones(&home.uuid).await?
+            .into_iter()
+            .filter(|zone| zone.matches(&filter))
+            .filter(|zone| room_filter.is_none() || zone.rooms.iter().any(|room| room.matches(&room_filter)))
+            .collect();
+        Ok(Response::new(EnumerateZonesResponse {
+            home: Some(home.name_uuid_pair()),
+            zones: zones,
+        }))
+    }
+
+    async fn enumerate_accessories(&self, request: Request<EnumerateAccessoriesRequest>) -> Result<Response<EnumerateAccessoriesResponse>, Status> {
+        require(&request, Scope::Read)?;
+        let request = request.into_inner();
+        let home = self.find_home(&request.home).await?;
+        let filter = filter_expression(&request.name_filter);
+        let room_filter = filter_expression(&request.room_filter);
+        let rooms = if request.zone_filter.len() == 0 {
+            None
+        } else {
+            let zone_filter = filter_expression(&request.zone_filter);
+            Some(self.backend.zones(&home.uuid).await?
+                 .into_iter()
+                 .filter(|zone| zone.matches(&zone_filter))
+                 .flat_map(|zone| zone.rooms)
+                 .map(|room| room.uuid)
+                 .collect::<Vec<String>>())
+        };
+        let accessories = self.backend.accessories(&home.uuid).await?
+            .into_iter()
+            .filter(|accessory| accessory.matches(&filter))
+            .filter(|accessory| match (&rooms, &accessory.room) {
+                (None, _) => true,
+                // Non-empty zone filter, so unassigned accessories do not match
+                (Some(_), None) => false,
+                (Some(rooms), Some(room)) => rooms.contains(&room.uuid),
+            })
+            .filter(|accessory| match &accessory.room {
+                Some(room) => room.matches(&room_filter),
+                // Matches when there is no room filter
+                None => request.room_filter.len() == 0,
+            })
+            .collect();
+        Ok(Response::new(EnumerateAccessoriesResponse {
+            home: Some(home.name_uuid_pair()),
+            accessories: accessories,
+        }))
+    }
+
+    async fn enumerate_service_groups(&self, request: Request<EnumerateServiceGroupsRequest>) -> Result<Response<EnumerateServiceGroupsResponse>, Status> {
+        require(&request, Scope::Read)?;
+        let request = request.into_inner();
+        let home = self.find_home(&request.home).await?;
+        let filter = filter_expression(&request.name_filter);
+        let service_groups = self.backend.service_groups(&home.uuid).await?
+            .into_iter()
+            .filter(|service_group| service_group.matches(&filter))
+            .collect();
+        Ok(Response::new(EnumerateServiceGroupsResponse {
+            home: Some(home.name_uuid_pair()),
+            service_groups: service_groups,
+        }))
+    }
+
+    async fn enumerate_services(&self, request: Request<EnumerateServicesRequest>) -> Result<Response<EnumerateServicesResponse>, Status> {
+        require(&request, Scope::Read)?;
+        let request = request.into_inner();
+        let home = self.find_home(&request.home).await?;
+        let filter = filter_expression(&request.name_filter);
+        let services = self.backend.services(&home.uuid).await?
+            .into_iter()
+            .filter(|service| request.types.len() == 0 || request.types.contains(&service.service_type))
+            .filter(|service| service.matches(&filter))
+            .collect();
+        Ok(Response::new(EnumerateServicesResponse {
+            home: Some(home.name_uuid_pair()),
+            services: services,
+        }))
+    }
+
+    async fn enumerate_action_sets(&self, request: Request<EnumerateActionSetsRequest>) -> Result<Response<EnumerateActionSetsResponse>, Status> {
+        require(&request, Scope::Read)?;
+        let request = request.into_inner();
+        let home = self.find_home(&request.home).await?;
+        let filter = filter_expression(&request.name_filter);
+        let action_sets = self.backend.action_sets(&home.uuid).await?
+            .into_iter()
+            .filter(|action_set| action_set.matches(&filter))
+            .collect();
+        Ok(Response::new(EnumerateActionSetsResponse {
+            home: Some(home.name_uuid_pair()),
+            action_sets: action_sets,
+        }))
+    }
+
+    async fn enumerate_triggers(&self, request: Request<EnumerateTriggersRequest>) -> Result<Response<EnumerateTriggersResponse>, Status> {
+        require(&request, Scope::Read)?;
+        let request = request.into_inner();
+        let home = self.find_home(&request.home).await?;
+        let filter = filter_expression(&request.name_filter);
+        let enabled_filter = EnabledFilter::from_i32(request.enabled_filter).unwrap_or(EnabledFilter::NoFilter);
+        let triggers = self.backend.triggers(&home.uuid).await?
+            .into_iter()
+            .filter(|trigger| trigger.matches(&filter))
+            .filter(|trigger| {
+                let common = match common_trigger_information(trigger) {
+                    Some(common) => common,
+                    None => return false,
+                };
+                if enabled_filter != EnabledFilter::NoFilter && common.is_enabled != (enabled_filter == EnabledFilter::EnabledOnly) {
+                    return false;
+                }
+                // A trigger that never fired has not fired before or after any date
+                if request.before != 0 && (common.last_fire_date == 0 || common.last_fire_date > request.before) {
+                    return false;
+                }
+                if request.after != 0 && (common.last_fire_date == 0 || common.last_fire_date < request.after) {
+                    return false;
+                }
+                true
+            })
+            .collect();
+        Ok(Response::new(EnumerateTriggersResponse {
+            home: Some(home.name_uuid_pair()),
+            triggers: triggers,
+        }))
+    }
+
+    async fn add_remove_room(&self, request: Request<AddRemoveRoomRequest>) -> Result<Response<AddRemoveRoomResponse>, Status> {
+        require(&request, Scope::Admin)?;
+        let request = request.into_inner();
+        let home = self.find_home(&request.home).await?;
+        let room = match operation(request.operation)? {
+            Operation::Add => {
+                let room = self.backend.add_room(&home.uuid, &request.name).await?;
+                let accessories = self.backend.accessories(&home.uuid).await?;
+                for name_or_uuid in request.accessories.iter() {
+                    for accessory in accessories.iter().filter(|a| a.matches_exactly(name_or_uuid)) {
+                        self.backend.assign_accessory(&home.uuid, &accessory.uuid, &room.uuid).await?;
+                    }
+                }
+                room
+            },
+            Operation::Remove => {
+                let room = find_first_match(self.backend.rooms(&home.uuid).await?, "room", &request.name)?;
+                self.backend.remove_room(&home.uuid, &room.uuid).await?;
+                room.name_uuid_pair()
+            },
+        };
+        Ok(Response::new(AddRemoveRoomResponse {
+            home: Some(home.name_uuid_pair()),
+            room: Some(room),
+        }))
+    }
+
+    async fn add_remove_zone(&self, request: Request<AddRemoveZoneRequest>) -> Result<Response<AddRemoveZoneResponse>, Status> {
+        require(&request, Scope::Admin)?;
+        let request = request.into_inner();
+        let home = self.find_home(&request.home).await?;
+        let zone = match operation(request.operation)? {
+            Operation::Add => {
+                let zone = self.backend.add_zone(&home.uuid, &request.name).await?;
+                let rooms = self.backend.rooms(&home.uuid).await?;
+                for name_or_uuid in request.rooms.iter() {
+                    for room in rooms.iter().filter(|r| r.matches_exactly(name_or_uuid)) {
+                        self.backend.add_room_to_zone(&home.uuid, &zone.uuid, &room.uuid).await?;
+                    }
+                }
+                zone
+            },
+            Operation::Remove => {
+                let zone = find_first_match(self.backend.zones(&home.uuid).await?, "zone", &request.name)?;
+                self.backend.remove_zone(&home.uuid, &zone.uuid).await?;
+                zone.name_uuid_pair()
+            },
+        };
+        Ok(Response::new(AddRemoveZoneResponse {
+            home: Some(home.name_uuid_pair()),
+            zone: Some(zone),
+        }))
+    }
+
+    async fn add_remove_service_group(&self, request: Request<AddRemoveServiceGroupRequest>) -> Result<Response<AddRemoveServiceGroupResponse>, Status> {
+        require(&request, Scope::Admin)?;
+        let request = request.into_inner();
+        let home = self.find_home(&request.home).await?;
+        let service_group = match operation(request.operation)? {
+            Operation::Add => {
+                let service_group = self.backend.add_service_group(&home.uuid, &request.name).await?;
+                let services = self.backend.services(&home.uuid).await?;
+                for name_or_uuid in request.services.iter() {
+                    for service in services.iter().filter(|s| s.matches_exactly(name_or_uuid)) {
+                        self.backend.add_service_to_group(&home.uuid, &service_group.uuid, &service.uuid).await?;
+                    }
+                }
+                service_group
+            },
+            Operation::Remove => {
+                let service_group = find_first_match(self.backend.service_groups(&home.uuid).await?, "service group", &request.name)?;
+                self.backend.remove_service_group(&home.uuid, &service_group.uuid).await?;
+                service_group.name_uuid_pair()
+            },
+        };
+        Ok(Response::new(AddRemoveServiceGroupResponse {
+            home: Some(home.name_uuid_pair()),
+            service_group: Some(service_group),
+        }))
+    }
+
+    async fn change_room_zone_membership(&self, request: Request<ChangeRoomZoneMembershipRequest>) -> Result<Response<ChangeRoomZoneMembershipResponse>, Status> {
+        require(&request, Scope::Admin)?;
+        let request = request.into_inner();
+        let home = self.find_home(&request.home).await?;
+        let room = find_exactly(self.backend.rooms(&home.uuid).await?, "room", &request.name)?;
+        let zone = find_exactly(self.backend.zones(&home.uuid).await?, "zone", &request.zone)?;
+        match operation(request.operation)? {
+            Operation::Add => self.backend.add_room_to_zone(&home.uuid, &zone.uuid, &room.uuid).await?,
+            Operation::Remove => self.backend.remove_room_from_zone(&home.uuid, &zone.uuid, &room.uuid).await?,
+        };
+        Ok(Response::new(ChangeRoomZoneMembershipResponse {
+            home: Some(home.name_uuid_pair()),
+            room: Some(room.name_uuid_pair()),
+            zone: Some(zone.name_uuid_pair()),
+        }))
+    }
+
+    async fn move_accessory_to_room(&self, request: Request<MoveAccessoryToRoomRequest>) -> Result<Response<MoveAccessoryToRoomResponse>, Status> {
+        require(&request, Scope::Admin)?;
+        let request = request.into_inner();
+        let home = self.find_home(&request.home).await?;
+        let accessory = find_exactly(self.backend.accessories(&home.uuid).await?, "accessory", &request.name)?;
+        let mut rooms = self.backend.rooms(&home.uuid).await?;
+        rooms.push(self.backend.room_for_entire_home(&home.uuid).await?);
+        let room = find_exactly(rooms, "room", &request.room)?;
+        self.backend.assign_accessory(&home.uuid, &accessory.uuid, &room.uuid).await?;
+        Ok(Response::new(MoveAccessoryToRoomResponse {
+            home: Some(home.name_uuid_pair()),
+            accessory: Some(accessory.name_uuid_pair()),
+            room: Some(room.name_uuid_pair()),
+        }))
+    }
+
+    async fn change_service_group_membership(&self, request: Request<ChangeServiceGroupMembershipRequest>) -> Result<Response<ChangeServiceGroupMembershipResponse>, Status> {
+        require(&request, Scope::Admin)?;
+        let request = request.into_inner();
+        let home = self.find_home(&request.home).await?;
+        let service_group = find_exactly(self.backend.service_groups(&home.uuid).await?, "service group", &request.name)?;
+        let filter = filter_expression(&request.service_filter);
+        let services = match operation(request.operation)? {
+            Operation::Add => {
+                let services = self.backend.services(&home.uuid).await?
+                    .into_iter()
+                    .filter(|service| service.matches(&filter))
+                    .map(|service| service.name_uuid_pair())
+                    .collect::<Vec<NameUuidPair>>();
+                for service in services.iter() {
+                    self.backend.add_service_to_group(&home.uuid, &service_group.uuid, &service.uuid).await?;
+                }
+                services
+            },
+            Operation::Remove => {
+                let services = service_group.services.iter()
+                    .filter(|service| service.matches(&filter))
+                    .cloned()
+                    .collect::<Vec<NameUuidPair>>();
+                for service in services.iter() {
+                    self.backend.remove_service_from_group(&home.uuid, &service_group.uuid, &service.uuid).await?;
+                }
+                services
+            },
+        };
+        Ok(Response::new(ChangeServiceGroupMembershipResponse {
+            home: Some(home.name_uuid_pair()),
+            service_group: Some(service_group.name_uuid_pair()),
+            services: services,
+        }))
+    }
+
+    async fn add_remove_actions(&self, request: Request<AddRemoveActionSetRequest>) -> Result<Response<AddRemoveActionSetResponse>, Status> {
+        require(&request, Scope::Admin)?;
+        let request = request.into_inner();
+        let home = self.find_home(&request.home).await?;
+        let action_set = match operation(request.operation)? {
+            Operation::Add => {
+                // Check every action before creating anything
+                let mut actions = vec![];
+                for definition in request.action_definition.iter() {
+                    let (characteristic, target_value) = self.resolve_action(&home.uuid, definition).await?;
+                    if actions.iter().any(|(c, _)| *c == characteristic) {
+                        return Err(Status::invalid_argument(format!("There are several actions for characteristic {}", characteristic)));
+                    }
+                    actions.push((characteristic, target_value));
+                }
+                let action_set = self.backend.add_action_set(&home.uuid, &request.name).await?;
+                for (characteristic, target_value) in actions.iter() {
+                    self.backend.add_action(&home.uuid, &action_set.uuid, characteristic, target_value).await?;
+                }
+                action_set
+            },
+            Operation::Remove => {
+                let action_set = find_first_match(self.backend.action_sets(&home.uuid).await?, "action set", &request.name)?;
+                self.backend.remove_action_set(&home.uuid, &action_set.uuid).await?;
+                action_set.name_uuid_pair()
+            },
+        };
+        Ok(Response::new(AddRemoveActionSetResponse {
+            home: Some(home.name_uuid_pair()),
+            action_set: Some(action_set),
+        }))
+    }
+
+    async fn add_remove_triggers(&self, request: Request<AddRemoveTriggersRequest>) -> Result<Response<AddRemoveTriggersResponse>, Status> {
+        require(&request, Scope::Admin)?;
+        let request = request.into_inner();
+        let home = self.find_home(&request.home).await?;
+        let trigger = match operation(request.operation)? {
+            Operation::Add => {
+                let definition = request.definition
+                    .ok_or_else(|| Status::invalid_argument("Adding a trigger requires a trigger definition"))?;
+                match definition.trigger_definition {
+                    Some(TriggerDefinitionEnum::Timer(ref timer)) => validate_timer(timer)?,
+                    Some(TriggerDefinitionEnum::Event(ref event)) => self.check_event_trigger(&home.uuid, event).await?,
+                    None => return Err(Status::invalid_argument("Missing trigger definition")),
+                };
+                // Check the action sets before creating anything
+                let all_action_sets = self.backend.action_sets(&home.uuid).await?;
+                let action_sets = request.action_sets.iter()
+                    .map(|name_or_uuid| all_action_sets.iter()
+                         .find(|a| a.matches_exactly(name_or_uuid))
+                         .ok_or_else(|| BackendError::not_found("action set", name_or_uuid)))
+                    .collect::<Result<Vec<&ActionSetInformation>, BackendError>>()?;
+                let trigger = self.backend.add_trigger(&home.uuid, &request.name, &definition).await?;
+                for action_set in action_sets {
+                    self.backend.add_action_set_to_trigger(&home.uuid, &trigger.uuid, &action_set.uuid).await?;
+                }
+                trigger
+            },
+            Operation::Remove => {
+                let trigger = find_first_match(self.backend.triggers(&home.uuid).await?, "trigger", &request.name)?;
+                self.backend.remove_trigger(&home.uuid, trigger.filterable_uuid()).await?;
+                trigger.name_uuid_pair()
+            },
+        };
+        Ok(Response::new(AddRemoveTriggersResponse {
+            home: Some(home.name_uuid_pair()),
+            trigger: Some(trigger),
+        }))
+    }
+
+    async fn enable_disable_trigger(&self, request: Request<EnableDisableTriggerRequest>) -> Result<Response<EnableDisableTriggerResponse>, Status> {
+        require(&request, Scope::Admin)?;
+        let request = request.into_inner();
+        let home = self.find_home(&request.home).await?;
+        let trigger = find_exactly(self.backend.triggers(&home.uuid).await?, "trigger", &request.name)?;
+        self.backend.enable_trigger(&home.uuid, trigger.filterable_uuid(), request.enable).await?;
+        Ok(Response::new(EnableDisableTriggerResponse {
+            home: Some(home.name_uuid_pair()),
+            trigger: Some(trigger.name_uuid_pair()),
+        }))
+    }
+
+    async fn change_action_set_membership(&self, request: Request<ChangeActionSetMembershipRequest>) -> Result<Response<ChangeActionSetMembershipResponse>, Status> {
+        require(&request, Scope::Admin)?;
+        let request = request.into_inner();
+        let home = self.find_home(&request.home).await?;
+        let action_set = find_exactly(self.backend.action_sets(&home.uuid).await?, "action set", &request.name)?;
+        let action = request.actions.and_then(|actions| actions.action)
+            .ok_or_else(|| Status::invalid_argument("Missing action"))?;
+        match (operation(request.operation)?, action) {
+            (Operation::Add, NameOrActionDefinition::Definition(definition)) => {
+                let (characteristic, target_value) = self.resolve_action(&home.uuid, &definition).await?;
+                self.backend.add_action(&home.uuid, &action_set.uuid, &characteristic, &target_value).await?;
+            },
+            (Operation::Remove, NameOrActionDefinition::Uuid(uuid)) => {
+                self.backend.remove_action(&home.uuid, &action_set.uuid, &uuid).await?
+            },
+            (Operation::Add, _) => return Err(Status::invalid_argument("Adding an action requires an action definition")),
+            (Operation::Remove, _) => return Err(Status::invalid_argument("Removing an action requires its uuid")),
+        };
+        Ok(Response::new(ChangeActionSetMembershipResponse {
+            home: Some(home.name_uuid_pair()),
+            action_set: Some(action_set.name_uuid_pair()),
+        }))
+    }
+
+    async fn change_trigger_membership(&self, request: Request<ChangeTriggerMembershipRequest>) -> Result<Response<ChangeTriggerMembershipResponse>, Status> {
+        require(&request, Scope::Admin)?;
+        let request = request.into_inner();
+        let home = self.find_home(&request.home).await?;
+        let trigger = find_exactly(self.backend.triggers(&home.uuid).await?, "trigger", &request.name)?;
+        let operation = operation(request.operation)?;
+        let action_sets = self.backend.action_sets(&home.uuid).await?;
+        for name_or_uuid in request.action_sets.iter() {
+            let action_set = action_sets.iter()
+                .find(|a| a.matches_exactly(name_or_uuid))
+                .ok_or_else(|| BackendError::not_found("action set", name_or_uuid))?;
+            match operation {
+                Operation::Add => self.backend.add_action_set_to_trigger(&home.uuid, trigger.filterable_uuid(), &action_set.uuid).await?,
+                Operation::Remove => self.backend.remove_action_set_from_trigger(&home.uuid, trigger.filterable_uuid(), &action_set.uuid).await?,
+            };
+        }
+        Ok(Response::new(ChangeTriggerMembershipResponse {
+            home: Some(home.name_uuid_pair()),
+            trigger: Some(trigger.name_uuid_pair()),
+        }))
+    }
+
+    async fn update_event_trigger(&self, request: Request<UpdateEventTriggerRequest>) -> Result<Response<UpdateEventTriggerResponse>, Status> {
+        require(&request, Scope::Admin)?;
+        let request = request.into_inner();
+        let home = self.find_home(&request.home).await?;
+        let trigger = find_exactly(self.backend.triggers(&home.uuid).await?, "trigger", &request.name)?;
+        let definition = request.definition
+            .ok_or_else(|| Status::invalid_argument("Missing trigger definition"))?;
+        self.check_event_trigger(&home.uuid, &definition).await?;
+        self.backend.update_event_trigger(&home.uuid, trigger.filterable_uuid(), &definition).await?;
+        Ok(Response::new(UpdateEventTriggerResponse {
+            home: Some(home.name_uuid_pair()),
+            trigger: Some(trigger.name_uuid_pair()),
+        }))
+    }
+
+    async fn run_action_set(&self, request: Request<RunActionSetRequest>) -> Result<Response<RunActionSetResponse>, Status> {
+        require(&request, Scope::Control)?;
+        let request = request.into_inner();
+        let home = self.find_home(&request.home).await?;
+        let action_set = find_exactly(self.backend.action_sets(&home.uuid).await?, "action set", &request.name)?;
+        let results = self.run_action_set(&home.uuid, &action_set).await?;
+        Ok(Response::new(RunActionSetResponse {
+            home: Some(home.name_uuid_pair()),
+            action_set: Some(action_set.name_uuid_pair()),
+            results: results,
+        }))
+    }
+
+    async fn run_trigger(&self, request: Request<RunTriggerRequest>) -> Result<Response<RunTriggerResponse>, Status> {
+        require(&request, Scope::Control)?;
+        let request = request.into_inner();
+        let home = self.find_home(&request.home).await?;
+        let trigger = find_exactly(self.backend.triggers(&home.uuid).await?, "trigger", &request.name)?;
+        // Running a trigger executes its action sets, as if it had fired
+        let mut action_set_results = vec![];
+        if let Some(common) = common_trigger_information(&trigger) {
+            let action_sets = self.backend.action_sets(&home.uuid).await?;
+            for pair in common.action_sets.iter() {
+                let action_set = find_exactly(action_sets.clone(), "action set", &pair.uuid)?;
+                action_set_results.push(ActionSetResult {
+                    action_set: Some(action_set.name_uuid_pair()),
+                    results: self.run_action_set(&home.uuid, &action_set).await?,
+                });
+            }
+        }
+        Ok(Response::new(RunTriggerResponse {
+            home: Some(home.name_uuid_pair()),
+            trigger: Some(trigger.name_uuid_pair()),
+            action_sets: action_set_results,
+        }))
+    }
+
+    async fn read_characteristic(&self, request: Request<ReadCharacteristicRequest>) -> Result<Response<ReadCharacteristicResponse>, Status> {
+        require(&request, Scope::Read)?;
+        let request = request.into_inner();
+        let home = self.find_home(&request.home).await?;
+        let (accessory, service, mut characteristic) = self.find_characteristic_by_address(
+            &home.uuid, &request.accessory, &request.service, &request.characteristic, request.characteristic_type).await?;
+        if !characteristic.properties.contains(&(Property::Readable as i32)) {
+            return Err(Status::invalid_argument(format!("Characteristic {} is not readable", characteristic.uuid)));
+        }
+        characteristic.value = Some(self.backend.read_characteristic(&home.uuid, &characteristic.uuid).await?);
+        Ok(Response::new(ReadCharacteristicResponse {
+            home: Some(home.name_uuid_pair()),
+            accessory: Some(accessory),
+            service: Some(service),
+            characteristic: Some(characteristic),
+        }))
+    }
+
+    async fn write_characteristic(&self, request: Request<WriteCharacteristicRequest>) -> Result<Response<WriteCharacteristicResponse>, Status> {
+        require(&request, Scope::Control)?;
+        let request = request.into_inner();
+        let home = self.find_home(&request.home).await?;
+        let (accessory, service, mut characteristic) = self.find_characteristic_by_address(
+            &home.uuid, &request.accessory, &request.service, &request.characteristic, request.characteristic_type).await?;
+        let value = request.value.ok_or_else(|| Status::invalid_argument("Missing value"))?;
+        validate_write(&characteristic, &value)?;
+        self.backend.write_characteristic(&home.uuid, &characteristic.uuid, &value).await?;
+        characteristic.value = Some(value);
+        Ok(Response::new(WriteCharacteristicResponse {
+            home: Some(home.name_uuid_pair()),
+            accessory: Some(accessory),
+            service: Some(service),
+            characteristic: Some(characteristic),
+        }))
+    }
+
+    type SubscribeCharacteristicsStream = mpsc::Receiver<Result<CharacteristicChangeEvent, Status>>;
+
+    async fn subscribe_characteristics(&self, request: Request<SubscribeCharacteristicsRequest>) -> Result<Response<Self::SubscribeCharacteristicsStream>, Status> {
+        require(&request, Scope::Read)?;
+        let request = request.into_inner();
+        let home = self.find_home(&request.home).await?;
+        // Subscribe before taking the snapshot so no change is missed in between
+        let mut changes = self.backend.subscribe_characteristics();
+        let mut characteristics = self.subscribed_characteristics(&home.uuid, &request).await?;
+        let (mut tx, rx) = mpsc::channel(16);
+        tokio::spawn(async move {
+            let home_pair = home.name_uuid_pair();
+            let event = |(accessory, service, characteristic): &(NameUuidPair, NameUuidPair, CharacteristicInformation), is_snapshot: bool| {
+                Ok(CharacteristicChangeEvent {
+                    home: Some(home_pair.clone()),
+                    accessory: Some(accessory.clone()),
+                    service: Some(service.clone()),
+                    characteristic: Some(characteristic.clone()),
+                    timestamp: now(),
+                    is_snapshot: is_snapshot,
+                })
+            };
+            if request.initial_snapshot {
+                for subscribed in characteristics.iter() {
+                    if tx.send(event(subscribed, true)).await.is_err() {
+                        return;
+                    }
+                }
+            }
+            loop {
+                let change = match changes.recv().await {
+                    Ok(change) => change,
+                    Err(broadcast::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::RecvError::Closed) => return,
+                };
+                if change.home != home.uuid {
+                    continue;
+                }
+                if let Some(subscribed) = characteristics.iter_mut().find(|(_, _, c)| c.uuid == change.characteristic) {
+                    subscribed.2.value = Some(change.value);
+                    // The client went away
+                    if tx.send(event(subscribed, false)).await.is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+        Ok(Response::new(rx))
+    }
+
+    type WatchHomeStream = mpsc::Receiver<Result<HomeEvent, Status>>;
+
+    async fn watch_home(&self, request: Request<WatchHomeRequest>) -> Result<Response<Self::WatchHomeStream>, Status> {
+        require(&request, Scope::Read)?;
+        let request = request.into_inner();
+        let home = self.find_home(&request.home).await?;
+        let mut changes = self.backend.watch_homes();
+        let (mut tx, rx) = mpsc::channel(16);
+        tokio::spawn(async move {
+            loop {
+                let change = match changes.recv().await {
+                    Ok(change) => change,
+                    Err(broadcast::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::RecvError::Closed) => return,
+                };
+                if change.home.uuid != home.uuid {
+                    continue;
+                }
+                let event = HomeEvent {
+                    home: Some(change.home),
+                    timestamp: now(),
+                    event: Some(change.event),
+                };
+                // The client went away
+                if tx.send(Ok(event)).await.is_err() {
+                    return;
+                }
+            }
+        });
+        Ok(Response::new(rx))
+    }
+
+    async fn set_name(&self, request: Request<SetNameRequest>) -> Result<Response<SetNameResponse>, Status> {
+        require(&request, Scope::Admin)?;
+        let request = request.into_inner();
+        let home = self.find_home(&request.home).await?;
+        let object_type = ObjectType::from_i32(request.object_type).unwrap_or(ObjectType::Unknown);
+        if request.new_name.trim().is_empty() {
+            return Err(Status::invalid_argument("The new name cannot be empty"));
+        }
+        let uuid = &home.uuid;
+        let object = match object_type {
+            ObjectType::Home => {
+                if request.name.len() != 0 && !home.matches_exactly(&request.name) {
+                    return Err(BackendError::not_found("home", &request.name).into());
+                }
+                home.name_uuid_pair()
+            },
+            ObjectType::Room => find_exactly(self.backend.rooms(uuid).await?, "room", &request.name)?.name_uuid_pair(),
+            ObjectType::Zone => find_exactly(self.backend.zones(uuid).await?, "zone", &request.name)?.name_uuid_pair(),
+            ObjectType::Accessory => find_exactly(self.backend.accessories(uuid).await?, "accessory", &request.name)?.name_uuid_pair(),
+            ObjectType::ServiceGroup => find_exactly(self.backend.service_groups(uuid).await?, "service group", &request.name)?.name_uuid_pair(),
+            ObjectType::ActionSet => find_exactly(self.backend.action_sets(uuid).await?, "action set", &request.name)?.name_uuid_pair(),
+            ObjectType::Trigger => find_exactly(self.backend.triggers(uuid).await?, "trigger", &request.name)?.name_uuid_pair(),
+            ObjectType::Unknown => return Err(Status::invalid_argument("Invalid value for object type")),
+        };
+        self.backend.set_name(uuid, object_type, &object.uuid, &request.new_name).await?;
+        let home = self.find_home(&home.uuid).await?;
+        Ok(Response::new(SetNameResponse {
+            home: Some(home.name_uuid_pair()),
+            object: Some(NameUuidPair {
+                name: request.new_name,
+                uuid: object.uuid,
+            }),
+        }))
+    }
+}
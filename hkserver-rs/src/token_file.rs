@@ -0,0 +1,64 @@
+use ring::digest::{digest, SHA256};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// What an API token may do. Each scope includes the ones before it: read
+/// enumerates the home and reads characteristics, control also runs action
+/// sets and triggers and writes characteristics, and admin also changes the
+/// configuration of the home.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    Read,
+    Control,
+    Admin,
+}
+
+impl std::fmt::Display for Scope {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(match self {
+            Scope::Read => "read",
+            Scope::Control => "control",
+            Scope::Admin => "admin",
+        })
+    }
+}
+
+impl std::str::FromStr for Scope {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Scope, String> {
+        match s {
+            "read" => Ok(Scope::Read),
+            "control" => Ok(Scope::Control),
+            "admin" => Ok(Scope::Admin),
+            _ => Err(format!("'{}' is not a scope. Use read, control or admin", s)),
+        }
+    }
+}
+
+/// A token is only stored as its SHA-256 hash, so the file does not give
+/// access to the server.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Token {
+    pub name: String,
+    pub scope: Scope,
+    pub sha256: String,
+}
+
+/// The tokens the server accepts, kept in a YAML file.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct TokenFile {
+    pub tokens: Vec<Token>,
+}
+
+pub fn hash(token: &str) -> String {
+    hex::encode(digest(&SHA256, token.as_bytes()))
+}
+
+impl TokenFile {
+    pub fn load(path: &Path) -> Result<TokenFile, Box<dyn std::error::Error>> {
+        let file = std::fs::File::open(path).map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+        Ok(serde_yaml::from_reader(file).map_err(|e| format!("Could not parse {}: {}", path.display(), e))?)
+    }
+}
@@ -0,0 +1,102 @@
+use clap::{Arg, App, AppSettings, SubCommand, crate_version};
+use ring::rand::{SecureRandom, SystemRandom};
+use std::io::Write;
+use std::path::Path;
+
+mod token_file;
+
+use token_file::{hash, Scope, Token, TokenFile};
+
+/// Replaces the token file through a temporary file, so the server never
+/// reads a partial one. The file is only readable by its owner.
+fn save(path: &Path, tokens: &TokenFile) -> Result<(), Box<dyn std::error::Error>> {
+    let temporary = path.with_extension("tmp");
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(&temporary).map_err(|e| format!("Could not create {}: {}", temporary.display(), e))?;
+    file.write_all(serde_yaml::to_string(tokens)?.as_bytes())?;
+    std::fs::rename(&temporary, path)?;
+    Ok(())
+}
+
+fn generate() -> Result<String, Box<dyn std::error::Error>> {
+    let mut bytes = [0u8; 32];
+    SystemRandom::new().fill(&mut bytes).map_err(|_| "Could not generate a token")?;
+    Ok(hex::encode(bytes))
+}
+
+/// Manages the API tokens given to the server with --tokens. Tokens are
+/// printed once when they are added, and only their hashes are kept.
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let matches = App::new("tokens")
+        .version(crate_version!())
+        .about("Manages the API tokens the server accepts")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .arg(Arg::with_name("file")
+             .long("file")
+             .value_name("FILE")
+             .help("Token file to manage. The server reloads it when it changes")
+             .required(true)
+             .takes_value(true))
+        .subcommand(SubCommand::with_name("add")
+                    .about("Adds a token and prints it")
+                    .arg(Arg::with_name("name")
+                         .help("Name of the token, such as the client it is given to")
+                         .required(true)
+                         .index(1))
+                    .arg(Arg::with_name("scope")
+                         .long("scope")
+                         .value_name("SCOPE")
+                         .help("What the token may do: read the home, also control accessories and scenes, or also administer the home")
+                         .possible_values(&["read", "control", "admin"])
+                         .default_value("read")
+                         .takes_value(true)))
+        .subcommand(SubCommand::with_name("remove")
+                    .about("Revokes a token")
+                    .arg(Arg::with_name("name")
+                         .help("Name of the token")
+                         .required(true)
+                         .index(1)))
+        .subcommand(SubCommand::with_name("list")
+                    .about("Lists tokens and their scopes"))
+        .get_matches();
+
+    let path = Path::new(matches.value_of("file").unwrap());
+    let mut tokens = if path.exists() { TokenFile::load(path)? } else { TokenFile::default() };
+
+    match matches.subcommand() {
+        ("add", Some(add)) => {
+            let name = add.value_of("name").unwrap();
+            if tokens.tokens.iter().any(|t| t.name == name) {
+                return Err(format!("A token named {} already exists", name).into());
+            }
+            let scope: Scope = add.value_of("scope").unwrap().parse()?;
+            let token = generate()?;
+            tokens.tokens.push(Token { name: name.to_string(), scope, sha256: hash(&token) });
+            save(path, &tokens)?;
+            println!("{}", token);
+        },
+        ("remove", Some(remove)) => {
+            let name = remove.value_of("name").unwrap();
+            let count = tokens.tokens.len();
+            tokens.tokens.retain(|t| t.name != name);
+            if tokens.tokens.len() == count {
+                return Err(format!("No token is named {}", name).into());
+            }
+            save(path, &tokens)?;
+            println!("Removed {}", name);
+        },
+        ("list", _) => {
+            for token in tokens.tokens.iter() {
+                println!("{:<20} {}", token.name, token.scope);
+            }
+        },
+        _ => unreachable!(),
+    }
+    Ok(())
+}
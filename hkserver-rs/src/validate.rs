@@ -0,0 +1,474 @@
+use chrono_tz::Tz;
+use crate::backend::BackendError;
+use crate::hkservice::{
+    CharacteristicInformation,
+    ComparisonOperator,
+    EventInformation,
+    EventTriggerDefinition,
+    NameUuidPair,
+    Number,
+    PredicateInformation,
+    PresenceEventType,
+    PresenceEventUserType,
+    SignificantEvent,
+    TimerTriggerDefinition,
+    Value,
+    Weekday,
+};
+use crate::hkservice::characteristic_information::{Format, Metadata, Property};
+use crate::hkservice::compound_predicate::Kind;
+use crate::hkservice::event_information::Event;
+use crate::hkservice::number::Value as NumberValue;
+use crate::hkservice::predicate_information::Predicate;
+use crate::hkservice::significant_time_predicate::Relation;
+use crate::hkservice::timer_recurrence::Unit;
+use crate::hkservice::value::Value as ValueEnum;
+
+fn invalid(message: String) -> BackendError {
+    BackendError::InvalidArgument(message)
+}
+
+fn number_as_f64(number: &Number) -> Option<f64> {
+    match number.value.as_ref()? {
+        NumberValue::SignedIntegerValue(i) => Some(*i as f64),
+        NumberValue::UnsignedIntegerValue(u) => Some(*u as f64),
+        NumberValue::FloatValue(f) => Some(*f as f64),
+        NumberValue::DoubleValue(d) => Some(*d),
+    }
+}
+
+fn integer_range(format: Format) -> Option<(f64, f64)> {
+    match format {
+        Format::Int => Some((i32::MIN as f64, i32::MAX as f64)),
+        Format::Uint8 => Some((0.0, u8::MAX as f64)),
+        Format::Uint16 => Some((0.0, u16::MAX as f64)),
+        Format::Uint32 => Some((0.0, u32::MAX as f64)),
+        Format::Uint64 => Some((0.0, u64::MAX as f64)),
+        _ => None,
+    }
+}
+
+fn validate_number(format: Format, metadata: &Metadata, number: &Number) -> Result<(), BackendError> {
+    let value = number_as_f64(number).ok_or_else(|| invalid("Missing number value".to_string()))?;
+    if let Some((min, max)) = integer_range(format) {
+        let is_integer = match number.value {
+            Some(NumberValue::SignedIntegerValue(_)) | Some(NumberValue::UnsignedIntegerValue(_)) => true,
+            _ => value.fract() == 0.0,
+        };
+        if !is_integer || value < min || value > max {
+            return Err(invalid(format!("{} is not a valid {:?} value", value, format)));
+        }
+    }
+    let minimum = metadata.minimum_value.as_ref().and_then(number_as_f64);
+    let maximum = metadata.maximum_value.as_ref().and_then(number_as_f64);
+    if let Some(minimum) = minimum {
+        if value < minimum {
+            return Err(invalid(format!("{} is less than the minimum value {}", value, minimum)));
+        }
+    }
+    if let Some(maximum) = maximum {
+        if value > maximum {
+            return Err(invalid(format!("{} is greater than the maximum value {}", value, maximum)));
+        }
+    }
+    if let Some(step) = metadata.step_value.as_ref().and_then(number_as_f64) {
+        if step > 0.0 {
+            let steps = (value - minimum.unwrap_or(0.0)) / step;
+            if (steps - steps.round()).abs() > 1e-6 {
+                return Err(invalid(format!("{} is not a multiple of the step value {}", value, step)));
+            }
+        }
+    }
+    if metadata.valid_values.len() != 0 && !metadata.valid_values.iter().any(|v| number_as_f64(v) == Some(value)) {
+        let valid_values = metadata.valid_values.iter()
+            .filter_map(number_as_f64)
+            .map(|v| v.to_string())
+            .collect::<Vec<String>>()
+            .join(", ");
+        return Err(invalid(format!("{} is not one of the valid values: {}", value, valid_values)));
+    }
+    Ok(())
+}
+
+fn validate_length(metadata: &Metadata, length: usize) -> Result<(), BackendError> {
+    if let Some(max_length) = metadata.max_length.as_ref().and_then(number_as_f64) {
+        if length as f64 > max_length {
+            return Err(invalid(format!("Length {} exceeds the maximum length {}", length, max_length)));
+        }
+    }
+    Ok(())
+}
+
+/// Checks that `value` may be written to `characteristic`, according to its
+/// properties and metadata.
+pub fn validate_write(characteristic: &CharacteristicInformation, value: &Value) -> Result<(), BackendError> {
+    if !characteristic.properties.contains(&(Property::Writable as i32)) {
+        return Err(invalid(format!("Characteristic {} is not writable", characteristic.uuid)));
+    }
+    validate_value(characteristic, value)
+}
+
+/// Checks that `value` is a valid value of `characteristic`, according to its
+/// metadata.
+fn validate_value(characteristic: &CharacteristicInformation, value: &Value) -> Result<(), BackendError> {
+    let value = value.value.as_ref().ok_or_else(|| invalid("Missing value".to_string()))?;
+    let metadata = match characteristic.metadata {
+        Some(ref metadata) => metadata,
+        // Without metadata there is nothing to validate against
+        None => return Ok(()),
+    };
+    let format = Format::from_i32(metadata.format).unwrap_or(Format::InvalidFormat);
+    match (format, value) {
+        (Format::InvalidFormat, _) => Ok(()),
+        (Format::Bool, ValueEnum::BoolValue(_)) => Ok(()),
+        (Format::Int, ValueEnum::NumberValue(number)) |
+        (Format::Float, ValueEnum::NumberValue(number)) |
+        (Format::Uint8, ValueEnum::NumberValue(number)) |
+        (Format::Uint16, ValueEnum::NumberValue(number)) |
+        (Format::Uint32, ValueEnum::NumberValue(number)) |
+        (Format::Uint64, ValueEnum::NumberValue(number)) => validate_number(format, metadata, number),
+        (Format::String, ValueEnum::StringValue(s)) => validate_length(metadata, s.chars().count()),
+        (Format::Data, ValueEnum::DataValue(data)) => validate_length(metadata, data.len()),
+        (Format::Tlv8, ValueEnum::DataValue(data)) => {
+            hktlv::decode(data).map_err(|e| invalid(e.to_string()))?;
+            validate_length(metadata, data.len())
+        },
+        (Format::Array, ValueEnum::ListValue(list)) => list.values.iter().try_for_each(validate_element),
+        (Format::Dictionary, ValueEnum::MapValue(map)) => map.entries.values().try_for_each(validate_element),
+        (format, _) => Err(invalid(format!("Value does not match characteristic format {:?}", format))),
+    }
+}
+
+/// Checks an element of an array or dictionary value. Elements have no
+/// metadata, so only their presence is checked.
+fn validate_element(value: &Value) -> Result<(), BackendError> {
+    match value.value.as_ref() {
+        None => Err(invalid("Missing value in array or dictionary".to_string())),
+        Some(ValueEnum::ListValue(list)) => list.values.iter().try_for_each(validate_element),
+        Some(ValueEnum::MapValue(map)) => map.entries.values().try_for_each(validate_element),
+        Some(_) => Ok(()),
+    }
+}
+
+/// Checks a timer trigger the way HomeKit does: it fires on whole minutes,
+/// repeats by a positive number of calendar units and follows a known time
+/// zone, if any.
+pub fn validate_timer(timer: &TimerTriggerDefinition) -> Result<(), BackendError> {
+    if timer.fire_date == 0 {
+        return Err(invalid("Timer triggers need a fire date".to_string()));
+    }
+    if timer.fire_date % 60 != 0 {
+        return Err(invalid("Timer triggers can only fire on whole minutes".to_string()));
+    }
+    if !timer.time_zone.is_empty() && timer.time_zone.parse::<Tz>().is_err() {
+        return Err(invalid(format!("Unknown time zone '{}'. Use an IANA name such as Europe/Paris", timer.time_zone)));
+    }
+    if let Some(ref recurrence) = timer.recurrence {
+        match Unit::from_i32(recurrence.unit) {
+            Some(Unit::InvalidUnit) | None => return Err(invalid(format!("Invalid recurrence unit {}", recurrence.unit))),
+            Some(_) => (),
+        }
+        if recurrence.interval == 0 {
+            return Err(invalid("Recurrence interval must be at least 1".to_string()));
+        }
+    }
+    Ok(())
+}
+
+fn event_characteristic<'a>(characteristics: &'a [CharacteristicInformation], pair: Option<&NameUuidPair>) -> Result<&'a CharacteristicInformation, BackendError> {
+    let uuid = pair.map_or("", |pair| pair.uuid.as_str());
+    let characteristic = characteristics.iter()
+        .find(|characteristic| characteristic.uuid == uuid)
+        .ok_or_else(|| BackendError::not_found("characteristic", uuid))?;
+    if !characteristic.properties.contains(&(Property::SupportsEvent as i32)) {
+        return Err(invalid(format!("Characteristic {} does not support events", characteristic.uuid)));
+    }
+    Ok(characteristic)
+}
+
+fn is_numeric(characteristic: &CharacteristicInformation) -> bool {
+    let format = characteristic.metadata.as_ref()
+        .and_then(|m| Format::from_i32(m.format))
+        .unwrap_or(Format::InvalidFormat);
+    format != Format::Bool && (integer_range(format).is_some() || format == Format::Float)
+}
+
+fn validate_event(event: &EventInformation, is_end_event: bool, characteristics: &[CharacteristicInformation]) -> Result<(), BackendError> {
+    match event.event.as_ref().ok_or_else(|| invalid("Missing event".to_string()))? {
+        Event::CharacteristicEvent(e) => {
+            let characteristic = event_characteristic(characteristics, e.characteristic.as_ref())?;
+            if let Some(ref value) = e.trigger_value {
+                validate_value(characteristic, value)?;
+            }
+        },
+        Event::CharacteristicThresholdRangeEvent(e) => {
+            let characteristic = event_characteristic(characteristics, e.characteristic.as_ref())?;
+            if !is_numeric(characteristic) {
+                return Err(invalid(format!("Characteristic {} is not numeric", characteristic.uuid)));
+            }
+            let range = e.range.as_ref().ok_or_else(|| invalid("Missing threshold range".to_string()))?;
+            match (range.min_value.as_ref().and_then(number_as_f64), range.max_value.as_ref().and_then(number_as_f64)) {
+                (None, None) => return Err(invalid("A threshold range needs a minimum or a maximum".to_string())),
+                (Some(min), Some(max)) if min > max => return Err(invalid(format!("Threshold minimum {} is above the maximum {}", min, max))),
+                _ => (),
+            }
+        },
+        Event::SignificantTimeEvent(e) => match SignificantEvent::from_i32(e.significant_event) {
+            Some(SignificantEvent::InvalidSignificantEvent) | None => return Err(invalid(format!("Invalid significant event {}", e.significant_event))),
+            Some(_) => (),
+        },
+        Event::PresenceEvent(e) => {
+            match PresenceEventType::from_i32(e.presence_event) {
+                Some(PresenceEventType::InvalidPresenceEventType) | None => return Err(invalid(format!("Invalid presence event {}", e.presence_event))),
+                Some(_) => (),
+            }
+            match PresenceEventUserType::from_i32(e.presence_user) {
+                Some(PresenceEventUserType::InvalidPresenceEventUserType) | None => return Err(invalid(format!("Invalid presence user {}", e.presence_user))),
+                Some(_) => (),
+            }
+        },
+        Event::LocationEvent(e) => {
+            if e.region.as_ref().map_or(true, |region| region.center.is_none() || region.radius <= 0.0) {
+                return Err(invalid("A location event needs a region with a center and a positive radius".to_string()));
+            }
+            if !e.notify_on_entry && !e.notify_on_exit {
+                return Err(invalid("A location event needs to notify on entry, exit or both".to_string()));
+            }
+        },
+        Event::CalendarEvent(e) => if e.fire_date == 0 {
+            return Err(invalid("A calendar event needs a fire date".to_string()));
+        },
+        Event::DurationEvent(e) => {
+            // HomeKit only ends triggers after a duration, it cannot start them
+            if !is_end_event {
+                return Err(invalid("Duration events can only be end events".to_string()));
+            }
+            if e.duration <= 0.0 {
+                return Err(invalid(format!("Invalid duration {}", e.duration)));
+            }
+        },
+    }
+    Ok(())
+}
+
+fn validate_predicate(predicate: &PredicateInformation, characteristics: &[CharacteristicInformation]) -> Result<(), BackendError> {
+    match predicate.predicate.as_ref().ok_or_else(|| invalid("Missing predicate".to_string()))? {
+        Predicate::Characteristic(p) => {
+            let uuid = p.characteristic.as_ref().map_or("", |pair| pair.uuid.as_str());
+            let characteristic = characteristics.iter()
+                .find(|characteristic| characteristic.uuid == uuid)
+                .ok_or_else(|| BackendError::not_found("characteristic", uuid))?;
+            if !characteristic.properties.contains(&(Property::Readable as i32)) {
+                return Err(invalid(format!("Characteristic {} is not readable", characteristic.uuid)));
+            }
+            match ComparisonOperator::from_i32(p.comparison) {
+                Some(ComparisonOperator::InvalidComparisonOperator) | None => return Err(invalid(format!("Invalid comparison operator {}", p.comparison))),
+                Some(ComparisonOperator::Equal) | Some(ComparisonOperator::NotEqual) => (),
+                Some(_) => if !is_numeric(characteristic) {
+                    return Err(invalid(format!("Characteristic {} is not numeric", characteristic.uuid)));
+                },
+            }
+            validate_value(characteristic, p.value.as_ref().ok_or_else(|| invalid("Missing comparison value".to_string()))?)?;
+        },
+        Predicate::SignificantTime(p) => {
+            match Relation::from_i32(p.relation) {
+                Some(Relation::InvalidRelation) | None => return Err(invalid(format!("Invalid relation {}", p.relation))),
+                Some(_) => (),
+            }
+            match SignificantEvent::from_i32(p.significant_event) {
+                Some(SignificantEvent::InvalidSignificantEvent) | None => return Err(invalid(format!("Invalid significant event {}", p.significant_event))),
+                Some(_) => (),
+            }
+        },
+        Predicate::TimeOfDay(p) => {
+            if p.after.is_none() && p.before.is_none() {
+                return Err(invalid("A time of day predicate needs a start, an end or both".to_string()));
+            }
+            for time in p.after.iter().chain(p.before.iter()) {
+                if time.hour > 23 || time.minute > 59 {
+                    return Err(invalid(format!("Invalid time of day {}:{:02}", time.hour, time.minute)));
+                }
+            }
+        },
+        Predicate::Presence(p) => {
+            // HomeKit only evaluates whether users are at home, not how they
+            // got there
+            match PresenceEventType::from_i32(p.presence_event) {
+                Some(PresenceEventType::FirstEntry) | Some(PresenceEventType::LastExit) => (),
+                _ => return Err(invalid(format!("Invalid presence {}: expected first entry (at home) or last exit (not at home)", p.presence_event))),
+            }
+            match PresenceEventUserType::from_i32(p.presence_user) {
+                Some(PresenceEventUserType::CurrentUser) | Some(PresenceEventUserType::HomeUsers) => (),
+                _ => return Err(invalid(format!("Invalid presence user {}", p.presence_user))),
+            }
+        },
+        Predicate::Compound(p) => {
+            match Kind::from_i32(p.kind) {
+                Some(Kind::InvalidKind) | None => return Err(invalid(format!("Invalid compound predicate kind {}", p.kind))),
+                Some(Kind::Not) if p.predicates.len() != 1 => return Err(invalid("A not predicate takes exactly one predicate".to_string())),
+                Some(_) if p.predicates.is_empty() => return Err(invalid("A compound predicate needs at least one predicate".to_string())),
+                Some(_) => (),
+            }
+            for predicate in p.predicates.iter() {
+                validate_predicate(predicate, characteristics)?;
+            }
+        },
+        Predicate::Format(_) => return Err(invalid("Predicates given as a format cannot be used in definitions".to_string())),
+    }
+    Ok(())
+}
+
+/// Checks an event trigger against the characteristics of its home.
+pub fn validate_event_trigger(definition: &EventTriggerDefinition, characteristics: &[CharacteristicInformation]) -> Result<(), BackendError> {
+    if definition.events.is_empty() {
+        return Err(invalid("Event triggers need at least one event".to_string()));
+    }
+    for event in definition.events.iter() {
+        validate_event(event, false, characteristics)?;
+    }
+    for event in definition.end_events.iter() {
+        validate_event(event, true, characteristics)?;
+    }
+    for recurrence in definition.recurrences.iter() {
+        match Weekday::from_i32(*recurrence) {
+            Some(Weekday::InvalidWeekday) | None => return Err(invalid(format!("Invalid weekday {}", recurrence))),
+            Some(_) => (),
+        }
+    }
+    if let Some(ref predicate) = definition.predicate {
+        validate_predicate(predicate, characteristics)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hkservice::TimerRecurrence;
+
+    fn number(value: NumberValue) -> Number {
+        Number { value: Some(value) }
+    }
+
+    fn int(n: i64) -> Value {
+        Value { value: Some(ValueEnum::NumberValue(number(NumberValue::SignedIntegerValue(n)))) }
+    }
+
+    fn float(f: f64) -> Value {
+        Value { value: Some(ValueEnum::NumberValue(number(NumberValue::DoubleValue(f)))) }
+    }
+
+    /// A writable characteristic with the given format and metadata.
+    fn characteristic(metadata: Metadata) -> CharacteristicInformation {
+        CharacteristicInformation {
+            uuid: "C1".to_string(),
+            properties: vec![Property::Readable as i32, Property::Writable as i32],
+            metadata: Some(metadata),
+            ..Default::default()
+        }
+    }
+
+    fn brightness() -> CharacteristicInformation {
+        characteristic(Metadata {
+            format: Format::Int as i32,
+            minimum_value: Some(number(NumberValue::SignedIntegerValue(0))),
+            maximum_value: Some(number(NumberValue::SignedIntegerValue(100))),
+            step_value: Some(number(NumberValue::SignedIntegerValue(5))),
+            ..Default::default()
+        })
+    }
+
+    fn message(result: Result<(), BackendError>) -> String {
+        result.expect_err("the value should be rejected").to_string()
+    }
+
+    #[test]
+    fn accepts_values_within_the_metadata() {
+        for n in [0, 5, 50, 100].iter() {
+            validate_write(&brightness(), &int(*n)).unwrap();
+        }
+    }
+
+    #[test]
+    fn checks_minimum_maximum_and_step() {
+        assert_eq!(message(validate_write(&brightness(), &int(-5))), "-5 is less than the minimum value 0");
+        assert_eq!(message(validate_write(&brightness(), &int(105))), "105 is greater than the maximum value 100");
+        assert_eq!(message(validate_write(&brightness(), &int(7))), "7 is not a multiple of the step value 5");
+    }
+
+    #[test]
+    fn steps_count_from_the_minimum() {
+        let temperature = characteristic(Metadata {
+            format: Format::Float as i32,
+            minimum_value: Some(number(NumberValue::DoubleValue(10.0))),
+            step_value: Some(number(NumberValue::DoubleValue(0.5))),
+            ..Default::default()
+        });
+        validate_write(&temperature, &float(21.5)).unwrap();
+        assert_eq!(message(validate_write(&temperature, &float(21.2))), "21.2 is not a multiple of the step value 0.5");
+    }
+
+    #[test]
+    fn checks_integer_formats() {
+        let level = characteristic(Metadata { format: Format::Uint8 as i32, ..Default::default() });
+        assert_eq!(message(validate_write(&level, &int(256))), "256 is not a valid Uint8 value");
+        assert_eq!(message(validate_write(&level, &float(1.5))), "1.5 is not a valid Uint8 value");
+    }
+
+    #[test]
+    fn checks_valid_values() {
+        let mode = characteristic(Metadata {
+            format: Format::Uint8 as i32,
+            valid_values: vec![number(NumberValue::UnsignedIntegerValue(0)), number(NumberValue::UnsignedIntegerValue(2))],
+            ..Default::default()
+        });
+        validate_write(&mode, &int(2)).unwrap();
+        assert_eq!(message(validate_write(&mode, &int(1))), "1 is not one of the valid values: 0, 2");
+    }
+
+    #[test]
+    fn rejects_writes_to_read_only_characteristics() {
+        let mut characteristic = brightness();
+        characteristic.properties = vec![Property::Readable as i32];
+        assert_eq!(message(validate_write(&characteristic, &int(50))), "Characteristic C1 is not writable");
+    }
+
+    #[test]
+    fn rejects_values_of_another_format() {
+        let value = Value { value: Some(ValueEnum::BoolValue(true)) };
+        assert_eq!(message(validate_write(&brightness(), &value)), "Value does not match characteristic format Int");
+    }
+
+    fn timer(fire_date: u64, recurrence: Option<(Unit, u32)>, time_zone: &str) -> TimerTriggerDefinition {
+        TimerTriggerDefinition {
+            fire_date: fire_date,
+            recurrence: recurrence.map(|(unit, interval)| TimerRecurrence { unit: unit as i32, interval: interval }),
+            time_zone: time_zone.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn accepts_timers_on_whole_minutes() {
+        validate_timer(&timer(1_600_000_020, None, "")).unwrap();
+        validate_timer(&timer(1_600_000_020, Some((Unit::Day, 1)), "Europe/Paris")).unwrap();
+    }
+
+    #[test]
+    fn checks_timer_fire_dates() {
+        assert_eq!(message(validate_timer(&timer(0, None, ""))), "Timer triggers need a fire date");
+        assert_eq!(message(validate_timer(&timer(1_600_000_001, None, ""))), "Timer triggers can only fire on whole minutes");
+    }
+
+    #[test]
+    fn checks_timer_recurrences() {
+        assert_eq!(message(validate_timer(&timer(1_600_000_020, Some((Unit::InvalidUnit, 1)), ""))), "Invalid recurrence unit 0");
+        assert_eq!(message(validate_timer(&timer(1_600_000_020, Some((Unit::Hour, 0)), ""))), "Recurrence interval must be at least 1");
+    }
+
+    #[test]
+    fn checks_timer_time_zones() {
+        assert_eq!(message(validate_timer(&timer(1_600_000_020, None, "Mars/Olympus"))),
+                   "Unknown time zone 'Mars/Olympus'. Use an IANA name such as Europe/Paris");
+    }
+}
@@ -0,0 +1,155 @@
+//! TLV8, the type-length-value encoding HomeKit uses for structured
+//! characteristic values such as camera stream configurations.
+//!
+//! Each item is a one byte tag, a one byte length and up to 255 bytes of
+//! value. Longer values are split into consecutive fragments with the same
+//! tag, and consecutive items that would otherwise run together, such as the
+//! elements of a list, are set apart by a zero length separator.
+
+mod schema;
+pub mod schemas;
+
+pub use schema::{Field, Kind, Node, Schema};
+
+/// Tag of the zero length item placed between consecutive list elements.
+pub const SEPARATOR: u8 = 0x00;
+
+const MAX_FRAGMENT: usize = 255;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Error {
+    /// The data ends in the middle of an item.
+    Truncated { offset: usize },
+    /// A value does not match the kind its schema gives it.
+    InvalidValue { field: String, message: String },
+    /// A value names a field its schema does not have.
+    UnknownField(String),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Error::Truncated { offset } => write!(f, "TLV8 data is truncated at byte {}", offset),
+            Error::InvalidValue { field, message } => write!(f, "Invalid value for {}: {}", field, message),
+            Error::UnknownField(field) => write!(f, "Unknown field {}", field),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// A tag and its value, with fragments already joined.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Item {
+    pub tag: u8,
+    pub value: Vec<u8>,
+}
+
+impl Item {
+    pub fn new(tag: u8, value: Vec<u8>) -> Item {
+        Item { tag, value }
+    }
+
+    pub fn is_separator(&self) -> bool {
+        self.value.is_empty()
+    }
+}
+
+/// Splits TLV8 data into items, joining the fragments of long values. A
+/// fragment follows an item with the same tag and a full 255 byte value.
+pub fn decode(data: &[u8]) -> Result<Vec<Item>, Error> {
+    let mut items: Vec<Item> = vec![];
+    let mut continues = false;
+    let mut offset = 0;
+    while offset < data.len() {
+        if offset + 2 > data.len() {
+            return Err(Error::Truncated { offset });
+        }
+        let tag = data[offset];
+        let length = data[offset + 1] as usize;
+        let start = offset + 2;
+        if start + length > data.len() {
+            return Err(Error::Truncated { offset });
+        }
+        let value = &data[start..start + length];
+        match items.last_mut() {
+            Some(last) if continues && last.tag == tag => last.value.extend_from_slice(value),
+            _ => items.push(Item::new(tag, value.to_vec())),
+        }
+        continues = length == MAX_FRAGMENT;
+        offset = start + length;
+    }
+    Ok(items)
+}
+
+/// Encodes items as TLV8, splitting long values into fragments. Consecutive
+/// items with the same tag are set apart by a separator, so that they are
+/// not read back as fragments of one value.
+pub fn encode(items: &[Item]) -> Vec<u8> {
+    let mut data = vec![];
+    let mut previous: Option<u8> = None;
+    for item in items {
+        if previous == Some(item.tag) && !item.is_separator() {
+            data.extend_from_slice(&[SEPARATOR, 0]);
+        }
+        if item.value.is_empty() {
+            data.extend_from_slice(&[item.tag, 0]);
+        }
+        for chunk in item.value.chunks(MAX_FRAGMENT) {
+            data.push(item.tag);
+            data.push(chunk.len() as u8);
+            data.extend_from_slice(chunk);
+        }
+        previous = Some(item.tag);
+    }
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn joins_fragments_of_long_values() {
+        let value: Vec<u8> = (0..300).map(|n| n as u8).collect();
+        let data = encode(&[Item::new(0x01, value.clone())]);
+        assert_eq!(data.len(), 2 + 255 + 2 + 45);
+        assert_eq!(&data[..2], &[0x01, 255]);
+        assert_eq!(&data[257..259], &[0x01, 45]);
+        assert_eq!(decode(&data).unwrap(), vec![Item::new(0x01, value)]);
+    }
+
+    #[test]
+    fn value_of_exactly_255_bytes_is_one_fragment() {
+        let value = vec![0xab; 255];
+        let items = vec![Item::new(0x01, value.clone()), Item::new(0x02, vec![1])];
+        let data = encode(&items);
+        assert_eq!(data.len(), 2 + 255 + 3);
+        assert_eq!(decode(&data).unwrap(), items);
+    }
+
+    #[test]
+    fn following_item_with_the_same_tag_is_not_a_fragment() {
+        let first = vec![0xab; 255];
+        let items = vec![Item::new(0x01, first.clone()), Item::new(0x01, vec![1, 2])];
+        let data = encode(&items);
+        assert_eq!(&data[257..259], &[SEPARATOR, 0]);
+        assert_eq!(decode(&data).unwrap(), vec![
+            Item::new(0x01, first),
+            Item::new(SEPARATOR, vec![]),
+            Item::new(0x01, vec![1, 2]),
+        ]);
+    }
+
+    #[test]
+    fn separates_repeated_tags() {
+        let items = vec![Item::new(0x01, vec![1]), Item::new(0x01, vec![2]), Item::new(0x02, vec![3])];
+        assert_eq!(encode(&items), vec![0x01, 1, 1, SEPARATOR, 0, 0x01, 1, 2, 0x02, 1, 3]);
+    }
+
+    #[test]
+    fn reports_truncated_data() {
+        assert_eq!(decode(&[0x01]), Err(Error::Truncated { offset: 0 }));
+        assert_eq!(decode(&[0x01, 1, 7, 0x02, 3, 1]), Err(Error::Truncated { offset: 3 }));
+    }
+}
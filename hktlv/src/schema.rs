@@ -0,0 +1,328 @@
+use serde_json::Value as Json;
+use crate::{decode, encode, Error, Item};
+
+/// How the value of a field is encoded.
+#[derive(Clone, Copy, Debug)]
+pub enum Kind {
+    /// A little-endian unsigned integer, written with the given number of
+    /// bytes.
+    Integer(usize),
+    /// A little-endian 32 bit float.
+    Float,
+    Bool,
+    /// A little-endian unsigned integer with named values.
+    Enum(usize, &'static [(u64, &'static str)]),
+    Bytes,
+    String,
+    /// 16 bytes, shown as a UUID.
+    Uuid,
+    /// Nested TLV8 data.
+    Tree(&'static [Field]),
+}
+
+/// A tagged field of a TLV8 structure. Fields may repeat, as the elements of
+/// a list.
+#[derive(Clone, Copy, Debug)]
+pub struct Field {
+    pub tag: u8,
+    pub name: &'static str,
+    pub kind: Kind,
+}
+
+impl Field {
+    pub const fn new(tag: u8, name: &'static str, kind: Kind) -> Field {
+        Field { tag, name, kind }
+    }
+}
+
+/// The fields of a TLV8 structure. Data with no known schema can be decoded
+/// with an empty one, which keeps every item as bytes.
+#[derive(Clone, Copy, Debug)]
+pub struct Schema {
+    pub fields: &'static [Field],
+}
+
+/// A decoded value.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Node {
+    Integer(u64),
+    Float(f32),
+    Bool(bool),
+    Enum(u64, Option<&'static str>),
+    Bytes(Vec<u8>),
+    String(String),
+    Uuid([u8; 16]),
+    /// Named fields in the order they were found. Items without a field in
+    /// the schema are named by their tag.
+    Tree(Vec<(String, Node)>),
+}
+
+fn invalid(field: &str, message: String) -> Error {
+    Error::InvalidValue { field: field.to_string(), message }
+}
+
+fn unknown_name(tag: u8) -> String {
+    format!("0x{:02x}", tag)
+}
+
+fn integer_from_bytes(field: &str, bytes: &[u8]) -> Result<u64, Error> {
+    if bytes.is_empty() || bytes.len() > 8 {
+        return Err(invalid(field, format!("{} bytes is not a valid integer length", bytes.len())));
+    }
+    Ok(bytes.iter().rev().fold(0, |n, b| (n << 8) | *b as u64))
+}
+
+fn integer_to_bytes(field: &str, n: u64, width: usize) -> Result<Vec<u8>, Error> {
+    if width < 8 && n >> (8 * width) != 0 {
+        return Err(invalid(field, format!("{} does not fit in {} bytes", n, width)));
+    }
+    Ok(n.to_le_bytes()[..width].to_vec())
+}
+
+fn format_uuid(bytes: &[u8; 16]) -> String {
+    let hex = hex::encode_upper(bytes);
+    format!("{}-{}-{}-{}-{}", &hex[0..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..32])
+}
+
+impl Kind {
+    fn decode(&self, field: &str, bytes: &[u8]) -> Result<Node, Error> {
+        Ok(match self {
+            Kind::Integer(_) => Node::Integer(integer_from_bytes(field, bytes)?),
+            Kind::Float => {
+                if bytes.len() != 4 {
+                    return Err(invalid(field, format!("{} bytes is not a valid float length", bytes.len())));
+                }
+                Node::Float(f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            },
+            Kind::Bool => Node::Bool(integer_from_bytes(field, bytes)? != 0),
+            Kind::Enum(_, names) => {
+                let n = integer_from_bytes(field, bytes)?;
+                Node::Enum(n, names.iter().find(|(value, _)| *value == n).map(|(_, name)| *name))
+            },
+            Kind::Bytes => Node::Bytes(bytes.to_vec()),
+            Kind::String => Node::String(String::from_utf8(bytes.to_vec()).map_err(|e| invalid(field, e.to_string()))?),
+            Kind::Uuid => {
+                if bytes.len() != 16 {
+                    return Err(invalid(field, format!("{} bytes is not a valid UUID length", bytes.len())));
+                }
+                let mut uuid = [0; 16];
+                uuid.copy_from_slice(bytes);
+                Node::Uuid(uuid)
+            },
+            Kind::Tree(fields) => Schema { fields }.decode(bytes)?,
+        })
+    }
+
+    fn encode(&self, field: &str, value: &Json) -> Result<Vec<u8>, Error> {
+        let expected = |what: &str| invalid(field, format!("expected {}, found {}", what, value));
+        let hex = |s: &str| hex::decode(s.trim_start_matches("0x")).map_err(|e| invalid(field, e.to_string()));
+        match (self, value) {
+            (Kind::Integer(width), Json::Number(n)) => integer_to_bytes(field, n.as_u64().ok_or_else(|| expected("an unsigned integer"))?, *width),
+            (Kind::Integer(_), _) => Err(expected("an unsigned integer")),
+            (Kind::Float, Json::Number(n)) => Ok((n.as_f64().unwrap_or_default() as f32).to_le_bytes().to_vec()),
+            (Kind::Float, _) => Err(expected("a number")),
+            (Kind::Bool, Json::Bool(b)) => Ok(vec![*b as u8]),
+            (Kind::Bool, _) => Err(expected("true or false")),
+            (Kind::Enum(width, names), Json::String(s)) => {
+                let n = names.iter()
+                    .find(|(_, name)| name.eq_ignore_ascii_case(s))
+                    .map(|(value, _)| *value)
+                    .ok_or_else(|| invalid(field, format!("'{}' is not one of {}", s,
+                                                          names.iter().map(|(_, name)| *name).collect::<Vec<&str>>().join(", "))))?;
+                integer_to_bytes(field, n, *width)
+            },
+            (Kind::Enum(width, _), Json::Number(n)) => integer_to_bytes(field, n.as_u64().ok_or_else(|| expected("a name or an unsigned integer"))?, *width),
+            (Kind::Enum(_, _), _) => Err(expected("a name or an unsigned integer")),
+            (Kind::Bytes, Json::String(s)) => hex(s),
+            (Kind::Bytes, _) => Err(expected("hex data")),
+            (Kind::String, Json::String(s)) => Ok(s.as_bytes().to_vec()),
+            (Kind::String, _) => Err(expected("a string")),
+            (Kind::Uuid, Json::String(s)) => {
+                let bytes = hex(&s.replace("-", ""))?;
+                if bytes.len() != 16 {
+                    return Err(expected("a UUID"));
+                }
+                Ok(bytes)
+            },
+            (Kind::Uuid, _) => Err(expected("a UUID")),
+            (Kind::Tree(fields), value) => Schema { fields }.encode(value),
+        }
+    }
+}
+
+impl Schema {
+    pub const EMPTY: Schema = Schema { fields: &[] };
+
+    fn field_by_tag(&self, tag: u8) -> Option<&'static Field> {
+        self.fields.iter().find(|f| f.tag == tag)
+    }
+
+    /// Finds a field by name, or by the tag of an unknown item written as a
+    /// number such as 0x0a or 10.
+    fn field_by_name(&self, name: &str) -> Result<(u8, Kind), Error> {
+        if let Some(field) = self.fields.iter().find(|f| f.name == name) {
+            return Ok((field.tag, field.kind));
+        }
+        let tag = match name.strip_prefix("0x") {
+            Some(hex) => u8::from_str_radix(hex, 16).ok(),
+            None => name.parse::<u8>().ok(),
+        };
+        match tag {
+            Some(tag) => Ok((tag, self.field_by_tag(tag).map_or(Kind::Bytes, |f| f.kind))),
+            None => Err(Error::UnknownField(name.to_string())),
+        }
+    }
+
+    /// Decodes TLV8 data into a tree of named values.
+    pub fn decode(&self, data: &[u8]) -> Result<Node, Error> {
+        let mut tree = vec![];
+        for Item { tag, value } in decode(data)? {
+            match self.field_by_tag(tag) {
+                Some(field) => tree.push((field.name.to_string(), field.kind.decode(field.name, &value)?)),
+                None if value.is_empty() && tag == crate::SEPARATOR => (),
+                None => tree.push((unknown_name(tag), Node::Bytes(value))),
+            }
+        }
+        Ok(Node::Tree(tree))
+    }
+
+    /// Encodes a JSON object of field names and values. Fields that repeat
+    /// are given as arrays, and items the schema does not know can be given
+    /// by tag, with hex data or nested objects as values.
+    pub fn encode(&self, value: &Json) -> Result<Vec<u8>, Error> {
+        let object = match value {
+            Json::Object(object) => object,
+            _ => return Err(invalid("TLV8 data", format!("expected an object, found {}", value))),
+        };
+        let mut fields = object.iter()
+            .map(|(name, value)| self.field_by_name(name).map(|(tag, kind)| (tag, kind, name, value)))
+            .collect::<Result<Vec<_>, Error>>()?;
+        // Items are written in schema order, since accessories may expect it
+        let position = |tag: u8| self.fields.iter().position(|f| f.tag == tag).unwrap_or(self.fields.len() + tag as usize);
+        fields.sort_by_key(|(tag, _, _, _)| position(*tag));
+        let mut items = vec![];
+        for (tag, kind, name, value) in fields {
+            let values = match value {
+                Json::Array(values) => values.iter().collect::<Vec<&Json>>(),
+                value => vec![value],
+            };
+            for value in values {
+                let bytes = match (kind, value) {
+                    (Kind::Bytes, Json::Object(_)) => Schema::EMPTY.encode(value)?,
+                    (kind, value) => kind.encode(name, value)?,
+                };
+                items.push(Item::new(tag, bytes));
+            }
+        }
+        Ok(encode(&items))
+    }
+}
+
+impl Node {
+    fn fmt_indented(&self, f: &mut std::fmt::Formatter, indent: usize) -> std::fmt::Result {
+        match self {
+            Node::Tree(entries) => {
+                for (index, (name, node)) in entries.iter().enumerate() {
+                    if index != 0 {
+                        writeln!(f)?;
+                    }
+                    write!(f, "{}{}:", " ".repeat(indent), name)?;
+                    match node {
+                        Node::Tree(children) if children.is_empty() => (),
+                        Node::Tree(_) => {
+                            writeln!(f)?;
+                            node.fmt_indented(f, indent + 2)?;
+                        },
+                        node => write!(f, " {}", node)?,
+                    }
+                }
+                Ok(())
+            },
+            node => write!(f, "{}", node),
+        }
+    }
+}
+
+/// Leaf values print on one line, and trees print one field per line.
+impl std::fmt::Display for Node {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Node::Integer(n) => write!(f, "{}", n),
+            Node::Float(x) => write!(f, "{}", x),
+            Node::Bool(b) => write!(f, "{}", b),
+            Node::Enum(_, Some(name)) => write!(f, "{}", name),
+            Node::Enum(n, None) => write!(f, "{} (unknown)", n),
+            Node::Bytes(bytes) => write!(f, "{{{}, b'{}'}}", bytes.len(), hex::encode(bytes)),
+            Node::String(s) => write!(f, "{}", s),
+            Node::Uuid(uuid) => write!(f, "{}", format_uuid(uuid)),
+            Node::Tree(_) => self.fmt_indented(f, 0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schemas::{LOCK_CONTROL_POINT, SETUP_ENDPOINTS};
+    use serde_json::json;
+
+    fn tree(entries: Vec<(&str, Node)>) -> Node {
+        Node::Tree(entries.into_iter().map(|(name, node)| (name.to_string(), node)).collect())
+    }
+
+    #[test]
+    fn round_trips_setup_endpoints() {
+        let value = json!({
+            "session_id": "12345678-9ABC-DEF0-1234-56789ABCDEF0",
+            "address": {
+                "ip_version": "IPV4",
+                "ip_address": "192.168.1.2",
+                "video_rtp_port": 50000,
+                "audio_rtp_port": 50002,
+            },
+            "video_srtp_parameters": {"crypto_suite": "AES_CM_128_HMAC_SHA1_80", "master_key": "00112233", "master_salt": "4455"},
+            "audio_srtp_parameters": {"crypto_suite": "DISABLED"},
+        });
+        let data = SETUP_ENDPOINTS.encode(&value).unwrap();
+        let mut session_id = [0; 16];
+        session_id.copy_from_slice(&hex::decode("123456789ABCDEF0123456789ABCDEF0").unwrap());
+        assert_eq!(SETUP_ENDPOINTS.decode(&data).unwrap(), tree(vec![
+            ("session_id", Node::Uuid(session_id)),
+            ("address", tree(vec![
+                ("ip_version", Node::Enum(0, Some("IPV4"))),
+                ("ip_address", Node::String("192.168.1.2".to_string())),
+                ("video_rtp_port", Node::Integer(50000)),
+                ("audio_rtp_port", Node::Integer(50002)),
+            ])),
+            ("video_srtp_parameters", tree(vec![
+                ("crypto_suite", Node::Enum(0, Some("AES_CM_128_HMAC_SHA1_80"))),
+                ("master_key", Node::Bytes(vec![0x00, 0x11, 0x22, 0x33])),
+                ("master_salt", Node::Bytes(vec![0x44, 0x55])),
+            ])),
+            ("audio_srtp_parameters", tree(vec![
+                ("crypto_suite", Node::Enum(2, Some("DISABLED"))),
+            ])),
+        ]));
+    }
+
+    #[test]
+    fn round_trips_lock_control_point() {
+        let data = LOCK_CONTROL_POINT.encode(&json!({"read_logs_from_time": 1600000000})).unwrap();
+        assert_eq!(data, vec![0x00, 4, 0x00, 0x10, 0x5e, 0x5f]);
+        assert_eq!(LOCK_CONTROL_POINT.decode(&data).unwrap(), tree(vec![
+            ("read_logs_from_time", Node::Integer(1600000000)),
+        ]));
+        let data = LOCK_CONTROL_POINT.encode(&json!({"set_current_time": 1600000000, "clear_logs": ""})).unwrap();
+        assert_eq!(LOCK_CONTROL_POINT.decode(&data).unwrap(), tree(vec![
+            ("clear_logs", Node::Bytes(vec![])),
+            ("set_current_time", Node::Integer(1600000000)),
+        ]));
+    }
+
+    #[test]
+    fn rejects_values_of_the_wrong_kind() {
+        assert_eq!(SETUP_ENDPOINTS.encode(&json!({"video_ssrc": "one"})),
+                   Err(invalid("video_ssrc", "expected an unsigned integer, found \"one\"".to_string())));
+        assert_eq!(LOCK_CONTROL_POINT.encode(&json!({"open": true})), Err(Error::UnknownField("open".to_string())));
+    }
+}
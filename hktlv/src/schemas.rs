@@ -0,0 +1,149 @@
+//! Schemas of the TLV8 characteristics defined by the HomeKit Accessory
+//! Protocol, for the camera RTP stream management and lock management
+//! services.
+
+use crate::schema::{Field, Kind, Schema};
+
+const VIDEO_CODEC_TYPES: &[(u64, &str)] = &[(0, "H264")];
+const H264_PROFILES: &[(u64, &str)] = &[(0, "CONSTRAINED_BASELINE"), (1, "MAIN"), (2, "HIGH")];
+const H264_LEVELS: &[(u64, &str)] = &[(0, "3.1"), (1, "3.2"), (2, "4")];
+const PACKETIZATION_MODES: &[(u64, &str)] = &[(0, "NON_INTERLEAVED")];
+const AUDIO_CODEC_TYPES: &[(u64, &str)] = &[
+    (0, "PCMU"), (1, "PCMA"), (2, "AAC_ELD"), (3, "OPUS"), (4, "MSBC"), (5, "AMR"), (6, "AMR_WB"),
+];
+const BIT_RATES: &[(u64, &str)] = &[(0, "VARIABLE"), (1, "CONSTANT")];
+const SAMPLE_RATES: &[(u64, &str)] = &[(0, "8KHZ"), (1, "16KHZ"), (2, "24KHZ")];
+const CRYPTO_SUITES: &[(u64, &str)] = &[(0, "AES_CM_128_HMAC_SHA1_80"), (1, "AES_256_CM_HMAC_SHA1_80"), (2, "DISABLED")];
+const IP_VERSIONS: &[(u64, &str)] = &[(0, "IPV4"), (1, "IPV6")];
+const SETUP_STATUSES: &[(u64, &str)] = &[(0, "SUCCESS"), (1, "BUSY"), (2, "ERROR")];
+const STREAMING_STATUSES: &[(u64, &str)] = &[(0, "AVAILABLE"), (1, "IN_USE"), (2, "UNAVAILABLE")];
+const SESSION_COMMANDS: &[(u64, &str)] = &[(0, "END"), (1, "START"), (2, "SUSPEND"), (3, "RESUME"), (4, "RECONFIGURE")];
+
+const VIDEO_CODEC_PARAMETERS: &[Field] = &[
+    Field::new(0x01, "profile_id", Kind::Enum(1, H264_PROFILES)),
+    Field::new(0x02, "level", Kind::Enum(1, H264_LEVELS)),
+    Field::new(0x03, "packetization_mode", Kind::Enum(1, PACKETIZATION_MODES)),
+    Field::new(0x04, "cvo_enabled", Kind::Bool),
+    Field::new(0x05, "cvo_id", Kind::Integer(1)),
+];
+
+const VIDEO_ATTRIBUTES: &[Field] = &[
+    Field::new(0x01, "image_width", Kind::Integer(2)),
+    Field::new(0x02, "image_height", Kind::Integer(2)),
+    Field::new(0x03, "frame_rate", Kind::Integer(1)),
+];
+
+const VIDEO_CODEC_CONFIGURATION: &[Field] = &[
+    Field::new(0x01, "codec_type", Kind::Enum(1, VIDEO_CODEC_TYPES)),
+    Field::new(0x02, "codec_parameters", Kind::Tree(VIDEO_CODEC_PARAMETERS)),
+    Field::new(0x03, "attributes", Kind::Tree(VIDEO_ATTRIBUTES)),
+];
+
+const AUDIO_CODEC_PARAMETERS: &[Field] = &[
+    Field::new(0x01, "channels", Kind::Integer(1)),
+    Field::new(0x02, "bit_rate", Kind::Enum(1, BIT_RATES)),
+    Field::new(0x03, "sample_rate", Kind::Enum(1, SAMPLE_RATES)),
+    Field::new(0x04, "rtp_time", Kind::Integer(1)),
+];
+
+const AUDIO_CODEC_CONFIGURATION: &[Field] = &[
+    Field::new(0x01, "codec_type", Kind::Enum(1, AUDIO_CODEC_TYPES)),
+    Field::new(0x02, "codec_parameters", Kind::Tree(AUDIO_CODEC_PARAMETERS)),
+];
+
+const ADDRESS: &[Field] = &[
+    Field::new(0x01, "ip_version", Kind::Enum(1, IP_VERSIONS)),
+    Field::new(0x02, "ip_address", Kind::String),
+    Field::new(0x03, "video_rtp_port", Kind::Integer(2)),
+    Field::new(0x04, "audio_rtp_port", Kind::Integer(2)),
+];
+
+const SRTP_PARAMETERS: &[Field] = &[
+    Field::new(0x01, "crypto_suite", Kind::Enum(1, CRYPTO_SUITES)),
+    Field::new(0x02, "master_key", Kind::Bytes),
+    Field::new(0x03, "master_salt", Kind::Bytes),
+];
+
+const SESSION_CONTROL: &[Field] = &[
+    Field::new(0x01, "session_id", Kind::Uuid),
+    Field::new(0x02, "command", Kind::Enum(1, SESSION_COMMANDS)),
+];
+
+const RTP_PARAMETERS: &[Field] = &[
+    Field::new(0x01, "payload_type", Kind::Integer(1)),
+    Field::new(0x02, "ssrc", Kind::Integer(4)),
+    Field::new(0x03, "max_bit_rate", Kind::Integer(2)),
+    Field::new(0x04, "min_rtcp_interval", Kind::Float),
+    Field::new(0x05, "max_mtu", Kind::Integer(2)),
+    Field::new(0x06, "comfort_noise_payload_type", Kind::Integer(1)),
+];
+
+const SELECTED_VIDEO_PARAMETERS: &[Field] = &[
+    Field::new(0x01, "codec_type", Kind::Enum(1, VIDEO_CODEC_TYPES)),
+    Field::new(0x02, "codec_parameters", Kind::Tree(VIDEO_CODEC_PARAMETERS)),
+    Field::new(0x03, "attributes", Kind::Tree(VIDEO_ATTRIBUTES)),
+    Field::new(0x04, "rtp_parameters", Kind::Tree(RTP_PARAMETERS)),
+];
+
+const SELECTED_AUDIO_PARAMETERS: &[Field] = &[
+    Field::new(0x01, "codec_type", Kind::Enum(1, AUDIO_CODEC_TYPES)),
+    Field::new(0x02, "codec_parameters", Kind::Tree(AUDIO_CODEC_PARAMETERS)),
+    Field::new(0x03, "rtp_parameters", Kind::Tree(RTP_PARAMETERS)),
+    Field::new(0x04, "comfort_noise", Kind::Bool),
+];
+
+pub const SUPPORTED_VIDEO_STREAM_CONFIGURATION: Schema = Schema {
+    fields: &[Field::new(0x01, "video_codec_configuration", Kind::Tree(VIDEO_CODEC_CONFIGURATION))],
+};
+
+pub const SUPPORTED_AUDIO_STREAM_CONFIGURATION: Schema = Schema {
+    fields: &[
+        Field::new(0x01, "audio_codec_configuration", Kind::Tree(AUDIO_CODEC_CONFIGURATION)),
+        Field::new(0x02, "comfort_noise", Kind::Bool),
+    ],
+};
+
+pub const SUPPORTED_RTP_CONFIGURATION: Schema = Schema {
+    fields: &[Field::new(0x02, "crypto_suite", Kind::Enum(1, CRYPTO_SUITES))],
+};
+
+pub const STREAMING_STATUS: Schema = Schema {
+    fields: &[Field::new(0x01, "status", Kind::Enum(1, STREAMING_STATUSES))],
+};
+
+/// Controllers write a session id, their address and SRTP parameters. The
+/// accessory answers with the same fields, a status and its SSRCs.
+pub const SETUP_ENDPOINTS: Schema = Schema {
+    fields: &[
+        Field::new(0x01, "session_id", Kind::Uuid),
+        Field::new(0x02, "status", Kind::Enum(1, SETUP_STATUSES)),
+        Field::new(0x03, "address", Kind::Tree(ADDRESS)),
+        Field::new(0x04, "video_srtp_parameters", Kind::Tree(SRTP_PARAMETERS)),
+        Field::new(0x05, "audio_srtp_parameters", Kind::Tree(SRTP_PARAMETERS)),
+        Field::new(0x06, "video_ssrc", Kind::Integer(4)),
+        Field::new(0x07, "audio_ssrc", Kind::Integer(4)),
+    ],
+};
+
+pub const SELECTED_RTP_STREAM_CONFIGURATION: Schema = Schema {
+    fields: &[
+        Field::new(0x01, "session_control", Kind::Tree(SESSION_CONTROL)),
+        Field::new(0x02, "video_parameters", Kind::Tree(SELECTED_VIDEO_PARAMETERS)),
+        Field::new(0x03, "audio_parameters", Kind::Tree(SELECTED_AUDIO_PARAMETERS)),
+    ],
+};
+
+/// Commands written to a lock's control point. Times are seconds since the
+/// epoch, and clear_logs takes an empty value.
+pub const LOCK_CONTROL_POINT: Schema = Schema {
+    fields: &[
+        Field::new(0x00, "read_logs_from_time", Kind::Integer(4)),
+        Field::new(0x02, "clear_logs", Kind::Bytes),
+        Field::new(0x03, "set_current_time", Kind::Integer(4)),
+    ],
+};
+
+/// Log entries read from a lock after a read_logs_from_time command. The
+/// protocol leaves the contents of entries to the accessory, so every item
+/// is listed by its tag with its value as bytes.
+pub const LOGS: Schema = Schema { fields: &[] };